@@ -1,9 +1,15 @@
 use std::io::Read;
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
 use serde_json::Value;
 
-use runbook_protocol::{HookEvent, UserPromptSubmitOutput};
+use runbook_protocol::{HookEvent, PreToolUseDecisionOutput, UserPromptSubmitOutput};
+
+/// Default for `--timeout-ms`: short, so a missing daemon never stalls Claude Code.
+const DEFAULT_TIMEOUT_MS: u64 = 250;
 
 /// Claude Code hook consumer.
 ///
@@ -23,6 +29,35 @@ struct Args {
     #[arg(long, default_value = "http://127.0.0.1:29381")]
     daemon: String,
 
+    /// Session tag to report, overriding `RUNBOOK_SESSION_TAG` (e.g. in CI).
+    #[arg(long)]
+    session_tag: Option<String>,
+
+    /// Session ID to report, overriding the hook payload's `session_id`.
+    #[arg(long)]
+    session_id: Option<String>,
+
+    /// runbook.yaml to read `tooling.hooks_timeout_ms` and
+    /// `policy.pre_tool_use.bash` (`deny_regex`, `allow_override_env`,
+    /// `override_log`) from.
+    #[arg(long)]
+    config: Option<PathBuf>,
+
+    /// How long to wait for the daemon over HTTP before dropping the event.
+    /// Overrides tooling.hooks_timeout_ms from --config. Must be at least 1.
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout_ms: Option<u64>,
+
+    /// Repository to read git context (branch, status) from, when Claude Code
+    /// runs the hook outside it. Defaults to the current directory.
+    #[arg(long)]
+    workspace_root: Option<PathBuf>,
+
+    /// Forward events through this named pipe instead of HTTP (Unix only).
+    /// Must match `daemon.fifo` in runbook.yaml; created if absent.
+    #[arg(long)]
+    fifo: Option<PathBuf>,
+
     /// If set, deny destructive Bash commands at PreToolUse.
     /// In production, prefer policy.pre_tool_use.bash.deny in runbook.yaml.
     #[arg(long)]
@@ -31,10 +66,65 @@ struct Args {
     /// Comma-separated list of additional deny patterns (supplements the built-in list).
     #[arg(long, value_delimiter = ',')]
     deny_patterns: Vec<String>,
+
+    /// Case-insensitive regex that denies a matching Bash command (repeatable).
+    /// Applies even without --deny-destructive-bash, on top of any deny_regex
+    /// from --config.
+    #[arg(long = "deny-regex")]
+    deny_regex: Vec<String>,
+
+    /// Environment variable that, set to 1, lets a denied Bash command through
+    /// with a warning (the daemon is still told). Overrides
+    /// policy.pre_tool_use.bash.allow_override_env from --config.
+    #[arg(long)]
+    allow_override_env: Option<String>,
+
+    /// File each override is appended to as a JSON line, alongside the copy
+    /// on stderr. If it can't be written, the command is denied after all.
+    /// Overrides policy.pre_tool_use.bash.override_log from --config.
+    #[arg(long)]
+    override_log: Option<PathBuf>,
+
+    /// Path substring that denies a MultiEdit call touching it (repeatable).
+    /// Mirrors policy.pre_tool_use.multi_edit.deny_path_patterns.
+    #[arg(long = "deny-edit-path")]
+    deny_edit_paths: Vec<String>,
+
+    /// Deny a MultiEdit call that makes more than this many edits.
+    #[arg(long)]
+    max_edits: Option<usize>,
+
+    /// Dry run for testing without a daemon: print events to stderr instead of
+    /// forwarding them, and always print the PreToolUse decision to stdout.
+    #[arg(long)]
+    once: bool,
 }
 
 fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+    let config = match args.config {
+        Some(ref path) => HooksConfig::load(path)?,
+        None => HooksConfig::default(),
+    };
+    args.timeout_ms = Some(config.timeout_ms(args.timeout_ms)?);
+    let bash = &config.policy.pre_tool_use.bash;
+    args.allow_override_env = args
+        .allow_override_env
+        .or_else(|| bash.allow_override_env.clone());
+    args.override_log = args.override_log.or_else(|| bash.override_log.clone());
+
+    // Compile policy up front so a bad pattern fails loudly before we touch stdin.
+    let substrings = if args.deny_destructive_bash {
+        [built_in_deny_patterns(), args.deny_patterns.clone()].concat()
+    } else {
+        Vec::new()
+    };
+    let deny_regex = [args.deny_regex.as_slice(), config.deny_regex()].concat();
+    let policy = CompiledBashPolicy::new(substrings, &deny_regex)?;
+    let multi_edit = MultiEditPolicy {
+        deny_paths: args.deny_edit_paths.clone(),
+        max_edits: args.max_edits,
+    };
 
     // Read stdin JSON (Claude Code hook payload).
     let mut buf = String::new();
@@ -45,43 +135,98 @@ fn main() -> anyhow::Result<()> {
         serde_json::from_str(&buf)?
     };
 
-    // Extract session_id from the hook input (Claude Code includes it in every payload).
-    let session_id = payload
-        .get("session_id")
-        .and_then(|v| v.as_str())
-        .map(|s| s.to_string());
-
-    // Extract session_tag from process environment (set by VS Code extension when
-    // launching Claude terminals via "Start Claude Session").
-    let session_tag = std::env::var("RUNBOOK_SESSION_TAG").ok();
+    // session_id: --session-id, else the hook input (Claude Code includes it in every payload).
+    let session_id = resolve_session_field(
+        args.session_id.as_deref(),
+        None,
+        payload.get("session_id").and_then(|v| v.as_str()),
+    );
+
+    // session_tag: --session-tag, else the process environment (set by VS Code extension
+    // when launching Claude terminals via "Start Claude Session"), else the payload.
+    let session_tag = resolve_session_field(
+        args.session_tag.as_deref(),
+        std::env::var("RUNBOOK_SESSION_TAG").ok(),
+        payload.get("session_tag").and_then(|v| v.as_str()),
+    );
+
+    // The daemon fills `{git_branch}` in gate URLs from this, so it follows checkouts.
+    // Only hooks that use git context pay for the subprocess; PreToolUse runs
+    // on every tool call.
+    let branch = wants_git_context(&args.hook)
+        .then(|| args.workspace_root.as_deref().map_or_else(git_branch, git_branch_in))
+        .flatten();
 
     // Forward event to daemon (best-effort, fire-and-forget).
-    forward_to_daemon(&args, &payload, session_id.as_deref(), session_tag.as_deref());
+    let forwarded = with_git_branch(&payload, branch.as_deref());
+    forward_to_daemon(&args, &forwarded, session_id.as_deref(), session_tag.as_deref());
 
     // --- Hook-specific enforcement ---
 
-    if args.hook == "PreToolUse" && args.deny_destructive_bash {
+    if args.hook == "PreToolUse" && !policy.is_empty() {
         if let Some(ref cmd) = extract_bash_command(&payload) {
-            let deny_patterns = built_in_deny_patterns();
-            let extra = &args.deny_patterns;
-
-            if matches_any_pattern(cmd, &deny_patterns)
-                || matches_any_pattern(cmd, extra)
-            {
-                // Notify the daemon that we blocked something (UI signal).
-                notify_daemon_blocked(&args, session_id.as_deref(), session_tag.as_deref(), cmd);
-
-                // Exit-code enforcement: exit 2 blocks the tool call.
-                // This is more reliable than JSON stdout (upstream issues #10875, #18312).
-                eprintln!("Blocked by Runbook policy: {cmd}");
-                std::process::exit(2);
+            if let Some(rule) = policy.matching_rule(cmd) {
+                let env = |var: &str| std::env::var(var).ok();
+                if let Some(var) = args.allow_override_env.as_deref().filter(|v| override_active(v, env)) {
+                    // Emergency bypass: let it run, but loudly and on the record.
+                    let ev = policy_event(
+                        "overridden",
+                        session_id.as_deref(),
+                        session_tag.as_deref(),
+                        rule,
+                        cmd,
+                        true,
+                    );
+                    match record_override(args.override_log.as_deref(), &ev) {
+                        Ok(()) => {
+                            eprintln!("WARNING: Runbook policy overridden by {var}=1: {cmd} (rule: {rule})");
+                            send_event(&args, &ev);
+                            if args.once {
+                                let reason = format!("Runbook policy overridden by {var}=1");
+                                let out = PreToolUseDecisionOutput::allow(Some(&reason));
+                                println!("{}", serde_json::to_string(&out)?);
+                            }
+                            return Ok(());
+                        }
+                        Err(e) => eprintln!("Runbook policy override not recorded, denying: {e}"),
+                    }
+                }
+                let context = format!("This matches the policy pattern: {rule}");
+                deny_tool_call(
+                    &args,
+                    session_id.as_deref(),
+                    session_tag.as_deref(),
+                    rule,
+                    cmd,
+                    &context,
+                )?;
+            }
+        }
+    }
+
+    if args.hook == "PreToolUse" && !multi_edit.is_empty() {
+        if let Some(paths) = extract_multi_edit_paths(&payload) {
+            if let Some(reason) = multi_edit.violation(&paths) {
+                let summary = format!("MultiEdit ({} edits)", paths.len());
+                deny_tool_call(
+                    &args,
+                    session_id.as_deref(),
+                    session_tag.as_deref(),
+                    "multi_edit",
+                    &summary,
+                    &reason,
+                )?;
             }
         }
     }
 
+    if args.once && args.hook == "PreToolUse" {
+        println!("{}", serde_json::to_string(&PreToolUseDecisionOutput::allow(None))?);
+    }
+
     if args.hook == "UserPromptSubmit" {
         // Inject git branch as additional context.
-        let branch = git_branch().unwrap_or_else(|| "(unknown)".to_string());
+        let branch = branch.as_deref().unwrap_or("(unknown)");
         let out = UserPromptSubmitOutput::with_context(&format!(
             "Runbook context: git_branch={branch}"
         ));
@@ -91,17 +236,168 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+// ---------------------------------------------------------------------------
+// Config
+// ---------------------------------------------------------------------------
+
+/// The parts of runbook.yaml that `--config` reads; everything else is ignored.
+#[derive(Debug, Default, Deserialize)]
+struct HooksConfig {
+    #[serde(default)]
+    tooling: ToolingSection,
+    #[serde(default)]
+    policy: PolicySection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct ToolingSection {
+    hooks_timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PolicySection {
+    #[serde(default)]
+    pre_tool_use: PreToolUseSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct PreToolUseSection {
+    #[serde(default)]
+    enabled: bool,
+    #[serde(default)]
+    bash: BashSection,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct BashSection {
+    #[serde(default)]
+    deny_regex: Vec<String>,
+    /// Fallback for `--allow-override-env`.
+    allow_override_env: Option<String>,
+    /// Fallback for `--override-log`.
+    override_log: Option<PathBuf>,
+}
+
+impl HooksConfig {
+    fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("failed to read config '{}': {e}", path.display()))?;
+        serde_yaml::from_str(&raw)
+            .map_err(|e| anyhow::anyhow!("invalid config '{}': {e}", path.display()))
+    }
+
+    /// `--timeout-ms` if given, else `tooling.hooks_timeout_ms`, else the default.
+    fn timeout_ms(&self, flag: Option<u64>) -> anyhow::Result<u64> {
+        match flag.or(self.tooling.hooks_timeout_ms) {
+            Some(0) => anyhow::bail!("tooling.hooks_timeout_ms must be at least 1"),
+            Some(ms) => Ok(ms),
+            None => Ok(DEFAULT_TIMEOUT_MS),
+        }
+    }
+
+    /// `policy.pre_tool_use.bash.deny_regex`, while the policy is enabled.
+    fn deny_regex(&self) -> &[String] {
+        let policy = &self.policy.pre_tool_use;
+        if policy.enabled {
+            &policy.bash.deny_regex
+        } else {
+            &[]
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Daemon forwarding
 // ---------------------------------------------------------------------------
 
-fn forward_to_daemon(args: &Args, payload: &Value, session_id: Option<&str>, session_tag: Option<&str>) {
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_millis(250))
-        .build();
+/// Pick a session field by priority: CLI flag > environment > hook payload.
+/// Empty strings count as unset.
+fn resolve_session_field(cli: Option<&str>, env: Option<String>, payload: Option<&str>) -> Option<String> {
+    let set = |s: &&str| !s.is_empty();
+    cli.filter(set)
+        .map(str::to_string)
+        .or(env.filter(|s| !s.is_empty()))
+        .or_else(|| payload.filter(set).map(str::to_string))
+}
+
+/// Deliver an event to the daemon over `--fifo` if set, else HTTP. Best-effort:
+/// failures are ignored so a missing daemon never breaks Claude Code.
+/// With `--once` the event is printed to stderr instead.
+fn send_event(args: &Args, ev: &HookEvent) {
+    if args.once {
+        if let Ok(json) = serde_json::to_string(ev) {
+            eprintln!("{json}");
+        }
+        return;
+    }
+
+    if let Some(ref path) = args.fifo {
+        let _ = write_fifo(path, ev);
+        return;
+    }
+
+    let Ok(client) = build_http_client(args.timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS)) else {
+        return;
+    };
+
+    let url = format!("{}/hook", args.daemon.trim_end_matches('/'));
+    let _ = client.post(url).json(ev).send();
+}
+
+/// HTTP client for posting events, giving up after `timeout_ms`.
+fn build_http_client(timeout_ms: u64) -> reqwest::Result<reqwest::blocking::Client> {
+    reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_millis(timeout_ms))
+        .build()
+}
+
+/// Write one JSON line to the FIFO without ever blocking the hook.
+#[cfg(unix)]
+fn write_fifo(path: &Path, ev: &HookEvent) -> anyhow::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+
+    use nix::fcntl::OFlag;
+    use nix::sys::stat::Mode;
+
+    if !path.exists() {
+        match nix::unistd::mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR) {
+            Ok(()) | Err(nix::errno::Errno::EEXIST) => {}
+            Err(e) => anyhow::bail!("failed to create fifo '{}': {e}", path.display()),
+        }
+    }
 
-    let Ok(client) = client else { return };
+    // O_NONBLOCK: opening fails fast (ENXIO) when runbookd isn't reading.
+    let mut pipe = std::fs::OpenOptions::new()
+        .write(true)
+        .custom_flags(OFlag::O_NONBLOCK.bits())
+        .open(path)?;
+
+    // Lines up to PIPE_BUF (4 KiB) are written atomically.
+    let mut line = serde_json::to_vec(ev)?;
+    line.push(b'\n');
+    match pipe.write_all(&line) {
+        // EAGAIN: pipe is full; drop the event rather than stall Claude Code.
+        Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(()),
+        other => Ok(other?),
+    }
+}
+
+#[cfg(not(unix))]
+fn write_fifo(_path: &Path, _ev: &HookEvent) -> anyhow::Result<()> {
+    anyhow::bail!("--fifo is only supported on Unix")
+}
 
+/// `payload` with `git_branch` added, unless it already has one or isn't an object.
+fn with_git_branch(payload: &Value, branch: Option<&str>) -> Value {
+    let mut payload = payload.clone();
+    if let (Some(obj), Some(branch)) = (payload.as_object_mut(), branch) {
+        obj.entry("git_branch").or_insert_with(|| Value::from(branch));
+    }
+    payload
+}
+
+fn forward_to_daemon(args: &Args, payload: &Value, session_id: Option<&str>, session_tag: Option<&str>) {
     let ev = HookEvent {
         hook: args.hook.clone(),
         matcher: args.matcher.clone(),
@@ -109,35 +405,97 @@ fn forward_to_daemon(args: &Args, payload: &Value, session_id: Option<&str>, ses
         session_tag: session_tag.map(|s| s.to_string()),
         payload: payload.clone(),
     };
+    send_event(args, &ev);
+}
 
-    let url = format!("{}/hook", args.daemon.trim_end_matches('/'));
-    let _ = client.post(url).json(&ev).send();
+/// Block a PreToolUse call: tell the daemon, then exit 2 with the reason and
+/// `context` (which rule matched) on stderr, where Claude sees it.
+/// With `--once` the deny decision is also printed to stdout.
+fn deny_tool_call(
+    args: &Args,
+    session_id: Option<&str>,
+    session_tag: Option<&str>,
+    policy: &str,
+    subject: &str,
+    context: &str,
+) -> anyhow::Result<()> {
+    // Notify the daemon that we blocked something (UI signal).
+    notify_daemon_blocked(args, session_id, session_tag, policy, subject);
+
+    if args.once {
+        let out = PreToolUseDecisionOutput::deny_with_context(
+            &format!("Blocked by Runbook policy: {subject}"),
+            context,
+        );
+        println!("{}", serde_json::to_string(&out)?);
+    }
+
+    // Exit-code enforcement: exit 2 blocks the tool call.
+    // This is more reliable than JSON stdout (upstream issues #10875, #18312).
+    eprintln!("Blocked by Runbook policy: {subject}. {context}");
+    std::process::exit(2);
 }
 
 /// Notify the daemon that we blocked a tool call via our policy.
 /// This is our own truth signal ("RunbookPolicy/blocked"), NOT a Claude lifecycle event.
-fn notify_daemon_blocked(args: &Args, session_id: Option<&str>, session_tag: Option<&str>, command: &str) {
-    let client = reqwest::blocking::Client::builder()
-        .timeout(std::time::Duration::from_millis(250))
-        .build();
-
-    let Ok(client) = client else { return };
+fn notify_daemon_blocked(
+    args: &Args,
+    session_id: Option<&str>,
+    session_tag: Option<&str>,
+    policy: &str,
+    command: &str,
+) {
+    let ev = policy_event("blocked", session_id, session_tag, policy, command, false);
+    send_event(args, &ev);
+}
 
-    let ev = HookEvent {
+/// A `RunbookPolicy` event; `overridden` marks a call let through by
+/// `--allow-override-env`, so the daemon's record shows the bypass.
+fn policy_event(
+    matcher: &str,
+    session_id: Option<&str>,
+    session_tag: Option<&str>,
+    policy: &str,
+    command: &str,
+    overridden: bool,
+) -> HookEvent {
+    HookEvent {
         hook: "RunbookPolicy".to_string(),
-        matcher: Some("blocked".to_string()),
+        matcher: Some(matcher.to_string()),
         session_id: session_id.map(|s| s.to_string()),
         session_tag: session_tag.map(|s| s.to_string()),
         payload: serde_json::json!({
             "runbook_policy": {
-                "name": "deny_destructive_bash",
+                "name": policy,
                 "command": command,
+                "override": overridden,
             }
         }),
-    };
+    }
+}
 
-    let url = format!("{}/hook", args.daemon.trim_end_matches('/'));
-    let _ = client.post(url).json(&ev).send();
+/// Whether the emergency bypass variable `var` is set to exactly "1",
+/// looking variables up with `env`.
+fn override_active(var: &str, env: impl Fn(&str) -> Option<String>) -> bool {
+    env(var).as_deref() == Some("1")
+}
+
+/// Keep a record of an override that doesn't depend on the daemon: `ev` as a
+/// JSON line on stderr, and appended to `log` when set.
+fn record_override(log: Option<&Path>, ev: &HookEvent) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    let line = serde_json::to_string(ev)?;
+    eprintln!("{line}");
+    if let Some(path) = log {
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| anyhow::anyhow!("failed to open '{}': {e}", path.display()))?;
+        writeln!(file, "{line}")?;
+    }
+    Ok(())
 }
 
 // ---------------------------------------------------------------------------
@@ -167,23 +525,488 @@ fn built_in_deny_patterns() -> Vec<String> {
     ]
 }
 
-fn matches_any_pattern(cmd: &str, patterns: &[String]) -> bool {
+/// First pattern contained in `cmd`, compared case-insensitively.
+fn first_matching_pattern<'a>(cmd: &str, patterns: &'a [String]) -> Option<&'a str> {
     let lower = cmd.to_lowercase();
-    patterns.iter().any(|p| lower.contains(&p.to_lowercase()))
+    patterns.iter().find(|p| lower.contains(&p.to_lowercase())).map(String::as_str)
+}
+
+/// Bash deny rules (`policy.pre_tool_use.bash.deny` / `deny_regex`), compiled once.
+#[derive(Debug, Default)]
+struct CompiledBashPolicy {
+    /// Case-insensitive substrings.
+    deny: Vec<String>,
+    deny_regex: Vec<Regex>,
+}
+
+impl CompiledBashPolicy {
+    fn new(deny: Vec<String>, deny_regex: &[String]) -> anyhow::Result<Self> {
+        let deny_regex = deny_regex
+            .iter()
+            .map(|p| {
+                RegexBuilder::new(p)
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| anyhow::anyhow!("invalid deny regex '{p}': {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Self { deny, deny_regex })
+    }
+
+    fn is_empty(&self) -> bool {
+        self.deny.is_empty() && self.deny_regex.is_empty()
+    }
+
+    #[cfg(test)]
+    fn denies(&self, cmd: &str) -> bool {
+        self.matching_rule(cmd).is_some()
+    }
+
+    /// The first deny rule (substring, then regex) that matches `cmd`.
+    fn matching_rule(&self, cmd: &str) -> Option<&str> {
+        first_matching_pattern(cmd, &self.deny)
+            .or_else(|| self.deny_regex.iter().find(|re| re.is_match(cmd)).map(Regex::as_str))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// MultiEdit analysis
+// ---------------------------------------------------------------------------
+
+/// Paths touched by a MultiEdit call, one per edit, or `None` for other tools.
+/// An edit without its own `path` uses the call's `file_path`.
+fn extract_multi_edit_paths(payload: &Value) -> Option<Vec<String>> {
+    let tool = payload.get("tool_name").and_then(|v| v.as_str())?;
+    if !tool.eq_ignore_ascii_case("multiedit") {
+        return None;
+    }
+    let input = payload.get("tool_input")?;
+    let file_path = input.get("file_path").and_then(|v| v.as_str()).unwrap_or_default();
+    let edits = input.get("edits").and_then(|v| v.as_array())?;
+    Some(
+        edits
+            .iter()
+            .map(|edit| {
+                edit.get("path")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or(file_path)
+                    .to_string()
+            })
+            .collect(),
+    )
+}
+
+/// MultiEdit deny rules (`policy.pre_tool_use.multi_edit`).
+#[derive(Debug, Default)]
+struct MultiEditPolicy {
+    /// Case-sensitive path substrings.
+    deny_paths: Vec<String>,
+    max_edits: Option<usize>,
+}
+
+impl MultiEditPolicy {
+    fn is_empty(&self) -> bool {
+        self.deny_paths.is_empty() && self.max_edits.is_none()
+    }
+
+    /// Why a MultiEdit touching `paths` is denied, if it is.
+    fn violation(&self, paths: &[String]) -> Option<String> {
+        if let Some(max) = self.max_edits.filter(|max| paths.len() > *max) {
+            return Some(format!("{} edits exceeds the limit of {max}", paths.len()));
+        }
+        paths.iter().find_map(|path| {
+            self.deny_paths
+                .iter()
+                .find(|p| path.contains(p.as_str()))
+                .map(|p| format!("Edit path '{path}' matches the policy pattern: {p}"))
+        })
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Git context
 // ---------------------------------------------------------------------------
 
+/// Whether `hook` reports git context: the branch at SessionStart and
+/// UserPromptSubmit, which is where a checkout change shows up.
+fn wants_git_context(hook: &str) -> bool {
+    matches!(hook, "SessionStart" | "UserPromptSubmit")
+}
+
+/// Branch checked out in the current directory's repository.
 fn git_branch() -> Option<String> {
+    git_branch_in(Path::new("."))
+}
+
+/// Branch checked out in the repository containing `root`.
+fn git_branch_in(root: &Path) -> Option<String> {
+    let s = git_output(root, &["rev-parse", "--abbrev-ref", "HEAD"])?;
+    let s = s.trim();
+    if s.is_empty() { None } else { Some(s.to_string()) }
+}
+
+/// `git status --porcelain` for the repository containing `root`: one line
+/// per changed path, empty when clean. No hook reports working-tree status
+/// yet, so only tests build it.
+#[cfg(test)]
+fn git_status_in(root: &Path) -> Option<String> {
+    git_output(root, &["status", "--porcelain"])
+}
+
+/// Stdout of `git -C root <args>`, or `None` if git fails or isn't installed.
+fn git_output(root: &Path, args: &[&str]) -> Option<String> {
     let out = std::process::Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .arg("-C")
+        .arg(root)
+        .args(args)
         .output()
         .ok()?;
     if !out.status.success() {
         return None;
     }
-    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
-    if s.is_empty() { None } else { Some(s) }
+    Some(String::from_utf8_lossy(&out.stdout).into_owned())
+}
+
+// ---------------------------------------------------------------------------
+// Tests
+// ---------------------------------------------------------------------------
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn regex_policy(patterns: &[&str]) -> CompiledBashPolicy {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        CompiledBashPolicy::new(Vec::new(), &patterns).unwrap()
+    }
+
+    #[test]
+    fn timeout_flag_defaults_and_overrides() {
+        let none = HooksConfig::default();
+        let args = Args::try_parse_from(["runbook-hooks", "Stop"]).unwrap();
+        assert_eq!(none.timeout_ms(args.timeout_ms).unwrap(), DEFAULT_TIMEOUT_MS);
+        let args = Args::try_parse_from(["runbook-hooks", "Stop", "--timeout-ms", "1000"]).unwrap();
+        assert_eq!(none.timeout_ms(args.timeout_ms).unwrap(), 1000);
+        assert!(Args::try_parse_from(["runbook-hooks", "Stop", "--timeout-ms", "0"]).is_err());
+    }
+
+    #[test]
+    fn config_supplies_timeout_and_deny_regex() {
+        let config: HooksConfig = serde_yaml::from_str(include_str!("../../../runbook.yaml")).unwrap();
+        assert_eq!(config.timeout_ms(None).unwrap(), 250);
+        assert_eq!(config.timeout_ms(Some(1000)).unwrap(), 1000);
+        let policy = CompiledBashPolicy::new(Vec::new(), config.deny_regex()).unwrap();
+        assert!(policy.denies("rm -fr target"));
+
+        let disabled: HooksConfig = serde_yaml::from_str(
+            "tooling:\n  hooks_timeout_ms: 0\npolicy:\n  pre_tool_use:\n    bash:\n      deny_regex: [rm]\n",
+        )
+        .unwrap();
+        assert!(disabled.deny_regex().is_empty());
+        assert!(disabled.timeout_ms(None).is_err());
+    }
+
+    #[test]
+    fn override_env_must_be_exactly_one() {
+        let var = "RUNBOOK_POLICY_OVERRIDE";
+        let env = |value: Option<&'static str>| move |_: &str| value.map(str::to_string);
+        assert!(!override_active(var, env(None)));
+        assert!(!override_active(var, env(Some("true"))));
+        assert!(override_active(var, env(Some("1"))));
+
+        let args =
+            Args::try_parse_from(["runbook-hooks", "PreToolUse", "--allow-override-env", var]).unwrap();
+        assert_eq!(args.allow_override_env.as_deref(), Some(var));
+    }
+
+    #[test]
+    fn config_supplies_override_settings() {
+        let config: HooksConfig = serde_yaml::from_str(
+            "policy:\n  pre_tool_use:\n    bash:\n      allow_override_env: RUNBOOK_POLICY_OVERRIDE\n      override_log: overrides.jsonl\n",
+        )
+        .unwrap();
+        let bash = &config.policy.pre_tool_use.bash;
+        assert_eq!(bash.allow_override_env.as_deref(), Some("RUNBOOK_POLICY_OVERRIDE"));
+        assert_eq!(bash.override_log.as_deref(), Some(Path::new("overrides.jsonl")));
+    }
+
+    #[test]
+    fn overrides_are_appended_to_the_log() {
+        let log = std::env::temp_dir().join(format!("runbook-overrides-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&log);
+        let ev = policy_event("overridden", Some("s1"), None, "deny_destructive_bash", "rm -rf /", true);
+        record_override(Some(&log), &ev).unwrap();
+        record_override(Some(&log), &ev).unwrap();
+
+        let written = std::fs::read_to_string(&log).unwrap();
+        let _ = std::fs::remove_file(&log);
+        let lines: Vec<Value> = written.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["payload"]["runbook_policy"]["override"], true);
+
+        let missing_dir = log.join("nope").join("overrides.jsonl");
+        assert!(record_override(Some(&missing_dir), &ev).is_err());
+    }
+
+    #[test]
+    fn policy_events_record_whether_overridden() {
+        let ev = policy_event("overridden", Some("s1"), None, "deny_destructive_bash", "rm -rf /", true);
+        assert_eq!(ev.hook, "RunbookPolicy");
+        assert_eq!(ev.matcher.as_deref(), Some("overridden"));
+        assert_eq!(ev.payload["runbook_policy"]["override"], true);
+        assert_eq!(ev.payload["runbook_policy"]["command"], "rm -rf /");
+
+        let ev = policy_event("blocked", None, None, "multi_edit", "MultiEdit (3 edits)", false);
+        assert_eq!(ev.payload["runbook_policy"]["override"], false);
+    }
+
+    #[test]
+    fn http_client_builds_for_any_accepted_timeout() {
+        assert!(build_http_client(DEFAULT_TIMEOUT_MS).is_ok());
+        assert!(build_http_client(1).is_ok());
+    }
+
+    #[test]
+    fn deny_regex_catches_reordered_flags() {
+        let policy = regex_policy(&[r"\brm\s+-[a-z]*(rf|fr)"]);
+        assert!(policy.denies("rm -fr target"));
+        assert!(policy.denies("rm -Rf target"));
+        assert!(!policy.denies("rm -f Cargo.lock"));
+
+        // Substring matching on the built-ins misses the reordered form.
+        assert_eq!(first_matching_pattern("rm -fr target", &built_in_deny_patterns()), None);
+    }
+
+    #[test]
+    fn deny_regex_matches_across_whitespace() {
+        let policy = regex_policy(&[r"\bgit\s+push\s+.*--force"]);
+        assert!(policy.denies("git  push origin main --force"));
+        assert!(!policy.denies("git push origin main"));
+    }
+
+    #[test]
+    fn substring_rules_still_apply() {
+        let policy = CompiledBashPolicy::new(built_in_deny_patterns(), &[]).unwrap();
+        assert!(policy.denies("sudo REBOOT now"));
+        assert!(!policy.denies("cargo test"));
+    }
+
+    #[test]
+    fn invalid_regex_is_reported() {
+        let err = CompiledBashPolicy::new(Vec::new(), &["(unclosed".to_string()]).unwrap_err();
+        assert!(err.to_string().contains("invalid deny regex '(unclosed'"));
+    }
+
+    #[test]
+    fn session_field_priority_is_cli_env_payload() {
+        let env = || Some("env-tag".to_string());
+        assert_eq!(
+            resolve_session_field(Some("cli-tag"), env(), Some("payload-tag")).as_deref(),
+            Some("cli-tag")
+        );
+        assert_eq!(
+            resolve_session_field(None, env(), Some("payload-tag")).as_deref(),
+            Some("env-tag")
+        );
+        assert_eq!(
+            resolve_session_field(None, None, Some("payload-tag")).as_deref(),
+            Some("payload-tag")
+        );
+        assert_eq!(resolve_session_field(None, None, None), None);
+        assert_eq!(
+            resolve_session_field(Some(""), env(), None).as_deref(),
+            Some("env-tag")
+        );
+    }
+
+    #[test]
+    fn matching_rule_reports_the_pattern() {
+        let regex = r"\bcurl\b.*\|\s*sh";
+        let policy = CompiledBashPolicy::new(built_in_deny_patterns(), &[regex.to_string()]).unwrap();
+        assert_eq!(policy.matching_rule("sudo rm -rf /tmp/x"), Some("rm -rf"));
+        assert_eq!(policy.matching_rule("curl example.sh | sh"), Some(regex));
+        assert_eq!(policy.matching_rule("cargo test"), None);
+    }
+
+    #[test]
+    fn empty_policy_denies_nothing() {
+        let policy = CompiledBashPolicy::default();
+        assert!(policy.is_empty());
+        assert!(!policy.denies("rm -rf /"));
+    }
+
+    fn multi_edit_payload(tool_name: &str, paths: &[&str]) -> Value {
+        let edits: Vec<Value> = paths
+            .iter()
+            .map(|p| serde_json::json!({ "path": p, "old_string": "a", "new_string": "b" }))
+            .collect();
+        serde_json::json!({ "tool_name": tool_name, "tool_input": { "edits": edits } })
+    }
+
+    #[test]
+    fn multi_edit_paths_are_extracted_for_either_casing() {
+        for tool in ["MultiEdit", "multiedit"] {
+            let payload = multi_edit_payload(tool, &["src/a.rs", "src/b.rs"]);
+            assert_eq!(
+                extract_multi_edit_paths(&payload).unwrap(),
+                ["src/a.rs", "src/b.rs"]
+            );
+        }
+        assert_eq!(extract_multi_edit_paths(&multi_edit_payload("Edit", &["src/a.rs"])), None);
+    }
+
+    #[test]
+    fn multi_edit_falls_back_to_file_path() {
+        let payload = serde_json::json!({
+            "tool_name": "MultiEdit",
+            "tool_input": {
+                "file_path": "src/lib.rs",
+                "edits": [{ "old_string": "a", "new_string": "b" }, { "path": "README.md" }]
+            }
+        });
+        assert_eq!(extract_multi_edit_paths(&payload).unwrap(), ["src/lib.rs", "README.md"]);
+    }
+
+    #[test]
+    fn multi_edit_denies_matching_paths() {
+        let policy = MultiEditPolicy {
+            deny_paths: vec![".github/".to_string()],
+            max_edits: None,
+        };
+        let paths = vec!["src/a.rs".to_string(), ".github/workflows/ci.yml".to_string()];
+        assert_eq!(
+            policy.violation(&paths).as_deref(),
+            Some("Edit path '.github/workflows/ci.yml' matches the policy pattern: .github/")
+        );
+        assert_eq!(policy.violation(&paths[..1]), None);
+    }
+
+    #[test]
+    fn multi_edit_denies_too_many_edits() {
+        let policy = MultiEditPolicy {
+            deny_paths: Vec::new(),
+            max_edits: Some(2),
+        };
+        let paths: Vec<String> = (0..3).map(|i| format!("src/{i}.rs")).collect();
+        assert_eq!(
+            policy.violation(&paths).as_deref(),
+            Some("3 edits exceeds the limit of 2")
+        );
+        assert_eq!(policy.violation(&paths[..2]), None);
+        assert!(MultiEditPolicy::default().is_empty());
+    }
+
+    /// A fresh repository with one commit on `branch`, under the temp dir.
+    fn temp_git_repo(name: &str, branch: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("runbook-hooks-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let git = |args: &[&str]| {
+            let status = std::process::Command::new("git")
+                .arg("-C")
+                .arg(&dir)
+                .args(["-c", "user.name=runbook", "-c", "user.email=runbook@example.com"])
+                .args(args)
+                .output()
+                .unwrap()
+                .status;
+            assert!(status.success(), "git {args:?}");
+        };
+        git(&["init", "-q"]);
+        git(&["checkout", "-q", "-b", branch]);
+        git(&["commit", "-q", "--allow-empty", "-m", "init"]);
+        dir
+    }
+
+    #[test]
+    fn git_helpers_read_an_explicit_root() {
+        let repo = temp_git_repo("branch", "feat/workspace-root");
+        assert_eq!(git_branch_in(&repo).as_deref(), Some("feat/workspace-root"));
+        assert_eq!(git_status_in(&repo).as_deref(), Some(""));
+
+        std::fs::write(repo.join("notes.txt"), "wip").unwrap();
+        let status = git_status_in(&repo).unwrap();
+        assert_eq!(status.lines().collect::<Vec<_>>(), ["?? notes.txt"]);
+
+        std::fs::remove_dir_all(&repo).unwrap();
+    }
+
+    #[test]
+    fn forwarded_payload_carries_git_branch() {
+        let payload = serde_json::json!({"prompt": "hi"});
+        let forwarded = with_git_branch(&payload, Some("feat/gates"));
+        assert_eq!(forwarded["git_branch"], "feat/gates");
+        assert_eq!(forwarded["prompt"], "hi");
+
+        let own = serde_json::json!({"git_branch": "main"});
+        assert_eq!(with_git_branch(&own, Some("feat/gates"))["git_branch"], "main");
+        assert_eq!(with_git_branch(&payload, None), payload);
+        assert_eq!(with_git_branch(&Value::Null, Some("main")), Value::Null);
+    }
+
+    #[test]
+    fn git_helpers_outside_a_repo_return_none() {
+        let dir = std::env::temp_dir().join(format!("runbook-hooks-nogit-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        // The temp dir itself might sit inside a checkout; only assert when it doesn't.
+        if git_output(&dir, &["rev-parse", "--git-dir"]).is_none() {
+            assert_eq!(git_branch_in(&dir), None);
+            assert_eq!(git_status_in(&dir), None);
+        }
+        assert_eq!(git_branch_in(&dir.join("missing")), None);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn workspace_root_flag_is_optional() {
+        let args = Args::try_parse_from(["runbook-hooks", "Stop"]).unwrap();
+        assert_eq!(args.workspace_root, None);
+        let args = Args::try_parse_from(["runbook-hooks", "Stop", "--workspace-root", "/src/app"])
+            .unwrap();
+        assert_eq!(args.workspace_root.as_deref(), Some(Path::new("/src/app")));
+    }
+
+    #[test]
+    fn only_session_start_and_prompts_read_git() {
+        assert!(wants_git_context("SessionStart"));
+        assert!(wants_git_context("UserPromptSubmit"));
+        assert!(!wants_git_context("PreToolUse"));
+        assert!(!wants_git_context("Notification"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_fifo_delivers_one_json_line() {
+        use std::io::BufRead;
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let path = std::env::temp_dir().join(format!("runbook-hooks-{}.fifo", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let ev = HookEvent {
+            hook: "Stop".to_string(),
+            matcher: None,
+            session_id: Some("sess1".to_string()),
+            session_tag: None,
+            payload: serde_json::Value::Null,
+        };
+
+        // No reader yet: creates the pipe, then fails fast instead of blocking.
+        assert!(write_fifo(&path, &ev).is_err());
+        assert!(path.exists());
+
+        let reader = std::fs::OpenOptions::new()
+            .read(true)
+            .custom_flags(nix::fcntl::OFlag::O_NONBLOCK.bits())
+            .open(&path)
+            .unwrap();
+        write_fifo(&path, &ev).unwrap();
+
+        let mut line = String::new();
+        std::io::BufReader::new(reader).read_line(&mut line).unwrap();
+        let received: HookEvent = serde_json::from_str(&line).unwrap();
+        assert_eq!(received.hook, "Stop");
+        assert_eq!(received.session_id.as_deref(), Some("sess1"));
+
+        std::fs::remove_file(&path).unwrap();
+    }
 }
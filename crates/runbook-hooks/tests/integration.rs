@@ -0,0 +1,171 @@
+//! Runs the built `runbook-hooks` binary against a mock daemon that expects
+//! each `POST /hook` body.
+
+use std::net::TcpListener;
+
+use assert_cmd::assert::Assert;
+use assert_cmd::Command;
+use mockito::{Matcher, Mock, Server, ServerGuard};
+use runbook_protocol::HookEvent;
+use serde_json::{json, Value};
+
+/// Run `runbook-hooks <args> --daemon <daemon>` with `stdin`.
+fn run(daemon: &str, args: &[&str], stdin: &str) -> Assert {
+    Command::cargo_bin("runbook-hooks")
+        .unwrap()
+        .args(args)
+        .args(["--daemon", daemon])
+        .env_remove("RUNBOOK_SESSION_TAG")
+        .write_stdin(stdin)
+        .assert()
+}
+
+/// Expect one `POST /hook` whose body contains `event`.
+fn expect_hook(server: &mut ServerGuard, event: Value) -> Mock {
+    server
+        .mock("POST", "/hook")
+        .match_body(Matcher::PartialJson(event))
+        .with_body("ok")
+        .create()
+}
+
+fn stderr(assert: &Assert) -> String {
+    String::from_utf8_lossy(&assert.get_output().stderr).into_owned()
+}
+
+#[test]
+fn user_prompt_submit_injects_git_branch() {
+    let mut server = Server::new();
+    let forwarded = expect_hook(
+        &mut server,
+        json!({"hook": "UserPromptSubmit", "payload": {"prompt": "hi"}}),
+    );
+    let out = run(&server.url(), &["UserPromptSubmit"], r#"{"prompt": "hi"}"#).code(0);
+
+    let stdout: Value =
+        serde_json::from_slice(&out.get_output().stdout).expect("stdout is one JSON object");
+    let hso = &stdout["hookSpecificOutput"];
+    assert_eq!(hso["hookEventName"], "UserPromptSubmit");
+    assert!(hso["additionalContext"]
+        .as_str()
+        .unwrap()
+        .starts_with("Runbook context: git_branch="));
+
+    forwarded.assert();
+}
+
+#[test]
+fn destructive_bash_exits_2_and_reports_block() {
+    let mut server = Server::new();
+    let tool_use = expect_hook(&mut server, json!({"hook": "PreToolUse"}));
+    let block = expect_hook(
+        &mut server,
+        json!({"hook": "RunbookPolicy", "matcher": "blocked", "session_id": "sess1"}),
+    );
+    let out = run(
+        &server.url(),
+        &["PreToolUse", "Bash", "--deny-destructive-bash"],
+        r#"{"session_id": "sess1", "tool_input": {"command": "rm -rf /"}}"#,
+    )
+    .code(2)
+    .stdout("");
+    let stderr = stderr(&out);
+    assert!(stderr.contains("Blocked by Runbook policy: rm -rf /"));
+    assert!(stderr.contains("This matches the policy pattern:"), "{stderr}");
+
+    tool_use.assert();
+    block.assert();
+}
+
+#[test]
+fn unknown_hook_is_forwarded() {
+    let mut server = Server::new();
+    let forwarded = expect_hook(
+        &mut server,
+        json!({
+            "hook": "FutureHook",
+            "matcher": "some_matcher",
+            "session_id": "sess1",
+            "session_tag": "tag-a",
+            "payload": {"detail": 42},
+        }),
+    );
+    run(
+        &server.url(),
+        &["FutureHook", "some_matcher", "--session-tag", "tag-a"],
+        r#"{"session_id": "sess1", "detail": 42}"#,
+    )
+    .code(0)
+    .stdout("");
+
+    forwarded.assert();
+}
+
+#[test]
+fn unreachable_daemon_is_not_an_error() {
+    // Bind then drop, so nothing is listening on the port.
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    run(&format!("http://{addr}"), &["Stop"], "{}").code(0).stdout("");
+}
+
+#[test]
+fn oversized_multi_edit_exits_2_and_reports_block() {
+    let mut server = Server::new();
+    let tool_use = expect_hook(&mut server, json!({"hook": "PreToolUse"}));
+    let block = expect_hook(
+        &mut server,
+        json!({"hook": "RunbookPolicy", "payload": {"runbook_policy": {"name": "multi_edit"}}}),
+    );
+    let out = run(
+        &server.url(),
+        &["PreToolUse", "MultiEdit", "--max-edits", "1"],
+        r#"{"session_id": "sess1", "tool_name": "MultiEdit",
+            "tool_input": {"file_path": "src/lib.rs", "edits": [{}, {}]}}"#,
+    )
+    .code(2);
+    let expected = "Blocked by Runbook policy: MultiEdit (2 edits). 2 edits exceeds the limit of 1";
+    assert!(stderr(&out).contains(expected));
+
+    tool_use.assert();
+    block.assert();
+}
+
+#[test]
+fn config_deny_regex_blocks_bash() {
+    let mut server = Server::new();
+    let tool_use = expect_hook(&mut server, json!({"hook": "PreToolUse"}));
+    let block = expect_hook(&mut server, json!({"hook": "RunbookPolicy", "matcher": "blocked"}));
+    let config = concat!(env!("CARGO_MANIFEST_DIR"), "/../../runbook.yaml");
+    let out = run(
+        &server.url(),
+        &["PreToolUse", "Bash", "--config", config],
+        r#"{"session_id": "sess1", "tool_input": {"command": "rm -fr target"}}"#,
+    )
+    .code(2);
+    assert!(stderr(&out).contains("Blocked by Runbook policy: rm -fr target"));
+
+    tool_use.assert();
+    block.assert();
+}
+
+#[test]
+fn tool_calls_are_forwarded_without_git_branch() {
+    let mut server = Server::new();
+    let forwarded = server
+        .mock("POST", "/hook")
+        .match_request(|req| {
+            let body = req.body().ok();
+            let ev: Option<HookEvent> = body.and_then(|b| serde_json::from_slice(b).ok());
+            ev.is_some_and(|ev| ev.hook == "PreToolUse" && ev.payload.get("git_branch").is_none())
+        })
+        .with_body("ok")
+        .create();
+    run(
+        &server.url(),
+        &["PreToolUse", "Read"],
+        r#"{"session_id": "sess1", "tool_name": "Read"}"#,
+    )
+    .code(0);
+
+    forwarded.assert();
+}
@@ -0,0 +1,130 @@
+//! Runs the built `runbook-hooks` binary with `--once` (no daemon needed).
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+use runbook_protocol::HookEvent;
+use serde_json::Value;
+
+fn run_once(args: &[&str], stdin: &str) -> Output {
+    run_once_with_env(args, &[], stdin)
+}
+
+/// Like [`run_once`], with extra environment variables set.
+fn run_once_with_env(args: &[&str], env: &[(&str, &str)], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_runbook-hooks"))
+        .args(args)
+        .arg("--once")
+        .env_remove("RUNBOOK_SESSION_TAG")
+        .envs(env.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .expect("spawn runbook-hooks");
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(stdin.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+/// Events printed to stderr, one JSON object per line (other lines skipped).
+fn stderr_events(out: &Output) -> Vec<HookEvent> {
+    String::from_utf8_lossy(&out.stderr)
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+fn stdout_json(out: &Output) -> Value {
+    serde_json::from_slice(&out.stdout).expect("stdout is one JSON object")
+}
+
+#[test]
+fn destructive_bash_is_denied_with_exit_code_2() {
+    let out = run_once(
+        &["PreToolUse", "Bash", "--deny-destructive-bash"],
+        r#"{"session_id": "sess1", "tool_input": {"command": "rm -rf /"}}"#,
+    );
+    assert_eq!(out.status.code(), Some(2));
+
+    let decision = &stdout_json(&out)["hookSpecificOutput"];
+    assert_eq!(decision["hookEventName"], "PreToolUse");
+    assert_eq!(decision["permissionDecision"], "deny");
+    assert_eq!(
+        decision["permissionDecisionReason"],
+        "Blocked by Runbook policy: rm -rf /"
+    );
+    assert_eq!(
+        decision["additionalContext"],
+        "This matches the policy pattern: rm -rf"
+    );
+
+    let events = stderr_events(&out);
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].hook, "PreToolUse");
+    assert_eq!(events[0].matcher.as_deref(), Some("Bash"));
+    assert_eq!(events[0].session_id.as_deref(), Some("sess1"));
+    assert_eq!(events[0].payload["tool_input"]["command"], "rm -rf /");
+    assert_eq!(events[1].hook, "RunbookPolicy");
+    assert_eq!(events[1].matcher.as_deref(), Some("blocked"));
+}
+
+#[test]
+fn config_override_allows_and_reports_the_matched_rule() {
+    let config = std::env::temp_dir().join(format!("runbook-hooks-once-{}.yaml", std::process::id()));
+    std::fs::write(
+        &config,
+        "policy:\n  pre_tool_use:\n    enabled: true\n    bash:\n      deny_regex: ['\\bgit\\s+push\\s+.*--force']\n      allow_override_env: RUNBOOK_TEST_OVERRIDE\n",
+    )
+    .unwrap();
+    let args = ["PreToolUse", "Bash", "--config", config.to_str().unwrap()];
+    let stdin = r#"{"tool_input": {"command": "git push origin main --force"}}"#;
+
+    let denied = run_once(&args, stdin);
+    assert_eq!(denied.status.code(), Some(2));
+    let events = stderr_events(&denied);
+    assert_eq!(events[1].payload["runbook_policy"]["name"], r"\bgit\s+push\s+.*--force");
+
+    let out = run_once_with_env(&args, &[("RUNBOOK_TEST_OVERRIDE", "1")], stdin);
+    let _ = std::fs::remove_file(&config);
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(
+        stdout_json(&out)["hookSpecificOutput"]["permissionDecision"],
+        "allow"
+    );
+    let overridden = stderr_events(&out)
+        .into_iter()
+        .find(|ev| ev.matcher.as_deref() == Some("overridden"))
+        .expect("override reported");
+    assert_eq!(overridden.payload["runbook_policy"]["name"], r"\bgit\s+push\s+.*--force");
+}
+
+#[test]
+fn safe_bash_is_allowed() {
+    let out = run_once(
+        &["PreToolUse", "Bash", "--deny-destructive-bash"],
+        r#"{"tool_input": {"command": "cargo test"}}"#,
+    );
+    assert_eq!(out.status.code(), Some(0));
+    assert_eq!(
+        stdout_json(&out)["hookSpecificOutput"]["permissionDecision"],
+        "allow"
+    );
+    assert_eq!(stderr_events(&out).len(), 1);
+}
+
+#[test]
+fn user_prompt_submit_prints_context() {
+    let out = run_once(&["UserPromptSubmit"], r#"{"prompt": "hi"}"#);
+    assert_eq!(out.status.code(), Some(0));
+    let context = stdout_json(&out)["hookSpecificOutput"]["additionalContext"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    assert!(context.starts_with("Runbook context: git_branch="));
+    assert_eq!(stderr_events(&out)[0].hook, "UserPromptSubmit");
+}
@@ -0,0 +1,34 @@
+//! Print the stdout a `PreToolUse` hook returns to Claude Code to deny or
+//! allow a tool call.
+//!
+//! ```bash
+//! cargo run -p runbook-protocol --example hook_decision
+//! ```
+
+use runbook_protocol::PreToolUseDecisionOutput;
+
+fn main() -> anyhow::Result<()> {
+    // These types follow Claude Code's hook output format rather than the
+    // daemon protocol, hence the camelCase keys: the hook prints one of them
+    // on stdout and Claude Code reads `hookSpecificOutput.permissionDecision`.
+    let deny = PreToolUseDecisionOutput::deny("Blocked by Runbook policy: rm -rf /");
+    println!("deny:\n{}", serde_json::to_string_pretty(&deny)?);
+
+    // The reason is optional on allow; `skip_serializing_if` leaves the key
+    // out entirely instead of sending `null`.
+    let allow = PreToolUseDecisionOutput::allow(None);
+    println!("allow:\n{}", serde_json::to_string_pretty(&allow)?);
+
+    // Context is shown to the user alongside the decision.
+    let allow = PreToolUseDecisionOutput::allow_with_context(
+        Some("read-only command"),
+        "Runbook context: git_branch=main",
+    );
+    println!("allow with context:\n{}", serde_json::to_string_pretty(&allow)?);
+
+    // Output from another hook can be checked the same way: `parse_str`
+    // rejects decisions other than allow, deny or ask.
+    let parsed = PreToolUseDecisionOutput::parse_str(&serde_json::to_string(&deny)?)?;
+    assert_eq!(parsed.hook_specific_output.permission_decision, "deny");
+    Ok(())
+}
@@ -0,0 +1,45 @@
+//! Parse a `render` message from stdin and print what a device would show.
+//!
+//! ```bash
+//! cargo run -p runbook-protocol --example parse_render < crates/runbook-protocol/fixtures/render.json
+//! ```
+
+use std::io::Read;
+
+use runbook_protocol::DaemonToClient;
+
+fn main() -> anyhow::Result<()> {
+    let mut raw = String::new();
+    std::io::stdin().read_to_string(&mut raw)?;
+
+    // Clients parse into the whole `DaemonToClient` enum rather than the one
+    // struct they expect: the `type` tag picks the variant, so the same
+    // code path handles every message on the socket.
+    let render = match serde_json::from_str(&raw)? {
+        DaemonToClient::Render(render) => render,
+        other => anyhow::bail!("expected a `render` message, got {other:?}"),
+    };
+
+    // A render is a full snapshot, not a diff: a client that reconnects or
+    // misses a message only needs the next one to be correct again.
+    println!(
+        "state: {:?}, page {}/{}",
+        render.agent_state,
+        render.page_index + 1,
+        render.page_count
+    );
+    if let Some(armed) = &render.armed {
+        println!("armed: {} -> {}", armed.label, armed.command);
+    }
+    for slot in &render.keypad.slots {
+        // Optional fields (`sublabel`, `progress`, ...) default when absent,
+        // which is how new fields are added without breaking old daemons.
+        let sublabel = slot.sublabel.as_deref().unwrap_or("");
+        let marker = if slot.armed { "*" } else { " " };
+        println!("{marker}[{}] {} {sublabel}", slot.slot, slot.label);
+    }
+    if let Some(status) = &render.status_line {
+        println!("status: {status}");
+    }
+    Ok(())
+}
@@ -0,0 +1,29 @@
+//! Build the `hello` a client sends first on `GET /ws`, and print its JSON.
+//!
+//! ```bash
+//! cargo run -p runbook-protocol --example send_hello
+//! ```
+
+use runbook_protocol::{ClientKind, ClientToDaemon, Hello, PROTOCOL_VERSION};
+
+fn main() -> anyhow::Result<()> {
+    let hello = ClientToDaemon::Hello(Hello {
+        client: ClientKind::Vscode,
+        // The daemon answers with its own `protocol` in the hello ack. The
+        // number only changes on a breaking change; new fields are additive
+        // and optional, so an older client keeps working against a newer daemon.
+        protocol: PROTOCOL_VERSION,
+        // Client build version, for logs only; it has no effect on the protocol.
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        // Free-form hints rather than an enum, so a client can advertise a
+        // feature the daemon doesn't know yet without failing to parse.
+        capabilities: vec!["terminals".to_string()],
+    });
+
+    // Every message is one JSON object with a snake_case `type` tag next to
+    // its fields (serde's internally tagged enums), so a C# or TypeScript
+    // client can switch on `type` without a wrapper object:
+    // {"type":"hello","client":"vscode","protocol":1,...}
+    println!("{}", serde_json::to_string_pretty(&hello)?);
+    Ok(())
+}
@@ -0,0 +1,131 @@
+//! Migration helpers for peers still speaking protocol v1.
+//!
+//! Strategy: fields are only ever added, and every field added after a
+//! message first shipped carries a serde default. v1 messages therefore
+//! already parse today. Protocol 2 is expected to make some of those fields
+//! required; [`upgrade_client_message_v1`] writes the defaults out explicitly
+//! so a v1 message keeps parsing once they are.
+
+use serde_json::{json, Map, Value};
+
+/// Whether `v` looks like a v1 message: an object with a `type` tag whose
+/// `protocol`, if it has one (only `hello` does), is 1.
+pub fn is_v1_message(v: &Value) -> bool {
+    let Some(obj) = v.as_object() else {
+        return false;
+    };
+    obj.get("type").is_some_and(Value::is_string)
+        && obj
+            .get("protocol")
+            .is_none_or(|p| p.as_u64() == Some(1))
+}
+
+/// `v` with every defaulted field of its client message written out.
+/// Anything that isn't a v1 client message is returned unchanged.
+pub fn upgrade_client_message_v1(v: &Value) -> Value {
+    let mut out = v.clone();
+    if !is_v1_message(v) {
+        return out;
+    }
+    let Some(obj) = out.as_object_mut() else {
+        return out;
+    };
+    match obj.get("type").and_then(Value::as_str).unwrap_or_default() {
+        "hello" if obj.contains_key("client") => {
+            fill(obj, &[("capabilities", json!([]))]);
+        }
+        "gesture_input" => fill(obj, &[("distance", json!(0))]),
+        "hook_event" => fill(
+            obj,
+            &[
+                ("matcher", Value::Null),
+                ("session_id", Value::Null),
+                ("session_tag", Value::Null),
+                ("payload", Value::Null),
+            ],
+        ),
+        "terminals_snapshot" => {
+            fill(obj, &[("timestamp", Value::Null)]);
+            let terminals = obj.get_mut("terminals").and_then(Value::as_array_mut);
+            for terminal in terminals.into_iter().flatten() {
+                if let Some(terminal) = terminal.as_object_mut() {
+                    fill(
+                        terminal,
+                        &[("session_tag", Value::Null), ("is_claude_session", json!(false))],
+                    );
+                }
+            }
+        }
+        "command_ack" => fill(obj, &[("error", Value::Null)]),
+        _ => {}
+    }
+    out
+}
+
+/// Insert each `(field, default)` the object doesn't already have.
+fn fill(obj: &mut Map<String, Value>, defaults: &[(&str, Value)]) {
+    for (field, default) in defaults {
+        obj.entry(*field).or_insert_with(|| default.clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ClientToDaemon, DaemonToClient};
+
+    #[test]
+    fn every_fixture_still_parses_after_upgrade() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/fixtures");
+        let mut count = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            let raw = std::fs::read_to_string(&path).unwrap();
+            let v: Value = serde_json::from_str(&raw).unwrap();
+            assert!(is_v1_message(&v), "{}", path.display());
+
+            let upgraded = upgrade_client_message_v1(&v);
+            if serde_json::from_value::<ClientToDaemon>(v).is_ok() {
+                serde_json::from_value::<ClientToDaemon>(upgraded)
+                    .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            } else {
+                serde_json::from_value::<DaemonToClient>(upgraded)
+                    .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+            }
+            count += 1;
+        }
+        assert!(count > 0, "no fixtures in {dir}");
+    }
+
+    #[test]
+    fn upgrade_fills_missing_defaults_only() {
+        let hello = json!({"type": "hello", "client": "logi", "protocol": 1, "version": "0.1.0"});
+        assert_eq!(upgrade_client_message_v1(&hello)["capabilities"], json!([]));
+
+        let kept = json!({"type": "hook_event", "hook": "Stop", "session_id": "s1"});
+        let upgraded = upgrade_client_message_v1(&kept);
+        assert_eq!(upgraded["session_id"], "s1");
+        assert_eq!(upgraded["matcher"], Value::Null);
+
+        let snapshot = json!({
+            "type": "terminals_snapshot",
+            "active_index": 0,
+            "terminals": [{"index": 0, "name": "bash"}],
+        });
+        let upgraded = upgrade_client_message_v1(&snapshot);
+        assert_eq!(upgraded["terminals"][0]["is_claude_session"], false);
+    }
+
+    #[test]
+    fn v1_heuristic() {
+        assert!(is_v1_message(&json!({"type": "keypad_press", "prompt_id": "p"})));
+        assert!(is_v1_message(&json!({"type": "hello", "protocol": 1})));
+        assert!(!is_v1_message(&json!({"type": "hello", "protocol": 2})));
+        assert!(!is_v1_message(&json!({"prompt_id": "p"})));
+        assert!(!is_v1_message(&json!("hello")));
+
+        // Not v1: left alone.
+        let v2 = json!({"type": "hello", "client": "logi", "protocol": 2, "version": "x"});
+        assert_eq!(upgrade_client_message_v1(&v2), v2);
+    }
+}
@@ -0,0 +1,66 @@
+//! Errors from parsing a protocol message off the wire.
+
+use std::fmt;
+
+use crate::validate::ValidationError;
+use crate::ErrorCode;
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    /// Not JSON, or not shaped like any message of this direction.
+    Deserialization(serde_json::Error),
+    /// Well-formed, but rejected by [`crate::validate`].
+    Validation(ValidationError),
+    /// The peer speaks a different [`crate::PROTOCOL_VERSION`].
+    VersionMismatch { expected: u32, got: u32 },
+}
+
+impl ProtocolError {
+    /// Wire error code reported to the client.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::Validation(e) => e.code(),
+            Self::Deserialization(_) | Self::VersionMismatch { .. } => ErrorCode::ValidationFailed,
+        }
+    }
+}
+
+impl fmt::Display for ProtocolError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Deserialization(e) => write!(f, "invalid message: {e}"),
+            Self::Validation(e) => e.fmt(f),
+            Self::VersionMismatch { expected, got } => {
+                write!(f, "protocol {got} not supported (expected {expected})")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ProtocolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Deserialization(e) => Some(e),
+            Self::Validation(e) => Some(e),
+            Self::VersionMismatch { .. } => None,
+        }
+    }
+}
+
+impl From<serde_json::Error> for ProtocolError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Deserialization(e)
+    }
+}
+
+/// A `hello` with the wrong protocol becomes [`ProtocolError::VersionMismatch`].
+impl From<ValidationError> for ProtocolError {
+    fn from(e: ValidationError) -> Self {
+        match e {
+            ValidationError::ProtocolMismatch { expected, got } => {
+                Self::VersionMismatch { expected, got }
+            }
+            other => Self::Validation(other),
+        }
+    }
+}
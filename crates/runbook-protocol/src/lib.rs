@@ -10,6 +10,12 @@
 
 use serde::{Deserialize, Serialize};
 
+pub mod compat;
+pub mod error;
+pub mod validate;
+
+use error::ProtocolError;
+
 /// Bump ONLY on breaking changes.
 pub const PROTOCOL_VERSION: u32 = 1;
 
@@ -17,7 +23,8 @@ pub const PROTOCOL_VERSION: u32 = 1;
 // Enums
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ClientKind {
     Logi,
@@ -25,10 +32,12 @@ pub enum ClientKind {
     Hooks,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum AgentState {
     /// No telemetry (non-Claude tools, or hooks not installed).
+    #[default]
     Unknown,
     /// Claude Code is ready for the next prompt (idle_prompt).
     Idle,
@@ -50,13 +59,26 @@ pub enum AgentState {
     Sent,
 }
 
-impl Default for AgentState {
-    fn default() -> Self {
-        Self::Unknown
+impl AgentState {
+    /// The snake_case wire name (e.g. `"waiting_permission"`).
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "unknown",
+            Self::Idle => "idle",
+            Self::Running => "running",
+            Self::WaitingPermission => "waiting_permission",
+            Self::WaitingInput => "waiting_input",
+            Self::Complete => "complete",
+            Self::Settled => "settled",
+            Self::Ended => "ended",
+            Self::Blocked => "blocked",
+            Self::Sent => "sent",
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum DialpadButton {
     CtrlC,
@@ -65,21 +87,49 @@ pub enum DialpadButton {
     Enter,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum NoticeSeverity {
+    #[default]
+    Info,
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum AdjustmentKind {
     Dial,
     Roller,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
-#[serde(rename_all = "snake_case")]
+/// Flattened into `page_nav`: `{"direction": "next"}` or
+/// `{"direction": "jump_to", "index": 4}`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(tag = "direction", rename_all = "snake_case")]
 pub enum PageDirection {
     Prev,
     Next,
+    /// Show page `index` (0-based); past the last page shows the last one.
+    JumpTo { index: usize },
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum GestureDirection {
+    SwipeLeft,
+    SwipeRight,
+    SwipeUp,
+    SwipeDown,
+    TapCenter,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum VscodeCommandKind {
     /// Send text to the target terminal.
@@ -92,13 +142,15 @@ pub enum VscodeCommandKind {
     OpenUri,
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TerminalScrollUnit {
     Lines,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum TerminalTarget {
     /// The daemon/extension's notion of the current Claude Code terminal.
@@ -107,56 +159,69 @@ pub enum TerminalTarget {
     Active,
     /// A terminal at a specific index in the terminal list.
     ByIndex(usize),
+    /// The terminal launched with this `RUNBOOK_SESSION_TAG`.
+    ///
+    /// The daemon resolves this to `by_index` before sending; clients never see it.
+    ByTag(String),
+    /// A terminal by display name; the daemon sends `by_index` when it knows the name.
+    ByName(String),
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum HooksMode {
     /// No hook events ever received.
+    #[default]
     Absent,
     /// Hook events arriving normally.
     Active,
+    /// Hook events arrive, but with gaps or out of order; session state may be stale.
+    Degraded { reason: String },
+    /// Hooks were active, but no session is live and none have arrived for a while.
+    Stale,
 }
 
-impl Default for HooksMode {
-    fn default() -> Self {
-        Self::Absent
+impl HooksMode {
+    /// The snake_case variant name, without any degraded reason.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Absent => "absent",
+            Self::Active => "active",
+            Self::Degraded { .. } => "degraded",
+            Self::Stale => "stale",
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum DialMode {
     /// Default: OS-level scroll (Logi profile built-in, no daemon involvement).
+    #[default]
     OsScroll,
     /// Route dial through daemon → VS Code terminal scroll.
     VscodeTerminalScroll,
 }
 
-impl Default for DialMode {
-    fn default() -> Self {
-        Self::OsScroll
-    }
-}
-
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, schemars::JsonSchema, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(rename_all = "snake_case")]
 pub enum ArmStyle {
+    #[default]
     Queue,
     Prefill,
-}
-
-impl Default for ArmStyle {
-    fn default() -> Self {
-        Self::Queue
-    }
+    /// Like `queue`, then the prompt's `chain` runs one step per `TaskCompleted`.
+    Chain,
 }
 
 // ---------------------------------------------------------------------------
 // Client → Daemon messages
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ClientToDaemon {
     Hello(Hello),
@@ -165,20 +230,42 @@ pub enum ClientToDaemon {
     KeypadPress(KeypadPress),
     DialpadButtonPress(DialpadButtonPress),
     Adjustment(Adjustment),
+    GestureInput(GestureInput),
     PageNav(PageNav),
+    PageJump(PageJump),
+
+    /// Queue prompts to run back to back, one per `TaskCompleted`.
+    Macro(MacroRequest),
+    /// Type Claude's `/export` into a session's terminal.
+    ExportRequest(ExportRequest),
 
     // --- Claude Code hook events (normalized) ---
     HookEvent(HookEvent),
 
     // --- VS Code extension telemetry ---
     TerminalsSnapshot(TerminalsSnapshot),
+    SlotProgress(SlotProgress),
+    /// The user focused a terminal whose Claude session has this tag.
+    TagSelect(TagSelect),
+    /// The extension ran (or failed to run) a `vscode_command`.
+    CommandAck(CommandAck),
+
+    // --- Client settings ---
+    /// Override config for the sending client's kind until the daemon restarts.
+    Reconfigure(ReconfigureRequest),
+    /// Render this session for the sending client instead of the shared one.
+    SetFocusedSession(SetFocusedSession),
+
+    // --- Monitoring ---
+    HealthCheck(HealthCheck),
 }
 
 // ---------------------------------------------------------------------------
 // Daemon → Client messages
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum DaemonToClient {
     Hello(HelloAck),
@@ -186,18 +273,99 @@ pub enum DaemonToClient {
     /// UI model update (key labels, armed prompt, agent state).
     Render(RenderModel),
 
+    /// Keys for the current page only, sent instead of `Render` when nothing
+    /// but the page changed (requires `delta_render` capability).
+    RenderPage(PageRender),
+
     /// Command to VS Code extension.
     VscodeCommand(VscodeCommand),
 
     /// Human-readable notification (debug / toast).
     Notice(Notice),
+
+    /// Tool-call progress for a running session.
+    Progress(ProgressUpdate),
+
+    /// A tool call was blocked by Runbook policy (requires `hooks` capability).
+    PolicyBlock(PolicyBlock),
+
+    /// Live Claude sessions known to the daemon (requires `sessions` capability).
+    SessionList(SessionList),
+
+    /// Config was hot-reloaded; clients should re-initialize page display and
+    /// expect a fresh `Render`.
+    ConfigReloaded(ConfigSummary),
+
+    /// Full daemon state dump, as `POST /debug/dump` returns it (developer
+    /// introspection; not broadcast).
+    Debug(DebugDump),
+
+    /// The daemon rejected a client message; may be followed by a close.
+    Error(ErrorMessage),
+
+    /// Short welcome text shown on the device after connecting.
+    Banner(BannerMessage),
+
+    /// Reply to `HealthCheck`, sent only to the asking client.
+    HealthAck(HealthAck),
+
+    /// Periodic liveness signal, broadcast every `daemon.heartbeat_secs`.
+    Heartbeat(Heartbeat),
+
+    /// Briefly light a key, e.g. to confirm its prompt was dispatched.
+    SlotHighlight(SlotHighlight),
+
+    /// A session's agent state changed; sent alongside the `render` that shows it.
+    AgentStateChange(AgentStateChange),
+
+    /// Transient overlay; unlike `notice`, it dismisses itself.
+    Toast(ToastMessage),
+
+    /// A prompt worth running next, sent when a task completes.
+    PromptSuggestion(PromptSuggestion),
+
+    /// The config was rejected: broadcast when a reload fails, and sent after
+    /// `hello` for errors from startup.
+    ConfigError(ConfigError),
+
+    /// The keypad moved to another page; sent before the render that shows
+    /// it, so clients can animate the transition.
+    PageChanged(PageChangedEvent),
+}
+
+impl ClientToDaemon {
+    /// Deserialize one client message and run [`validate::validate_client_message`] on it.
+    pub fn parse(json: &str) -> Result<Self, ProtocolError> {
+        let msg: Self = serde_json::from_str(json)?;
+        validate::validate_client_message(&msg)?;
+        Ok(msg)
+    }
+}
+
+impl DaemonToClient {
+    /// Deserialize one daemon message, rejecting a `hello` ack for another
+    /// protocol version.
+    pub fn parse(json: &str) -> Result<Self, ProtocolError> {
+        let msg: Self = serde_json::from_str(json)?;
+        if let Self::Hello(ack) = &msg {
+            if ack.protocol != PROTOCOL_VERSION {
+                return Err(ProtocolError::VersionMismatch {
+                    expected: PROTOCOL_VERSION,
+                    got: ack.protocol,
+                });
+            }
+        }
+        Ok(msg)
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Payload structs
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+/// Client handshake.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Hello {
     pub client: ClientKind,
     pub protocol: u32,
@@ -207,36 +375,183 @@ pub struct Hello {
     pub capabilities: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+/// Test default: a Logi client at the current protocol, version `"test"`,
+/// no capabilities. Override only the fields a test cares about:
+///
+/// ```
+/// use runbook_protocol::{ClientKind, Hello, HelloAck, HookEvent, PROTOCOL_VERSION};
+///
+/// let hello = Hello {
+///     client: ClientKind::Vscode,
+///     ..Hello::default()
+/// };
+/// assert_eq!(hello.protocol, PROTOCOL_VERSION);
+///
+/// let ack = HelloAck::default();
+/// assert_eq!(ack.daemon_version, "test");
+///
+/// let ev = HookEvent {
+///     hook: "Stop".to_string(),
+///     ..HookEvent::default()
+/// };
+/// assert!(ev.payload.is_null());
+/// ```
+impl Default for Hello {
+    fn default() -> Self {
+        Self {
+            client: ClientKind::Logi,
+            protocol: PROTOCOL_VERSION,
+            version: "test".to_string(),
+            capabilities: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct HelloAck {
     pub protocol: u32,
     pub daemon_version: String,
+    /// Capabilities the daemon supports (clients opt in via `Hello.capabilities`).
+    #[serde(default)]
+    pub capabilities: Vec<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+/// Test default: current protocol, daemon version `"test"`, no capabilities.
+impl Default for HelloAck {
+    fn default() -> Self {
+        Self {
+            protocol: PROTOCOL_VERSION,
+            daemon_version: "test".to_string(),
+            capabilities: vec![],
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct KeypadPress {
     /// Prompt ID from the current page slot (not a raw index).
     pub prompt_id: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct DialpadButtonPress {
     pub button: DialpadButton,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Adjustment {
     pub kind: AdjustmentKind,
     /// Signed number of detents/steps.
     pub delta: i32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+/// A swipe or tap on devices with a touch-sensitive dial face.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct GestureInput {
+    pub direction: GestureDirection,
+    /// Swipe length in device steps; ignored for taps.
+    #[serde(default)]
+    pub distance: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PageNav {
+    #[serde(flatten)]
     pub direction: PageDirection,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+/// Show the page whose `hotkey` matches; unknown hotkeys are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PageJump {
+    pub hotkey: String,
+}
+
+/// A multi-step sequence. Each `TaskCompleted` hook dispatches the next step,
+/// as if it had been armed and confirmed; a new macro replaces a pending one.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MacroRequest {
+    pub steps: Vec<MacroStep>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MacroStep {
+    /// Prompt to dispatch (key into `prompts`).
+    pub prompt_id: String,
+}
+
+/// Type `/export` (plus the format, unless `default`) into the terminal of
+/// `session_id` (nothing is sent if its terminal isn't known), or the active
+/// Claude terminal. Not submitted: Claude's own prompt stays the confirmation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ExportRequest {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ExportFormat {
+    /// Whatever `/export` does without an argument.
+    #[default]
+    Default,
+    Json,
+    Markdown,
+}
+
+impl ExportFormat {
+    /// The `/export` command line for this format.
+    pub fn command(self) -> &'static str {
+        match self {
+            Self::Default => "/export",
+            Self::Json => "/export json",
+            Self::Markdown => "/export markdown",
+        }
+    }
+}
+
+/// Show the session that reported `session_tag`; unknown tags are ignored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct TagSelect {
+    pub session_tag: String,
+}
+
+/// Live settings for the sending client's kind; unset fields keep their
+/// current value.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ReconfigureRequest {
+    /// Overrides `dial.mode`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dial_mode: Option<DialMode>,
+    /// Overrides `keypad.label_max_chars` (Logi only; it draws the keys).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label_max_chars: Option<usize>,
+}
+
+/// Pin the sending client to one session. Its renders follow that session
+/// while it is live and the shared one otherwise; other clients are unaffected.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SetFocusedSession {
+    pub session_id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct HookEvent {
     /// Claude Code hook name, e.g. "UserPromptSubmit", "Notification".
     pub hook: String,
@@ -254,16 +569,186 @@ pub struct HookEvent {
     pub payload: serde_json::Value,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+/// Test default: empty hook name, no session, null payload; set `hook`.
+impl Default for HookEvent {
+    fn default() -> Self {
+        Self {
+            hook: String::new(),
+            matcher: None,
+            session_id: None,
+            session_tag: None,
+            payload: serde_json::Value::Null,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Notice {
     pub message: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PolicyBlock {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    /// The blocked command, if the hook reported it.
+    #[serde(default)]
+    pub command: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SessionList {
+    pub sessions: Vec<SessionSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SessionSummary {
+    pub session_id: String,
+    #[serde(default)]
+    pub session_tag: Option<String>,
+    pub agent_state: AgentState,
+    #[serde(default)]
+    pub last_tool: Option<String>,
+    #[serde(default)]
+    pub tool_call_count: u32,
+    /// Tool calls blocked by Runbook policy in this session.
+    #[serde(default)]
+    pub blocked_count: u32,
+    /// Most recent submitted prompt (first 200 chars).
+    #[serde(default)]
+    pub last_prompt: Option<String>,
+    /// Outstanding permission dialog, if Claude is waiting on one.
+    #[serde(default)]
+    pub pending_permission: Option<PendingPermission>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PendingPermission {
+    pub tool_name: String,
+    pub description: String,
+    /// Seconds since the dialog appeared.
+    #[serde(default)]
+    pub waiting_secs: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ProgressUpdate {
+    pub session_id: String,
+    /// Number of `PreToolUse` events seen for this session.
+    pub tool_call_count: u32,
+    #[serde(default)]
+    pub last_tool_name: Option<String>,
+}
+
+/// Shape of the active config, without prompt bodies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ConfigSummary {
+    pub prompt_count: u32,
+    pub gate_count: u32,
+    /// Page names in display order.
+    pub pages: Vec<String>,
+    pub initial_page: usize,
+    pub policy_enabled: bool,
+    /// Per-page details, in the same order as `pages`.
+    #[serde(default)]
+    pub page_summaries: Vec<PageSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PageSummary {
+    pub name: String,
+    /// Human-readable description from `keypad.pages[].description`.
+    #[serde(default)]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct BannerMessage {
+    pub line1: String,
+    #[serde(default)]
+    pub line2: Option<String>,
+    /// How long the device should show the banner before its normal display.
+    pub duration_ms: u32,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// Message exceeded `daemon.ws_max_message_size`; the connection is closed.
+    PayloadTooLarge,
+    /// Message parsed but failed semantic checks (see `validate`); it was ignored.
+    ValidationFailed,
+    /// Hook event rejected by the daemon's `hook_policy`; state was not updated.
+    PolicyViolation,
+    /// Hook event over `daemon.hook_rate_limit`; state was not updated.
+    RateLimited,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ErrorMessage {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+/// `GET /status` response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DaemonStatus {
+    pub daemon_version: String,
+    pub protocol: u32,
+    pub uptime_secs: u64,
+    pub client_count: u32,
+    pub session_count: u32,
+    pub hooks_mode: HooksMode,
+    /// Largest WebSocket message (bytes) the daemon will parse.
+    pub ws_max_message_size: usize,
+    /// Prompt dispatched most recently, if its session hasn't ended.
+    #[serde(default)]
+    pub last_dispatched: Option<String>,
+    /// Session that prompt went to, when one could be resolved.
+    #[serde(default)]
+    pub last_dispatched_session_id: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct DebugDump {
+    /// Daemon state as pretty-printed JSON.
+    pub state_json: String,
+    /// Effective (merged) config as pretty-printed JSON.
+    pub config_json: String,
+    pub metrics: MetricsSnapshot,
+    /// Unix timestamp (seconds) when the dump was taken.
+    pub timestamp: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct MetricsSnapshot {
+    pub session_count: u32,
+    pub client_count: u32,
+    /// Total prompt dispatches since startup.
+    pub dispatch_count: u32,
+    pub uptime_secs: u64,
+}
+
 // ---------------------------------------------------------------------------
 // Render model (daemon → device)
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RenderModel {
     pub agent_state: AgentState,
     pub armed: Option<ArmedPrompt>,
@@ -272,9 +757,33 @@ pub struct RenderModel {
     pub page_count: usize,
     /// Hook integration status.
     pub hooks_mode: HooksMode,
+    /// Seconds without hook events while `hooks_mode` is `absent` (since the
+    /// daemon started, if none ever arrived) or `stale`; `None` otherwise.
+    #[serde(default)]
+    pub hooks_stale_secs: Option<u64>,
+    /// One-line status for the device, e.g. `"running | tool: Bash"`.
+    #[serde(default)]
+    pub status_line: Option<String>,
+    /// What Claude is asking permission for (when `waiting_permission`).
+    #[serde(default)]
+    pub permission_description: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+/// One page of keys; the rest of the last `RenderModel` still applies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PageRender {
+    pub page_index: usize,
+    pub page_count: usize,
+    pub page_name: String,
+    /// Longer description of the page, for a status display on navigation.
+    #[serde(default)]
+    pub page_description: Option<String>,
+    pub slots: Vec<KeypadSlotRender>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct ArmedPrompt {
     pub prompt_id: String,
     pub label: String,
@@ -284,13 +793,15 @@ pub struct ArmedPrompt {
     pub command: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct KeypadRender {
-    /// What to show on each of the 9 LCD keys.
+    /// What to show on each LCD key (9 on the default 3×3 keypad).
     pub slots: Vec<KeypadSlotRender>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct KeypadSlotRender {
     pub slot: u8,
     pub prompt_id: String,
@@ -298,63 +809,125 @@ pub struct KeypadSlotRender {
     #[serde(default)]
     pub sublabel: Option<String>,
     pub armed: bool,
+    /// Progress (0.0–1.0) of a subtask started from this slot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub progress: Option<f32>,
+    /// Unix time (seconds) this slot's prompt was last dispatched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_dispatched_at: Option<u64>,
 }
 
 // ---------------------------------------------------------------------------
 // VS Code commands
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct VscodeCommand {
     pub kind: VscodeCommandKind,
     pub target: TerminalTarget,
     pub payload: serde_json::Value,
+    /// Assigned by the daemon when it sends the command; the extension
+    /// echoes it in `command_ack`. 0 means unassigned.
+    #[serde(default)]
+    pub command_id: u64,
 }
 
 impl VscodeCommand {
+    /// Start a command for `target`; pick the kind by passing a typed payload.
+    pub fn builder(target: TerminalTarget) -> VscodeCommandBuilder {
+        VscodeCommandBuilder { target }
+    }
+
     pub fn send_text(target: TerminalTarget, text: &str, add_newline: bool) -> Self {
-        Self {
-            kind: VscodeCommandKind::SendText,
-            target,
-            payload: serde_json::json!({
-                "text": text,
-                "add_newline": add_newline,
-            }),
-        }
+        Self::builder(target).payload(SendTextPayload {
+            text: text.to_string(),
+            add_newline,
+        })
     }
 
     pub fn focus_terminal(target: TerminalTarget, direction: i32) -> Self {
-        Self {
-            kind: VscodeCommandKind::FocusTerminal,
-            target,
-            payload: serde_json::json!({
-                "direction": direction,
-            }),
-        }
+        Self::builder(target).payload(FocusTerminalPayload { direction })
     }
 
     pub fn scroll_terminal(target: TerminalTarget, delta: i32, unit: TerminalScrollUnit) -> Self {
-        Self {
-            kind: VscodeCommandKind::ScrollTerminal,
-            target,
-            payload: serde_json::json!({
-                "delta": delta,
-                "unit": unit,
-            }),
-        }
+        Self::builder(target).payload(ScrollTerminalPayload { delta, unit })
     }
 
     pub fn open_uri(uri: &str) -> Self {
-        Self {
-            kind: VscodeCommandKind::OpenUri,
-            target: TerminalTarget::Active,
-            payload: serde_json::json!({
-                "uri": uri,
-            }),
+        Self::builder(TerminalTarget::Active).payload(OpenUriPayload {
+            uri: uri.to_string(),
+        })
+    }
+
+    /// The payload as a typed struct, or `None` if it doesn't have that shape.
+    pub fn payload_as<T: serde::de::DeserializeOwned>(&self) -> Option<T> {
+        T::deserialize(&self.payload).ok()
+    }
+}
+
+/// Builds a [`VscodeCommand`] from a typed payload; see [`VscodeCommand::builder`].
+#[derive(Debug, Clone)]
+pub struct VscodeCommandBuilder {
+    target: TerminalTarget,
+}
+
+impl VscodeCommandBuilder {
+    /// Finish the command; its kind comes from the payload type.
+    pub fn payload<P: VscodeCommandPayload>(self, payload: P) -> VscodeCommand {
+        VscodeCommand {
+            kind: P::KIND,
+            target: self.target,
+            payload: serde_json::to_value(payload).expect("payload structs serialize"),
+            command_id: 0,
         }
     }
 }
 
+/// A typed `VscodeCommand::payload` for one [`VscodeCommandKind`].
+pub trait VscodeCommandPayload: Serialize {
+    const KIND: VscodeCommandKind;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SendTextPayload {
+    pub text: String,
+    pub add_newline: bool,
+}
+
+impl VscodeCommandPayload for SendTextPayload {
+    const KIND: VscodeCommandKind = VscodeCommandKind::SendText;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FocusTerminalPayload {
+    /// Terminals to move by; negative moves back.
+    pub direction: i32,
+}
+
+impl VscodeCommandPayload for FocusTerminalPayload {
+    const KIND: VscodeCommandKind = VscodeCommandKind::FocusTerminal;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScrollTerminalPayload {
+    pub delta: i32,
+    pub unit: TerminalScrollUnit,
+}
+
+impl VscodeCommandPayload for ScrollTerminalPayload {
+    const KIND: VscodeCommandKind = VscodeCommandKind::ScrollTerminal;
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OpenUriPayload {
+    pub uri: String,
+}
+
+impl VscodeCommandPayload for OpenUriPayload {
+    const KIND: VscodeCommandKind = VscodeCommandKind::OpenUri;
+}
+
 // ---------------------------------------------------------------------------
 // Hook decision output types (for runbook-hooks stdout)
 // ---------------------------------------------------------------------------
@@ -363,13 +936,15 @@ impl VscodeCommand {
 ///
 /// Claude Code expects `hookSpecificOutput.hookEventName = "PreToolUse"` with
 /// `permissionDecision` ∈ {"allow", "deny", "ask"}.
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PreToolUseDecisionOutput {
     #[serde(rename = "hookSpecificOutput")]
     pub hook_specific_output: PreToolUseHookOutput,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct PreToolUseHookOutput {
     #[serde(rename = "hookEventName")]
     pub hook_event_name: String,
@@ -384,37 +959,73 @@ pub struct PreToolUseHookOutput {
 }
 
 impl PreToolUseDecisionOutput {
-    pub fn deny(reason: &str) -> Self {
+    fn decision(decision: &str, reason: Option<&str>, context: Option<&str>) -> Self {
         Self {
             hook_specific_output: PreToolUseHookOutput {
                 hook_event_name: "PreToolUse".to_string(),
-                permission_decision: "deny".to_string(),
-                permission_decision_reason: Some(reason.to_string()),
-                additional_context: None,
+                permission_decision: decision.to_string(),
+                permission_decision_reason: reason.map(|s| s.to_string()),
+                additional_context: context.map(|s| s.to_string()),
             },
         }
     }
 
+    pub fn deny(reason: &str) -> Self {
+        Self::decision("deny", Some(reason), None)
+    }
+
+    /// Deny with extra context, shown in Claude Code's permission prompt.
+    pub fn deny_with_context(reason: &str, context: &str) -> Self {
+        Self::decision("deny", Some(reason), Some(context))
+    }
+
     pub fn allow(reason: Option<&str>) -> Self {
-        Self {
-            hook_specific_output: PreToolUseHookOutput {
-                hook_event_name: "PreToolUse".to_string(),
-                permission_decision: "allow".to_string(),
-                permission_decision_reason: reason.map(|s| s.to_string()),
-                additional_context: None,
-            },
+        Self::decision("allow", reason, None)
+    }
+
+    /// Allow with extra context, shown in Claude Code's permission prompt.
+    pub fn allow_with_context(reason: Option<&str>, context: &str) -> Self {
+        Self::decision("allow", reason, Some(context))
+    }
+
+    /// Parse decision JSON from another hook or tool (see the `TryFrom` impl).
+    pub fn parse_str(json: &str) -> anyhow::Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)?;
+        Self::try_from(&value)
+    }
+}
+
+/// Reads `hookSpecificOutput`, rejecting a `permissionDecision` other than
+/// "allow", "deny" or "ask".
+impl TryFrom<&serde_json::Value> for PreToolUseDecisionOutput {
+    type Error = anyhow::Error;
+
+    fn try_from(value: &serde_json::Value) -> anyhow::Result<Self> {
+        let hso = value
+            .get("hookSpecificOutput")
+            .ok_or_else(|| anyhow::anyhow!("missing hookSpecificOutput"))?;
+        let hook_specific_output = PreToolUseHookOutput::deserialize(hso)?;
+        match hook_specific_output.permission_decision.as_str() {
+            "allow" | "deny" | "ask" => Ok(Self {
+                hook_specific_output,
+            }),
+            other => anyhow::bail!(
+                "unknown permissionDecision '{other}' (expected allow, deny or ask)"
+            ),
         }
     }
 }
 
 /// Spec-compliant output for UserPromptSubmit hooks.
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct UserPromptSubmitOutput {
     #[serde(rename = "hookSpecificOutput")]
     pub hook_specific_output: UserPromptSubmitHookOutput,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct UserPromptSubmitHookOutput {
     #[serde(rename = "hookEventName")]
     pub hook_event_name: String,
@@ -438,21 +1049,155 @@ impl UserPromptSubmitOutput {
 // VS Code terminal telemetry
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TerminalsSnapshot {
     /// Ordered list of terminals as reported by VS Code.
     pub terminals: Vec<TerminalInfo>,
     /// Index of the currently active terminal.
     pub active_index: usize,
+    /// When VS Code took the snapshot (ISO-8601 UTC, e.g. `2025-10-16T13:20:00.000Z`).
+    /// The daemon ignores a snapshot older than the last one it applied.
+    #[serde(default)]
+    pub timestamp: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct TerminalInfo {
     pub index: usize,
     pub name: String,
     /// Session tag if this terminal was launched via "Start Claude Session".
     #[serde(default)]
     pub session_tag: Option<String>,
+    /// `session_tag.is_some()`, so clients can filter without a null check.
+    /// The daemon recomputes it from `session_tag` on every snapshot.
+    #[serde(default)]
+    pub is_claude_session: bool,
+}
+
+/// Progress of a subtask started from a keypad slot, reported by VS Code.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SlotProgress {
+    pub prompt_id: String,
+    /// 0.0–1.0; the daemon clamps out-of-range values.
+    pub progress: f32,
+}
+
+/// Reply to a `vscode_command`, matched by its `command_id`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct CommandAck {
+    pub command_id: u64,
+    pub success: bool,
+    /// Why the command failed, when `success` is false.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Liveness probe; cheaper than waiting for a `Render`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HealthCheck {
+    /// Echoed back in `HealthAck` so callers can match replies.
+    pub request_id: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct HealthAck {
+    pub request_id: u64,
+    pub uptime_secs: u64,
+    pub session_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct Heartbeat {
+    /// Daemon wall clock, Unix seconds; lets clients spot clock skew.
+    pub ts: u64,
+}
+
+/// Overlay shown for `duration_ms`, after which the client goes back to the
+/// last render.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ToastMessage {
+    pub message: String,
+    pub duration_ms: u32,
+    #[serde(default)]
+    pub severity: NoticeSeverity,
+}
+
+/// A prompt the user may want to arm next.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PromptSuggestion {
+    pub prompt_id: String,
+    /// Why it was suggested, for display.
+    pub reason: String,
+    /// 0.0–1.0; configured suggestions are 1.0.
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct ConfigError {
+    /// Offending field, e.g. `keypad.pages[0]`; empty when the error isn't
+    /// about one field (unreadable or unparsable file).
+    pub field_path: String,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct PageChangedEvent {
+    pub from_page: usize,
+    pub to_page: usize,
+    pub from_name: String,
+    pub to_name: String,
+    /// `prev` or `next`, flattened like `page_nav`; absent for a jump
+    /// (hotkey, page name, `jump_to`, auto-advance).
+    #[serde(flatten)]
+    #[cfg_attr(feature = "schemars", schemars(schema_with = "page_step_schema"))]
+    pub direction: Option<PageDirection>,
+}
+
+/// Schema for [`PageChangedEvent::direction`]: an optional `prev`/`next`.
+/// Derived, the flattened `PageDirection` variants would make it required.
+#[cfg(feature = "schemars")]
+fn page_step_schema(_: &mut schemars::gen::SchemaGenerator) -> schemars::schema::Schema {
+    serde_json::from_value(serde_json::json!({
+        "type": "object",
+        "properties": {
+            "direction": { "type": "string", "enum": ["prev", "next"] }
+        }
+    }))
+    .expect("static schema is valid")
+}
+
+/// Transient key feedback; the client restores the key after `duration_ms`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct SlotHighlight {
+    /// Index into the current page's keys, as in `KeypadSlotRender::slot`.
+    pub slot: u8,
+    pub color_rgb: [u8; 3],
+    pub duration_ms: u32,
+}
+
+/// One session's state transition, for clients that animate transitions
+/// instead of diffing renders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+pub struct AgentStateChange {
+    #[serde(default)]
+    pub session_id: Option<String>,
+    pub previous: AgentState,
+    pub current: AgentState,
+    /// The hook event that caused it, e.g. `"UserPromptSubmit"`.
+    pub hook_trigger: String,
 }
 
 // ---------------------------------------------------------------------------
@@ -467,10 +1212,8 @@ mod tests {
     fn round_trip_client_to_daemon() {
         let messages: Vec<ClientToDaemon> = vec![
             ClientToDaemon::Hello(Hello {
-                client: ClientKind::Logi,
-                protocol: PROTOCOL_VERSION,
-                version: "0.1.0".to_string(),
                 capabilities: vec!["keypad".to_string()],
+                ..Hello::default()
             }),
             ClientToDaemon::KeypadPress(KeypadPress {
                 prompt_id: "prep_pr".to_string(),
@@ -485,21 +1228,62 @@ mod tests {
             ClientToDaemon::PageNav(PageNav {
                 direction: PageDirection::Next,
             }),
+            ClientToDaemon::PageNav(PageNav {
+                direction: PageDirection::JumpTo { index: 4 },
+            }),
+            ClientToDaemon::GestureInput(GestureInput {
+                direction: GestureDirection::SwipeUp,
+                distance: 3,
+            }),
+            ClientToDaemon::PageJump(PageJump {
+                hotkey: "p2".to_string(),
+            }),
+            ClientToDaemon::Macro(MacroRequest {
+                steps: vec![MacroStep {
+                    prompt_id: "prep_pr".to_string(),
+                }],
+            }),
+            ClientToDaemon::ExportRequest(ExportRequest {
+                session_id: Some("sess-abc123".to_string()),
+                format: ExportFormat::Markdown,
+            }),
             ClientToDaemon::HookEvent(HookEvent {
                 hook: "UserPromptSubmit".to_string(),
-                matcher: None,
                 session_id: Some("sess-abc123".to_string()),
                 session_tag: Some("tag-001".to_string()),
                 payload: serde_json::json!({"prompt": "do stuff"}),
+                ..HookEvent::default()
             }),
             ClientToDaemon::TerminalsSnapshot(TerminalsSnapshot {
                 terminals: vec![TerminalInfo {
                     index: 0,
                     name: "Claude".to_string(),
                     session_tag: Some("tag-001".to_string()),
+                    is_claude_session: true,
                 }],
                 active_index: 0,
+                timestamp: Some("2025-10-16T13:20:00.000Z".to_string()),
+            }),
+            ClientToDaemon::SlotProgress(SlotProgress {
+                prompt_id: "prep_pr".to_string(),
+                progress: 0.5,
+            }),
+            ClientToDaemon::TagSelect(TagSelect {
+                session_tag: "tag-001".to_string(),
             }),
+            ClientToDaemon::CommandAck(CommandAck {
+                command_id: 1,
+                success: false,
+                error: Some("no terminal at index 3".to_string()),
+            }),
+            ClientToDaemon::Reconfigure(ReconfigureRequest {
+                dial_mode: Some(DialMode::VscodeTerminalScroll),
+                label_max_chars: Some(8),
+            }),
+            ClientToDaemon::SetFocusedSession(SetFocusedSession {
+                session_id: "sess-abc123".to_string(),
+            }),
+            ClientToDaemon::HealthCheck(HealthCheck { request_id: 7 }),
         ];
 
         for msg in &messages {
@@ -510,12 +1294,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_defaults_use_current_protocol() {
+        assert_eq!(Hello::default().protocol, PROTOCOL_VERSION);
+        assert_eq!(Hello::default().client, ClientKind::Logi);
+        assert_eq!(HelloAck::default().protocol, PROTOCOL_VERSION);
+        assert!(HookEvent::default().payload.is_null());
+    }
+
     #[test]
     fn round_trip_daemon_to_client() {
         let messages: Vec<DaemonToClient> = vec![
             DaemonToClient::Hello(HelloAck {
-                protocol: PROTOCOL_VERSION,
-                daemon_version: "0.1.0".to_string(),
+                capabilities: vec!["hooks".to_string(), "sessions".to_string()],
+                ..HelloAck::default()
             }),
             DaemonToClient::Render(RenderModel {
                 agent_state: AgentState::Idle,
@@ -532,15 +1324,127 @@ mod tests {
                         label: "PREP PR".to_string(),
                         sublabel: Some("receipts".to_string()),
                         armed: true,
+                        progress: Some(0.25),
+                        last_dispatched_at: Some(1760620800),
                     }],
                 },
                 page_index: 0,
                 page_count: 2,
                 hooks_mode: HooksMode::Active,
+                hooks_stale_secs: None,
+                status_line: Some("idle".to_string()),
+                permission_description: None,
             }),
             DaemonToClient::Notice(Notice {
                 message: "hello".to_string(),
             }),
+            DaemonToClient::Progress(ProgressUpdate {
+                session_id: "sess-abc123".to_string(),
+                tool_call_count: 4,
+                last_tool_name: Some("Bash".to_string()),
+            }),
+            DaemonToClient::PolicyBlock(PolicyBlock {
+                session_id: Some("sess-abc123".to_string()),
+                command: Some("rm -rf /".to_string()),
+            }),
+            DaemonToClient::SessionList(SessionList {
+                sessions: vec![SessionSummary {
+                    session_id: "sess-abc123".to_string(),
+                    session_tag: Some("tag-001".to_string()),
+                    agent_state: AgentState::Running,
+                    last_tool: Some("Bash".to_string()),
+                    tool_call_count: 4,
+                    blocked_count: 1,
+                    last_prompt: Some("prep a PR".to_string()),
+                    pending_permission: Some(PendingPermission {
+                        tool_name: "Bash".to_string(),
+                        description: "cargo publish".to_string(),
+                        waiting_secs: 12,
+                    }),
+                }],
+            }),
+            DaemonToClient::ConfigReloaded(ConfigSummary {
+                prompt_count: 12,
+                gate_count: 2,
+                pages: vec!["Core".to_string(), "Review".to_string()],
+                initial_page: 0,
+                policy_enabled: true,
+                page_summaries: vec![
+                    PageSummary {
+                        name: "Core".to_string(),
+                        description: Some("Core workflow prompts".to_string()),
+                    },
+                    PageSummary {
+                        name: "Review".to_string(),
+                        description: None,
+                    },
+                ],
+            }),
+            DaemonToClient::Debug(DebugDump {
+                state_json: "{\"page\": 0}".to_string(),
+                config_json: "{\"version\": 1}".to_string(),
+                metrics: MetricsSnapshot {
+                    session_count: 1,
+                    client_count: 2,
+                    dispatch_count: 3,
+                    uptime_secs: 60,
+                },
+                timestamp: "1760620800".to_string(),
+            }),
+            DaemonToClient::Error(ErrorMessage {
+                code: ErrorCode::PayloadTooLarge,
+                message: "message of 2097152 bytes exceeds limit of 1048576".to_string(),
+            }),
+            DaemonToClient::Banner(BannerMessage {
+                line1: "runbookd 0.1.0".to_string(),
+                line2: Some("hooks: active".to_string()),
+                duration_ms: 2000,
+            }),
+            DaemonToClient::HealthAck(HealthAck {
+                request_id: 7,
+                uptime_secs: 3600,
+                session_count: 2,
+            }),
+            DaemonToClient::Heartbeat(Heartbeat { ts: 1_760_000_000 }),
+            DaemonToClient::RenderPage(PageRender {
+                page_index: 1,
+                page_count: 2,
+                page_name: "review".to_string(),
+                page_description: Some("Review and merge".to_string()),
+                slots: vec![],
+            }),
+            DaemonToClient::SlotHighlight(SlotHighlight {
+                slot: 4,
+                color_rgb: [0, 255, 0],
+                duration_ms: 500,
+            }),
+            DaemonToClient::AgentStateChange(AgentStateChange {
+                session_id: None,
+                previous: AgentState::Idle,
+                current: AgentState::Running,
+                hook_trigger: "UserPromptSubmit".to_string(),
+            }),
+            DaemonToClient::Toast(ToastMessage {
+                message: "macro step 'nope' has no command; skipped".to_string(),
+                duration_ms: 3000,
+                severity: NoticeSeverity::Warning,
+            }),
+            DaemonToClient::PromptSuggestion(PromptSuggestion {
+                prompt_id: "run_gates".to_string(),
+                reason: "usually follows prep_pr".to_string(),
+                confidence: 1.0,
+            }),
+            DaemonToClient::ConfigError(ConfigError {
+                field_path: "keypad.grid_size".to_string(),
+                message: "keypad.grid_size must be at least 1x1. Got 0x3.".to_string(),
+            }),
+            DaemonToClient::PageChanged(PageChangedEvent {
+                from_page: 0,
+                to_page: 2,
+                from_name: "core".to_string(),
+                to_name: "ship".to_string(),
+                direction: None,
+            }),
         ];
 
         for msg in &messages {
@@ -551,6 +1455,196 @@ mod tests {
         }
     }
 
+    /// `type` discriminators of a tagged enum's schema, in declaration order.
+    #[cfg(feature = "schemars")]
+    fn schema_tags(schema: schemars::schema::RootSchema) -> Vec<String> {
+        let schema = serde_json::to_value(schema).unwrap();
+        schema["oneOf"]
+            .as_array()
+            .expect("tagged enum schema is a oneOf")
+            .iter()
+            .map(|variant| {
+                assert!(
+                    variant["required"].as_array().unwrap().contains(&"type".into()),
+                    "variant without a required type tag: {variant}"
+                );
+                variant["properties"]["type"]["enum"][0]
+                    .as_str()
+                    .unwrap()
+                    .to_string()
+            })
+            .collect()
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn client_schema_tags_every_variant() {
+        assert_eq!(
+            schema_tags(schemars::schema_for!(ClientToDaemon)),
+            [
+                "hello",
+                "keypad_press",
+                "dialpad_button_press",
+                "adjustment",
+                "gesture_input",
+                "page_nav",
+                "page_jump",
+                "macro",
+                "export_request",
+                "hook_event",
+                "terminals_snapshot",
+                "slot_progress",
+                "tag_select",
+                "command_ack",
+                "reconfigure",
+                "set_focused_session",
+                "health_check",
+            ]
+        );
+    }
+
+    #[cfg(feature = "schemars")]
+    #[test]
+    fn daemon_schema_tags_every_variant() {
+        assert_eq!(
+            schema_tags(schemars::schema_for!(DaemonToClient)),
+            [
+                "hello",
+                "render",
+                "render_page",
+                "vscode_command",
+                "notice",
+                "progress",
+                "policy_block",
+                "session_list",
+                "config_reloaded",
+                "debug",
+                "error",
+                "banner",
+                "health_ack",
+                "heartbeat",
+                "slot_highlight",
+                "agent_state_change",
+                "toast",
+                "prompt_suggestion",
+                "config_error",
+                "page_changed",
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_reports_each_protocol_error() {
+        use crate::validate::ValidationError;
+
+        let err = ClientToDaemon::parse("{not json").unwrap_err();
+        assert!(matches!(err, ProtocolError::Deserialization(_)));
+        assert!(std::error::Error::source(&err).is_some());
+
+        let err = ClientToDaemon::parse(r#"{"type":"keypad_press","prompt_id":""}"#).unwrap_err();
+        assert!(matches!(err, ProtocolError::Validation(ValidationError::EmptyPromptId)));
+        assert_eq!(err.code(), ErrorCode::ValidationFailed);
+
+        let hello = r#"{"type":"hello","client":"logi","protocol":99,"version":"x"}"#;
+        let err = ClientToDaemon::parse(hello).unwrap_err();
+        assert!(matches!(
+            err,
+            ProtocolError::VersionMismatch {
+                expected: PROTOCOL_VERSION,
+                got: 99
+            }
+        ));
+        assert_eq!(err.to_string(), "protocol 99 not supported (expected 1)");
+
+        let ack = r#"{"type":"hello","protocol":2,"daemon_version":"x"}"#;
+        assert!(matches!(
+            DaemonToClient::parse(ack),
+            Err(ProtocolError::VersionMismatch { got: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn parse_accepts_valid_messages() {
+        let press = ClientToDaemon::parse(r#"{"type":"keypad_press","prompt_id":"prep_pr"}"#);
+        assert!(matches!(press, Ok(ClientToDaemon::KeypadPress(_))));
+        let beat = DaemonToClient::parse(r#"{"type":"heartbeat","ts":1}"#);
+        assert!(matches!(beat, Ok(DaemonToClient::Heartbeat(_))));
+    }
+
+    #[test]
+    fn vscode_command_payloads_round_trip() {
+        let target = || TerminalTarget::ByIndex(1);
+
+        let cmd = VscodeCommand::send_text(target(), "/runbook:prep-pr", true);
+        assert_eq!(cmd.kind, VscodeCommandKind::SendText);
+        assert_eq!(
+            cmd.payload,
+            serde_json::json!({ "text": "/runbook:prep-pr", "add_newline": true })
+        );
+        assert_eq!(
+            cmd.payload_as::<SendTextPayload>(),
+            Some(SendTextPayload {
+                text: "/runbook:prep-pr".to_string(),
+                add_newline: true,
+            })
+        );
+
+        let cmd = VscodeCommand::focus_terminal(target(), -1);
+        assert_eq!(cmd.kind, VscodeCommandKind::FocusTerminal);
+        assert_eq!(cmd.payload_as(), Some(FocusTerminalPayload { direction: -1 }));
+
+        let cmd = VscodeCommand::scroll_terminal(target(), 3, TerminalScrollUnit::Lines);
+        assert_eq!(cmd.payload, serde_json::json!({ "delta": 3, "unit": "lines" }));
+        assert_eq!(
+            cmd.payload_as(),
+            Some(ScrollTerminalPayload {
+                delta: 3,
+                unit: TerminalScrollUnit::Lines,
+            })
+        );
+
+        let cmd = VscodeCommand::open_uri("https://example.com");
+        assert_eq!(cmd.kind, VscodeCommandKind::OpenUri);
+        assert_eq!(cmd.target, TerminalTarget::Active);
+        assert_eq!(
+            cmd.payload_as(),
+            Some(OpenUriPayload {
+                uri: "https://example.com".to_string(),
+            })
+        );
+
+        // A payload of another shape doesn't parse.
+        assert_eq!(cmd.payload_as::<SendTextPayload>(), None);
+    }
+
+    #[test]
+    fn pre_tool_use_decision_parses_valid_decisions() {
+        for decision in ["allow", "deny", "ask"] {
+            let json = format!(
+                r#"{{"hookSpecificOutput": {{"hookEventName": "PreToolUse",
+                    "permissionDecision": "{decision}", "permissionDecisionReason": "why"}}}}"#
+            );
+            let out = PreToolUseDecisionOutput::parse_str(&json).unwrap();
+            let hso = &out.hook_specific_output;
+            assert_eq!(hso.permission_decision, decision);
+            assert_eq!(hso.permission_decision_reason.as_deref(), Some("why"));
+        }
+
+        let value = serde_json::to_value(PreToolUseDecisionOutput::deny("no")).unwrap();
+        let out = PreToolUseDecisionOutput::try_from(&value).unwrap();
+        assert_eq!(out.hook_specific_output.permission_decision, "deny");
+    }
+
+    #[test]
+    fn pre_tool_use_decision_rejects_unknown_decision() {
+        let json = r#"{"hookSpecificOutput": {"hookEventName": "PreToolUse",
+            "permissionDecision": "maybe"}}"#;
+        let err = PreToolUseDecisionOutput::parse_str(json).unwrap_err();
+        assert!(err.to_string().contains("unknown permissionDecision 'maybe'"), "{err}");
+
+        assert!(PreToolUseDecisionOutput::parse_str("{}").is_err());
+    }
+
     #[test]
     fn pre_tool_use_deny_output_matches_spec() {
         let out = PreToolUseDecisionOutput::deny("rm -rf is blocked by policy");
@@ -570,6 +1664,32 @@ mod tests {
         let v: serde_json::Value = serde_json::from_str(&json).unwrap();
 
         assert_eq!(v["hookSpecificOutput"]["permissionDecision"], "allow");
+        assert!(v["hookSpecificOutput"].get("additionalContext").is_none());
+    }
+
+    #[test]
+    fn pre_tool_use_deny_with_context_sets_additional_context() {
+        let out = PreToolUseDecisionOutput::deny_with_context(
+            "Blocked by Runbook policy: rm -rf /",
+            "This matches the policy pattern: rm -rf",
+        );
+        let v = serde_json::to_value(&out).unwrap();
+
+        let hso = &v["hookSpecificOutput"];
+        assert_eq!(hso["permissionDecision"], "deny");
+        assert_eq!(hso["permissionDecisionReason"], "Blocked by Runbook policy: rm -rf /");
+        assert_eq!(hso["additionalContext"], "This matches the policy pattern: rm -rf");
+    }
+
+    #[test]
+    fn pre_tool_use_allow_with_context_sets_additional_context() {
+        let out = PreToolUseDecisionOutput::allow_with_context(None, "No policy rule matched");
+        let v = serde_json::to_value(&out).unwrap();
+
+        let hso = &v["hookSpecificOutput"];
+        assert_eq!(hso["permissionDecision"], "allow");
+        assert!(hso.get("permissionDecisionReason").is_none());
+        assert_eq!(hso["additionalContext"], "No policy rule matched");
     }
 
     #[test]
@@ -590,6 +1710,52 @@ mod tests {
         assert_eq!(AgentState::default(), AgentState::Unknown);
     }
 
+    #[test]
+    fn agent_state_as_str_matches_serde() {
+        for state in [
+            AgentState::Unknown,
+            AgentState::WaitingPermission,
+            AgentState::Blocked,
+            AgentState::Sent,
+        ] {
+            let json = serde_json::to_value(state).unwrap();
+            assert_eq!(json.as_str(), Some(state.as_str()));
+        }
+    }
+
+    #[test]
+    fn gesture_directions_are_snake_case() {
+        let names: Vec<String> = [
+            GestureDirection::SwipeLeft,
+            GestureDirection::SwipeRight,
+            GestureDirection::SwipeUp,
+            GestureDirection::SwipeDown,
+            GestureDirection::TapCenter,
+        ]
+        .iter()
+        .map(|d| serde_json::to_value(d).unwrap().as_str().unwrap().to_string())
+        .collect();
+        assert_eq!(
+            names,
+            ["swipe_left", "swipe_right", "swipe_up", "swipe_down", "tap_center"]
+        );
+    }
+
+    #[test]
+    fn hooks_mode_degraded_carries_reason() {
+        let mode = HooksMode::Degraded {
+            reason: "events out of order".to_string(),
+        };
+        let json = serde_json::to_value(&mode).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({"degraded": {"reason": "events out of order"}})
+        );
+        assert_eq!(mode.as_str(), "degraded");
+        assert_eq!(serde_json::to_value(HooksMode::Active).unwrap(), "active");
+        assert_eq!(HooksMode::default(), HooksMode::Absent);
+    }
+
     #[test]
     fn terminal_target_by_index_serializes() {
         let target = TerminalTarget::ByIndex(3);
@@ -598,6 +1764,15 @@ mod tests {
         assert_eq!(parsed, target);
     }
 
+    #[test]
+    fn terminal_target_by_name_serializes() {
+        let target = TerminalTarget::ByName("Claude".to_string());
+        let json = serde_json::to_string(&target).unwrap();
+        assert_eq!(json, r#"{"by_name":"Claude"}"#);
+        let parsed: TerminalTarget = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, target);
+    }
+
     // -----------------------------------------------------------------------
     // Fixture round-trip tests — canonical JSON used across all repos
     // -----------------------------------------------------------------------
@@ -646,6 +1821,62 @@ mod tests {
         assert_fixture_roundtrip::<ClientToDaemon>("page_nav.json");
     }
 
+    #[test]
+    fn fixture_page_nav_jump_to() {
+        assert_fixture_roundtrip::<ClientToDaemon>("page_nav_jump_to.json");
+    }
+
+    #[test]
+    fn page_nav_direction_is_flattened() {
+        let jump: ClientToDaemon =
+            serde_json::from_str(r#"{"type":"page_nav","direction":"jump_to","index":4}"#).unwrap();
+        assert!(matches!(
+            jump,
+            ClientToDaemon::PageNav(PageNav {
+                direction: PageDirection::JumpTo { index: 4 }
+            })
+        ));
+        let next = ClientToDaemon::PageNav(PageNav {
+            direction: PageDirection::Next,
+        });
+        assert_eq!(
+            serde_json::to_string(&next).unwrap(),
+            r#"{"type":"page_nav","direction":"next"}"#
+        );
+    }
+
+    #[test]
+    fn fixture_gesture_input() {
+        assert_fixture_roundtrip::<ClientToDaemon>("gesture_input.json");
+    }
+
+    #[test]
+    fn fixture_page_jump() {
+        assert_fixture_roundtrip::<ClientToDaemon>("page_jump.json");
+    }
+
+    #[test]
+    fn fixture_macro() {
+        assert_fixture_roundtrip::<ClientToDaemon>("macro.json");
+    }
+
+    #[test]
+    fn fixture_export_request() {
+        assert_fixture_roundtrip::<ClientToDaemon>("export_request.json");
+    }
+
+    #[test]
+    fn export_request_defaults_to_active_session_and_plain_export() {
+        let msg: ClientToDaemon = serde_json::from_str(r#"{"type":"export_request"}"#).unwrap();
+        let ClientToDaemon::ExportRequest(req) = msg else {
+            panic!("expected export_request");
+        };
+        assert_eq!(req.session_id, None);
+        assert_eq!(req.format, ExportFormat::Default);
+        assert_eq!(req.format.command(), "/export");
+        assert_eq!(ExportFormat::Json.command(), "/export json");
+    }
+
     #[test]
     fn fixture_hook_event() {
         assert_fixture_roundtrip::<ClientToDaemon>("hook_event.json");
@@ -656,6 +1887,36 @@ mod tests {
         assert_fixture_roundtrip::<ClientToDaemon>("terminals_snapshot.json");
     }
 
+    #[test]
+    fn fixture_slot_progress() {
+        assert_fixture_roundtrip::<ClientToDaemon>("slot_progress.json");
+    }
+
+    #[test]
+    fn fixture_tag_select() {
+        assert_fixture_roundtrip::<ClientToDaemon>("tag_select.json");
+    }
+
+    #[test]
+    fn fixture_command_ack() {
+        assert_fixture_roundtrip::<ClientToDaemon>("command_ack.json");
+    }
+
+    #[test]
+    fn fixture_reconfigure() {
+        assert_fixture_roundtrip::<ClientToDaemon>("reconfigure.json");
+    }
+
+    #[test]
+    fn fixture_set_focused_session() {
+        assert_fixture_roundtrip::<ClientToDaemon>("set_focused_session.json");
+    }
+
+    #[test]
+    fn fixture_health_check() {
+        assert_fixture_roundtrip::<ClientToDaemon>("health_check.json");
+    }
+
     #[test]
     fn fixture_daemon_hello_ack() {
         assert_fixture_roundtrip::<DaemonToClient>("daemon_hello_ack.json");
@@ -670,4 +1931,110 @@ mod tests {
     fn fixture_vscode_command() {
         assert_fixture_roundtrip::<DaemonToClient>("vscode_command.json");
     }
+
+    #[test]
+    fn fixture_vscode_command_by_tag() {
+        assert_fixture_roundtrip::<DaemonToClient>("vscode_command_by_tag.json");
+    }
+
+    #[test]
+    fn fixture_progress() {
+        assert_fixture_roundtrip::<DaemonToClient>("progress.json");
+    }
+
+    #[test]
+    fn fixture_policy_block() {
+        assert_fixture_roundtrip::<DaemonToClient>("policy_block.json");
+    }
+
+    #[test]
+    fn fixture_session_list() {
+        assert_fixture_roundtrip::<DaemonToClient>("session_list.json");
+    }
+
+    #[test]
+    fn fixture_debug() {
+        assert_fixture_roundtrip::<DaemonToClient>("debug.json");
+    }
+
+    #[test]
+    fn fixture_banner() {
+        assert_fixture_roundtrip::<DaemonToClient>("banner.json");
+    }
+
+    #[test]
+    fn fixture_health_ack() {
+        assert_fixture_roundtrip::<DaemonToClient>("health_ack.json");
+    }
+
+    #[test]
+    fn fixture_heartbeat() {
+        assert_fixture_roundtrip::<DaemonToClient>("heartbeat.json");
+    }
+
+    #[test]
+    fn fixture_render_page() {
+        assert_fixture_roundtrip::<DaemonToClient>("render_page.json");
+    }
+
+    #[test]
+    fn fixture_slot_highlight() {
+        assert_fixture_roundtrip::<DaemonToClient>("slot_highlight.json");
+    }
+
+    #[test]
+    fn fixture_agent_state_change() {
+        assert_fixture_roundtrip::<DaemonToClient>("agent_state_change.json");
+    }
+
+    #[test]
+    fn fixture_toast() {
+        assert_fixture_roundtrip::<DaemonToClient>("toast.json");
+    }
+
+    #[test]
+    fn fixture_prompt_suggestion() {
+        assert_fixture_roundtrip::<DaemonToClient>("prompt_suggestion.json");
+    }
+
+    #[test]
+    fn fixture_config_error() {
+        assert_fixture_roundtrip::<DaemonToClient>("config_error.json");
+    }
+
+    #[test]
+    fn fixture_page_changed() {
+        assert_fixture_roundtrip::<DaemonToClient>("page_changed.json");
+    }
+
+    #[test]
+    fn page_changed_direction_is_optional() {
+        let next = DaemonToClient::PageChanged(PageChangedEvent {
+            from_page: 0,
+            to_page: 1,
+            from_name: "core".to_string(),
+            to_name: "review".to_string(),
+            direction: Some(PageDirection::Next),
+        });
+        let json = serde_json::to_value(&next).unwrap();
+        assert_eq!(json["direction"], "next");
+
+        let jump = r#"{"type":"page_changed","from_page":1,"to_page":0,"from_name":"review","to_name":"core"}"#;
+        let DaemonToClient::PageChanged(ev) = serde_json::from_str(jump).unwrap() else {
+            panic!("expected page_changed");
+        };
+        assert_eq!(ev.direction, None);
+        let json = serde_json::to_value(DaemonToClient::PageChanged(ev)).unwrap();
+        assert!(json.get("direction").is_none());
+    }
+
+    #[test]
+    fn fixture_error() {
+        assert_fixture_roundtrip::<DaemonToClient>("error.json");
+    }
+
+    #[test]
+    fn fixture_config_reloaded() {
+        assert_fixture_roundtrip::<DaemonToClient>("config_reloaded.json");
+    }
 }
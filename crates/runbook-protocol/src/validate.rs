@@ -0,0 +1,162 @@
+//! Semantic validation of incoming client messages.
+//!
+//! Serde guarantees shape; these checks catch values that are well-formed
+//! JSON but meaningless to the daemon (empty ids, zero deltas, wrong protocol).
+
+use std::fmt;
+
+use crate::{ClientToDaemon, ErrorCode, PROTOCOL_VERSION};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ValidationError {
+    /// Raw message exceeds the configured size limit.
+    TooLarge {
+        len: usize,
+        limit: usize,
+    },
+    /// `hello.protocol` differs from [`PROTOCOL_VERSION`].
+    ProtocolMismatch {
+        expected: u32,
+        got: u32,
+    },
+    EmptyPromptId,
+    ZeroDelta,
+    EmptyHookName,
+}
+
+impl ValidationError {
+    /// Wire error code reported to the client.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            Self::TooLarge { .. } => ErrorCode::PayloadTooLarge,
+            _ => ErrorCode::ValidationFailed,
+        }
+    }
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::TooLarge { len, limit } => {
+                write!(f, "message of {len} bytes exceeds limit of {limit}")
+            }
+            Self::ProtocolMismatch { expected, got } => {
+                write!(f, "protocol {got} not supported (expected {expected})")
+            }
+            Self::EmptyPromptId => f.write_str("keypad_press.prompt_id must not be empty"),
+            Self::ZeroDelta => f.write_str("adjustment.delta must not be zero"),
+            Self::EmptyHookName => f.write_str("hook_event.hook must not be empty"),
+        }
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Check a parsed client message before it reaches the reducer.
+pub fn validate_client_message(msg: &ClientToDaemon) -> Result<(), ValidationError> {
+    match msg {
+        ClientToDaemon::Hello(h) if h.protocol != PROTOCOL_VERSION => {
+            Err(ValidationError::ProtocolMismatch {
+                expected: PROTOCOL_VERSION,
+                got: h.protocol,
+            })
+        }
+        ClientToDaemon::KeypadPress(kp) if kp.prompt_id.is_empty() => {
+            Err(ValidationError::EmptyPromptId)
+        }
+        ClientToDaemon::Adjustment(adj) if adj.delta == 0 => Err(ValidationError::ZeroDelta),
+        ClientToDaemon::HookEvent(ev) if ev.hook.is_empty() => Err(ValidationError::EmptyHookName),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Adjustment, AdjustmentKind, Hello, HookEvent, KeypadPress, PageDirection, PageNav,
+    };
+
+    fn keypad(prompt_id: &str) -> ClientToDaemon {
+        ClientToDaemon::KeypadPress(KeypadPress {
+            prompt_id: prompt_id.to_string(),
+        })
+    }
+
+    fn adjustment(delta: i32) -> ClientToDaemon {
+        ClientToDaemon::Adjustment(Adjustment {
+            kind: AdjustmentKind::Dial,
+            delta,
+        })
+    }
+
+    fn hook(name: &str) -> ClientToDaemon {
+        ClientToDaemon::HookEvent(HookEvent {
+            hook: name.to_string(),
+            ..HookEvent::default()
+        })
+    }
+
+    #[test]
+    fn hello_protocol() {
+        let ok = ClientToDaemon::Hello(Hello::default());
+        assert_eq!(validate_client_message(&ok), Ok(()));
+
+        let bad = ClientToDaemon::Hello(Hello {
+            protocol: PROTOCOL_VERSION + 1,
+            ..Hello::default()
+        });
+        let err = validate_client_message(&bad).unwrap_err();
+        assert_eq!(
+            err,
+            ValidationError::ProtocolMismatch {
+                expected: PROTOCOL_VERSION,
+                got: PROTOCOL_VERSION + 1,
+            }
+        );
+        assert_eq!(err.code(), ErrorCode::ValidationFailed);
+    }
+
+    #[test]
+    fn keypad_prompt_id() {
+        assert_eq!(validate_client_message(&keypad("prep_pr")), Ok(()));
+        assert_eq!(
+            validate_client_message(&keypad("")),
+            Err(ValidationError::EmptyPromptId)
+        );
+    }
+
+    #[test]
+    fn adjustment_delta() {
+        assert_eq!(validate_client_message(&adjustment(-2)), Ok(()));
+        assert_eq!(
+            validate_client_message(&adjustment(0)),
+            Err(ValidationError::ZeroDelta)
+        );
+    }
+
+    #[test]
+    fn hook_name() {
+        assert_eq!(validate_client_message(&hook("Stop")), Ok(()));
+        assert_eq!(
+            validate_client_message(&hook("")),
+            Err(ValidationError::EmptyHookName)
+        );
+    }
+
+    #[test]
+    fn other_messages_pass() {
+        let nav = ClientToDaemon::PageNav(PageNav {
+            direction: PageDirection::Next,
+        });
+        assert_eq!(validate_client_message(&nav), Ok(()));
+    }
+
+    #[test]
+    fn errors_have_readable_messages() {
+        assert_eq!(
+            ValidationError::EmptyHookName.to_string(),
+            "hook_event.hook must not be empty"
+        );
+    }
+}
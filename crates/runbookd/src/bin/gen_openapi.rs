@@ -0,0 +1,13 @@
+use std::fs;
+use std::path::PathBuf;
+
+fn main() {
+    let schema_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("schema");
+    fs::create_dir_all(&schema_dir).expect("Failed to create schema directory");
+
+    let path = schema_dir.join("openapi.json");
+    let json = serde_json::to_string_pretty(&runbookd::openapi::spec())
+        .expect("Failed to serialize OpenAPI spec");
+    fs::write(&path, json).expect("Failed to write OpenAPI spec");
+    println!("Generated {}", path.display());
+}
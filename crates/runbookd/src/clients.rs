@@ -0,0 +1,431 @@
+//! Connection registry: one entry per live WebSocket client.
+//!
+//! The IO layer routes outbound messages through here so targeted side
+//! effects (e.g. VS Code commands) only reach clients of the right kind.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::sync::{mpsc, Mutex};
+use tracing::warn;
+
+use runbook_protocol::{ClientKind, DaemonToClient};
+
+/// Opaque per-connection identifier.
+pub type ClientId = u64;
+
+/// Outbound messages queued per client before further ones are dropped.
+pub const CLIENT_QUEUE_CAPACITY: usize = 256;
+
+/// Capabilities the daemon advertises in `HelloAck`.
+pub const DAEMON_CAPABILITIES: &[&str] = &["hooks", "sessions", "delta_render"];
+
+/// Opt-in features a client declared in `Hello.capabilities`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClientCapabilities {
+    /// Wants policy notifications (`policy_block`).
+    pub hooks: bool,
+    /// Wants `session_list` updates.
+    pub sessions: bool,
+    /// Understands partial render updates.
+    pub delta_render: bool,
+}
+
+impl ClientCapabilities {
+    /// Parse the capability strings from a client `hello`; unknown entries are ignored.
+    pub fn from_list(caps: &[String]) -> Self {
+        let has = |name: &str| caps.iter().any(|c| c == name);
+        Self {
+            hooks: has("hooks"),
+            sessions: has("sessions"),
+            delta_render: has("delta_render"),
+        }
+    }
+
+    /// Whether a message may be sent to a client with these capabilities.
+    pub fn allows(&self, msg: &DaemonToClient) -> bool {
+        match msg {
+            DaemonToClient::PolicyBlock(_) => self.hooks,
+            DaemonToClient::SessionList(_) => self.sessions,
+            DaemonToClient::RenderPage(_) => self.delta_render,
+            _ => true,
+        }
+    }
+}
+
+/// A connected client and its outbound queue.
+#[derive(Debug)]
+pub struct ClientEntry {
+    /// Set once the client sends `hello`; `None` until then.
+    pub kind: Option<ClientKind>,
+    /// Negotiated from `hello`; empty until then.
+    pub capabilities: ClientCapabilities,
+    /// Session this client renders, from `set_focused_session`; `None` shows
+    /// the shared session.
+    pub focused_session_id: Option<String>,
+    tx: mpsc::Sender<DaemonToClient>,
+}
+
+impl ClientEntry {
+    /// Queue a message if the client's capabilities allow it. A client that
+    /// isn't draining its queue misses the message rather than holding up
+    /// everyone else.
+    fn offer(&self, msg: &DaemonToClient) -> bool {
+        if !self.capabilities.allows(msg) {
+            return false;
+        }
+        match self.tx.try_send(msg.clone()) {
+            Ok(()) => true,
+            Err(TrySendError::Full(_)) => {
+                warn!(kind = ?self.kind, "client queue full; dropping message");
+                false
+            }
+            Err(TrySendError::Closed(_)) => false,
+        }
+    }
+
+    /// This client's render from `focused` if it has one there, else `shared`.
+    fn pick_render<'a>(
+        &self,
+        shared: &'a DaemonToClient,
+        focused: &'a HashMap<String, DaemonToClient>,
+    ) -> &'a DaemonToClient {
+        self.focused_session_id
+            .as_ref()
+            .and_then(|sid| focused.get(sid))
+            .unwrap_or(shared)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ClientRegistry {
+    next_id: ClientId,
+    clients: HashMap<ClientId, ClientEntry>,
+}
+
+impl ClientRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a new connection and return its id.
+    pub fn register(&mut self, tx: mpsc::Sender<DaemonToClient>) -> ClientId {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.clients.insert(
+            id,
+            ClientEntry {
+                kind: None,
+                capabilities: ClientCapabilities::default(),
+                focused_session_id: None,
+                tx,
+            },
+        );
+        id
+    }
+
+    pub fn unregister(&mut self, id: ClientId) -> Option<ClientEntry> {
+        self.clients.remove(&id)
+    }
+
+    pub fn get(&self, id: ClientId) -> Option<&ClientEntry> {
+        self.clients.get(&id)
+    }
+
+    pub fn get_mut(&mut self, id: ClientId) -> Option<&mut ClientEntry> {
+        self.clients.get_mut(&id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Send to a single client. Returns false if it is gone or didn't opt in.
+    pub fn send_to(&self, id: ClientId, msg: DaemonToClient) -> bool {
+        self.clients.get(&id).is_some_and(|c| c.offer(&msg))
+    }
+
+    /// Send to every connected client. Returns the number of recipients.
+    pub fn broadcast(&self, msg: &DaemonToClient) -> usize {
+        self.clients.values().filter(|c| c.offer(msg)).count()
+    }
+
+    /// Sessions some client has focused, to build their renders.
+    pub fn focused_sessions(&self) -> HashSet<String> {
+        self.clients
+            .values()
+            .filter_map(|c| c.focused_session_id.clone())
+            .collect()
+    }
+
+    /// Send each client its render: the one in `focused` for its focused
+    /// session, `shared` otherwise. Returns the number of recipients.
+    pub fn broadcast_render(
+        &self,
+        shared: &DaemonToClient,
+        focused: &HashMap<String, DaemonToClient>,
+    ) -> usize {
+        self.clients
+            .values()
+            .filter(|c| c.offer(c.pick_render(shared, focused)))
+            .count()
+    }
+
+    /// Like [`broadcast_render`](Self::broadcast_render), but only to
+    /// clients that identified as `kind`.
+    pub fn send_render_to_kind(
+        &self,
+        kind: ClientKind,
+        shared: &DaemonToClient,
+        focused: &HashMap<String, DaemonToClient>,
+    ) -> usize {
+        self.clients
+            .values()
+            .filter(|c| c.kind == Some(kind))
+            .filter(|c| c.offer(c.pick_render(shared, focused)))
+            .count()
+    }
+
+    /// Send `page` to clients that understand partial renders and a full
+    /// render (picked as in [`broadcast_render`](Self::broadcast_render)) to
+    /// everyone else. Returns the number of recipients.
+    pub fn broadcast_render_page(
+        &self,
+        page: &DaemonToClient,
+        full: &DaemonToClient,
+        focused: &HashMap<String, DaemonToClient>,
+    ) -> usize {
+        self.clients
+            .values()
+            .filter(|c| {
+                if c.capabilities.delta_render {
+                    c.offer(page)
+                } else {
+                    c.offer(c.pick_render(full, focused))
+                }
+            })
+            .count()
+    }
+
+    /// Send only to clients that identified as `kind`. Returns the number of recipients.
+    pub fn send_to_kind(&self, kind: ClientKind, msg: &DaemonToClient) -> usize {
+        self.clients
+            .values()
+            .filter(|c| c.kind == Some(kind))
+            .filter(|c| c.offer(msg))
+            .count()
+    }
+}
+
+/// Wait until every client has unregistered, or `timeout` elapses.
+///
+/// Returns true if the registry drained in time.
+pub async fn wait_for_drain(clients: &Mutex<ClientRegistry>, timeout: Duration) -> bool {
+    let drained = async {
+        while !clients.lock().await.is_empty() {
+            tokio::time::sleep(Duration::from_millis(25)).await;
+        }
+    };
+    tokio::time::timeout(timeout, drained).await.is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use runbook_protocol::{Notice, PageRender};
+
+    fn notice(text: &str) -> DaemonToClient {
+        DaemonToClient::Notice(Notice {
+            message: text.to_string(),
+        })
+    }
+
+    #[test]
+    fn send_to_kind_only_reaches_matching_clients() {
+        let mut reg = ClientRegistry::new();
+        let (logi_tx, mut logi_rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let (vscode_tx, mut vscode_rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let logi = reg.register(logi_tx);
+        let vscode = reg.register(vscode_tx);
+        reg.get_mut(logi).unwrap().kind = Some(ClientKind::Logi);
+        reg.get_mut(vscode).unwrap().kind = Some(ClientKind::Vscode);
+
+        assert_eq!(reg.send_to_kind(ClientKind::Logi, &notice("render")), 1);
+        assert!(logi_rx.try_recv().is_ok());
+        assert!(vscode_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn broadcast_reaches_all_clients() {
+        let mut reg = ClientRegistry::new();
+        let (a_tx, mut a_rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let (b_tx, mut b_rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        reg.register(a_tx);
+        reg.register(b_tx);
+
+        assert_eq!(reg.broadcast(&notice("hi")), 2);
+        assert!(a_rx.try_recv().is_ok());
+        assert!(b_rx.try_recv().is_ok());
+    }
+
+    #[test]
+    fn full_queue_drops_instead_of_blocking() {
+        let mut reg = ClientRegistry::new();
+        let (slow_tx, mut slow_rx) = mpsc::channel(1);
+        let (fast_tx, mut fast_rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        reg.register(slow_tx);
+        reg.register(fast_tx);
+
+        assert_eq!(reg.broadcast(&notice("first")), 2);
+        assert_eq!(reg.broadcast(&notice("second")), 1);
+        assert!(slow_rx.try_recv().is_ok());
+        assert!(slow_rx.try_recv().is_err());
+        assert!(fast_rx.try_recv().is_ok());
+        assert!(fast_rx.try_recv().is_ok());
+    }
+
+    #[test]
+    fn unregister_stops_delivery() {
+        let mut reg = ClientRegistry::new();
+        let (tx, _rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let id = reg.register(tx);
+        assert!(reg.unregister(id).is_some());
+        assert!(reg.unregister(id).is_none());
+        assert!(!reg.send_to(id, notice("gone")));
+    }
+
+    #[tokio::test]
+    async fn wait_for_drain_returns_once_clients_leave() {
+        let clients = std::sync::Arc::new(Mutex::new(ClientRegistry::new()));
+        let (tx, _rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let id = clients.lock().await.register(tx);
+
+        let leaver = std::sync::Arc::clone(&clients);
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            leaver.lock().await.unregister(id);
+        });
+
+        assert!(wait_for_drain(&clients, Duration::from_secs(2)).await);
+    }
+
+    #[tokio::test]
+    async fn wait_for_drain_times_out_with_lingering_client() {
+        let clients = Mutex::new(ClientRegistry::new());
+        let (tx, _rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        clients.lock().await.register(tx);
+
+        assert!(!wait_for_drain(&clients, Duration::from_millis(50)).await);
+    }
+
+    /// Register a mock client that completed `hello` with the given capabilities.
+    fn mock_client(
+        reg: &mut ClientRegistry,
+        caps: &[&str],
+    ) -> mpsc::Receiver<DaemonToClient> {
+        let (tx, rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let id = reg.register(tx);
+        let caps: Vec<String> = caps.iter().map(|c| c.to_string()).collect();
+        let entry = reg.get_mut(id).unwrap();
+        entry.kind = Some(ClientKind::Logi);
+        entry.capabilities = ClientCapabilities::from_list(&caps);
+        rx
+    }
+
+    #[test]
+    fn capabilities_parse_known_names() {
+        let caps = ClientCapabilities::from_list(&[
+            "keypad".to_string(),
+            "sessions".to_string(),
+            "delta_render".to_string(),
+        ]);
+        assert!(!caps.hooks);
+        assert!(caps.sessions);
+        assert!(caps.delta_render);
+    }
+
+    #[test]
+    fn every_advertised_capability_unlocks_a_message() {
+        use runbook_protocol::{PolicyBlock, SessionList};
+
+        let gated = [
+            DaemonToClient::PolicyBlock(PolicyBlock {
+                session_id: None,
+                command: None,
+            }),
+            DaemonToClient::SessionList(SessionList { sessions: vec![] }),
+            DaemonToClient::RenderPage(PageRender {
+                page_index: 0,
+                page_count: 1,
+                page_name: "core".to_string(),
+                page_description: None,
+                slots: vec![],
+            }),
+        ];
+        // A capability is only advertised once the daemon sends what it gates.
+        let none = ClientCapabilities::default();
+        for cap in DAEMON_CAPABILITIES {
+            let caps = ClientCapabilities::from_list(&[cap.to_string()]);
+            assert!(
+                gated.iter().any(|msg| caps.allows(msg) && !none.allows(msg)),
+                "{cap} is advertised but gates nothing"
+            );
+        }
+    }
+
+    #[test]
+    fn render_page_only_reaches_delta_render_clients() {
+        let mut reg = ClientRegistry::new();
+        let mut plain = mock_client(&mut reg, &["keypad"]);
+        let mut delta = mock_client(&mut reg, &["delta_render"]);
+
+        let page = DaemonToClient::RenderPage(PageRender {
+            page_index: 1,
+            page_count: 2,
+            page_name: "review".to_string(),
+            page_description: None,
+            slots: vec![],
+        });
+        assert_eq!(reg.broadcast(&page), 1);
+        assert!(plain.try_recv().is_err());
+        assert!(matches!(delta.try_recv(), Ok(DaemonToClient::RenderPage(_))));
+
+        // Clients without the capability get the fallback instead.
+        let focused = HashMap::new();
+        assert_eq!(reg.broadcast_render_page(&page, &notice("full"), &focused), 2);
+        assert!(matches!(plain.try_recv(), Ok(DaemonToClient::Notice(_))));
+        assert!(matches!(delta.try_recv(), Ok(DaemonToClient::RenderPage(_))));
+    }
+
+    #[test]
+    fn focused_clients_get_their_session_render() {
+        let mut reg = ClientRegistry::new();
+        let mut shared = mock_client(&mut reg, &[]);
+        let (tx, mut pinned) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let id = reg.register(tx);
+        reg.get_mut(id).unwrap().focused_session_id = Some("s2".to_string());
+        let (tx, mut stale) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let id = reg.register(tx);
+        reg.get_mut(id).unwrap().focused_session_id = Some("gone".to_string());
+
+        let focused_ids = reg.focused_sessions();
+        assert_eq!(focused_ids.len(), 2);
+        assert!(focused_ids.contains("s2"));
+
+        let focused = HashMap::from([("s2".to_string(), notice("s2"))]);
+        assert_eq!(reg.broadcast_render(&notice("shared"), &focused), 3);
+        let text = |rx: &mut mpsc::Receiver<DaemonToClient>| match rx.try_recv() {
+            Ok(DaemonToClient::Notice(n)) => n.message,
+            other => panic!("expected notice, got {other:?}"),
+        };
+        assert_eq!(text(&mut shared), "shared");
+        assert_eq!(text(&mut pinned), "s2");
+        // No render for its session: falls back to the shared one.
+        assert_eq!(text(&mut stale), "shared");
+    }
+}
@@ -1,11 +1,11 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 
-use runbook_protocol::{ArmStyle, DialMode};
+use runbook_protocol::{ArmStyle, ConfigError, ConfigSummary, DialMode, HooksMode, PageSummary};
 
 /// Top-level config loaded from `runbook.yaml`.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RunbookConfig {
     /// Schema version (must be 1).
     #[serde(default = "default_version")]
@@ -23,6 +23,8 @@ pub struct RunbookConfig {
     #[serde(default)]
     pub defaults: DefaultsConfig,
 
+    /// Optional only so override files can omit it; `validate()` requires pages.
+    #[serde(default)]
     pub keypad: KeypadConfig,
 
     /// Named prompt templates, keyed by prompt_id.
@@ -35,6 +37,17 @@ pub struct RunbookConfig {
 
     #[serde(default)]
     pub policy: PolicyConfig,
+
+    #[serde(default)]
+    pub hook_policy: HookPolicyConfig,
+
+    /// What `${VAR}` tokens expanded to; redacted from debug dumps.
+    #[serde(skip)]
+    pub env_values: Vec<String>,
+
+    /// Dotted paths of the keys this layer's YAML sets (see [`Self::from_yaml`]).
+    #[serde(skip)]
+    set_keys: HashSet<String>,
 }
 
 fn default_version() -> u32 {
@@ -45,20 +58,93 @@ fn default_version() -> u32 {
 // Daemon
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DaemonConfig {
     #[serde(default = "default_listen")]
     pub listen: String,
+
+    /// TCP accept backlog for the listener.
+    #[serde(default = "default_listen_backlog")]
+    pub listen_backlog: u32,
+
+    /// How long to wait for WebSocket clients to disconnect on shutdown.
+    #[serde(default = "default_shutdown_timeout_secs")]
+    pub shutdown_timeout_secs: u64,
+
+    /// Largest WebSocket message (bytes) accepted from a client, enforced as
+    /// frames are read. Bigger messages get a `payload_too_large` error and
+    /// the connection is closed. Applies to connections opened after a reload.
+    #[serde(default = "default_ws_max_message_size")]
+    pub ws_max_message_size: usize,
+
+    /// How long the Logi welcome banner stays on screen.
+    #[serde(default = "default_banner_duration_ms")]
+    pub banner_duration_ms: u32,
+
+    /// Named pipe to read hook events from (Unix only), in addition to `POST /hook`.
+    #[serde(default)]
+    pub fifo: Option<String>,
+
+    /// Enables `POST /debug/dump` (full state + config dump). Off by default.
+    #[serde(default)]
+    pub debug_enabled: bool,
+
+    /// Seconds between `heartbeat` broadcasts; 0 disables them.
+    #[serde(default = "default_heartbeat_secs")]
+    pub heartbeat_secs: u64,
+
+    /// Sessions with no hook event for this many seconds are dropped (checked
+    /// every minute); 0 keeps them until `SessionEnd`.
+    #[serde(default = "default_session_ttl_secs")]
+    pub session_ttl_secs: u64,
+
+    /// Most `POST /hook` events accepted per second; more get a 429. Unset
+    /// means unlimited.
+    #[serde(default)]
+    pub hook_rate_limit: Option<u32>,
 }
 
 fn default_listen() -> String {
     "127.0.0.1:29381".to_string()
 }
 
+fn default_listen_backlog() -> u32 {
+    128
+}
+
+fn default_shutdown_timeout_secs() -> u64 {
+    5
+}
+
+fn default_ws_max_message_size() -> usize {
+    1024 * 1024
+}
+
+fn default_banner_duration_ms() -> u32 {
+    2000
+}
+
+fn default_heartbeat_secs() -> u64 {
+    10
+}
+
+fn default_session_ttl_secs() -> u64 {
+    600
+}
+
 impl Default for DaemonConfig {
     fn default() -> Self {
         Self {
             listen: default_listen(),
+            listen_backlog: default_listen_backlog(),
+            shutdown_timeout_secs: default_shutdown_timeout_secs(),
+            ws_max_message_size: default_ws_max_message_size(),
+            banner_duration_ms: default_banner_duration_ms(),
+            fifo: None,
+            debug_enabled: false,
+            heartbeat_secs: default_heartbeat_secs(),
+            session_ttl_secs: default_session_ttl_secs(),
+            hook_rate_limit: None,
         }
     }
 }
@@ -67,7 +153,7 @@ impl Default for DaemonConfig {
 // Tooling
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolingConfig {
     /// Which tool integration is primary: "claude_code" or "other".
     #[serde(default = "default_primary")]
@@ -76,6 +162,11 @@ pub struct ToolingConfig {
     /// Label shown on device when running in degraded (non-hook) mode.
     #[serde(default = "default_degraded_label")]
     pub degraded_mode_label: String,
+
+    /// How long `runbook-hooks` waits for the daemon before giving up on an
+    /// event. Read by `runbook-hooks --config`; `--timeout-ms` overrides it.
+    #[serde(default = "default_hooks_timeout_ms")]
+    pub hooks_timeout_ms: u64,
 }
 
 fn default_primary() -> String {
@@ -86,11 +177,16 @@ fn default_degraded_label() -> String {
     "KEYSTROKE MODE".to_string()
 }
 
+fn default_hooks_timeout_ms() -> u64 {
+    250
+}
+
 impl Default for ToolingConfig {
     fn default() -> Self {
         Self {
             primary: default_primary(),
             degraded_mode_label: default_degraded_label(),
+            hooks_timeout_ms: default_hooks_timeout_ms(),
         }
     }
 }
@@ -99,17 +195,29 @@ impl Default for ToolingConfig {
 // Dial
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DialConfig {
     /// `os_scroll` (default) or `vscode_terminal_scroll`.
     #[serde(default)]
     pub mode: DialMode,
+
+    /// Lines scrolled per detent (0.1–5.0). Below 1.0 slows scrolling down.
+    #[serde(default = "default_dial_sensitivity")]
+    pub sensitivity: f32,
 }
 
+fn default_dial_sensitivity() -> f32 {
+    1.0
+}
+
+/// Accepted range for `dial.sensitivity`.
+pub const DIAL_SENSITIVITY_RANGE: std::ops::RangeInclusive<f32> = 0.1..=5.0;
+
 impl Default for DialConfig {
     fn default() -> Self {
         Self {
             mode: DialMode::default(),
+            sensitivity: default_dial_sensitivity(),
         }
     }
 }
@@ -118,7 +226,7 @@ impl Default for DialConfig {
 // Defaults
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DefaultsConfig {
     #[serde(default)]
     pub arm_style: ArmStyle,
@@ -144,39 +252,96 @@ fn default_max_prefill_chars() -> usize {
     400
 }
 
-#[derive(Debug, Clone, Copy, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
 pub enum EscWhenPending {
+    #[default]
     CancelOnly,
     CancelAndPassthrough,
 }
 
-impl Default for EscWhenPending {
-    fn default() -> Self {
-        Self::CancelOnly
-    }
-}
-
 // ---------------------------------------------------------------------------
 // Keypad
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeypadConfig {
+    #[serde(default)]
     pub pages: Vec<KeypadPageConfig>,
 
     #[serde(default)]
     pub initial_page: usize,
+
+    /// Start on the page with this name instead; wins over `initial_page`.
+    /// `validate()` resolves it into `initial_page`.
+    #[serde(default)]
+    pub initial_page_by_name: Option<String>,
+
+    /// Max characters shown on an LCD key label before truncating with "…".
+    #[serde(default = "default_label_max_chars")]
+    pub label_max_chars: usize,
+
+    /// Keypad layout as `[rows, columns]`; every page needs `rows * columns` slots.
+    /// Unset means the 3×3 keypad.
+    #[serde(default)]
+    pub grid_size: Option<(u8, u8)>,
+}
+
+fn default_label_max_chars() -> usize {
+    8
+}
+
+/// Layout of the standard Logi keypad.
+const DEFAULT_GRID_SIZE: (u8, u8) = (3, 3);
+
+/// `keypad.pages[].description` must be shorter than this; it goes on a
+/// one-line status display.
+pub const PAGE_DESCRIPTION_CHAR_LIMIT: usize = 120;
+
+impl KeypadConfig {
+    /// `[rows, columns]` of the keypad, defaulting to 3×3.
+    pub fn grid(&self) -> (u8, u8) {
+        self.grid_size.unwrap_or(DEFAULT_GRID_SIZE)
+    }
+
+    /// Number of slots every page must define.
+    pub fn slot_count(&self) -> usize {
+        let (rows, cols) = self.grid();
+        rows as usize * cols as usize
+    }
 }
 
-#[derive(Debug, Clone, Deserialize)]
+impl Default for KeypadConfig {
+    fn default() -> Self {
+        Self {
+            pages: Vec::new(),
+            initial_page: 0,
+            initial_page_by_name: None,
+            label_max_chars: default_label_max_chars(),
+            grid_size: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeypadPageConfig {
     pub name: String,
+    /// Key a client sends in `page_jump` to show this page directly (e.g. `"p1"`).
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    /// Human-readable description (e.g. "Core workflow prompts"); shorter
+    /// than [`PAGE_DESCRIPTION_CHAR_LIMIT`] characters.
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Page (by name) to show when a `TaskCompleted` hook arrives while this
+    /// page is shown.
+    #[serde(default)]
+    pub on_complete_advance_to: Option<String>,
     pub slots: Vec<KeypadSlotConfig>,
 }
 
 /// A slot on the keypad. Exactly one of `prompt_id` or `gate` should be set.
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeypadSlotConfig {
     /// Which prompt this slot arms (references `prompts.<id>`).
     #[serde(default)]
@@ -191,7 +356,7 @@ pub struct KeypadSlotConfig {
 // Prompts
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PromptConfig {
     /// What to show on the LCD key.
     pub label: String,
@@ -204,6 +369,11 @@ pub struct PromptConfig {
     #[serde(default)]
     pub arm_style: Option<ArmStyle>,
 
+    /// Arm style while no hooks have been seen (`HooksMode::Absent`); `queue`
+    /// if unset.
+    #[serde(default)]
+    pub fallback_arm_style: Option<ArmStyle>,
+
     /// Claude Code slash command (used when tooling.primary == "claude_code").
     #[serde(default)]
     pub claude_command: Option<String>,
@@ -211,6 +381,16 @@ pub struct PromptConfig {
     /// Fallback text dispatched when hooks are not available.
     #[serde(default)]
     pub fallback_text: Option<String>,
+
+    /// With `arm_style: chain`: prompt ids dispatched in order after this
+    /// one, each when the previous task completes.
+    #[serde(default)]
+    pub chain: Vec<String>,
+
+    /// Prompt id suggested (`prompt_suggestion`) when a task completes after
+    /// this prompt was dispatched.
+    #[serde(default)]
+    pub suggests_next: Option<String>,
 }
 
 impl PromptConfig {
@@ -228,11 +408,35 @@ impl PromptConfig {
     }
 }
 
+/// Max characters of the command shown in a prompt summary line.
+pub const PROMPT_SUMMARY_COMMAND_CHARS: usize = 80;
+
+/// `{label} ({sublabel}) → {command}`, using the Claude command when set and
+/// truncating it to [`PROMPT_SUMMARY_COMMAND_CHARS`].
+impl std::fmt::Display for PromptConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.label)?;
+        if let Some(sublabel) = &self.sublabel {
+            write!(f, " ({sublabel})")?;
+        }
+        let command = self.effective_command(true).unwrap_or("(no command)");
+        if command.chars().count() > PROMPT_SUMMARY_COMMAND_CHARS {
+            let cut: String = command
+                .chars()
+                .take(PROMPT_SUMMARY_COMMAND_CHARS - 1)
+                .collect();
+            write!(f, " → {cut}…")
+        } else {
+            write!(f, " → {command}")
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Gates
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GateConfig {
     pub label: String,
 
@@ -241,36 +445,327 @@ pub struct GateConfig {
 
     /// Action to invoke (e.g. "open_pr", "open_issue", "open_receipt").
     pub action: String,
+
+    /// URL opened instead of `action`. `{session_tag}`, `{git_branch}` and
+    /// `{hostname}` are filled in when the gate fires.
+    #[serde(default)]
+    pub url_template: Option<String>,
+
+    /// Environment variables that must equal these values for the gate to
+    /// show; otherwise its slot renders empty.
+    #[serde(default)]
+    pub requires_env: HashMap<String, String>,
 }
 
 // ---------------------------------------------------------------------------
 // Policy
 // ---------------------------------------------------------------------------
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PolicyConfig {
     #[serde(default)]
     pub pre_tool_use: PreToolUsePolicy,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct PreToolUsePolicy {
     #[serde(default)]
     pub enabled: bool,
 
     #[serde(default)]
     pub bash: BashPolicy,
+
+    #[serde(default)]
+    pub multi_edit: MultiEditPolicy,
 }
 
-#[derive(Debug, Clone, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct BashPolicy {
     /// Patterns that will DENY a Bash tool call.
     #[serde(default)]
     pub deny: Vec<String>,
 
+    /// Regexes (case-insensitive) that will DENY a Bash tool call, for variants
+    /// substring matching misses (e.g. `rm -fr`). Applied by
+    /// `runbook-hooks --config` while `pre_tool_use.enabled`.
+    #[serde(default)]
+    pub deny_regex: Vec<String>,
+
     /// Patterns that will unconditionally ALLOW a Bash tool call.
     #[serde(default)]
     pub allow: Vec<String>,
+
+    /// Environment variable that, set to `"1"` in the hook's environment,
+    /// lets denied Bash calls through (emergency bypass). Overridden calls
+    /// are still reported to the daemon.
+    #[serde(default)]
+    pub allow_override_env: Option<String>,
+
+    /// File each override is appended to as a JSON line; an override that
+    /// can't be recorded there is denied.
+    #[serde(default)]
+    pub override_log: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct MultiEditPolicy {
+    /// Substrings that will DENY a MultiEdit call touching a matching path.
+    #[serde(default)]
+    pub deny_path_patterns: Vec<String>,
+
+    /// DENY a MultiEdit call that makes more than this many edits.
+    #[serde(default)]
+    pub deny_if_count_exceeds: Option<usize>,
+}
+
+/// Checks runbookd applies to every incoming hook event (HTTP, WebSocket or
+/// fifo) before it touches state. Unlike `policy`, which `runbook-hooks`
+/// enforces, these need no hook-side flags.
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct HookPolicyConfig {
+    /// Reject events for a new session while another session is running.
+    #[serde(default)]
+    pub block_on_active_session: bool,
+
+    /// Reject events without a `session_tag`.
+    #[serde(default)]
+    pub require_session_tag: bool,
+
+    /// Reject events for a new session once this many are tracked; 0 means no limit.
+    #[serde(default)]
+    pub max_concurrent_sessions: usize,
+}
+
+// ---------------------------------------------------------------------------
+// Layering
+// ---------------------------------------------------------------------------
+
+/// Take `local` if its file sets the key, else `base`.
+fn pick<T: Clone>(base: &T, local: &T, local_sets: bool) -> T {
+    if local_sets {
+        local.clone()
+    } else {
+        base.clone()
+    }
+}
+
+/// Add the dotted path of every mapping key under `value` to `keys`.
+fn collect_keys(value: &serde_yaml::Value, prefix: &str, keys: &mut HashSet<String>) {
+    let serde_yaml::Value::Mapping(map) = value else {
+        return;
+    };
+    for (key, value) in map {
+        let Some(key) = key.as_str() else { continue };
+        let path = if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        };
+        collect_keys(value, &path, keys);
+        keys.insert(path);
+    }
+}
+
+impl RunbookConfig {
+    /// Parse one config layer, remembering which keys it sets so
+    /// [`merge`](Self::merge) can tell an explicit default from an omission.
+    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml::Error> {
+        let mut config: Self = serde_yaml::from_str(yaml)?;
+        let value: serde_yaml::Value = serde_yaml::from_str(yaml)?;
+        collect_keys(&value, "", &mut config.set_keys);
+        Ok(config)
+    }
+
+    /// Layer a local override (e.g. `runbook.local.yaml`) on top of a base config.
+    ///
+    /// - prompts/gates: union, `local` wins on id conflicts
+    /// - keypad pages: `local` if it defines any, else `base`
+    /// - policy deny/deny_regex/allow/deny_path_patterns lists: concatenated (`base` first)
+    /// - scalars: `local` when its file sets them (even to the default), else `base`
+    pub fn merge(base: &Self, local: &Self) -> Self {
+        let sets = |key: &str| local.set_keys.contains(key);
+        let mut prompts = base.prompts.clone();
+        prompts.extend(local.prompts.clone());
+        let mut gates = base.gates.clone();
+        gates.extend(local.gates.clone());
+
+        let (b, l) = (&base.daemon, &local.daemon);
+        let daemon = DaemonConfig {
+            listen: pick(&b.listen, &l.listen, sets("daemon.listen")),
+            listen_backlog: pick(
+                &b.listen_backlog,
+                &l.listen_backlog,
+                sets("daemon.listen_backlog"),
+            ),
+            shutdown_timeout_secs: pick(
+                &b.shutdown_timeout_secs,
+                &l.shutdown_timeout_secs,
+                sets("daemon.shutdown_timeout_secs"),
+            ),
+            ws_max_message_size: pick(
+                &b.ws_max_message_size,
+                &l.ws_max_message_size,
+                sets("daemon.ws_max_message_size"),
+            ),
+            banner_duration_ms: pick(
+                &b.banner_duration_ms,
+                &l.banner_duration_ms,
+                sets("daemon.banner_duration_ms"),
+            ),
+            fifo: pick(&b.fifo, &l.fifo, sets("daemon.fifo")),
+            debug_enabled: pick(
+                &b.debug_enabled,
+                &l.debug_enabled,
+                sets("daemon.debug_enabled"),
+            ),
+            heartbeat_secs: pick(
+                &b.heartbeat_secs,
+                &l.heartbeat_secs,
+                sets("daemon.heartbeat_secs"),
+            ),
+            session_ttl_secs: pick(
+                &b.session_ttl_secs,
+                &l.session_ttl_secs,
+                sets("daemon.session_ttl_secs"),
+            ),
+            hook_rate_limit: pick(
+                &b.hook_rate_limit,
+                &l.hook_rate_limit,
+                sets("daemon.hook_rate_limit"),
+            ),
+        };
+
+        let (b, l) = (&base.tooling, &local.tooling);
+        let tooling = ToolingConfig {
+            primary: pick(&b.primary, &l.primary, sets("tooling.primary")),
+            degraded_mode_label: pick(
+                &b.degraded_mode_label,
+                &l.degraded_mode_label,
+                sets("tooling.degraded_mode_label"),
+            ),
+            hooks_timeout_ms: pick(
+                &b.hooks_timeout_ms,
+                &l.hooks_timeout_ms,
+                sets("tooling.hooks_timeout_ms"),
+            ),
+        };
+
+        let (b, l) = (&base.dial, &local.dial);
+        let dial = DialConfig {
+            mode: pick(&b.mode, &l.mode, sets("dial.mode")),
+            sensitivity: pick(&b.sensitivity, &l.sensitivity, sets("dial.sensitivity")),
+        };
+
+        let (b, l) = (&base.defaults, &local.defaults);
+        let defaults = DefaultsConfig {
+            arm_style: pick(&b.arm_style, &l.arm_style, sets("defaults.arm_style")),
+            esc_when_pending: pick(
+                &b.esc_when_pending,
+                &l.esc_when_pending,
+                sets("defaults.esc_when_pending"),
+            ),
+            max_prefill_chars: pick(
+                &b.max_prefill_chars,
+                &l.max_prefill_chars,
+                sets("defaults.max_prefill_chars"),
+            ),
+        };
+
+        let (b, l) = (&base.keypad, &local.keypad);
+        let keypad = KeypadConfig {
+            pages: if l.pages.is_empty() {
+                b.pages.clone()
+            } else {
+                l.pages.clone()
+            },
+            initial_page: pick(
+                &b.initial_page,
+                &l.initial_page,
+                sets("keypad.initial_page"),
+            ),
+            initial_page_by_name: match &l.initial_page_by_name {
+                Some(name) => Some(name.clone()),
+                // A local `initial_page` beats the base file's page name.
+                None if sets("keypad.initial_page") => None,
+                None => b.initial_page_by_name.clone(),
+            },
+            label_max_chars: pick(
+                &b.label_max_chars,
+                &l.label_max_chars,
+                sets("keypad.label_max_chars"),
+            ),
+            grid_size: pick(&b.grid_size, &l.grid_size, sets("keypad.grid_size")),
+        };
+
+        let (b, l) = (&base.policy.pre_tool_use, &local.policy.pre_tool_use);
+        let policy = PolicyConfig {
+            pre_tool_use: PreToolUsePolicy {
+                enabled: pick(&b.enabled, &l.enabled, sets("policy.pre_tool_use.enabled")),
+                bash: BashPolicy {
+                    deny: [b.bash.deny.as_slice(), l.bash.deny.as_slice()].concat(),
+                    deny_regex: [b.bash.deny_regex.as_slice(), l.bash.deny_regex.as_slice()]
+                        .concat(),
+                    allow: [b.bash.allow.as_slice(), l.bash.allow.as_slice()].concat(),
+                    allow_override_env: pick(
+                        &b.bash.allow_override_env,
+                        &l.bash.allow_override_env,
+                        sets("policy.pre_tool_use.bash.allow_override_env"),
+                    ),
+                    override_log: pick(
+                        &b.bash.override_log,
+                        &l.bash.override_log,
+                        sets("policy.pre_tool_use.bash.override_log"),
+                    ),
+                },
+                multi_edit: MultiEditPolicy {
+                    deny_path_patterns: [
+                        b.multi_edit.deny_path_patterns.as_slice(),
+                        l.multi_edit.deny_path_patterns.as_slice(),
+                    ]
+                    .concat(),
+                    deny_if_count_exceeds: pick(
+                        &b.multi_edit.deny_if_count_exceeds,
+                        &l.multi_edit.deny_if_count_exceeds,
+                        sets("policy.pre_tool_use.multi_edit.deny_if_count_exceeds"),
+                    ),
+                },
+            },
+        };
+
+        let (b, l) = (&base.hook_policy, &local.hook_policy);
+        let hook_policy = HookPolicyConfig {
+            block_on_active_session: pick(
+                &b.block_on_active_session,
+                &l.block_on_active_session,
+                sets("hook_policy.block_on_active_session"),
+            ),
+            require_session_tag: pick(
+                &b.require_session_tag,
+                &l.require_session_tag,
+                sets("hook_policy.require_session_tag"),
+            ),
+            max_concurrent_sessions: pick(
+                &b.max_concurrent_sessions,
+                &l.max_concurrent_sessions,
+                sets("hook_policy.max_concurrent_sessions"),
+            ),
+        };
+
+        Self {
+            version: pick(&base.version, &local.version, sets("version")),
+            daemon,
+            tooling,
+            dial,
+            defaults,
+            keypad,
+            prompts,
+            gates,
+            policy,
+            hook_policy,
+            env_values: [base.env_values.clone(), local.env_values.clone()].concat(),
+            set_keys: base.set_keys.union(&local.set_keys).cloned().collect(),
+        }
+    }
 }
 
 // ---------------------------------------------------------------------------
@@ -278,34 +773,110 @@ pub struct BashPolicy {
 // ---------------------------------------------------------------------------
 
 impl RunbookConfig {
-    pub fn validate(&self) -> anyhow::Result<()> {
+    /// Check the config and resolve `keypad.initial_page_by_name` into
+    /// `keypad.initial_page`.
+    ///
+    /// Returns warnings for problems the daemon can run with (e.g. a
+    /// `claude_command` without a leading `/`); `--strict-config` makes them
+    /// errors.
+    pub fn validate(&mut self) -> anyhow::Result<Vec<String>> {
         if self.keypad.pages.is_empty() {
-            anyhow::bail!("keypad.pages must have at least 1 page");
+            return Err(invalid("keypad.pages", "must have at least 1 page"));
+        }
+        if !DIAL_SENSITIVITY_RANGE.contains(&self.dial.sensitivity) {
+            return Err(invalid(
+                "dial.sensitivity",
+                format!(
+                    "must be between {} and {}. Got {}.",
+                    DIAL_SENSITIVITY_RANGE.start(),
+                    DIAL_SENSITIVITY_RANGE.end(),
+                    self.dial.sensitivity
+                ),
+            ));
         }
+        if let Some(ref name) = self.keypad.initial_page_by_name {
+            self.keypad.initial_page = self
+                .keypad
+                .pages
+                .iter()
+                .position(|p| p.name == *name)
+                .ok_or_else(|| {
+                    invalid("keypad.initial_page_by_name", format!("'{name}' matches no page"))
+                })?;
+        }
+        let (rows, cols) = self.keypad.grid();
+        if rows == 0 || cols == 0 {
+            return Err(invalid(
+                "keypad.grid_size",
+                format!("must be at least 1x1. Got {rows}x{cols}."),
+            ));
+        }
+        let slot_count = self.keypad.slot_count();
+        // Render slot indices are `u8`.
+        if slot_count > u8::MAX as usize + 1 {
+            return Err(invalid(
+                "keypad.grid_size",
+                format!("must have at most 256 slots. Got {rows}x{cols} ({slot_count})."),
+            ));
+        }
+        let mut hotkeys: HashMap<&str, &str> = HashMap::new();
         for (pi, p) in self.keypad.pages.iter().enumerate() {
-            if p.slots.len() != 9 {
-                anyhow::bail!(
-                    "keypad.pages[{pi}] '{name}' must have exactly 9 slots (3x3 keypad). Got {n}.",
-                    name = p.name,
-                    n = p.slots.len()
-                );
+            let page = || format!("keypad.pages[{pi}]");
+            if let Some(ref key) = p.hotkey {
+                if let Some(other) = hotkeys.insert(key, &p.name) {
+                    return Err(invalid(
+                        page(),
+                        format!("'{}' hotkey '{key}' is already used by page '{other}'", p.name),
+                    ));
+                }
+            }
+            if let Some(ref desc) = p.description {
+                let n = desc.chars().count();
+                if n >= PAGE_DESCRIPTION_CHAR_LIMIT {
+                    return Err(invalid(
+                        page(),
+                        format!(
+                            "'{}' description must be under \
+                             {PAGE_DESCRIPTION_CHAR_LIMIT} characters. Got {n}.",
+                            p.name
+                        ),
+                    ));
+                }
+            }
+            if let Some(ref target) = p.on_complete_advance_to {
+                if !self.keypad.pages.iter().any(|other| other.name == *target) {
+                    return Err(invalid(
+                        page(),
+                        format!("'{}' on_complete_advance_to '{target}' matches no page", p.name),
+                    ));
+                }
+            }
+            if p.slots.len() != slot_count {
+                return Err(invalid(
+                    page(),
+                    format!(
+                        "'{}' must have exactly {slot_count} slots ({rows}x{cols} keypad). Got {}.",
+                        p.name,
+                        p.slots.len()
+                    ),
+                ));
             }
             // Validate references.
             for (si, slot) in p.slots.iter().enumerate() {
                 if let Some(ref pid) = slot.prompt_id {
                     if !self.prompts.contains_key(pid) {
-                        anyhow::bail!(
-                            "keypad.pages[{pi}].slots[{si}].prompt_id '{pid}' \
-                             references unknown prompt"
-                        );
+                        return Err(invalid(
+                            format!("keypad.pages[{pi}].slots[{si}].prompt_id"),
+                            format!("'{pid}' references unknown prompt"),
+                        ));
                     }
                 }
                 if let Some(ref gid) = slot.gate {
                     if !self.gates.contains_key(gid) {
-                        anyhow::bail!(
-                            "keypad.pages[{pi}].slots[{si}].gate '{gid}' \
-                             references unknown gate"
-                        );
+                        return Err(invalid(
+                            format!("keypad.pages[{pi}].slots[{si}].gate"),
+                            format!("'{gid}' references unknown gate"),
+                        ));
                     }
                 }
                 if slot.prompt_id.is_none() && slot.gate.is_none() {
@@ -313,7 +884,69 @@ impl RunbookConfig {
                 }
             }
         }
-        Ok(())
+        let mut ids: Vec<&String> = self.prompts.keys().collect();
+        ids.sort();
+        for id in ids {
+            if let Some(step) = self.prompts[id]
+                .chain
+                .iter()
+                .find(|step| !self.prompts.contains_key(*step))
+            {
+                return Err(invalid(
+                    format!("prompts.{id}.chain"),
+                    format!("'{step}' references unknown prompt"),
+                ));
+            }
+            if let Some(next) = &self.prompts[id].suggests_next {
+                if !self.prompts.contains_key(next) {
+                    return Err(invalid(
+                        format!("prompts.{id}.suggests_next"),
+                        format!("'{next}' references unknown prompt"),
+                    ));
+                }
+            }
+        }
+        self.validate_prompt_commands()
+    }
+
+    /// A prompt must dispatch something; slash commands should look like one.
+    fn validate_prompt_commands(&self) -> anyhow::Result<Vec<String>> {
+        let mut ids: Vec<&String> = self.prompts.keys().collect();
+        ids.sort();
+        let mut warnings = Vec::new();
+        for id in ids {
+            let prompt = &self.prompts[id];
+            match (&prompt.claude_command, &prompt.fallback_text) {
+                (Some(cmd), _) if !cmd.starts_with('/') => warnings.push(format!(
+                    "prompts.{id}.claude_command '{cmd}' does not start with '/'"
+                )),
+                (Some(_), _) => {}
+                (None, Some(text)) if text.is_empty() => {
+                    return Err(invalid(
+                        format!("prompts.{id}.fallback_text"),
+                        "is empty and there is no claude_command",
+                    ))
+                }
+                (None, Some(_)) => {}
+                (None, None) => {
+                    return Err(invalid(
+                        format!("prompts.{id}"),
+                        "needs a claude_command or fallback_text",
+                    ))
+                }
+            }
+        }
+        Ok(warnings)
+    }
+
+    /// Whether a gate exists and every `requires_env` entry matches the
+    /// daemon's environment.
+    pub fn gate_is_available(&self, gate_id: &str) -> bool {
+        self.gates.get(gate_id).is_some_and(|g| {
+            g.requires_env
+                .iter()
+                .all(|(k, v)| std::env::var(k).is_ok_and(|actual| actual == *v))
+        })
     }
 
     /// Returns true when the primary tooling is Claude Code.
@@ -321,6 +954,15 @@ impl RunbookConfig {
         self.tooling.primary == "claude_code"
     }
 
+    /// One `[{id}] {prompt}` line per prompt, sorted by id.
+    pub fn prompt_summary(&self) -> Vec<String> {
+        let mut ids: Vec<&String> = self.prompts.keys().collect();
+        ids.sort();
+        ids.into_iter()
+            .map(|id| format!("[{id}] {}", self.prompts[id]))
+            .collect()
+    }
+
     /// Returns the effective ArmStyle for a prompt.
     pub fn arm_style_for(&self, prompt_id: &str) -> ArmStyle {
         self.prompts
@@ -328,12 +970,90 @@ impl RunbookConfig {
             .and_then(|p| p.arm_style)
             .unwrap_or(self.defaults.arm_style)
     }
+
+    /// [`arm_style_for`](Self::arm_style_for), except with no hooks there is
+    /// no Claude terminal to prefill: the prompt's `fallback_arm_style`, else
+    /// `Queue`.
+    pub fn arm_style_for_mode(&self, prompt_id: &str, hooks_mode: &HooksMode) -> ArmStyle {
+        if *hooks_mode != HooksMode::Absent {
+            return self.arm_style_for(prompt_id);
+        }
+        self.prompts
+            .get(prompt_id)
+            .and_then(|p| p.fallback_arm_style)
+            .unwrap_or(ArmStyle::Queue)
+    }
+
+    /// Client-facing summary of this config (sent in `config_reloaded`).
+    pub fn summary(&self) -> ConfigSummary {
+        ConfigSummary {
+            prompt_count: self.prompts.len() as u32,
+            gate_count: self.gates.len() as u32,
+            pages: self.keypad.pages.iter().map(|p| p.name.clone()).collect(),
+            initial_page: self.keypad.initial_page,
+            policy_enabled: self.policy.pre_tool_use.enabled,
+            page_summaries: self
+                .keypad
+                .pages
+                .iter()
+                .map(|p| PageSummary {
+                    name: p.name.clone(),
+                    description: p.description.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A [`RunbookConfig::validate`] failure: the offending field and what is
+/// wrong with it, shown as `"{field_path} {problem}"`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidField {
+    pub field_path: String,
+    pub problem: String,
+}
+
+impl std::fmt::Display for InvalidField {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.field_path, self.problem)
+    }
+}
+
+impl std::error::Error for InvalidField {}
+
+fn invalid(field_path: impl Into<String>, problem: impl Into<String>) -> anyhow::Error {
+    InvalidField {
+        field_path: field_path.into(),
+        problem: problem.into(),
+    }
+    .into()
+}
+
+/// A load or validation error as a `config_error` message. `field_path` comes
+/// from an [`InvalidField`]; other errors (unreadable or unparsable files)
+/// leave it empty.
+pub fn config_error(e: &anyhow::Error) -> ConfigError {
+    let field_path = e
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<InvalidField>())
+        .map(|invalid| invalid.field_path.clone())
+        .unwrap_or_default();
+    ConfigError {
+        field_path,
+        message: format!("{e:#}"),
+    }
 }
 
 // ---------------------------------------------------------------------------
 // Tests
 // ---------------------------------------------------------------------------
 
+/// YAML flow list of `n` empty slots, e.g. `[{}, {}]`.
+#[cfg(test)]
+pub(crate) fn empty_slots(n: usize) -> String {
+    format!("[{}]", vec!["{}"; n].join(", "))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -411,6 +1131,10 @@ policy:
         - "git status"
         - "rg "
         - "cargo test"
+    multi_edit:
+      deny_path_patterns:
+        - ".env"
+      deny_if_count_exceeds: 50
 "#;
 
     #[test]
@@ -423,11 +1147,29 @@ policy:
         assert_eq!(cfg.gates.len(), 3);
         assert!(cfg.policy.pre_tool_use.enabled);
         assert_eq!(cfg.policy.pre_tool_use.bash.deny.len(), 3);
+        assert_eq!(cfg.policy.pre_tool_use.multi_edit.deny_if_count_exceeds, Some(50));
+        assert_eq!(cfg.daemon.listen_backlog, 128);
+        assert_eq!(cfg.daemon.shutdown_timeout_secs, 5);
+        assert_eq!(cfg.daemon.session_ttl_secs, 600);
+        assert!(!cfg.daemon.debug_enabled);
+        assert_eq!(cfg.daemon.ws_max_message_size, 1024 * 1024);
+        assert_eq!(cfg.daemon.banner_duration_ms, 2000);
+        assert_eq!(cfg.tooling.hooks_timeout_ms, 250);
     }
 
     #[test]
-    fn validate_sample_config() {
+    fn summary_reports_config_shape() {
         let cfg: RunbookConfig = serde_yaml::from_str(SAMPLE_YAML).unwrap();
+        let summary = cfg.summary();
+        assert_eq!(summary.prompt_count, 4);
+        assert_eq!(summary.gate_count, 3);
+        assert_eq!(summary.pages, vec!["core".to_string()]);
+        assert!(summary.policy_enabled);
+    }
+
+    #[test]
+    fn validate_sample_config() {
+        let mut cfg: RunbookConfig = serde_yaml::from_str(SAMPLE_YAML).unwrap();
         cfg.validate().unwrap();
     }
 
@@ -451,6 +1193,83 @@ policy:
         );
     }
 
+    #[test]
+    fn prompt_display_prefers_claude_command() {
+        let cfg: RunbookConfig = serde_yaml::from_str(SAMPLE_YAML).unwrap();
+        assert_eq!(
+            cfg.prompts["prep_pr"].to_string(),
+            "PREP PR (receipts) → /runbook:prep-pr"
+        );
+    }
+
+    #[test]
+    fn arm_style_for_mode_falls_back_without_hooks() {
+        let yaml = r#"
+prompts:
+  prep_pr:
+    label: PREP
+    arm_style: prefill
+  review:
+    label: REVIEW
+    arm_style: chain
+    fallback_arm_style: prefill
+"#;
+        let cfg: RunbookConfig = serde_yaml::from_str(yaml).unwrap();
+        let style = |id, mode| cfg.arm_style_for_mode(id, &mode);
+
+        assert_eq!(style("prep_pr", HooksMode::Active), ArmStyle::Prefill);
+        assert_eq!(style("review", HooksMode::Active), ArmStyle::Chain);
+        assert_eq!(style("review", HooksMode::Absent), ArmStyle::Prefill);
+        assert_eq!(style("prep_pr", HooksMode::Absent), ArmStyle::Queue);
+    }
+
+    #[test]
+    fn prompt_display_degraded_uses_fallback_text() {
+        let prompt = PromptConfig {
+            label: "EXPLAIN".to_string(),
+            sublabel: None,
+            arm_style: None,
+            fallback_arm_style: None,
+            claude_command: None,
+            fallback_text: Some("Explain the selected code.".to_string()),
+            chain: vec![],
+            suggests_next: None,
+        };
+        assert_eq!(prompt.to_string(), "EXPLAIN → Explain the selected code.");
+    }
+
+    #[test]
+    fn prompt_display_truncates_long_commands() {
+        let prompt = PromptConfig {
+            label: "LONG".to_string(),
+            sublabel: None,
+            arm_style: None,
+            fallback_arm_style: None,
+            claude_command: Some("x".repeat(200)),
+            fallback_text: None,
+            chain: vec![],
+            suggests_next: None,
+        };
+        let command = prompt.to_string().split_once(" → ").unwrap().1.to_string();
+        assert_eq!(command.chars().count(), PROMPT_SUMMARY_COMMAND_CHARS);
+        assert!(command.ends_with('…'));
+    }
+
+    #[test]
+    fn prompt_summary_is_sorted_by_id() {
+        let mut cfg: RunbookConfig = serde_yaml::from_str(SAMPLE_YAML).unwrap();
+        let mut extra = cfg.prompts["prep_pr"].clone();
+        extra.label = "AUDIT".to_string();
+        cfg.prompts.insert("audit".to_string(), extra);
+
+        let summary = cfg.prompt_summary();
+        assert_eq!(summary.len(), cfg.prompts.len());
+        assert!(summary[0].starts_with("[audit] AUDIT"));
+        let mut sorted = summary.clone();
+        sorted.sort();
+        assert_eq!(summary, sorted);
+    }
+
     #[test]
     fn validate_bad_prompt_ref() {
         let yaml = r#"
@@ -468,7 +1287,473 @@ keypad:
         - {}
         - {}
 "#;
-        let cfg: RunbookConfig = serde_yaml::from_str(yaml).unwrap();
+        let mut cfg: RunbookConfig = serde_yaml::from_str(yaml).unwrap();
         assert!(cfg.validate().is_err());
     }
+
+    #[test]
+    fn validate_bad_chain_ref() {
+        let mut cfg = prompt_config("    arm_style: chain\n    chain: [nonexistent]\n");
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(err, "prompts.p.chain 'nonexistent' references unknown prompt");
+    }
+
+    /// One-page config around a single prompt, given as YAML fields.
+    fn prompt_config(fields: &str) -> RunbookConfig {
+        let yaml = format!(
+            "keypad:\n  pages:\n    - name: test\n      slots: {}\n\
+             prompts:\n  p:\n    label: P\n{fields}",
+            empty_slots(9)
+        );
+        serde_yaml::from_str(&yaml).unwrap()
+    }
+
+    #[test]
+    fn validate_slash_command_has_no_warnings() {
+        let mut cfg = prompt_config("    claude_command: /runbook:prep-pr\n");
+        assert!(cfg.validate().unwrap().is_empty());
+    }
+
+    #[test]
+    fn validate_warns_on_command_without_slash() {
+        let mut cfg = prompt_config("    claude_command: runbook:prep-pr\n");
+        assert_eq!(
+            cfg.validate().unwrap(),
+            ["prompts.p.claude_command 'runbook:prep-pr' does not start with '/'"]
+        );
+    }
+
+    #[test]
+    fn validate_rejects_empty_fallback() {
+        let mut cfg = prompt_config("    fallback_text: \"\"\n");
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(err, "prompts.p.fallback_text is empty and there is no claude_command");
+    }
+
+    #[test]
+    fn validate_rejects_prompt_with_nothing_to_dispatch() {
+        let mut cfg = prompt_config("");
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(err, "prompts.p needs a claude_command or fallback_text");
+    }
+
+    #[test]
+    fn validate_bad_suggests_next_ref() {
+        let mut cfg = prompt_config("    claude_command: /p\n    suggests_next: nonexistent\n");
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(err, "prompts.p.suggests_next 'nonexistent' references unknown prompt");
+    }
+
+    #[test]
+    fn validate_duplicate_hotkey() {
+        let yaml = format!(
+            r#"
+keypad:
+  pages:
+    - name: one
+      hotkey: p1
+      slots: {slots}
+    - name: two
+      hotkey: p1
+      slots: {slots}
+"#,
+            slots = empty_slots(9)
+        );
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("already used by page 'one'"), "{err}");
+    }
+
+    #[test]
+    fn page_description_is_optional_and_summarized() {
+        let yaml = format!(
+            r#"
+keypad:
+  pages:
+    - name: core
+      description: "Core workflow prompts for feature development"
+      slots: {slots}
+    - name: review
+      slots: {slots}
+"#,
+            slots = empty_slots(9)
+        );
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        cfg.validate().unwrap();
+        let pages = cfg.summary().page_summaries;
+        assert_eq!(
+            pages[0].description.as_deref(),
+            Some("Core workflow prompts for feature development")
+        );
+        assert_eq!(pages[1].name, "review");
+        assert_eq!(pages[1].description, None);
+    }
+
+    #[test]
+    fn validate_page_description_length() {
+        let yaml = format!(
+            r#"
+keypad:
+  pages:
+    - name: core
+      description: "{desc}"
+      slots: {slots}
+"#,
+            desc = "x".repeat(PAGE_DESCRIPTION_CHAR_LIMIT),
+            slots = empty_slots(9)
+        );
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "keypad.pages[0] 'core' description must be under 120 characters. Got 120."
+        );
+
+        cfg.keypad.pages[0].description = Some("x".repeat(PAGE_DESCRIPTION_CHAR_LIMIT - 1));
+        cfg.validate().unwrap();
+    }
+
+    #[test]
+    fn validate_on_complete_advance_to_names_a_page() {
+        let yaml = format!(
+            r#"
+keypad:
+  pages:
+    - name: core
+      on_complete_advance_to: review
+      slots: {slots}
+"#,
+            slots = empty_slots(9)
+        );
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "keypad.pages[0] 'core' on_complete_advance_to 'review' matches no page"
+        );
+    }
+
+    #[test]
+    fn config_error_takes_field_path_from_validation() {
+        let mut cfg: RunbookConfig = serde_yaml::from_str("{}").unwrap();
+        let err = config_error(&cfg.validate().unwrap_err());
+        assert_eq!(err.field_path, "keypad.pages");
+        assert_eq!(err.message, "keypad.pages must have at least 1 page");
+
+        let yaml = format!(
+            "keypad:\n  pages:\n    - name: core\n      slots: {}\n",
+            empty_slots(9).replacen("{}", "{prompt_id: nope}", 1)
+        );
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        let err = config_error(&cfg.validate().unwrap_err().context("reload"));
+        assert_eq!(err.field_path, "keypad.pages[0].slots[0].prompt_id");
+
+        let err = config_error(&anyhow::anyhow!("failed to read config 'runbook.yaml': gone"));
+        assert_eq!(err.field_path, "");
+        assert_eq!(err.message, "failed to read config 'runbook.yaml': gone");
+    }
+
+    #[test]
+    fn grid_size_sets_slot_count() {
+        let yaml = format!(
+            "keypad:\n  grid_size: [2, 3]\n  pages:\n    - name: small\n      slots: {}\n",
+            empty_slots(6)
+        );
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(cfg.keypad.slot_count(), 6);
+        cfg.validate().unwrap();
+
+        cfg.keypad.grid_size = None;
+        assert_eq!(cfg.keypad.slot_count(), 9);
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(
+            err,
+            "keypad.pages[0] 'small' must have exactly 9 slots (3x3 keypad). Got 6."
+        );
+    }
+
+    #[test]
+    fn grid_size_must_be_nonzero() {
+        let yaml = "keypad:\n  grid_size: [0, 3]\n  pages:\n    - name: none\n      slots: []\n";
+        let mut cfg: RunbookConfig = serde_yaml::from_str(yaml).unwrap();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.starts_with("keypad.grid_size must be at least 1x1"), "{err}");
+    }
+
+    #[test]
+    fn grid_size_fits_u8_slot_indices() {
+        let yaml = "keypad:\n  grid_size: [16, 17]\n  pages:\n    - name: big\n      slots: []\n";
+        let mut cfg: RunbookConfig = serde_yaml::from_str(yaml).unwrap();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(err, "keypad.grid_size must have at most 256 slots. Got 16x17 (272).");
+
+        cfg.keypad.grid_size = Some((16, 16));
+        let err = cfg.validate().unwrap_err().to_string();
+        assert!(err.contains("must have exactly 256 slots"), "{err}");
+    }
+
+    fn two_page_yaml() -> String {
+        format!(
+            "keypad:\n  pages:\n    - name: core\n      slots: {0}\n    \
+             - name: review\n      slots: {0}\n",
+            empty_slots(9)
+        )
+    }
+
+    #[test]
+    fn dial_sensitivity_defaults_to_one() {
+        let cfg: RunbookConfig = serde_yaml::from_str(&two_page_yaml()).unwrap();
+        assert_eq!(cfg.dial.sensitivity, 1.0);
+    }
+
+    #[test]
+    fn dial_sensitivity_must_be_in_range() {
+        for (value, ok) in [("0.1", true), ("5.0", true), ("0.05", false), ("5.5", false)] {
+            let yaml = format!("{}dial:\n  sensitivity: {value}\n", two_page_yaml());
+            let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+            let result = cfg.validate();
+            assert_eq!(result.is_ok(), ok, "sensitivity {value}: {result:?}");
+            if !ok {
+                let err = result.unwrap_err().to_string();
+                assert!(err.starts_with("dial.sensitivity must be between 0.1 and 5"), "{err}");
+            }
+        }
+    }
+
+    #[test]
+    fn initial_page_by_name_resolves_to_index() {
+        let yaml = format!("{}  initial_page_by_name: review\n", two_page_yaml());
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(cfg.keypad.initial_page, 0);
+        cfg.validate().unwrap();
+        assert_eq!(cfg.keypad.initial_page, 1);
+    }
+
+    #[test]
+    fn initial_page_by_name_wins_over_index() {
+        let yaml = format!("{}  initial_page: 1\n  initial_page_by_name: core\n", two_page_yaml());
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        cfg.validate().unwrap();
+        assert_eq!(cfg.keypad.initial_page, 0);
+    }
+
+    #[test]
+    fn initial_page_by_name_must_match_a_page() {
+        let yaml = format!("{}  initial_page_by_name: deploy\n", two_page_yaml());
+        let mut cfg: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        let err = cfg.validate().unwrap_err().to_string();
+        assert_eq!(err, "keypad.initial_page_by_name 'deploy' matches no page");
+    }
+
+    const LOCAL_YAML: &str = r#"
+prompts:
+  prep_pr:
+    label: "MY PR"
+    claude_command: "/mine:prep-pr"
+  scratch:
+    label: "SCRATCH"
+    fallback_text: "Draft a note"
+
+policy:
+  pre_tool_use:
+    bash:
+      deny:
+        - "curl | sh"
+      allow:
+        - "cargo fmt"
+    multi_edit:
+      deny_path_patterns:
+        - ".github/"
+      deny_if_count_exceeds: 20
+"#;
+
+    #[test]
+    fn merge_local_prompt_wins() {
+        let base = RunbookConfig::from_yaml(SAMPLE_YAML).unwrap();
+        let local = RunbookConfig::from_yaml(LOCAL_YAML).unwrap();
+        let mut merged = RunbookConfig::merge(&base, &local);
+
+        assert_eq!(merged.prompts.len(), 5);
+        assert_eq!(merged.prompts["prep_pr"].label, "MY PR");
+        assert_eq!(merged.prompts["break_task"].label, "BREAK TASK");
+        assert_eq!(merged.gates.len(), 3);
+        assert_eq!(merged.daemon.listen, "127.0.0.1:29381");
+        merged.validate().unwrap();
+    }
+
+    #[test]
+    fn merge_concatenates_policy_lists() {
+        let base = RunbookConfig::from_yaml(SAMPLE_YAML).unwrap();
+        let local = RunbookConfig::from_yaml(LOCAL_YAML).unwrap();
+        let merged = RunbookConfig::merge(&base, &local);
+
+        let bash = &merged.policy.pre_tool_use.bash;
+        assert_eq!(bash.deny.len(), 4);
+        assert_eq!(bash.deny.last().map(String::as_str), Some("curl | sh"));
+        assert_eq!(bash.allow.len(), 4);
+        assert!(merged.policy.pre_tool_use.enabled);
+
+        let multi_edit = &merged.policy.pre_tool_use.multi_edit;
+        assert_eq!(multi_edit.deny_path_patterns, [".env", ".github/"]);
+        assert_eq!(multi_edit.deny_if_count_exceeds, Some(20));
+    }
+
+    #[test]
+    fn allow_override_env_parses_and_local_wins() {
+        let base = RunbookConfig::from_yaml(SAMPLE_YAML).unwrap();
+        assert_eq!(base.policy.pre_tool_use.bash.allow_override_env, None);
+
+        let with_env = |var: &str| -> RunbookConfig {
+            let yaml = format!(
+                "policy:\n  pre_tool_use:\n    bash:\n      allow_override_env: {var}\n      override_log: /tmp/{var}.jsonl\n"
+            );
+            RunbookConfig::from_yaml(&yaml).unwrap()
+        };
+        let merged = RunbookConfig::merge(&with_env("RUNBOOK_BYPASS"), &base);
+        assert_eq!(
+            merged.policy.pre_tool_use.bash.allow_override_env.as_deref(),
+            Some("RUNBOOK_BYPASS")
+        );
+        assert_eq!(
+            merged.policy.pre_tool_use.bash.override_log.as_deref(),
+            Some("/tmp/RUNBOOK_BYPASS.jsonl")
+        );
+        let merged = RunbookConfig::merge(&with_env("RUNBOOK_BYPASS"), &with_env("MY_BYPASS"));
+        assert_eq!(
+            merged.policy.pre_tool_use.bash.allow_override_env.as_deref(),
+            Some("MY_BYPASS")
+        );
+    }
+
+    #[test]
+    fn hook_policy_parses_and_merges() {
+        let base = RunbookConfig::from_yaml(SAMPLE_YAML).unwrap();
+        assert!(!base.hook_policy.require_session_tag);
+        assert_eq!(base.hook_policy.max_concurrent_sessions, 0);
+
+        let local = RunbookConfig::from_yaml(
+            "hook_policy:\n  require_session_tag: true\n  max_concurrent_sessions: 3\n",
+        )
+        .unwrap();
+        let merged = RunbookConfig::merge(&base, &local);
+        assert!(merged.hook_policy.require_session_tag);
+        assert!(!merged.hook_policy.block_on_active_session);
+        assert_eq!(merged.hook_policy.max_concurrent_sessions, 3);
+    }
+
+    #[test]
+    fn merge_replaces_pages_when_local_defines_them() {
+        let base = RunbookConfig::from_yaml(SAMPLE_YAML).unwrap();
+        let local = RunbookConfig::from_yaml(&format!(
+            "keypad:\n  pages:\n    - name: mine\n      slots: {}\n",
+            empty_slots(9)
+        ))
+        .unwrap();
+        let merged = RunbookConfig::merge(&base, &local);
+        assert_eq!(merged.keypad.pages.len(), 1);
+        assert_eq!(merged.keypad.pages[0].name, "mine");
+
+        let no_pages = RunbookConfig::from_yaml(LOCAL_YAML).unwrap();
+        let merged = RunbookConfig::merge(&base, &no_pages);
+        assert_eq!(merged.keypad.pages[0].name, "core");
+    }
+
+    #[test]
+    fn merge_local_initial_page_overrides_base_page_name() {
+        let yaml = format!("{}  initial_page: 1\n  initial_page_by_name: core\n", two_page_yaml());
+        let base = RunbookConfig::from_yaml(&yaml).unwrap();
+        let local = RunbookConfig::from_yaml("keypad:\n  initial_page: 1\n").unwrap();
+        let mut merged = RunbookConfig::merge(&base, &local);
+        assert_eq!(merged.keypad.initial_page_by_name, None);
+        merged.validate().unwrap();
+        assert_eq!(merged.keypad.initial_page, 1);
+
+        // Without a local index the base name still applies.
+        let mut merged = RunbookConfig::merge(&base, &RunbookConfig::from_yaml("{}").unwrap());
+        merged.validate().unwrap();
+        assert_eq!(merged.keypad.initial_page, 0);
+    }
+
+    #[test]
+    fn merge_local_resets_base_settings_to_defaults() {
+        let base = RunbookConfig::from_yaml(
+            "daemon:\n  debug_enabled: true\n  listen: 0.0.0.0:4000\n  fifo: /tmp/base.fifo\n\
+             policy:\n  pre_tool_use:\n    enabled: true\n\
+             hook_policy:\n  block_on_active_session: true\n  require_session_tag: true\n",
+        )
+        .unwrap();
+        let local = RunbookConfig::from_yaml(
+            "daemon:\n  debug_enabled: false\n  listen: 127.0.0.1:29381\n  fifo: null\n\
+             policy:\n  pre_tool_use:\n    enabled: false\n\
+             hook_policy:\n  block_on_active_session: false\n  require_session_tag: false\n",
+        )
+        .unwrap();
+        let merged = RunbookConfig::merge(&base, &local);
+        assert!(!merged.daemon.debug_enabled);
+        assert_eq!(merged.daemon.listen, default_listen());
+        assert_eq!(merged.daemon.fifo, None);
+        assert!(!merged.policy.pre_tool_use.enabled);
+        assert!(!merged.hook_policy.block_on_active_session);
+        assert!(!merged.hook_policy.require_session_tag);
+
+        // Keys the local file leaves out keep the base values.
+        let merged = RunbookConfig::merge(&base, &RunbookConfig::from_yaml("{}").unwrap());
+        assert!(merged.daemon.debug_enabled);
+        assert_eq!(merged.daemon.listen, "0.0.0.0:4000");
+        assert!(merged.hook_policy.require_session_tag);
+    }
+
+    /// Serializes tests that mutate the process environment.
+    static ENV_LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+
+    const GATED_YAML: &str = r#"
+keypad:
+  pages:
+    - name: ops
+      slots: [{gate: deploy}, {gate: logs}, {}, {}, {}, {}, {}, {}, {}]
+gates:
+  deploy:
+    label: "DEPLOY"
+    action: deploy
+    requires_env:
+      RUNBOOK_TEST_DEPLOY_ENV: production
+      RUNBOOK_TEST_DEPLOY_REGION: eu
+  logs:
+    label: "LOGS"
+    action: open_logs
+"#;
+
+    #[test]
+    fn parse_gate_requires_env() {
+        let mut cfg: RunbookConfig = serde_yaml::from_str(GATED_YAML).unwrap();
+        cfg.validate().unwrap();
+        let deploy = &cfg.gates["deploy"];
+        assert_eq!(deploy.requires_env.len(), 2);
+        assert_eq!(deploy.requires_env["RUNBOOK_TEST_DEPLOY_ENV"], "production");
+        assert!(cfg.gates["logs"].requires_env.is_empty());
+    }
+
+    #[test]
+    fn gate_is_available_checks_every_required_var() {
+        let _guard = ENV_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let cfg: RunbookConfig = serde_yaml::from_str(GATED_YAML).unwrap();
+
+        std::env::remove_var("RUNBOOK_TEST_DEPLOY_ENV");
+        std::env::remove_var("RUNBOOK_TEST_DEPLOY_REGION");
+        assert!(!cfg.gate_is_available("deploy"));
+        assert!(cfg.gate_is_available("logs"));
+        assert!(!cfg.gate_is_available("missing"));
+
+        std::env::set_var("RUNBOOK_TEST_DEPLOY_ENV", "production");
+        assert!(!cfg.gate_is_available("deploy"));
+
+        std::env::set_var("RUNBOOK_TEST_DEPLOY_REGION", "eu");
+        assert!(cfg.gate_is_available("deploy"));
+
+        std::env::set_var("RUNBOOK_TEST_DEPLOY_ENV", "staging");
+        assert!(!cfg.gate_is_available("deploy"));
+
+        std::env::remove_var("RUNBOOK_TEST_DEPLOY_ENV");
+        std::env::remove_var("RUNBOOK_TEST_DEPLOY_REGION");
+    }
 }
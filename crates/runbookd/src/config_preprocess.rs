@@ -0,0 +1,194 @@
+//! `${VAR}` interpolation applied to the raw YAML before parsing.
+//!
+//! Substitution is a single pass: a variable whose value itself contains
+//! `${...}` is inserted verbatim, never re-expanded. Comment text (from a `#`
+//! at the start of a line or after whitespace, outside quotes) is left alone.
+
+/// Replace `${VAR_NAME}` tokens with the value of the environment variable.
+///
+/// Undefined variables are an error when `strict`, otherwise the token is
+/// left unchanged. Anything that isn't a well-formed `${NAME}` token passes
+/// through as-is.
+pub fn preprocess_config(raw: &str, strict: bool) -> anyhow::Result<String> {
+    preprocess_with(raw, strict, |name| std::env::var(name).ok())
+}
+
+/// The values [`preprocess_config`] substitutes into `raw` (undefined and
+/// empty variables aside), so they can be kept out of debug output.
+pub fn env_values(raw: &str) -> Vec<String> {
+    env_values_with(raw, |name| std::env::var(name).ok())
+}
+
+fn env_values_with(raw: &str, lookup: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let values = std::cell::RefCell::new(Vec::new());
+    let _ = preprocess_with(raw, false, |name| {
+        let value = lookup(name);
+        if let Some(v) = value.as_ref().filter(|v| !v.is_empty()) {
+            values.borrow_mut().push(v.clone());
+        }
+        value
+    });
+    values.into_inner()
+}
+
+fn preprocess_with(
+    raw: &str,
+    strict: bool,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> anyhow::Result<String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut missing: Vec<&str> = Vec::new();
+
+    for line in raw.split_inclusive('\n') {
+        let (code, comment) = line.split_at(comment_start(line));
+        expand(code, &lookup, &mut out, &mut missing);
+        out.push_str(comment);
+    }
+
+    if strict && !missing.is_empty() {
+        anyhow::bail!(
+            "undefined environment variable(s) in config: {}",
+            missing.join(", ")
+        );
+    }
+    Ok(out)
+}
+
+/// Byte offset of the `#` that starts a comment on `line`, or its length.
+fn comment_start(line: &str) -> usize {
+    let mut quote = None;
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        match (quote, c) {
+            (None, '#') if prev.is_whitespace() => return i,
+            (None, '"' | '\'') => quote = Some(c),
+            (Some(q), _) if c == q => quote = None,
+            _ => {}
+        }
+        prev = c;
+    }
+    line.len()
+}
+
+/// Append `text` to `out` with its `${NAME}` tokens expanded.
+fn expand<'a>(
+    text: &'a str,
+    lookup: &impl Fn(&str) -> Option<String>,
+    out: &mut String,
+    missing: &mut Vec<&'a str>,
+) {
+    let mut rest = text;
+
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let name = after.find('}').map(|end| &after[..end]);
+
+        match name.filter(|n| is_var_name(n)) {
+            Some(name) => {
+                match lookup(name) {
+                    Some(value) => out.push_str(&value),
+                    None => {
+                        if !missing.contains(&name) {
+                            missing.push(name);
+                        }
+                        out.push_str(&rest[start..start + name.len() + 3]);
+                    }
+                }
+                rest = &after[name.len() + 1..];
+            }
+            None => {
+                out.push_str("${");
+                rest = after;
+            }
+        }
+    }
+    out.push_str(rest);
+}
+
+fn is_var_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn env(name: &str) -> Option<String> {
+        match name {
+            "RUNBOOK_LISTEN" => Some("127.0.0.1:4000".to_string()),
+            "HOME_DIR" => Some("/home/dev".to_string()),
+            "INDIRECT" => Some("${HOME_DIR}".to_string()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn defined_variables_are_replaced() {
+        let out = preprocess_with(
+            "listen: \"${RUNBOOK_LISTEN}\"\nfifo: ${HOME_DIR}/.runbook/${HOME_DIR}\n",
+            true,
+            env,
+        )
+        .unwrap();
+        assert_eq!(
+            out,
+            "listen: \"127.0.0.1:4000\"\nfifo: /home/dev/.runbook//home/dev\n"
+        );
+    }
+
+    #[test]
+    fn undefined_variable_errors_in_strict_mode() {
+        let err = preprocess_with("a: ${NOPE}\nb: ${NOPE}\nc: ${ALSO_NOPE}", true, env)
+            .unwrap_err()
+            .to_string();
+        assert_eq!(
+            err,
+            "undefined environment variable(s) in config: NOPE, ALSO_NOPE"
+        );
+    }
+
+    #[test]
+    fn undefined_variable_passes_through_in_permissive_mode() {
+        let out = preprocess_with("a: ${NOPE} b: ${HOME_DIR}", false, env).unwrap();
+        assert_eq!(out, "a: ${NOPE} b: /home/dev");
+    }
+
+    #[test]
+    fn nested_references_are_not_re_expanded() {
+        let out = preprocess_with("path: ${INDIRECT}", true, env).unwrap();
+        assert_eq!(out, "path: ${HOME_DIR}");
+
+        // `${A_${B}}` isn't a valid name, so only the inner token expands.
+        let out = preprocess_with("x: ${A_${HOME_DIR}}", false, env).unwrap();
+        assert_eq!(out, "x: ${A_/home/dev}");
+    }
+
+    #[test]
+    fn comments_are_not_expanded() {
+        let raw = "# listen: ${NOPE}\nfifo: ${HOME_DIR} # was ${ALSO_NOPE}\n\
+                   label: \"#${HOME_DIR}\" # ${NOPE}\ntag: a#${HOME_DIR}\n";
+        let out = preprocess_with(raw, true, env).unwrap();
+        assert_eq!(
+            out,
+            "# listen: ${NOPE}\nfifo: /home/dev # was ${ALSO_NOPE}\n\
+             label: \"#/home/dev\" # ${NOPE}\ntag: a#/home/dev\n"
+        );
+    }
+
+    #[test]
+    fn env_values_lists_expanded_values() {
+        let raw = "a: ${HOME_DIR}/x ${NOPE}\n# ${RUNBOOK_LISTEN}\nb: ${INDIRECT}\n";
+        assert_eq!(env_values_with(raw, env), ["/home/dev", "${HOME_DIR}"]);
+    }
+
+    #[test]
+    fn malformed_tokens_pass_through() {
+        let out = preprocess_with("a: $HOME_DIR ${} ${1X} ${HOME_DIR", true, env).unwrap();
+        assert_eq!(out, "a: $HOME_DIR ${} ${1X} ${HOME_DIR");
+    }
+}
@@ -0,0 +1,128 @@
+//! Named-pipe (FIFO) transport for hook events, for environments where
+//! localhost HTTP is blocked. Each line written to the pipe is one JSON
+//! `HookEvent`; `runbook-hooks --fifo <path>` is the writer.
+
+use std::path::Path;
+
+use nix::sys::stat::Mode;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::unix::pipe;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+use runbook_protocol::HookEvent;
+
+/// Create the FIFO at `path` (mode 0600) unless something already exists there.
+pub fn ensure_fifo(path: &Path) -> anyhow::Result<()> {
+    if path.exists() {
+        return Ok(());
+    }
+    match nix::unistd::mkfifo(path, Mode::S_IRUSR | Mode::S_IWUSR) {
+        Ok(()) | Err(nix::errno::Errno::EEXIST) => Ok(()),
+        Err(e) => anyhow::bail!("failed to create fifo '{}': {e}", path.display()),
+    }
+}
+
+/// Read hook events from the FIFO forever, sending each parsed line to `tx`.
+///
+/// Malformed lines are logged and skipped. Returns when `tx` is closed or the
+/// pipe can't be opened.
+pub async fn read_events(path: &Path, tx: mpsc::UnboundedSender<HookEvent>) -> anyhow::Result<()> {
+    ensure_fifo(path)?;
+    loop {
+        let mut opts = pipe::OpenOptions::new();
+        // Holding a write end ourselves keeps reads from hitting EOF every time
+        // a hook process closes the pipe.
+        #[cfg(target_os = "linux")]
+        opts.read_write(true);
+        let receiver = opts
+            .open_receiver(path)
+            .map_err(|e| anyhow::anyhow!("failed to open fifo '{}': {e}", path.display()))?;
+
+        let mut lines = BufReader::new(receiver).lines();
+        // The receiver is non-blocking; tokio parks on EAGAIN until data arrives.
+        while let Some(line) = lines.next_line().await? {
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<HookEvent>(&line) {
+                Ok(ev) => {
+                    if tx.send(ev).is_err() {
+                        return Ok(());
+                    }
+                }
+                Err(e) => warn!("invalid hook event on fifo: {e}"),
+            }
+        }
+
+        // EOF: every writer closed. Reopen and wait for the next one.
+        debug!("fifo writers closed; reopening");
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::Duration;
+
+    fn temp_fifo(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("runbookd-{name}-{}.fifo", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[tokio::test]
+    async fn reads_hook_events_from_fifo() {
+        let path = temp_fifo("read");
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let reader_path = path.clone();
+        tokio::spawn(async move { read_events(&reader_path, tx).await });
+
+        // Wait for the reader to create the pipe.
+        while !path.exists() {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+
+        let writer_path = path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut pipe = std::fs::OpenOptions::new()
+                .write(true)
+                .open(&writer_path)
+                .unwrap();
+            writeln!(pipe, r#"{{"hook":"SessionStart","session_id":"sess1"}}"#).unwrap();
+            writeln!(pipe, "not json").unwrap();
+            writeln!(pipe, r#"{{"hook":"Stop","session_id":"sess1"}}"#).unwrap();
+        })
+        .await
+        .unwrap();
+
+        let first = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.hook, "SessionStart");
+        assert_eq!(first.session_id.as_deref(), Some("sess1"));
+
+        // The malformed line is skipped.
+        let second = tokio::time::timeout(Duration::from_secs(2), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(second.hook, "Stop");
+
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn ensure_fifo_keeps_existing_path() {
+        let path = temp_fifo("ensure");
+        ensure_fifo(&path).unwrap();
+        ensure_fifo(&path).unwrap();
+        assert!(path.exists());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,6 +1,12 @@
 //! Runbook daemon library — re-exports for tests and integration consumers.
 
+pub mod clients;
 pub mod config;
+pub mod config_preprocess;
+#[cfg(unix)]
+pub mod fifo;
+pub mod openapi;
+pub mod rate_limit;
 pub mod reducer;
 pub mod render;
 pub mod state;
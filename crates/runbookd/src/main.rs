@@ -1,43 +1,106 @@
-use std::{net::SocketAddr, sync::Arc};
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 
 use axum::{
-    extract::{ws::WebSocketUpgrade, State},
-    response::IntoResponse,
+    extract::{ws::WebSocketUpgrade, Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
     routing::{get, post},
     Json, Router,
 };
 use clap::Parser;
 use futures::{SinkExt, StreamExt};
-use tokio::sync::{broadcast, Mutex};
-use tracing::{error, info, warn};
+use tokio::sync::{mpsc, watch, Mutex};
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 use runbook_protocol::{
-    ClientKind, ClientToDaemon, DaemonToClient, HelloAck, HookEvent, Notice,
+    error::ProtocolError,
+    validate::ValidationError,
+    ClientKind, ClientToDaemon, ConfigError, ConfigSummary, DaemonStatus, DaemonToClient,
+    DebugDump, ErrorCode, ErrorMessage, ExportRequest, HealthAck, Heartbeat, HelloAck, HookEvent,
+    Notice, RenderModel, SessionList, SlotHighlight, TerminalTarget, ToastMessage,
     PROTOCOL_VERSION,
 };
 
+mod clients;
 mod config;
+mod config_preprocess;
+#[cfg(unix)]
+mod fifo;
+mod rate_limit;
 mod reducer;
 mod render;
 mod state;
 
+use clients::{
+    ClientCapabilities, ClientId, ClientRegistry, CLIENT_QUEUE_CAPACITY, DAEMON_CAPABILITIES,
+};
 use config::RunbookConfig;
+use rate_limit::RateLimiter;
 use reducer::{ClientKindTag, Event, SideEffect};
-use state::DaemonState;
+use state::{
+    DaemonState, DaemonStateSnapshot, PromptStats, ResolvedTarget, VSCODE_TERMINAL_INDEX,
+};
 
-#[derive(Debug, Parser)]
+#[derive(Debug, Clone, Parser)]
 #[command(name = "runbookd", about = "Runbook daemon")]
 struct Args {
     /// Path to runbook.yaml
     #[arg(long, default_value = "./runbook.yaml")]
     config: String,
+
+    /// Optional second YAML layered on top of --config (e.g. runbook.local.yaml)
+    #[arg(long)]
+    config_override: Option<String>,
+
+    /// Fail on `${VAR}` references to undefined environment variables
+    /// (default: leave them in place)
+    #[arg(long)]
+    strict_env: bool,
+
+    /// Fail on config warnings (e.g. a `claude_command` without a leading
+    /// `/`) instead of logging them
+    #[arg(long)]
+    strict_config: bool,
+
+    /// Restore session state from this file on startup; rewritten on shutdown
+    #[arg(long)]
+    state_file: Option<String>,
 }
 
+/// How often `Event::Tick` runs.
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// How often sessions past `daemon.session_ttl_secs` are dropped.
+const SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long VS Code has to `command_ack` a `vscode_command`.
+const COMMAND_ACK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often unacked commands are checked against [`COMMAND_ACK_TIMEOUT`].
+const COMMAND_CLEANUP_INTERVAL: Duration = Duration::from_secs(1);
+
 #[derive(Clone)]
 struct App {
-    config: Arc<RunbookConfig>,
+    args: Arc<Args>,
+    /// Swapped wholesale on reload; read via `App::config()`.
+    config: Arc<RwLock<Arc<RunbookConfig>>>,
     state: Arc<Mutex<DaemonState>>,
-    tx: broadcast::Sender<DaemonToClient>,
+    clients: Arc<Mutex<ClientRegistry>>,
+    /// Flipped to `true` once shutdown starts; WebSocket handlers watch it.
+    shutdown: Arc<watch::Sender<bool>>,
+    /// Enforces `daemon.hook_rate_limit` on `POST /hook`.
+    hook_limiter: Arc<RateLimiter>,
+    /// Config errors from startup, sent to each client after `hello` until a
+    /// reload succeeds.
+    startup_errors: Arc<RwLock<Vec<ConfigError>>>,
 }
 
 #[tokio::main]
@@ -47,69 +110,509 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     let args = Args::parse();
-    let config = load_config(&args.config)?;
-    config.validate()?;
-
-    let initial_page = config.keypad.initial_page;
-
-    let (tx, _rx) = broadcast::channel::<DaemonToClient>(256);
+    let (config, startup_errors) = load_startup(&args)?;
 
-    let app = App {
-        config: Arc::new(config),
-        state: Arc::new(Mutex::new(DaemonState::new(initial_page))),
-        tx,
-    };
+    let daemon = config.daemon.clone();
+    let app = App::new(args, config);
+    *app.startup_errors.write().expect("startup errors lock poisoned") = startup_errors;
+    app.restore_state().await;
+    {
+        let mut state = app.state.lock().await;
+        state.current_branch = git_branch();
+        state.hostname = hostname();
+    }
 
     // Emit initial render.
     app.broadcast_render().await;
 
-    let router = Router::new()
-        .route("/ws", get(ws_handler))
-        .route("/hook", post(hook_handler))
-        .with_state(app.clone());
+    #[cfg(unix)]
+    tokio::spawn(reload_on_sighup(app.clone()));
+
+    if let Some(path) = daemon.fifo.clone() {
+        spawn_fifo_reader(app.clone(), path);
+    }
 
-    let addr: SocketAddr = app
-        .config
-        .daemon
+    tokio::spawn(tick(app.clone()));
+    tokio::spawn(heartbeat(app.clone()));
+    tokio::spawn(expire_sessions(app.clone()));
+    tokio::spawn(expire_commands(app.clone()));
+
+    let router = router(app.clone());
+
+    let addr: SocketAddr = daemon
         .listen
         .parse()
         .map_err(|e| anyhow::anyhow!("invalid daemon.listen: {e}"))?;
 
-    info!(%addr, "runbookd listening");
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, router).await?;
+    let socket = if addr.is_ipv4() {
+        tokio::net::TcpSocket::new_v4()?
+    } else {
+        tokio::net::TcpSocket::new_v6()?
+    };
+    socket.set_reuseaddr(true)?;
+    socket.bind(addr)?;
+    let listener = socket.listen(daemon.listen_backlog)?;
+    info!(%addr, backlog = daemon.listen_backlog, "runbookd listening");
+
+    let shutdown_app = app.clone();
+    axum::serve(listener, router)
+        .with_graceful_shutdown(async move {
+            shutdown_signal().await;
+            shutdown_app.begin_shutdown().await;
+        })
+        .await?;
+
+    // Stop accepting is done; give open WebSocket handlers time to finish.
+    let timeout = Duration::from_secs(daemon.shutdown_timeout_secs);
+    if clients::wait_for_drain(&app.clients, timeout).await {
+        info!("all clients disconnected");
+    } else {
+        warn!(?timeout, "shutdown timeout elapsed with clients still connected");
+    }
+    app.save_state().await;
 
     Ok(())
 }
 
-fn load_config(path: &str) -> anyhow::Result<RunbookConfig> {
-    let bytes =
-        std::fs::read(path).map_err(|e| anyhow::anyhow!("failed to read config '{path}': {e}"))?;
-    let cfg: RunbookConfig = serde_yaml::from_slice(&bytes)
+fn router(app: App) -> Router {
+    Router::new()
+        .route("/ws", get(ws_handler))
+        .route("/hook", post(hook_handler))
+        .route("/sessions", get(sessions_handler))
+        .route("/sessions/:id/select", post(select_session_handler))
+        .route("/gates/:id/url", get(gate_url_handler))
+        .route("/export", post(export_handler))
+        .route("/prompts", get(prompts_handler))
+        .route("/prompt-stats", get(prompt_stats_handler))
+        .route("/reload", post(reload_handler))
+        .route("/status", get(status_handler))
+        .route("/debug/dump", post(debug_dump_handler))
+        .with_state(app)
+}
+
+/// Resolve on SIGINT (Ctrl+C) or, on Unix, SIGTERM.
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("failed to listen for ctrl-c: {e}");
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                error!("failed to listen for SIGTERM: {e}");
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Reload config on SIGHUP. Listener settings (`daemon.*`) need a restart.
+#[cfg(unix)]
+async fn reload_on_sighup(app: App) {
+    let mut sig = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(sig) => sig,
+        Err(e) => {
+            error!("failed to listen for SIGHUP: {e}");
+            return;
+        }
+    };
+    while sig.recv().await.is_some() {
+        // Failures are logged; the previous config stays active.
+        let _ = app.reload_config().await;
+    }
+}
+
+/// Drive periodic reducer housekeeping.
+async fn tick(app: App) {
+    let mut interval = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        interval.tick().await;
+        app.apply_event(Event::Tick).await;
+    }
+}
+
+/// Drop sessions that have gone quiet for `daemon.session_ttl_secs` (e.g.
+/// Claude was killed before `SessionEnd`), so `sessions` can't grow forever.
+async fn expire_sessions(app: App) {
+    let mut interval = tokio::time::interval(SESSION_CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let ttl = app.config().daemon.session_ttl_secs;
+        if ttl == 0 {
+            continue;
+        }
+        let removed = {
+            let mut state = app.state.lock().await;
+            let expired = state.expired_sessions(Duration::from_secs(ttl), Instant::now());
+            for session_id in &expired {
+                info!(session_id, "session expired");
+                state.expire_session(session_id);
+            }
+            expired.len()
+        };
+        if removed > 0 {
+            app.broadcast_render().await;
+        }
+    }
+}
+
+/// Number `cmd` and hold it for a `command_ack` if VS Code sends them;
+/// otherwise there is nothing to wait for, so just note it.
+fn track_command(state: &mut DaemonState, cmd: &mut runbook_protocol::VscodeCommand) {
+    if !state.track_command(cmd, Instant::now()) {
+        debug!(command_id = cmd.command_id, kind = ?cmd.kind, "vscode command not tracked for ack");
+    }
+}
+
+/// Give up on `vscode_command`s VS Code hasn't acked within
+/// [`COMMAND_ACK_TIMEOUT`], and say so.
+async fn expire_commands(app: App) {
+    let mut interval = tokio::time::interval(COMMAND_CLEANUP_INTERVAL);
+    loop {
+        interval.tick().await;
+        let expired = app
+            .state
+            .lock()
+            .await
+            .expire_commands(COMMAND_ACK_TIMEOUT, Instant::now());
+        for cmd in expired {
+            warn!(command_id = cmd.command_id, kind = ?cmd.kind, "vscode command not acked");
+            app.broadcast(DaemonToClient::Notice(Notice {
+                message: format!(
+                    "VS Code did not ack command {} ({:?}) within {}s",
+                    cmd.command_id,
+                    cmd.kind,
+                    COMMAND_ACK_TIMEOUT.as_secs()
+                ),
+            }))
+            .await;
+        }
+    }
+}
+
+/// Broadcast `heartbeat` every `daemon.heartbeat_secs` (re-read after each
+/// beat so reloads apply; 0 pauses heartbeats).
+async fn heartbeat(app: App) {
+    loop {
+        let secs = app.config().daemon.heartbeat_secs;
+        tokio::time::sleep(Duration::from_secs(secs.max(1))).await;
+        if secs == 0 {
+            continue;
+        }
+        let ts = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        app.broadcast(DaemonToClient::Heartbeat(Heartbeat { ts })).await;
+    }
+}
+
+/// Feed hook events from `daemon.fifo` into the reducer.
+#[cfg(unix)]
+fn spawn_fifo_reader(app: App, path: String) {
+    let (tx, mut rx) = mpsc::unbounded_channel::<HookEvent>();
+    tokio::spawn(async move {
+        info!(path, "reading hook events from fifo");
+        if let Err(e) = fifo::read_events(std::path::Path::new(&path), tx).await {
+            error!("fifo reader stopped: {e:#}");
+        }
+    });
+    tokio::spawn(async move {
+        while let Some(ev) = rx.recv().await {
+            // Rejections are logged by apply_hook_event; there is no one to reply to.
+            let _ = app.apply_hook_event(ev).await;
+        }
+    });
+}
+
+#[cfg(not(unix))]
+fn spawn_fifo_reader(_app: App, path: String) {
+    warn!(path, "daemon.fifo is only supported on Unix; ignoring");
+}
+
+/// Load `--config`, layer `--config-override` on top, and validate the result.
+fn load_layered(args: &Args) -> anyhow::Result<RunbookConfig> {
+    let mut config = load_config(&args.config, args.strict_env)?;
+    if let Some(ref path) = args.config_override {
+        let local = load_config(path, args.strict_env)?;
+        config = RunbookConfig::merge(&config, &local);
+        info!(path, "applied config override");
+    }
+    let warnings = config.validate()?;
+    if args.strict_config {
+        if let Some(first) = warnings.first() {
+            anyhow::bail!("{first} (--strict-config)");
+        }
+    }
+    for warning in &warnings {
+        warn!("config: {warning}");
+    }
+    Ok(config)
+}
+
+/// Config to start with. An override rejected outside `policy` and
+/// `hook_policy` isn't fatal: the base config is used with only the
+/// override's policy sections, and the error is kept for clients. Anything
+/// else (a broken base config, an unparsable override, a bad policy field)
+/// still stops the daemon, since starting without the override's policy
+/// lists would silently loosen them.
+fn load_startup(args: &Args) -> anyhow::Result<(RunbookConfig, Vec<ConfigError>)> {
+    let err = match load_layered(args) {
+        Ok(config) => return Ok((config, vec![])),
+        Err(e) => e,
+    };
+    let Some(ref path) = args.config_override else {
+        return Err(err);
+    };
+    let reported = config::config_error(&err);
+    let field = reported.field_path.as_str();
+    if field.is_empty() || field.starts_with("policy") || field.starts_with("hook_policy") {
+        return Err(err);
+    }
+    let base = Args {
+        config_override: None,
+        ..args.clone()
+    };
+    let mut config = load_layered(&base)?;
+    let merged = RunbookConfig::merge(&config, &load_config(path, args.strict_env)?);
+    config.policy = merged.policy;
+    config.hook_policy = merged.hook_policy;
+    warn!(path, "config override rejected; using its policy sections only: {err:#}");
+    Ok((config, vec![reported]))
+}
+
+fn load_config(path: &str, strict_env: bool) -> anyhow::Result<RunbookConfig> {
+    let raw = std::fs::read_to_string(path)
+        .map_err(|e| anyhow::anyhow!("failed to read config '{path}': {e}"))?;
+    let yaml = config_preprocess::preprocess_config(&raw, strict_env)
+        .map_err(|e| anyhow::anyhow!("failed to expand '{path}': {e}"))?;
+    let mut cfg = RunbookConfig::from_yaml(&yaml)
         .map_err(|e| anyhow::anyhow!("failed to parse yaml '{path}': {e}"))?;
+    cfg.env_values = config_preprocess::env_values(&raw);
     Ok(cfg)
 }
 
+/// This machine's name: `gethostname(2)`, or `%COMPUTERNAME%` on Windows.
+#[cfg(unix)]
+fn hostname() -> Option<String> {
+    nix::unistd::gethostname().ok()?.into_string().ok()
+}
+
+#[cfg(not(unix))]
+fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME").ok()
+}
+
+/// Branch checked out in the daemon's working directory, if it's a git repo.
+fn git_branch() -> Option<String> {
+    let out = std::process::Command::new("git")
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .output()
+        .ok()?;
+    if !out.status.success() {
+        return None;
+    }
+    let s = String::from_utf8_lossy(&out.stdout).trim().to_string();
+    (!s.is_empty()).then_some(s)
+}
+
 // ---------------------------------------------------------------------------
 // HTTP handlers
 // ---------------------------------------------------------------------------
 
+/// Correlates log lines from one HTTP request or WebSocket connection.
+fn next_request_id() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(1);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+/// Frames and messages over `daemon.ws_max_message_size` are refused while
+/// reading, before they are buffered.
 async fn ws_handler(ws: WebSocketUpgrade, State(app): State<App>) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_socket(app, socket))
+    let span = info_span!("ws", request_id = next_request_id());
+    let limit = app.config().daemon.ws_max_message_size;
+    ws.max_message_size(limit)
+        .max_frame_size(limit)
+        .on_upgrade(move |socket| handle_socket(app, socket).instrument(span))
 }
 
+/// The `TooLarge` error behind a WebSocket read that hit the size limit.
+fn too_large(e: axum::Error) -> Option<ValidationError> {
+    use tungstenite::error::{CapacityError, Error};
+    match e.into_inner().downcast_ref::<Error>()? {
+        Error::Capacity(CapacityError::MessageTooLong { size, max_size }) => {
+            Some(ValidationError::TooLarge {
+                len: *size,
+                limit: *max_size,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// 403 with an `ErrorMessage` body when `hook_policy` rejects the event.
 async fn hook_handler(
     State(app): State<App>,
     Json(ev): Json<HookEvent>,
-) -> impl IntoResponse {
-    app.apply_event(Event::HookEvent {
-        hook: ev.hook,
-        matcher: ev.matcher,
-        session_id: ev.session_id,
-        session_tag: ev.session_tag,
+) -> Result<&'static str, Response> {
+    if let Some(per_sec) = app.config().daemon.hook_rate_limit {
+        if let Err(rejected) = app.hook_limiter.check(per_sec, Instant::now()) {
+            if rejected.first {
+                warn!(per_sec, "hook rate limit exceeded; rejecting events until the next second");
+            }
+            let retry_after = rejected.retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            let err = ErrorMessage {
+                code: ErrorCode::RateLimited,
+                message: format!("more than {per_sec} hook events per second"),
+            };
+            return Err((
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(err),
+            )
+                .into_response());
+        }
+    }
+    let span = info_span!("hook", request_id = next_request_id());
+    app.apply_hook_event(ev)
+        .instrument(span)
+        .await
+        .map(|()| "ok")
+        .map_err(|err| (StatusCode::FORBIDDEN, Json(err)).into_response())
+}
+
+async fn sessions_handler(State(app): State<App>) -> Json<SessionList> {
+    let state = app.state.lock().await;
+    Json(render::build_session_list(&state))
+}
+
+/// Focus a live session from outside the WebSocket (e.g. VS Code terminal focus).
+async fn select_session_handler(
+    State(app): State<App>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if !app.state.lock().await.sessions.contains_key(&id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    app.apply_event(Event::SelectSession {
+        session_id: id.clone(),
+    })
+    .await;
+    Ok(Json(serde_json::json!({ "active_session": id })))
+}
+
+/// Same as an `export_request` over the WebSocket, for scripts and CI.
+/// 404 if `session_id` names a session that isn't live.
+async fn export_handler(
+    State(app): State<App>,
+    Json(req): Json<ExportRequest>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    if let Some(ref id) = req.session_id {
+        let state = app.state.lock().await;
+        if !state.sessions.contains_key(id) {
+            return Err(StatusCode::NOT_FOUND);
+        }
+        if state.session_terminal(id).is_none() {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+    let command = req.format.command();
+    app.apply_event(Event::Export {
+        session_id: req.session_id,
+        format: req.format,
     })
     .await;
-    "ok"
+    Ok(Json(serde_json::json!({ "command": command })))
+}
+
+/// A gate's `url_template` with the current session tag, branch and host filled in.
+async fn gate_url_handler(
+    State(app): State<App>,
+    Path(id): Path<String>,
+) -> Result<Json<serde_json::Value>, StatusCode> {
+    let config = app.config();
+    let template = config
+        .gates
+        .get(&id)
+        .and_then(|g| g.url_template.as_deref())
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let url = app.state.lock().await.resolve_url_template(template);
+    Ok(Json(serde_json::json!({ "gate": id, "url": url })))
+}
+
+/// Reload config from disk, like SIGHUP (which Windows doesn't have).
+async fn reload_handler(State(app): State<App>) -> (StatusCode, Json<serde_json::Value>) {
+    match app.reload_config().await {
+        Ok(summary) => (
+            StatusCode::OK,
+            Json(serde_json::json!({ "status": "reloaded", "page_count": summary.pages.len() })),
+        ),
+        Err(e) => (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(serde_json::json!({ "error": "validation failed", "detail": format!("{e:#}") })),
+        ),
+    }
+}
+
+/// One human-readable line per configured prompt, sorted by id.
+async fn prompts_handler(State(app): State<App>) -> Json<Vec<String>> {
+    Json(app.config().prompt_summary())
+}
+
+/// Arm/dispatch counts for every configured prompt, sorted by id.
+async fn prompt_stats_handler(State(app): State<App>) -> Json<Vec<PromptStats>> {
+    let config = app.config();
+    let mut ids: Vec<&String> = config.prompts.keys().collect();
+    ids.sort();
+    let state = app.state.lock().await;
+    Json(ids.into_iter().map(|id| state.prompt_stats(id)).collect())
+}
+
+async fn status_handler(State(app): State<App>) -> Json<DaemonStatus> {
+    let client_count = app.clients.lock().await.len() as u32;
+    let state = app.state.lock().await;
+    Json(DaemonStatus {
+        daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+        protocol: PROTOCOL_VERSION,
+        uptime_secs: state.uptime().as_secs(),
+        client_count,
+        session_count: state.sessions.len() as u32,
+        hooks_mode: state.hooks_mode.clone(),
+        ws_max_message_size: app.config().daemon.ws_max_message_size,
+        last_dispatched: state.last_dispatched.clone(),
+        last_dispatched_session_id: state.last_dispatched_session_id.clone(),
+    })
+}
+
+/// Dump state + config for the caller only. 404 unless `daemon.debug_enabled`.
+async fn debug_dump_handler(State(app): State<App>) -> Result<Json<DebugDump>, StatusCode> {
+    let config = app.config();
+    if !config.daemon.debug_enabled {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let client_count = app.clients.lock().await.len();
+    let dump = {
+        let state = app.state.lock().await;
+        render::build_debug_dump(&state, &config, client_count, state.uptime())
+    };
+    info!("debug dump requested");
+    app.broadcast(DaemonToClient::Debug(dump.clone())).await;
+    Ok(Json(dump))
 }
 
 // ---------------------------------------------------------------------------
@@ -117,65 +620,73 @@ async fn hook_handler(
 // ---------------------------------------------------------------------------
 
 async fn handle_socket(app: App, socket: axum::extract::ws::WebSocket) {
-    let (ws_tx, mut ws_rx) = socket.split();
-
-    // Wrap the sender in an Arc<Mutex> so both tasks can use it.
-    let ws_tx = Arc::new(Mutex::new(ws_tx));
+    let (mut ws_tx, mut ws_rx) = socket.split();
 
-    // Subscribe to daemon broadcast.
-    let mut rx = app.tx.subscribe();
+    // Register this connection; outbound messages are queued per client.
+    let (tx, mut rx) = mpsc::channel::<DaemonToClient>(CLIENT_QUEUE_CAPACITY);
+    let client_id = app.clients.lock().await.register(tx);
 
     // Send hello proactively.
-    {
-        let mut tx = ws_tx.lock().await;
-        let _ = tx
-            .send(axum::extract::ws::Message::Text(
-                serde_json::to_string(&DaemonToClient::Hello(HelloAck {
-                    protocol: PROTOCOL_VERSION,
-                    daemon_version: env!("CARGO_PKG_VERSION").to_string(),
-                }))
-                .unwrap(),
-            ))
-            .await;
-    }
+    app.clients.lock().await.send_to(
+        client_id,
+        DaemonToClient::Hello(HelloAck {
+            protocol: PROTOCOL_VERSION,
+            daemon_version: env!("CARGO_PKG_VERSION").to_string(),
+            capabilities: DAEMON_CAPABILITIES.iter().map(|c| c.to_string()).collect(),
+        }),
+    );
 
-    // Track which client kind this is for disconnect handling.
-    let client_kind: Arc<Mutex<Option<ClientKindTag>>> = Arc::new(Mutex::new(None));
-
-    // Task: forward broadcast → websocket
-    let forward = {
-        let ws_tx_fwd = Arc::clone(&ws_tx);
-        tokio::spawn(async move {
-            while let Ok(msg) = rx.recv().await {
-                let text = match serde_json::to_string(&msg) {
-                    Ok(t) => t,
-                    Err(e) => {
-                        error!("failed to serialize daemon msg: {e}");
-                        continue;
-                    }
-                };
-                let mut tx = ws_tx_fwd.lock().await;
-                if tx
-                    .send(axum::extract::ws::Message::Text(text))
-                    .await
-                    .is_err()
-                {
-                    break;
+    // Task: forward queued messages → websocket
+    let forward = tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            let text = match serde_json::to_string(&msg) {
+                Ok(t) => t,
+                Err(e) => {
+                    error!("failed to serialize daemon msg: {e}");
+                    continue;
                 }
+            };
+            if ws_tx
+                .send(axum::extract::ws::Message::Text(text))
+                .await
+                .is_err()
+            {
+                break;
             }
-        })
-    };
+        }
+        let _ = ws_tx.close().await;
+    });
 
-    // Receive loop
-    while let Some(Ok(msg)) = ws_rx.next().await {
+    // Receive loop (ends on client close or daemon shutdown)
+    let mut shutdown = app.shutdown.subscribe();
+    loop {
+        let msg = tokio::select! {
+            msg = ws_rx.next() => match msg {
+                Some(msg) => msg,
+                None => break,
+            },
+            _ = shutdown.wait_for(|stop| *stop) => break,
+        };
+        let msg = match msg {
+            Ok(msg) => msg,
+            Err(e) => {
+                if let Some(e) = too_large(e) {
+                    warn!(client_id, "{e}; closing");
+                    app.send_error(client_id, &e.into()).await;
+                }
+                break;
+            }
+        };
         match msg {
             axum::extract::ws::Message::Text(ref text) => {
-                match serde_json::from_str::<ClientToDaemon>(text) {
-                    Ok(parsed) => {
-                        app.handle_client_message(parsed, &client_kind).await;
+                match ClientToDaemon::parse(text) {
+                    Ok(parsed) => app.handle_client_message(client_id, parsed).await,
+                    Err(ProtocolError::Deserialization(e)) => {
+                        error!("invalid json from client: {e}; text={text}");
                     }
                     Err(e) => {
-                        error!("invalid json from client: {e}; text={text}");
+                        warn!(client_id, "rejected client message: {e}");
+                        app.send_error(client_id, &e).await;
                     }
                 }
             }
@@ -184,44 +695,125 @@ async fn handle_socket(app: App, socket: axum::extract::ws::WebSocket) {
         }
     }
 
-    forward.abort();
-
-    // Handle disconnect.
-    let kind = client_kind.lock().await.take();
-    if let Some(k) = kind {
+    // Handle disconnect. Unregistering drops the queue sender, so the forward
+    // task flushes anything still queued (e.g. the shutdown notice) and exits.
+    let kind = app
+        .clients
+        .lock()
+        .await
+        .unregister(client_id)
+        .and_then(|e| e.kind);
+    let _ = forward.await;
+    if let Some(k) = kind.and_then(kind_tag) {
         app.apply_event(Event::ClientDisconnected { kind: k }).await;
     }
 }
 
+/// Map a protocol client kind to the reducer's connection tag (hooks aren't tracked).
+fn kind_tag(kind: ClientKind) -> Option<ClientKindTag> {
+    match kind {
+        ClientKind::Logi => Some(ClientKindTag::Logi),
+        ClientKind::Vscode => Some(ClientKindTag::Vscode),
+        ClientKind::Hooks => None,
+    }
+}
+
 // ---------------------------------------------------------------------------
 // App methods
 // ---------------------------------------------------------------------------
 
 impl App {
-    async fn handle_client_message(
-        &self,
-        msg: ClientToDaemon,
-        client_kind: &Arc<Mutex<Option<ClientKindTag>>>,
-    ) {
+    fn new(args: Args, config: RunbookConfig) -> Self {
+        let initial_page = config.keypad.initial_page;
+        Self {
+            args: Arc::new(args),
+            config: Arc::new(RwLock::new(Arc::new(config))),
+            state: Arc::new(Mutex::new(DaemonState::new(initial_page))),
+            clients: Arc::new(Mutex::new(ClientRegistry::new())),
+            shutdown: Arc::new(watch::channel(false).0),
+            hook_limiter: Arc::new(RateLimiter::new(Instant::now())),
+            startup_errors: Arc::new(RwLock::new(Vec::new())),
+        }
+    }
+
+    /// Snapshot of the current config.
+    fn config(&self) -> Arc<RunbookConfig> {
+        Arc::clone(&self.config.read().expect("config lock poisoned"))
+    }
+
+    /// Re-read config from disk. On error the previous config stays active.
+    async fn reload_config(&self) -> anyhow::Result<ConfigSummary> {
+        let config = match load_layered(&self.args) {
+            Ok(config) => config,
+            Err(e) => {
+                warn!("config reload failed; keeping previous config: {e:#}");
+                self.broadcast(DaemonToClient::ConfigError(config::config_error(&e)))
+                    .await;
+                return Err(e);
+            }
+        };
+        let summary = config.summary();
+        *self.config.write().expect("config lock poisoned") = Arc::new(config);
+        self.startup_errors
+            .write()
+            .expect("startup errors lock poisoned")
+            .clear();
+        info!(path = %self.args.config, "config reloaded");
+
+        self.apply_event(Event::ConfigReloaded).await;
+        self.broadcast(DaemonToClient::ConfigReloaded(summary.clone()))
+            .await;
+        Ok(summary)
+    }
+
+    async fn handle_client_message(&self, client_id: ClientId, msg: ClientToDaemon) {
         match msg {
             ClientToDaemon::Hello(hello) => {
-                // Track client kind.
-                let kind_tag = match hello.client {
-                    ClientKind::Logi => Some(ClientKindTag::Logi),
-                    ClientKind::Vscode => Some(ClientKindTag::Vscode),
-                    ClientKind::Hooks => None,
-                };
-                if let Some(k) = kind_tag {
-                    *client_kind.lock().await = Some(k);
+                // Track client kind and negotiated capabilities.
+                if let Some(entry) = self.clients.lock().await.get_mut(client_id) {
+                    entry.kind = Some(hello.client);
+                    entry.capabilities = ClientCapabilities::from_list(&hello.capabilities);
+                }
+                if hello.client == ClientKind::Vscode {
+                    self.state.lock().await.vscode_capabilities =
+                        hello.capabilities.iter().cloned().collect();
+                }
+                if let Some(k) = kind_tag(hello.client) {
                     self.apply_event(Event::ClientConnected { kind: k }).await;
                 }
 
-                let _ = self.tx.send(DaemonToClient::Notice(Notice {
+                if hello.client == ClientKind::Logi {
+                    let (banner, toasts) = {
+                        let mut state = self.state.lock().await;
+                        let banner = render::build_banner(&state, &self.config());
+                        (banner, std::mem::take(&mut state.pending_toasts))
+                    };
+                    let clients = self.clients.lock().await;
+                    clients.send_to(client_id, DaemonToClient::Banner(banner));
+                    for toast in toasts {
+                        clients.send_to(client_id, DaemonToClient::Toast(toast));
+                    }
+                }
+
+                let startup_errors = self
+                    .startup_errors
+                    .read()
+                    .expect("startup errors lock poisoned")
+                    .clone();
+                {
+                    let clients = self.clients.lock().await;
+                    for err in startup_errors {
+                        clients.send_to(client_id, DaemonToClient::ConfigError(err));
+                    }
+                }
+
+                self.broadcast(DaemonToClient::Notice(Notice {
                     message: format!(
                         "client connected: {:?} v{} (protocol {})",
                         hello.client, hello.version, hello.protocol
                     ),
-                }));
+                }))
+                .await;
 
                 // Send current render state.
                 self.broadcast_render().await;
@@ -258,29 +850,139 @@ impl App {
                 .await;
             }
 
+            ClientToDaemon::PageJump(pj) => {
+                self.apply_event(Event::PageJump { hotkey: pj.hotkey })
+                    .await;
+            }
+
+            ClientToDaemon::GestureInput(g) => {
+                self.apply_event(Event::Gesture {
+                    direction: g.direction,
+                    distance: g.distance,
+                })
+                .await;
+            }
+
+            ClientToDaemon::Macro(m) => {
+                self.apply_event(Event::StartMacro { steps: m.steps }).await;
+            }
+
+            ClientToDaemon::ExportRequest(req) => {
+                self.apply_event(Event::Export {
+                    session_id: req.session_id,
+                    format: req.format,
+                })
+                .await;
+            }
+
             ClientToDaemon::HookEvent(ev) => {
-                self.apply_event(Event::HookEvent {
-                    hook: ev.hook,
-                    matcher: ev.matcher,
-                    session_id: ev.session_id,
-                    session_tag: ev.session_tag,
+                if let Err(err) = self.apply_hook_event(ev).await {
+                    self.clients.lock().await.send_to(client_id, DaemonToClient::Error(err));
+                }
+            }
+
+            ClientToDaemon::SetFocusedSession(f) => {
+                debug!(client_id, session_id = %f.session_id, "client focused session");
+                if let Some(entry) = self.clients.lock().await.get_mut(client_id) {
+                    entry.focused_session_id = Some(f.session_id.clone());
+                }
+                let model = {
+                    let state = self.state.lock().await;
+                    render::build_render_model_for_session(
+                        &state,
+                        &self.config(),
+                        Some(&f.session_id),
+                    )
+                };
+                self.clients
+                    .lock()
+                    .await
+                    .send_to(client_id, DaemonToClient::Render(model));
+            }
+
+            ClientToDaemon::TagSelect(t) => {
+                self.apply_event(Event::TagSelect {
+                    session_tag: t.session_tag,
                 })
                 .await;
             }
 
             ClientToDaemon::TerminalsSnapshot(snapshot) => {
                 self.apply_event(Event::TerminalsSnapshot(snapshot)).await;
+                let state = self.state.lock().await;
+                debug!(
+                    terminal = state.active_terminal_name(),
+                    session_tag = state.active_terminal_session_tag(),
+                    "active terminal"
+                );
+            }
+
+            ClientToDaemon::SlotProgress(sp) => {
+                self.apply_event(Event::SlotProgress {
+                    prompt_id: sp.prompt_id,
+                    progress: sp.progress,
+                })
+                .await;
+            }
+
+            ClientToDaemon::CommandAck(ack) => {
+                let cmd = self.state.lock().await.ack_command(ack.command_id);
+                match (cmd, ack.success) {
+                    (None, _) => debug!(command_id = ack.command_id, "ack for unknown command"),
+                    (Some(cmd), true) => debug!(command_id = ack.command_id, kind = ?cmd.kind, "command acked"),
+                    (Some(cmd), false) => warn!(
+                        command_id = ack.command_id,
+                        kind = ?cmd.kind,
+                        error = ack.error.as_deref().unwrap_or("unknown"),
+                        "vscode command failed"
+                    ),
+                }
+            }
+
+            ClientToDaemon::Reconfigure(request) => {
+                let kind = self.clients.lock().await.get(client_id).and_then(|c| c.kind);
+                match kind {
+                    Some(kind) => self.apply_event(Event::Reconfigure { kind, request }).await,
+                    None => warn!(client_id, "reconfigure before hello; ignored"),
+                }
+            }
+
+            ClientToDaemon::HealthCheck(check) => {
+                let ack = {
+                    let state = self.state.lock().await;
+                    HealthAck {
+                        request_id: check.request_id,
+                        uptime_secs: state.uptime().as_secs(),
+                        session_count: state.sessions.len(),
+                    }
+                };
+                self.clients
+                    .lock()
+                    .await
+                    .send_to(client_id, DaemonToClient::HealthAck(ack));
             }
         }
     }
 
     /// Check if a prompt_id is actually a gate; if so, dispatch it immediately.
     async fn check_gate(&self, id: &str) -> bool {
-        if let Some(gate) = self.config.gates.get(id) {
+        let config = self.config();
+        if !config.gate_is_available(id) {
+            return false;
+        }
+        if let Some(gate) = config.gates.get(id) {
             // Gates dispatch immediately (they're navigation, not prompts).
             info!(gate_id = id, action = %gate.action, "gate triggered");
-            let cmd = runbook_protocol::VscodeCommand::open_uri(&gate.action);
-            let _ = self.tx.send(DaemonToClient::VscodeCommand(cmd));
+            let mut state = self.state.lock().await;
+            let uri = match gate.url_template {
+                Some(ref template) => state.resolve_url_template(template),
+                None => gate.action.clone(),
+            };
+            let mut cmd = runbook_protocol::VscodeCommand::open_uri(&uri);
+            track_command(&mut state, &mut cmd);
+            drop(state);
+            self.send_to_kind(ClientKind::Vscode, DaemonToClient::VscodeCommand(cmd))
+                .await;
             true
         } else {
             false
@@ -289,29 +991,1002 @@ impl App {
 
     /// Apply a reducer event: mutate state, then execute side effects.
     async fn apply_event(&self, event: Event) {
-        let effects = {
+        let effects: Vec<SideEffect> = {
             let mut state = self.state.lock().await;
-            reducer::reduce(&mut state, &self.config, event)
+            let config = self.config();
+            reducer::reduce(&mut state, &config, event)
+                .into_iter()
+                .filter_map(|effect| {
+                    let effect = match effect {
+                        SideEffect::SendVscodeCommand(mut cmd) => {
+                            cmd.target = self.dispatch_target(&state, &cmd.target)?;
+                            track_command(&mut state, &mut cmd);
+                            SideEffect::SendVscodeCommand(cmd)
+                        }
+                        other => other,
+                    };
+                    Some(effect.into_targeted(&state, &config))
+                })
+                .collect()
         };
 
         for effect in effects {
             match effect {
-                SideEffect::BroadcastRender => {
-                    self.broadcast_render().await;
+                SideEffect::BroadcastRenderPage => {
+                    self.broadcast_render_page().await;
                 }
-                SideEffect::SendVscodeCommand(cmd) => {
-                    if let Err(e) = self.tx.send(DaemonToClient::VscodeCommand(cmd)) {
-                        warn!("no clients to receive VS Code command: {e}");
-                    }
+                SideEffect::BroadcastProgress(progress) => {
+                    self.broadcast(DaemonToClient::Progress(progress)).await;
+                }
+                SideEffect::BroadcastPolicyBlock(block) => {
+                    self.broadcast(DaemonToClient::PolicyBlock(block)).await;
+                }
+                SideEffect::BroadcastSessionList => {
+                    let list = render::build_session_list(&*self.state.lock().await);
+                    self.broadcast(DaemonToClient::SessionList(list)).await;
+                }
+                SideEffect::BroadcastToClientKind(kind, msg) => match *msg {
+                    DaemonToClient::Render(model) => self.send_render_to_kind(kind, model).await,
+                    msg => self.send_to_kind(kind, msg).await,
+                },
+                SideEffect::BroadcastSlotHighlight(slot, color_rgb, duration_ms) => {
+                    let highlight = SlotHighlight {
+                        slot,
+                        color_rgb,
+                        duration_ms,
+                    };
+                    self.broadcast(DaemonToClient::SlotHighlight(highlight)).await;
+                }
+                SideEffect::BroadcastStateChange(change) => {
+                    self.broadcast(DaemonToClient::AgentStateChange(change)).await;
+                }
+                SideEffect::BroadcastToast(message, duration_ms, severity) => {
+                    let toast = ToastMessage {
+                        message,
+                        duration_ms,
+                        severity,
+                    };
+                    self.broadcast(DaemonToClient::Toast(toast)).await;
+                }
+                SideEffect::BroadcastSuggestion(suggestion) => {
+                    self.broadcast(DaemonToClient::PromptSuggestion(suggestion)).await;
+                }
+                SideEffect::BroadcastPageChanged(changed) => {
+                    self.broadcast(DaemonToClient::PageChanged(changed)).await;
                 }
+                SideEffect::BroadcastRender | SideEffect::SendVscodeCommand(_) => {
+                    unreachable!("expanded by into_targeted")
+                }
+            }
+        }
+    }
+
+    /// Apply a hook event unless `hook_policy` rejects it.
+    async fn apply_hook_event(&self, ev: HookEvent) -> Result<(), ErrorMessage> {
+        let verdict = {
+            let state = self.state.lock().await;
+            reducer::check_hook_policy(&state, &self.config(), &ev)
+        };
+        if let Err(e) = verdict {
+            warn!(hook = %ev.hook, session_id = ev.session_id.as_deref(), "{e}");
+            return Err(ErrorMessage {
+                code: ErrorCode::PolicyViolation,
+                message: e.to_string(),
+            });
+        }
+        self.apply_event(ev.into()).await;
+        Ok(())
+    }
+
+    /// Pin `ActiveClaude` commands to a concrete terminal index so keys land
+    /// in the session the device is showing (see
+    /// `DaemonState::resolve_active_claude_index`). Left to the extension
+    /// when no terminals are known, or when it didn't declare
+    /// [`VSCODE_TERMINAL_INDEX`].
+    fn resolve_vscode_target(&self, state: &DaemonState) -> TerminalTarget {
+        if !state.vscode_supports(VSCODE_TERMINAL_INDEX) {
+            return TerminalTarget::ActiveClaude;
+        }
+        state
+            .resolve_active_claude_index()
+            .map(TerminalTarget::ByIndex)
+            .unwrap_or(TerminalTarget::ActiveClaude)
+    }
+
+    /// The target a command goes out with, or `None` to drop it. `ByTag` is
+    /// daemon-only: it becomes the tagged terminal's `ByIndex` (via
+    /// `DaemonState::resolve_target`, as in the reducer). With an unknown tag,
+    /// or an extension that didn't declare [`VSCODE_TERMINAL_INDEX`], there is
+    /// no terminal to send it to.
+    fn dispatch_target(
+        &self,
+        state: &DaemonState,
+        target: &TerminalTarget,
+    ) -> Option<TerminalTarget> {
+        match target {
+            TerminalTarget::ByTag(tag) if !state.vscode_supports(VSCODE_TERMINAL_INDEX) => {
+                warn!(
+                    tag,
+                    "tagged vscode command, but the extension takes no index; not sent"
+                );
+                None
+            }
+            TerminalTarget::ByTag(tag) => match state.resolve_target(target) {
+                ResolvedTarget::Index(i) => Some(TerminalTarget::ByIndex(i)),
+                _ => {
+                    warn!(tag, "vscode command for an unknown terminal tag; not sent");
+                    None
+                }
+            },
+            TerminalTarget::ActiveClaude => Some(self.resolve_vscode_target(state)),
+            other => Some(other.clone()),
+        }
+    }
+
+    /// Report a rejected message back to the client that sent it.
+    async fn send_error(&self, client_id: ClientId, err: &ProtocolError) {
+        self.clients.lock().await.send_to(
+            client_id,
+            DaemonToClient::Error(ErrorMessage {
+                code: err.code(),
+                message: err.to_string(),
+            }),
+        );
+    }
+
+    /// Load `--state-file` if present. A missing or unreadable file starts fresh.
+    async fn restore_state(&self) {
+        let Some(ref path) = self.args.state_file else {
+            return;
+        };
+        let raw = match std::fs::read_to_string(path) {
+            Ok(raw) => raw,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return,
+            Err(e) => {
+                warn!(path, "failed to read state file; starting fresh: {e}");
+                return;
+            }
+        };
+        let snap: DaemonStateSnapshot = match serde_json::from_str(&raw) {
+            Ok(snap) => snap,
+            Err(e) => {
+                warn!(path, "invalid state file; starting fresh: {e}");
+                return;
+            }
+        };
+        *self.state.lock().await = DaemonState::from_snapshot(snap, Instant::now());
+        // The config may have changed since the snapshot; same fix-ups as a reload.
+        self.apply_event(Event::ConfigReloaded).await;
+        info!(path, "restored state");
+    }
+
+    /// Write `--state-file`, via a temp file so a crash mid-write keeps the old one.
+    async fn save_state(&self) {
+        let Some(ref path) = self.args.state_file else {
+            return;
+        };
+        let mut snap = self.state.lock().await.snapshot();
+        snap.saved_at = Some(SystemTime::now());
+        let json = match serde_json::to_string_pretty(&snap) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("failed to serialize state: {e}");
+                return;
             }
+        };
+        let tmp = format!("{path}.tmp");
+        match std::fs::write(&tmp, json).and_then(|()| std::fs::rename(&tmp, path)) {
+            Ok(()) => info!(path, "saved state"),
+            Err(e) => error!(path, "failed to write state file: {e}"),
         }
     }
 
+    /// Tell clients we're going away and signal WebSocket handlers to close.
+    async fn begin_shutdown(&self) {
+        info!("shutdown requested; draining connections");
+        self.broadcast(DaemonToClient::Notice(Notice {
+            message: "daemon shutting down".to_string(),
+        }))
+        .await;
+        self.shutdown.send_replace(true);
+    }
+
+    /// Send every client a full render, of its focused session if it has one.
     async fn broadcast_render(&self) {
+        let (model, focused) = self.build_renders().await;
+        self.clients
+            .lock()
+            .await
+            .broadcast_render(&DaemonToClient::Render(model), &focused);
+    }
+
+    /// Send `model` to clients of one kind, or their focused session's render.
+    async fn send_render_to_kind(&self, kind: ClientKind, model: RenderModel) {
+        let (_, focused) = self.build_renders().await;
+        self.clients.lock().await.send_render_to_kind(
+            kind,
+            &DaemonToClient::Render(model),
+            &focused,
+        );
+    }
+
+    /// Send the current page to `delta_render` clients, a full render to the rest.
+    async fn broadcast_render_page(&self) {
+        let page = {
+            let state = self.state.lock().await;
+            render::build_page_render(&state, &self.config())
+        };
+        let (model, focused) = self.build_renders().await;
+        self.clients.lock().await.broadcast_render_page(
+            &DaemonToClient::RenderPage(page),
+            &DaemonToClient::Render(model),
+            &focused,
+        );
+    }
+
+    /// The shared render, plus one per session some client has focused.
+    async fn build_renders(&self) -> (RenderModel, HashMap<String, DaemonToClient>) {
+        let sessions = self.clients.lock().await.focused_sessions();
         let state = self.state.lock().await;
-        let model = render::build_render_model(&state, &self.config);
-        drop(state);
-        let _ = self.tx.send(DaemonToClient::Render(model));
+        let config = self.config();
+        let focused = sessions
+            .into_iter()
+            .map(|sid| {
+                let model = render::build_render_model_for_session(&state, &config, Some(&sid));
+                (sid, DaemonToClient::Render(model))
+            })
+            .collect();
+        (render::build_render_model(&state, &config), focused)
+    }
+
+    /// Send to every connected client.
+    async fn broadcast(&self, msg: DaemonToClient) {
+        self.clients.lock().await.broadcast(&msg);
+    }
+
+    /// Send only to clients of one kind (e.g. VS Code commands).
+    async fn send_to_kind(&self, kind: ClientKind, msg: DaemonToClient) {
+        if self.clients.lock().await.send_to_kind(kind, &msg) == 0 {
+            warn!(?kind, "no clients to receive targeted message");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::tungstenite::Message;
+
+    fn test_yaml() -> String {
+        format!(
+            "daemon:\n  ws_max_message_size: 1048576\nkeypad:\n  pages:\n    - name: test\n      \
+             slots: {}\n",
+            config::empty_slots(9)
+        )
+    }
+
+    fn test_app(config: RunbookConfig) -> App {
+        let args = Args {
+            config: String::new(),
+            config_override: None,
+            strict_env: false,
+            strict_config: false,
+            state_file: None,
+        };
+        App::new(args, config)
+    }
+
+    /// Serve the daemon router on an ephemeral port.
+    async fn spawn_daemon(config: RunbookConfig) -> SocketAddr {
+        let app = test_app(config);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(app)).await });
+        addr
+    }
+
+    #[tokio::test]
+    async fn invalid_message_gets_validation_error() {
+        let config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        let addr = spawn_daemon(config).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+        ws.next().await.unwrap().unwrap(); // hello
+
+        ws.send(Message::text(r#"{"type":"keypad_press","prompt_id":""}"#))
+            .await
+            .unwrap();
+
+        let reply = ws.next().await.unwrap().unwrap();
+        let parsed: DaemonToClient = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert!(matches!(
+            parsed,
+            DaemonToClient::Error(ErrorMessage {
+                code: ErrorCode::ValidationFailed,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn prompts_lists_one_summary_line_per_prompt() {
+        let yaml = format!(
+            "{base}prompts:\n  review:\n    label: REVIEW\n    claude_command: /review\n  \
+             explain:\n    label: EXPLAIN\n    fallback_text: Explain this.\n",
+            base = test_yaml()
+        );
+        let config: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        let Json(lines) = prompts_handler(State(test_app(config))).await;
+        assert_eq!(
+            lines,
+            ["[explain] EXPLAIN → Explain this.", "[review] REVIEW → /review"]
+        );
+    }
+
+    #[tokio::test]
+    async fn heartbeat_is_broadcast_on_interval() {
+        let mut config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        config.daemon.heartbeat_secs = 1;
+        let app = test_app(config);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(heartbeat(app.clone()));
+        tokio::spawn(async move { axum::serve(listener, router(app)).await });
+
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+        let before = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let beat = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let msg = ws.next().await.unwrap().unwrap();
+                let parsed: DaemonToClient = serde_json::from_str(msg.to_text().unwrap()).unwrap();
+                if let DaemonToClient::Heartbeat(beat) = parsed {
+                    return beat;
+                }
+            }
+        })
+        .await
+        .expect("no heartbeat within 5s");
+        assert!(beat.ts >= before);
+    }
+
+    /// Minimal HTTP/1.1 POST; returns the status code and body.
+    async fn http_post(addr: SocketAddr, path: &str, body: &str) -> (u16, String) {
+        http_request(addr, "POST", path, body).await
+    }
+
+    async fn http_get(addr: SocketAddr, path: &str) -> (u16, String) {
+        http_request(addr, "GET", path, "").await
+    }
+
+    async fn http_request(addr: SocketAddr, method: &str, path: &str, body: &str) -> (u16, String) {
+        let response = http_response(addr, method, path, body).await;
+        let status = response[9..12].parse().unwrap();
+        let body = response
+            .split_once("\r\n\r\n")
+            .map(|(_, b)| b.to_string())
+            .unwrap_or_default();
+        (status, body)
+    }
+
+    /// The raw response, status line and headers included.
+    async fn http_response(addr: SocketAddr, method: &str, path: &str, body: &str) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn reload_endpoint_applies_config_from_disk() {
+        let path = std::env::temp_dir().join(format!("runbookd-reload-{}.yaml", std::process::id()));
+        std::fs::write(&path, test_yaml()).unwrap();
+        let args = Args {
+            config: path.to_string_lossy().into_owned(),
+            config_override: None,
+            strict_env: false,
+            strict_config: false,
+            state_file: None,
+        };
+        let app = App::new(args, serde_yaml::from_str(&test_yaml()).unwrap());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(app)).await });
+
+        let two_pages = format!(
+            "{}    - name: review\n      slots: {}\n",
+            test_yaml(),
+            config::empty_slots(9)
+        );
+        std::fs::write(&path, two_pages).unwrap();
+        let (status, body) = http_post(addr, "/reload", "").await;
+        assert_eq!(status, 200);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body, serde_json::json!({ "status": "reloaded", "page_count": 2 }));
+
+        // An invalid file is rejected and the previous config stays active.
+        std::fs::write(&path, "keypad:\n  pages: []\n").unwrap();
+        let (status, body) = http_post(addr, "/reload", "").await;
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(status, 422);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["error"], "validation failed");
+        assert!(body["detail"].as_str().unwrap().contains("page"), "{body}");
+    }
+
+    #[tokio::test]
+    async fn hook_policy_rejects_untagged_hooks() {
+        let yaml = format!("{}hook_policy:\n  require_session_tag: true\n", test_yaml());
+        let app = test_app(serde_yaml::from_str(&yaml).unwrap());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let router = router(app.clone());
+        tokio::spawn(async move { axum::serve(listener, router).await });
+
+        let (status, body) =
+            http_post(addr, "/hook", r#"{"hook":"Stop","session_id":"s1","payload":null}"#).await;
+        assert_eq!(status, 403);
+        let err: ErrorMessage = serde_json::from_str(&body).unwrap();
+        assert_eq!(err.code, ErrorCode::PolicyViolation);
+        assert!(app.state.lock().await.sessions.is_empty());
+
+        let tagged = r#"{"hook":"Stop","session_id":"s1","session_tag":"t1","payload":null}"#;
+        assert_eq!(http_post(addr, "/hook", tagged).await.0, 200);
+        assert!(app.state.lock().await.sessions.contains_key("s1"));
+    }
+
+    const STOP_HOOK: &str = r#"{"hook":"Stop","session_id":"s1","payload":null}"#;
+
+    #[tokio::test]
+    async fn hook_rate_limit_answers_429() {
+        let mut config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        config.daemon.hook_rate_limit = Some(5);
+        let addr = spawn_daemon(config).await;
+
+        let mut rejected = Vec::new();
+        for _ in 0..100 {
+            let response = http_response(addr, "POST", "/hook", STOP_HOOK).await;
+            if response.starts_with("HTTP/1.1 429") {
+                rejected.push(response);
+            }
+        }
+        assert!(!rejected.is_empty(), "100 rapid hooks against a limit of 5/s");
+        let response = rejected[0].to_ascii_lowercase();
+        assert!(response.contains("\r\nretry-after: 1\r\n"), "{response}");
+        assert!(response.contains("\"rate_limited\""), "{response}");
+    }
+
+    #[tokio::test]
+    async fn hooks_are_unlimited_by_default() {
+        let config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        assert_eq!(config.daemon.hook_rate_limit, None);
+        let addr = spawn_daemon(config).await;
+
+        for _ in 0..100 {
+            assert_eq!(http_post(addr, "/hook", STOP_HOOK).await.0, 200);
+        }
+    }
+
+    #[tokio::test]
+    async fn prompt_stats_lists_configured_prompts() {
+        let yaml = format!(
+            "{base}prompts:\n  prep_pr:\n    label: PREP\n    fallback_text: prep\n  \
+             break_task:\n    label: BREAK\n    fallback_text: break\n",
+            base = test_yaml()
+        );
+        let app = test_app(serde_yaml::from_str(&yaml).unwrap());
+        {
+            let mut state = app.state.lock().await;
+            state.arm_counts.insert("prep_pr".to_string(), 2);
+            state.dispatch_counts.insert("prep_pr".to_string(), 1);
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(app)).await });
+
+        let (status, body) = http_get(addr, "/prompt-stats").await;
+        assert_eq!(status, 200);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(
+            body,
+            serde_json::json!([
+                { "prompt_id": "break_task", "arm_count": 0, "dispatch_count": 0, "arm_rate": 0.0 },
+                { "prompt_id": "prep_pr", "arm_count": 2, "dispatch_count": 1, "arm_rate": 0.5 },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn status_reports_last_dispatch() {
+        let config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        let app = test_app(config);
+        {
+            let mut state = app.state.lock().await;
+            state.last_dispatched = Some("prep_pr".to_string());
+            state.last_dispatched_session_id = Some("s1".to_string());
+        }
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(app)).await });
+
+        let (status, body) = http_get(addr, "/status").await;
+        assert_eq!(status, 200);
+        let body: DaemonStatus = serde_json::from_str(&body).unwrap();
+        assert_eq!(body.last_dispatched.as_deref(), Some("prep_pr"));
+        assert_eq!(body.last_dispatched_session_id.as_deref(), Some("s1"));
+    }
+
+    #[tokio::test]
+    async fn export_over_http() {
+        let config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        let addr = spawn_daemon(config).await;
+
+        let (status, body) = http_post(addr, "/export", "{}").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"command":"/export"}"#);
+        let (status, body) = http_post(addr, "/export", r#"{"format":"json"}"#).await;
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"command":"/export json"}"#);
+        let (status, _) = http_post(addr, "/export", r#"{"session_id":"s9"}"#).await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn export_to_a_session_without_a_terminal_is_a_conflict() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        app.state.lock().await.ensure_session("s1");
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(app)).await });
+
+        let (status, _) = http_post(addr, "/export", r#"{"session_id":"s1"}"#).await;
+        assert_eq!(status, 409);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hostname_is_looked_up() {
+        assert!(hostname().is_some_and(|h| !h.is_empty()));
+    }
+
+    #[tokio::test]
+    async fn gate_url_resolves_template_over_http() {
+        let yaml = format!(
+            "{base}gates:\n  pr:\n    label: PR\n    action: open_pr\n    \
+             url_template: \"https://git.example/pulls?head={{git_branch}}\"\n  \
+             issue:\n    label: ISSUE\n    action: open_issue\n",
+            base = test_yaml()
+        );
+        let config: RunbookConfig = serde_yaml::from_str(&yaml).unwrap();
+        let app = test_app(config);
+        app.state.lock().await.current_branch = Some("main".to_string());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, router(app)).await });
+
+        let (status, body) = http_get(addr, "/gates/pr/url").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"gate":"pr","url":"https://git.example/pulls?head=main"}"#);
+        assert_eq!(http_get(addr, "/gates/issue/url").await.0, 404, "no url_template");
+        assert_eq!(http_get(addr, "/gates/deploy/url").await.0, 404);
+    }
+
+    #[tokio::test]
+    async fn debug_dump_is_returned_and_broadcast() {
+        let mut config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        config.daemon.fifo = Some("/run/s3cr3t/fifo".to_string());
+        config.env_values = vec!["s3cr3t".to_string()];
+        let disabled = spawn_daemon(config.clone()).await;
+        assert_eq!(http_post(disabled, "/debug/dump", "").await.0, 404);
+
+        config.daemon.debug_enabled = true;
+        let addr = spawn_daemon(config).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+        ws.next().await.unwrap().unwrap(); // hello
+        let hook = r#"{"hook":"SessionStart","session_id":"s1","payload":null}"#;
+        assert_eq!(http_post(addr, "/hook", hook).await.0, 200);
+
+        let (status, body) = http_post(addr, "/debug/dump", "").await;
+        assert_eq!(status, 200);
+        let dump: DebugDump = serde_json::from_str(&body).unwrap();
+        assert_eq!(dump.metrics.session_count, 1);
+        assert!(!dump.config_json.contains("s3cr3t"));
+
+        // Connected clients get the same dump.
+        let broadcast = async {
+            loop {
+                let msg = ws.next().await.unwrap().unwrap();
+                if let Ok(DaemonToClient::Debug(dump)) = serde_json::from_str(msg.to_text().unwrap())
+                {
+                    break dump;
+                }
+            }
+        };
+        let sent = tokio::time::timeout(Duration::from_secs(2), broadcast)
+            .await
+            .expect("debug broadcast");
+        assert_eq!(sent.metrics.session_count, 1);
+        assert!(!sent.config_json.contains("s3cr3t"));
+    }
+
+    #[tokio::test]
+    async fn select_session_switches_rendered_session() {
+        let config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        let addr = spawn_daemon(config).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+        ws.next().await.unwrap().unwrap(); // hello
+        // Renders go to Logi devices.
+        let hello = r#"{"type":"hello","client":"logi","protocol":1,"version":"0.1.0"}"#;
+        ws.send(Message::Text(hello.into())).await.unwrap();
+
+        for body in [
+            r#"{"hook":"Stop","session_id":"s1","payload":null}"#,
+            r#"{"hook":"UserPromptSubmit","session_id":"s2","payload":null}"#,
+        ] {
+            assert_eq!(http_post(addr, "/hook", body).await.0, 200);
+        }
+
+        let (status, _) = http_post(addr, "/sessions/nope/select", "").await;
+        assert_eq!(status, 404);
+        let (status, body) = http_post(addr, "/sessions/s2/select", "").await;
+        assert_eq!(status, 200);
+        assert_eq!(body, r#"{"active_session":"s2"}"#);
+
+        // Two uncorrelated sessions render as unknown until one is selected.
+        let selected = async {
+            loop {
+                let msg = ws.next().await.unwrap().unwrap();
+                let parsed = serde_json::from_str(msg.to_text().unwrap());
+                if let Ok(DaemonToClient::Render(model)) = parsed {
+                    if model.agent_state == runbook_protocol::AgentState::Running {
+                        break;
+                    }
+                }
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), selected)
+            .await
+            .expect("render for selected session");
+    }
+
+    #[tokio::test]
+    async fn health_check_gets_ack() {
+        let config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        let addr = spawn_daemon(config).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+        ws.next().await.unwrap().unwrap(); // hello
+
+        ws.send(Message::text(r#"{"type":"health_check","request_id":42}"#))
+            .await
+            .unwrap();
+
+        let reply = ws.next().await.unwrap().unwrap();
+        let parsed: DaemonToClient = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert!(matches!(
+            parsed,
+            DaemonToClient::HealthAck(HealthAck {
+                request_id: 42,
+                session_count: 0,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn oversized_message_closes_connection() {
+        let config: RunbookConfig = serde_yaml::from_str(&test_yaml()).unwrap();
+        let addr = spawn_daemon(config).await;
+        let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws"))
+            .await
+            .unwrap();
+
+        // Daemon greets first.
+        let hello = ws.next().await.unwrap().unwrap();
+        assert!(hello.to_text().unwrap().contains("\"type\":\"hello\""));
+
+        ws.send(Message::text("x".repeat(2 * 1024 * 1024)))
+            .await
+            .unwrap();
+
+        let reply = ws.next().await.unwrap().unwrap();
+        let parsed: DaemonToClient = serde_json::from_str(reply.to_text().unwrap()).unwrap();
+        assert!(matches!(
+            parsed,
+            DaemonToClient::Error(ErrorMessage {
+                code: ErrorCode::PayloadTooLarge,
+                ..
+            })
+        ));
+
+        // Then the daemon closes the socket.
+        let next = ws.next().await;
+        assert!(
+            matches!(next, Some(Ok(Message::Close(_))) | Some(Err(_)) | None),
+            "expected close after error, got {next:?}"
+        );
+    }
+
+    /// State with hooks active, two tagged terminals, and the given live sessions.
+    fn terminal_state(sessions: &[(&str, &str)]) -> DaemonState {
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = runbook_protocol::HooksMode::Active;
+        state
+            .vscode_capabilities
+            .insert(VSCODE_TERMINAL_INDEX.to_string());
+        state.terminal_tag_map.insert(0, "tag-a".to_string());
+        state.terminal_tag_map.insert(1, "tag-b".to_string());
+        for (session_id, tag) in sessions {
+            state.ensure_session(session_id);
+            state.learn_session_tag(tag, session_id);
+        }
+        state
+    }
+
+    #[test]
+    fn vscode_target_single_session_uses_its_terminal() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let state = terminal_state(&[("s1", "tag-b")]);
+        assert_eq!(app.resolve_vscode_target(&state), TerminalTarget::ByIndex(1));
+
+        let untagged = terminal_state(&[("s1", "tag-unknown")]);
+        assert_eq!(
+            app.resolve_vscode_target(&untagged),
+            TerminalTarget::ActiveClaude
+        );
+    }
+
+    #[test]
+    fn dispatch_target_resolves_tags_to_indices() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let mut state = terminal_state(&[("s1", "tag-a")]);
+        let terminal = |index, tag: &str| runbook_protocol::TerminalInfo {
+            index,
+            name: format!("t{index}"),
+            session_tag: Some(tag.to_string()),
+            is_claude_session: true,
+        };
+        state.terminals = vec![terminal(0, "tag-a"), terminal(1, "tag-b")];
+        let by_tag = |tag: &str| TerminalTarget::ByTag(tag.to_string());
+        assert_eq!(
+            app.dispatch_target(&state, &by_tag("tag-b")),
+            Some(TerminalTarget::ByIndex(1))
+        );
+        assert_eq!(
+            app.dispatch_target(&state, &TerminalTarget::ByIndex(5)),
+            Some(TerminalTarget::ByIndex(5))
+        );
+
+        // An extension that can't take by_index can't be sent tagged commands.
+        state.vscode_capabilities.clear();
+        assert_eq!(app.dispatch_target(&state, &by_tag("tag-b")), None);
+    }
+
+    #[test]
+    fn dispatch_target_drops_unknown_tags() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let mut state = terminal_state(&[("s1", "tag-a")]);
+        state.terminals = vec![runbook_protocol::TerminalInfo {
+            index: 0,
+            name: "t0".to_string(),
+            session_tag: Some("tag-a".to_string()),
+            is_claude_session: true,
+        }];
+        assert_eq!(
+            app.dispatch_target(&state, &TerminalTarget::ByTag("tag-z".to_string())),
+            None
+        );
+        // Untagged commands still go to the active Claude terminal.
+        assert_eq!(
+            app.dispatch_target(&state, &TerminalTarget::ActiveClaude),
+            Some(TerminalTarget::ByIndex(0))
+        );
+    }
+
+    #[test]
+    fn vscode_target_needs_terminal_index_capability() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let mut state = terminal_state(&[("s1", "tag-b")]);
+        state.vscode_capabilities.clear();
+        assert_eq!(
+            app.resolve_vscode_target(&state),
+            TerminalTarget::ActiveClaude
+        );
+    }
+
+    #[tokio::test]
+    async fn vscode_hello_records_capabilities() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let hello = |client, caps: &[&str]| {
+            ClientToDaemon::Hello(runbook_protocol::Hello {
+                client,
+                protocol: runbook_protocol::PROTOCOL_VERSION,
+                version: "test".to_string(),
+                capabilities: caps.iter().map(|c| c.to_string()).collect(),
+            })
+        };
+
+        app.handle_client_message(1, hello(ClientKind::Logi, &["keypad"]))
+            .await;
+        assert!(app.state.lock().await.vscode_capabilities.is_empty());
+
+        app.handle_client_message(2, hello(ClientKind::Vscode, &["terminal_index"]))
+            .await;
+        assert!(app.state.lock().await.vscode_supports(VSCODE_TERMINAL_INDEX));
+    }
+
+    #[tokio::test]
+    async fn bad_config_override_is_reported_after_hello() {
+        let dir = std::env::temp_dir();
+        let base = dir.join(format!("runbookd-startup-{}.yaml", std::process::id()));
+        let local = dir.join(format!("runbookd-startup-{}.local.yaml", std::process::id()));
+        std::fs::write(&base, test_yaml()).unwrap();
+        std::fs::write(
+            &local,
+            "policy:\n  pre_tool_use:\n    bash:\n      deny: [\"git push\"]\n\
+             keypad:\n  grid_size: [0, 3]\n",
+        )
+        .unwrap();
+        let args = Args {
+            config: base.to_string_lossy().into_owned(),
+            config_override: Some(local.to_string_lossy().into_owned()),
+            strict_env: false,
+            strict_config: false,
+            state_file: None,
+        };
+        let (config, errors) = load_startup(&args).unwrap();
+        assert_eq!(config.keypad.slot_count(), 9, "base keypad");
+        assert_eq!(config.policy.pre_tool_use.bash.deny, ["git push"], "override policy kept");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field_path, "keypad.grid_size");
+
+        let app = test_app(config);
+        *app.startup_errors.write().unwrap() = errors;
+        let (tx, mut rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        let id = app.clients.lock().await.register(tx);
+        app.handle_client_message(
+            id,
+            ClientToDaemon::Hello(runbook_protocol::Hello {
+                client: ClientKind::Logi,
+                protocol: runbook_protocol::PROTOCOL_VERSION,
+                version: "test".to_string(),
+                capabilities: vec![],
+            }),
+        )
+        .await;
+        let reported = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|msg| match msg {
+            DaemonToClient::ConfigError(err) => Some(err),
+            _ => None,
+        });
+        assert_eq!(reported.unwrap().field_path, "keypad.grid_size");
+
+        // An override whose policy can't be read still stops the daemon.
+        std::fs::write(&local, "policy:\n  pre_tool_use: [oops]\n").unwrap();
+        assert!(load_startup(&args).is_err());
+        // So does a broken base config.
+        std::fs::write(&base, "keypad:\n  pages: []\n").unwrap();
+        assert!(load_startup(&Args { config_override: None, ..args }).is_err());
+        std::fs::remove_file(&base).unwrap();
+        std::fs::remove_file(&local).unwrap();
+    }
+
+    #[tokio::test]
+    async fn bad_config_is_reported_on_reload() {
+        let dir = std::env::temp_dir();
+        let base = dir.join(format!("runbookd-reload-{}.yaml", std::process::id()));
+        std::fs::write(&base, "keypad:\n  grid_size: [0, 3]\n").unwrap();
+        let args = Args {
+            config: base.to_string_lossy().into_owned(),
+            config_override: None,
+            strict_env: false,
+            strict_config: false,
+            state_file: None,
+        };
+        let app = App::new(args, serde_yaml::from_str(&test_yaml()).unwrap());
+        let (tx, mut rx) = mpsc::channel(CLIENT_QUEUE_CAPACITY);
+        app.clients.lock().await.register(tx);
+        assert!(app.reload_config().await.is_err());
+        std::fs::remove_file(&base).unwrap();
+        let reported = std::iter::from_fn(|| rx.try_recv().ok()).find_map(|msg| match msg {
+            DaemonToClient::ConfigError(err) => Some(err),
+            _ => None,
+        });
+        assert!(reported.is_some());
+    }
+
+    #[tokio::test]
+    async fn command_ack_clears_pending_command() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        app.handle_client_message(
+            1,
+            ClientToDaemon::Hello(runbook_protocol::Hello {
+                client: ClientKind::Vscode,
+                protocol: runbook_protocol::PROTOCOL_VERSION,
+                version: "test".to_string(),
+                capabilities: vec![state::VSCODE_COMMAND_ACK.to_string()],
+            }),
+        )
+        .await;
+        app.handle_client_message(
+            2,
+            ClientToDaemon::DialpadButtonPress(runbook_protocol::DialpadButtonPress {
+                button: runbook_protocol::DialpadButton::CtrlC,
+            }),
+        )
+        .await;
+        let ids: Vec<u64> = app.state.lock().await.pending_commands.keys().copied().collect();
+        assert!(!ids.is_empty());
+
+        for command_id in ids {
+            let ack = runbook_protocol::CommandAck {
+                command_id,
+                success: true,
+                error: None,
+            };
+            app.handle_client_message(1, ClientToDaemon::CommandAck(ack))
+                .await;
+        }
+        assert!(app.state.lock().await.pending_commands.is_empty());
+    }
+
+    #[test]
+    fn vscode_target_multi_session_follows_selected_terminal() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let mut state = terminal_state(&[("s1", "tag-a"), ("s2", "tag-b")]);
+        state.selected_terminal_index = 1;
+        assert_eq!(app.resolve_vscode_target(&state), TerminalTarget::ByIndex(1));
+
+        state.selected_terminal_index = 0;
+        assert_eq!(app.resolve_vscode_target(&state), TerminalTarget::ByIndex(0));
+    }
+
+    #[test]
+    fn vscode_target_multi_session_without_correlation_stays_active_claude() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let mut state = terminal_state(&[("s1", "tag-x"), ("s2", "tag-y")]);
+        state.selected_terminal_index = 1;
+        assert_eq!(
+            app.resolve_vscode_target(&state),
+            TerminalTarget::ActiveClaude
+        );
+    }
+
+    #[test]
+    fn vscode_target_uncorrelated_session_uses_a_claude_terminal() {
+        let app = test_app(serde_yaml::from_str(&test_yaml()).unwrap());
+        let mut state = terminal_state(&[("s1", "tag-x")]);
+        state.terminals = vec![
+            runbook_protocol::TerminalInfo {
+                index: 0,
+                name: "bash".to_string(),
+                session_tag: None,
+                is_claude_session: false,
+            },
+            runbook_protocol::TerminalInfo {
+                index: 1,
+                name: "Claude".to_string(),
+                session_tag: Some("tag-b".to_string()),
+                is_claude_session: true,
+            },
+        ];
+        assert_eq!(app.resolve_vscode_target(&state), TerminalTarget::ByIndex(1));
     }
 }
@@ -0,0 +1,349 @@
+//! OpenAPI 3.0 description of the daemon's HTTP surface.
+//!
+//! Built by hand (paths) plus schemars (component schemas) so the spec stays
+//! in lockstep with `runbook-protocol`. Regenerate `schema/openapi.json` with
+//! `cargo run -p runbookd --bin gen_openapi`.
+
+use schemars::gen::SchemaSettings;
+use serde_json::{json, Value};
+
+use runbook_protocol::{
+    DaemonStatus, DebugDump, ErrorMessage, ExportRequest, HookEvent, SessionList,
+};
+
+/// Every HTTP route the daemon serves.
+pub const ROUTES: &[&str] = &[
+    "/ws",
+    "/hook",
+    "/sessions",
+    "/sessions/{id}/select",
+    "/gates/{id}/url",
+    "/export",
+    "/prompts",
+    "/prompt-stats",
+    "/reload",
+    "/status",
+    "/debug/dump",
+];
+
+const HOOK_EVENT_FIXTURE: &str = include_str!("../../runbook-protocol/fixtures/hook_event.json");
+const SESSION_LIST_FIXTURE: &str =
+    include_str!("../../runbook-protocol/fixtures/session_list.json");
+const EXPORT_REQUEST_FIXTURE: &str =
+    include_str!("../../runbook-protocol/fixtures/export_request.json");
+
+/// Build the OpenAPI document.
+pub fn spec() -> Value {
+    let mut gen = SchemaSettings::openapi3().into_generator();
+    gen.subschema_for::<HookEvent>();
+    gen.subschema_for::<SessionList>();
+    gen.subschema_for::<DaemonStatus>();
+    gen.subschema_for::<DebugDump>();
+    gen.subschema_for::<ErrorMessage>();
+    gen.subschema_for::<ExportRequest>();
+    let schemas = serde_json::to_value(gen.definitions()).expect("schemas serialize");
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "runbookd",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Runbook daemon HTTP API. Interactive clients use the WebSocket at /ws; see docs/protocol.md for message types.",
+        },
+        "servers": [{ "url": "http://127.0.0.1:29381" }],
+        "paths": {
+            "/ws": {
+                "get": {
+                    "summary": "Upgrade to the Runbook WebSocket protocol",
+                    "responses": {
+                        "101": { "description": "Switching protocols" },
+                    },
+                },
+            },
+            "/hook": {
+                "post": {
+                    "summary": "Submit a Claude Code hook event",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/HookEvent" },
+                                "example": fixture_body(HOOK_EVENT_FIXTURE),
+                            },
+                        },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Event accepted",
+                            "content": { "text/plain": { "example": "ok" } },
+                        },
+                        "403": {
+                            "description": "Rejected by `hook_policy`; state was not updated",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ErrorMessage" },
+                                    "example": {
+                                        "code": "policy_violation",
+                                        "message": "hook_policy.require_session_tag: Stop event has no session_tag",
+                                    },
+                                },
+                            },
+                        },
+                        "429": {
+                            "description": "Over `daemon.hook_rate_limit`; state was not updated",
+                            "headers": {
+                                "Retry-After": {
+                                    "description": "Seconds until events are accepted again",
+                                    "schema": { "type": "integer" },
+                                },
+                            },
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/ErrorMessage" },
+                                    "example": {
+                                        "code": "rate_limited",
+                                        "message": "more than 50 hook events per second",
+                                    },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "/sessions": {
+                "get": {
+                    "summary": "List live Claude sessions",
+                    "responses": {
+                        "200": {
+                            "description": "Current sessions",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/SessionList" },
+                                    "example": fixture_body(SESSION_LIST_FIXTURE),
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "/sessions/{id}/select": {
+                "post": {
+                    "summary": "Make a live session the one the device shows",
+                    "parameters": [{
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "Session selected",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": { "active_session": { "type": "string" } },
+                                    },
+                                },
+                            },
+                        },
+                        "404": { "description": "No live session with that id" },
+                    },
+                },
+            },
+            "/gates/{id}/url": {
+                "get": {
+                    "summary": "A gate's url_template with session tag, branch and host filled in",
+                    "parameters": [{
+                        "name": "id",
+                        "in": "path",
+                        "required": true,
+                        "schema": { "type": "string" },
+                    }],
+                    "responses": {
+                        "200": {
+                            "description": "Resolved URL",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": {
+                                            "gate": { "type": "string" },
+                                            "url": { "type": "string" },
+                                        },
+                                    },
+                                },
+                            },
+                        },
+                        "404": { "description": "No gate with that id, or it has no url_template" },
+                    },
+                },
+            },
+            "/export": {
+                "post": {
+                    "summary": "Type Claude's /export into a session's terminal (not submitted)",
+                    "requestBody": {
+                        "required": true,
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/ExportRequest" },
+                                "example": fixture_body(EXPORT_REQUEST_FIXTURE),
+                            },
+                        },
+                    },
+                    "responses": {
+                        "200": {
+                            "description": "Command sent to VS Code",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "object",
+                                        "properties": { "command": { "type": "string" } },
+                                    },
+                                },
+                            },
+                        },
+                        "404": { "description": "session_id names a session that isn't live" },
+                        "409": { "description": "session_id's terminal isn't known; nothing was sent" },
+                    },
+                },
+            },
+            "/prompts": {
+                "get": {
+                    "summary": "Human-readable summary of the configured prompts",
+                    "responses": {
+                        "200": {
+                            "description": "One `[id] label (sublabel) → command` line per prompt, sorted by id",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "type": "array", "items": { "type": "string" } },
+                                    "example": ["[prep_pr] PREP PR (receipts) → /runbook:prep-pr"],
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "/prompt-stats": {
+                "get": {
+                    "summary": "How often each configured prompt was armed versus dispatched",
+                    "responses": {
+                        "200": {
+                            "description": "One entry per prompt, sorted by id; `arm_rate` is dispatches per arm (0 if never armed)",
+                            "content": {
+                                "application/json": {
+                                    "schema": {
+                                        "type": "array",
+                                        "items": {
+                                            "type": "object",
+                                            "properties": {
+                                                "prompt_id": { "type": "string" },
+                                                "arm_count": { "type": "integer" },
+                                                "dispatch_count": { "type": "integer" },
+                                                "arm_rate": { "type": "number" },
+                                            },
+                                        },
+                                    },
+                                    "example": [{ "prompt_id": "prep_pr", "arm_count": 4, "dispatch_count": 3, "arm_rate": 0.75 }],
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "/reload": {
+                "post": {
+                    "summary": "Re-read the config file (same as SIGHUP; works on Windows)",
+                    "responses": {
+                        "200": {
+                            "description": "New config is active; clients get `config_reloaded`",
+                            "content": {
+                                "application/json": {
+                                    "example": { "status": "reloaded", "page_count": 2 },
+                                },
+                            },
+                        },
+                        "422": {
+                            "description": "Config failed to load or validate; the previous config stays active",
+                            "content": {
+                                "application/json": {
+                                    "example": { "error": "validation failed", "detail": "keypad.pages must have at least 1 page" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "/status": {
+                "get": {
+                    "summary": "Daemon health and limits",
+                    "responses": {
+                        "200": {
+                            "description": "Current status",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/DaemonStatus" },
+                                },
+                            },
+                        },
+                    },
+                },
+            },
+            "/debug/dump": {
+                "post": {
+                    "summary": "Dump daemon state and config (requires daemon.debug_enabled)",
+                    "responses": {
+                        "200": {
+                            "description": "Dump, returned to the caller only; values from `${VAR}` expansion are redacted",
+                            "content": {
+                                "application/json": {
+                                    "schema": { "$ref": "#/components/schemas/DebugDump" },
+                                },
+                            },
+                        },
+                        "404": { "description": "Debug endpoints disabled" },
+                    },
+                },
+            },
+        },
+        "components": { "schemas": schemas },
+    })
+}
+
+/// Parse a protocol fixture and drop the envelope `type` tag.
+fn fixture_body(raw: &str) -> Value {
+    let mut v: Value = serde_json::from_str(raw).expect("fixture is valid JSON");
+    if let Some(obj) = v.as_object_mut() {
+        obj.remove("type");
+    }
+    v
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spec_covers_all_routes() {
+        let spec = spec();
+        assert_eq!(spec["openapi"], "3.0.3");
+        for route in ROUTES {
+            assert!(spec["paths"].get(route).is_some(), "missing path {route}");
+        }
+        assert!(spec["components"]["schemas"].get("HookEvent").is_some());
+    }
+
+    #[test]
+    fn committed_spec_is_valid_and_complete() {
+        let path = format!("{}/schema/openapi.json", env!("CARGO_MANIFEST_DIR"));
+        let raw = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("failed to read {path}: {e}"));
+        let committed: Value = serde_json::from_str(&raw).expect("openapi.json is valid JSON");
+        for route in ROUTES {
+            assert!(
+                committed["paths"].get(route).is_some(),
+                "openapi.json missing {route}; rerun gen_openapi"
+            );
+        }
+    }
+}
@@ -0,0 +1,92 @@
+//! Fixed-window limiter for `POST /hook`, so a runaway hook script can't
+//! flood the reducer.
+
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+const WINDOW: Duration = Duration::from_secs(1);
+
+/// Counts events in the current one-second window.
+#[derive(Debug)]
+pub struct RateLimiter {
+    window: Mutex<Window>,
+}
+
+#[derive(Debug)]
+struct Window {
+    start: Instant,
+    count: u32,
+    /// Whether this window has already rejected an event (log once per window).
+    rejected: bool,
+}
+
+/// Outcome of [`RateLimiter::check`] for a rejected event.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rejected {
+    /// Until the current window ends.
+    pub retry_after: Duration,
+    /// First rejection in this window.
+    pub first: bool,
+}
+
+impl RateLimiter {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            window: Mutex::new(Window {
+                start: now,
+                count: 0,
+                rejected: false,
+            }),
+        }
+    }
+
+    /// Admit one event if fewer than `per_sec` have been admitted this window.
+    pub fn check(&self, per_sec: u32, now: Instant) -> Result<(), Rejected> {
+        let mut w = self.window.lock().expect("rate limiter lock poisoned");
+        if now.saturating_duration_since(w.start) >= WINDOW {
+            *w = Window {
+                start: now,
+                count: 0,
+                rejected: false,
+            };
+        }
+        if w.count < per_sec {
+            w.count += 1;
+            return Ok(());
+        }
+        let first = !w.rejected;
+        w.rejected = true;
+        Err(Rejected {
+            retry_after: WINDOW.saturating_sub(now.saturating_duration_since(w.start)),
+            first,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admits_up_to_limit_per_window() {
+        let t0 = Instant::now();
+        let limiter = RateLimiter::new(t0);
+        assert!(limiter.check(2, t0).is_ok());
+        assert!(limiter.check(2, t0).is_ok());
+
+        let later = t0 + Duration::from_millis(400);
+        let err = limiter.check(2, later).unwrap_err();
+        assert_eq!(err.retry_after, Duration::from_millis(600));
+        assert!(err.first);
+        assert!(!limiter.check(2, later).unwrap_err().first);
+
+        // Next window starts fresh.
+        assert!(limiter.check(2, t0 + WINDOW).is_ok());
+    }
+
+    #[test]
+    fn zero_limit_rejects_everything() {
+        let t0 = Instant::now();
+        assert!(RateLimiter::new(t0).check(0, t0).is_err());
+    }
+}
@@ -3,13 +3,24 @@
 //! All state transitions happen here, making the daemon logic testable
 //! without network or I/O.
 
+use std::hash::{DefaultHasher, Hash, Hasher};
+
 use runbook_protocol::{
-    AgentState, AdjustmentKind, ArmStyle, DialpadButton, HooksMode, PageDirection,
-    TerminalScrollUnit, TerminalTarget, TerminalsSnapshot, VscodeCommand,
+    AdjustmentKind, AgentState, AgentStateChange, ArmStyle, ClientKind, DaemonToClient, DialMode,
+    DialpadButton, ExportFormat, GestureDirection, HookEvent, NoticeSeverity,
+    HooksMode, MacroStep,
+    PageChangedEvent, PageDirection, PolicyBlock, ProgressUpdate, PromptSuggestion, ReconfigureRequest,
+    TerminalScrollUnit,
+    TerminalTarget, TerminalsSnapshot, ToastMessage, VscodeCommand,
 };
 
+use tracing::{debug, debug_span, info_span, warn};
+
 use crate::config::RunbookConfig;
-use crate::state::DaemonState;
+use crate::state::{
+    DaemonState, PermissionRequest, HOOKS_STALE_AFTER, MAX_PROMPT_TEXT_CHARS,
+    UNKNOWN_SESSION_LIMIT,
+};
 
 /// Events the reducer consumes.
 #[derive(Debug)]
@@ -17,16 +28,65 @@ pub enum Event {
     KeypadPress { prompt_id: String },
     DialpadButton { button: DialpadButton },
     Adjustment { kind: AdjustmentKind, delta: i32 },
+    Gesture { direction: GestureDirection, distance: u32 },
     PageNav { direction: PageDirection },
+    PageJump { hotkey: String },
+    /// Queue prompts to dispatch one per `TaskCompleted`.
+    StartMacro { steps: Vec<MacroStep> },
+    /// Type `/export` into a session's terminal (the active Claude one if `None`).
+    Export {
+        session_id: Option<String>,
+        format: ExportFormat,
+    },
+    // Named after the protocol's `HookEvent` it is built from.
+    #[allow(clippy::enum_variant_names)]
     HookEvent {
         hook: String,
         matcher: Option<String>,
         session_id: Option<String>,
         session_tag: Option<String>,
+        /// Raw hook JSON payload (tool name, prompt text, etc.).
+        payload: serde_json::Value,
     },
     TerminalsSnapshot(TerminalsSnapshot),
+    /// Focus a live session explicitly (HTTP `select`); ignored if unknown.
+    SelectSession { session_id: String },
+    /// Focus the session that reported this tag (VS Code terminal click); ignored if unknown.
+    TagSelect { session_tag: String },
+    SlotProgress { prompt_id: String, progress: f32 },
     ClientConnected { kind: ClientKindTag },
     ClientDisconnected { kind: ClientKindTag },
+    /// A client of `kind` sent `reconfigure`.
+    Reconfigure {
+        kind: ClientKind,
+        request: ReconfigureRequest,
+    },
+    /// `config` is the freshly reloaded config; fix up state that referenced the old one.
+    ConfigReloaded,
+    /// Periodic housekeeping (hooks staleness).
+    Tick,
+}
+
+impl From<HookEvent> for Event {
+    fn from(ev: HookEvent) -> Self {
+        Event::HookEvent {
+            hook: ev.hook,
+            matcher: ev.matcher,
+            session_id: ev.session_id,
+            session_tag: ev.session_tag,
+            payload: ev.payload,
+        }
+    }
+}
+
+impl Event {
+    /// The Claude session this event came from, when it carries one.
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            Event::HookEvent { session_id, .. } => session_id.as_deref(),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -38,10 +98,71 @@ pub enum ClientKindTag {
 /// Side effects emitted by the reducer (executed by the IO layer).
 #[derive(Debug)]
 pub enum SideEffect {
-    /// Broadcast an updated render model to all connected devices.
+    /// Send an updated render model to the Logi devices
+    /// (shorthand for `BroadcastToClientKind(Logi, ..)`).
     BroadcastRender,
-    /// Send a VS Code command.
+    /// Only the page changed: send `RenderPage` to clients with `delta_render`,
+    /// a full render to the rest.
+    BroadcastRenderPage,
+    /// Send a VS Code command (shorthand for `BroadcastToClientKind(Vscode, ..)`).
     SendVscodeCommand(VscodeCommand),
+    /// Broadcast tool-call progress for a running session.
+    BroadcastProgress(ProgressUpdate),
+    /// Notify clients (with the `hooks` capability) that policy blocked a tool call.
+    BroadcastPolicyBlock(PolicyBlock),
+    /// Broadcast the live session list (to clients with the `sessions` capability).
+    BroadcastSessionList,
+    /// Send a message only to clients that identified as the given kind.
+    BroadcastToClientKind(ClientKind, Box<DaemonToClient>),
+    /// Flash a key: slot index on the current page, RGB color, duration in ms.
+    BroadcastSlotHighlight(u8, [u8; 3], u32),
+    /// Announce a session's agent state transition.
+    BroadcastStateChange(AgentStateChange),
+    /// Show a self-dismissing overlay: message, duration (ms), severity.
+    BroadcastToast(String, u32, NoticeSeverity),
+    /// Suggest the prompt to arm next.
+    BroadcastSuggestion(PromptSuggestion),
+    /// Announce a page move (before the render showing the new page).
+    BroadcastPageChanged(PageChangedEvent),
+}
+
+impl SideEffect {
+    /// Expand targeted shorthands into an explicit `BroadcastToClientKind`:
+    /// VS Code commands go to the extension, renders to Logi devices.
+    ///
+    /// Effects meant for all clients are returned unchanged.
+    pub fn into_targeted(self, state: &DaemonState, config: &RunbookConfig) -> Self {
+        match self {
+            Self::SendVscodeCommand(cmd) => Self::BroadcastToClientKind(
+                ClientKind::Vscode,
+                Box::new(DaemonToClient::VscodeCommand(cmd)),
+            ),
+            Self::BroadcastRender => Self::BroadcastToClientKind(
+                ClientKind::Logi,
+                Box::new(DaemonToClient::Render(crate::render::build_render_model(
+                    state, config,
+                ))),
+            ),
+            other => other,
+        }
+    }
+}
+
+/// Key color and duration confirming a dispatched prompt.
+const DISPATCH_HIGHLIGHT_RGB: [u8; 3] = [0, 255, 0];
+const DISPATCH_HIGHLIGHT_MS: u32 = 500;
+
+/// How long a toast stays up.
+const TOAST_MS: u32 = 3000;
+
+/// Raise a toast, holding it for the Logi plugin if it isn't connected.
+fn toast(state: &mut DaemonState, message: String, severity: NoticeSeverity) -> SideEffect {
+    state.hold_toast(ToastMessage {
+        message: message.clone(),
+        duration_ms: TOAST_MS,
+        severity,
+    });
+    SideEffect::BroadcastToast(message, TOAST_MS, severity)
 }
 
 /// Apply an event to the daemon state, returning side effects to execute.
@@ -50,54 +171,141 @@ pub fn reduce(
     config: &RunbookConfig,
     event: Event,
 ) -> Vec<SideEffect> {
+    let _span = info_span!("reduce", session_id = event.session_id()).entered();
     match event {
         Event::KeypadPress { prompt_id } => {
             // Arm the prompt
+            let mut effects = Vec::new();
             if let Some(prompt) = config.prompts.get(&prompt_id) {
                 state.armed = Some(prompt_id.clone());
+                state.associate_prompt(&prompt_id);
+                *state.arm_counts.entry(prompt_id.clone()).or_insert(0) += 1;
+                effects.extend(show_armed_page(state, config));
 
-                let style = config.arm_style_for(&prompt_id);
+                let style = config.arm_style_for_mode(&prompt_id, &state.hooks_mode);
                 if style == ArmStyle::Prefill {
                     let is_claude = config.is_claude_primary();
                     if let Some(cmd_text) = prompt.effective_command(is_claude) {
                         let cmd = VscodeCommand::send_text(
-                            TerminalTarget::ActiveClaude,
+                            state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                             cmd_text,
                             false, // prefill without newline
                         );
-                        return vec![
-                            SideEffect::SendVscodeCommand(cmd),
-                            SideEffect::BroadcastRender,
-                        ];
+                        effects.push(SideEffect::SendVscodeCommand(cmd));
                     }
                 }
             }
             // Gates get dispatched immediately (they're navigation, not prompts).
             // The caller checks this before emitting the Event.
-            vec![SideEffect::BroadcastRender]
+            effects.push(SideEffect::BroadcastRender);
+            effects
         }
 
         Event::DialpadButton { button } => reduce_dialpad(state, config, button),
 
-        Event::Adjustment { kind, delta } => reduce_adjustment(state, kind, delta),
+        // The plugin switched the dial back to OS scrolling; drop stragglers.
+        Event::Adjustment {
+            kind: AdjustmentKind::Dial,
+            ..
+        } if state.per_client_dial_mode.get(&ClientKind::Logi) == Some(&DialMode::OsScroll) => {
+            debug!("dial event ignored: logi dial_mode is os_scroll");
+            vec![]
+        }
+
+        Event::Adjustment { kind, delta } => reduce_adjustment(state, config, kind, delta),
+
+        Event::Gesture {
+            direction,
+            distance,
+        } => {
+            // A swipe counts as at least one detent.
+            let lines = i32::try_from(distance.max(1)).unwrap_or(i32::MAX);
+            match direction {
+                GestureDirection::SwipeLeft => reduce(
+                    state,
+                    config,
+                    Event::PageNav {
+                        direction: PageDirection::Prev,
+                    },
+                ),
+                GestureDirection::SwipeRight => reduce(
+                    state,
+                    config,
+                    Event::PageNav {
+                        direction: PageDirection::Next,
+                    },
+                ),
+                GestureDirection::SwipeUp => {
+                    reduce_adjustment(state, config, AdjustmentKind::Dial, -lines)
+                }
+                GestureDirection::SwipeDown => {
+                    reduce_adjustment(state, config, AdjustmentKind::Dial, lines)
+                }
+                // Confirm an armed prompt; a stray tap with nothing armed does nothing
+                // (unlike Enter, which always reaches the terminal).
+                GestureDirection::TapCenter if state.armed.is_some() => {
+                    reduce_dialpad(state, config, DialpadButton::Enter)
+                }
+                GestureDirection::TapCenter => vec![],
+            }
+        }
+
+        Event::StartMacro { steps } => {
+            state.start_macro(steps);
+            vec![]
+        }
+
+        Event::Export { session_id, format } => {
+            // A named session gets its own terminal or nothing: typing into
+            // the active one could hit a different session.
+            let target = match session_id.as_deref() {
+                Some(sid) => match state.session_terminal(sid) {
+                    Some(t) => TerminalTarget::ByIndex(t.index),
+                    None => {
+                        warn!(session_id = sid, "export: session has no known terminal; not sent");
+                        return vec![];
+                    }
+                },
+                None => state.resolve_target(&TerminalTarget::ActiveClaude).into(),
+            };
+            vec![SideEffect::SendVscodeCommand(VscodeCommand::send_text(
+                target,
+                format.command(),
+                false,
+            ))]
+        }
 
         Event::PageNav { direction } => {
             let count = config.keypad.pages.len();
             if count == 0 {
                 return vec![];
             }
-            match direction {
-                PageDirection::Next => state.page = (state.page + 1) % count,
-                PageDirection::Prev => {
-                    state.page = if state.page == 0 {
-                        count - 1
-                    } else {
-                        state.page - 1
-                    };
-                }
+            let page = match direction {
+                PageDirection::Next => (state.page + 1) % count,
+                PageDirection::Prev if state.page == 0 => count - 1,
+                PageDirection::Prev => state.page - 1,
+                PageDirection::JumpTo { index } => index.min(count - 1),
+            };
+            if page == state.page {
+                return vec![leave_page(state, config)];
+            }
+            // Only steps carry a direction; a jump_to is a jump like any other.
+            let step = (!matches!(direction, PageDirection::JumpTo { .. })).then_some(direction);
+            change_page(state, config, page, step)
+        }
+
+        Event::PageJump { hotkey } => {
+            let Some(page) = config
+                .keypad
+                .pages
+                .iter()
+                .position(|p| p.hotkey.as_deref() == Some(hotkey.as_str()))
+            else {
+                return vec![];
+            };
+            if page != state.page {
+                return change_page(state, config, page, None);
             }
-            // Clear armed prompt on page change (prompt_id may not exist on new page).
-            state.armed = None;
             vec![SideEffect::BroadcastRender]
         }
 
@@ -106,22 +314,68 @@ pub fn reduce(
             matcher,
             session_id,
             session_tag,
-        } => reduce_hook(state, hook, matcher, session_id, session_tag),
+            payload,
+        } => reduce_hook(state, config, hook, matcher, session_id, session_tag, &payload),
 
         Event::TerminalsSnapshot(snapshot) => {
+            // Same-format ISO-8601 UTC timestamps order correctly as strings.
+            if let (Some(ts), Some(last)) = (&snapshot.timestamp, &state.last_terminal_snapshot_ts) {
+                if ts < last {
+                    debug!(ts, last, "ignoring out-of-order terminals snapshot");
+                    return vec![];
+                }
+            }
+            if snapshot.timestamp.is_some() {
+                state.last_terminal_snapshot_ts = snapshot.timestamp;
+            }
+
             // Update terminal list and tag mapping from VS Code extension.
+            let mut terminals = snapshot.terminals;
             state.terminal_tag_map.clear();
-            for t in &snapshot.terminals {
+            for t in &mut terminals {
+                t.is_claude_session = t.session_tag.is_some();
                 if let Some(ref tag) = t.session_tag {
                     state.terminal_tag_map.insert(t.index, tag.clone());
                 }
             }
-            state.terminals = snapshot.terminals;
+            state.terminals = terminals;
+            if state.selected_terminal_index != snapshot.active_index {
+                // The newer terminal focus replaces an explicit session selection.
+                state.active_session = None;
+            }
             state.selected_terminal_index = snapshot.active_index;
             vec![SideEffect::BroadcastRender]
         }
 
+        Event::SelectSession { session_id } => {
+            if !state.sessions.contains_key(&session_id) {
+                return vec![];
+            }
+            state.active_session = Some(session_id);
+            vec![SideEffect::BroadcastRender]
+        }
+
+        Event::TagSelect { session_tag } => {
+            if state.set_active_session_by_tag(&session_tag) {
+                vec![SideEffect::BroadcastRender]
+            } else {
+                vec![]
+            }
+        }
+
+        Event::SlotProgress {
+            prompt_id,
+            progress,
+        } => {
+            if progress.is_nan() {
+                return vec![];
+            }
+            state.slot_progress.insert(prompt_id, progress.clamp(0.0, 1.0));
+            vec![SideEffect::BroadcastRender]
+        }
+
         Event::ClientConnected { kind } => {
+            state.note_client_connected(std::time::Instant::now());
             match kind {
                 ClientKindTag::Logi => state.logi_connected = true,
                 ClientKindTag::Vscode => state.vscode_connected = true,
@@ -129,10 +383,49 @@ pub fn reduce(
             vec![SideEffect::BroadcastRender]
         }
 
+        Event::Reconfigure { kind, request } => {
+            if let Some(mode) = request.dial_mode {
+                state.per_client_dial_mode.insert(kind, mode);
+            }
+            match request.label_max_chars {
+                Some(max) if state.per_client_label_max_chars.insert(kind, max) != Some(max) => {
+                    vec![SideEffect::BroadcastRender]
+                }
+                _ => vec![],
+            }
+        }
+
         Event::ClientDisconnected { kind } => {
             match kind {
                 ClientKindTag::Logi => state.logi_connected = false,
-                ClientKindTag::Vscode => state.vscode_connected = false,
+                ClientKindTag::Vscode => {
+                    state.vscode_connected = false;
+                    state.vscode_capabilities.clear();
+                }
+            }
+            vec![SideEffect::BroadcastRender]
+        }
+
+        Event::Tick => {
+            let before = state.hooks_mode.clone();
+            state.update_hooks_mode(std::time::Instant::now(), HOOKS_STALE_AFTER);
+            if state.hooks_mode == before {
+                vec![]
+            } else {
+                vec![SideEffect::BroadcastRender]
+            }
+        }
+
+        Event::ConfigReloaded => {
+            if state.current_page(config).is_none() {
+                let count = config.keypad.pages.len();
+                state.page = config.keypad.initial_page.min(count.saturating_sub(1));
+            }
+            // Drop an armed prompt that no longer exists.
+            if let Some(ref id) = state.armed {
+                if !config.prompts.contains_key(id) {
+                    state.armed = None;
+                }
             }
             vec![SideEffect::BroadcastRender]
         }
@@ -144,46 +437,61 @@ fn reduce_dialpad(
     config: &RunbookConfig,
     button: DialpadButton,
 ) -> Vec<SideEffect> {
+    let session_id = state.current_session_id();
+    let _span =
+        debug_span!("reduce_dialpad", ?button, session_id = session_id.as_deref()).entered();
     match button {
         DialpadButton::Enter => {
+            let armed_slot = state.armed_slot_index(config);
             if let Some(prompt_id) = state.armed.take() {
-                state.last_dispatched = Some(prompt_id.clone());
-                let style = config.arm_style_for(&prompt_id);
+                record_dispatch(state, &prompt_id);
+                let style = config.arm_style_for_mode(&prompt_id, &state.hooks_mode);
+                let highlight = armed_slot.map(|slot| {
+                    SideEffect::BroadcastSlotHighlight(
+                        slot,
+                        DISPATCH_HIGHLIGHT_RGB,
+                        DISPATCH_HIGHLIGHT_MS,
+                    )
+                });
 
-                if style == ArmStyle::Queue {
+                if style != ArmStyle::Prefill {
                     // Resolve the prompt to a command.
                     if let Some(prompt) = config.prompts.get(&prompt_id) {
                         let is_claude = config.is_claude_primary();
                         if let Some(cmd_text) = prompt.effective_command(is_claude) {
+                            if style == ArmStyle::Chain {
+                                // The rest of the chain goes one per `TaskCompleted`.
+                                state.start_chain(prompt.chain.clone());
+                            }
                             let cmd = VscodeCommand::send_text(
-                                TerminalTarget::ActiveClaude,
+                                state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                                 cmd_text,
                                 true,
                             );
-                            return vec![
-                                SideEffect::SendVscodeCommand(cmd),
-                                SideEffect::BroadcastRender,
-                            ];
+                            return [SideEffect::SendVscodeCommand(cmd), SideEffect::BroadcastRender]
+                                .into_iter()
+                                .chain(highlight)
+                                .collect();
                         }
                     }
                 } else {
                     // Prefill style: the text is already in the terminal.
                     // Just send a bare Enter.
                     let cmd = VscodeCommand::send_text(
-                        TerminalTarget::ActiveClaude,
+                        state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                         "",
                         true,
                     );
-                    return vec![
-                        SideEffect::SendVscodeCommand(cmd),
-                        SideEffect::BroadcastRender,
-                    ];
+                    return [SideEffect::SendVscodeCommand(cmd), SideEffect::BroadcastRender]
+                        .into_iter()
+                        .chain(highlight)
+                        .collect();
                 }
                 vec![SideEffect::BroadcastRender]
             } else {
                 // No prompt armed: send bare Enter (for /export confirmation, etc.)
                 let cmd = VscodeCommand::send_text(
-                    TerminalTarget::ActiveClaude,
+                    state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                     "",
                     true,
                 );
@@ -202,7 +510,7 @@ fn reduce_dialpad(
                     }
                     EscWhenPending::CancelAndPassthrough => {
                         let cmd = VscodeCommand::send_text(
-                            TerminalTarget::ActiveClaude,
+                            state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                             "\u{1b}",
                             false,
                         );
@@ -215,7 +523,7 @@ fn reduce_dialpad(
             } else {
                 // Send Esc to Claude terminal.
                 let cmd = VscodeCommand::send_text(
-                    TerminalTarget::ActiveClaude,
+                    state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                     "\u{1b}",
                     false,
                 );
@@ -226,7 +534,7 @@ fn reduce_dialpad(
         DialpadButton::CtrlC => {
             // Always forward Ctrl+C. Claude Code handles null-first-press gate.
             let cmd = VscodeCommand::send_text(
-                TerminalTarget::ActiveClaude,
+                state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                 "\u{0003}",
                 false,
             );
@@ -237,7 +545,7 @@ fn reduce_dialpad(
             // Send /export with newline — starts the flow immediately.
             // Claude's own confirmation prompts remain the safety gate.
             let cmd = VscodeCommand::send_text(
-                TerminalTarget::ActiveClaude,
+                state.resolve_target(&TerminalTarget::ActiveClaude).into(),
                 "/export",
                 true,
             );
@@ -246,17 +554,43 @@ fn reduce_dialpad(
     }
 }
 
+/// Most lines a single dial event may scroll.
+const MAX_SCROLL_LINES: i32 = 20;
+
+/// Slack for float error when adding up fractional detents (ten 0.1s are one line).
+const SCROLL_EPSILON: f32 = 1e-4;
+
+/// Scale a dial delta by `dial.sensitivity` on top of `remainder`, the part
+/// of a line left over from earlier turns. Returns the whole lines to scroll,
+/// clamped to ±[`MAX_SCROLL_LINES`], and the new remainder. Turning the other
+/// way drops the remainder.
+fn scale_scroll(delta: i32, sensitivity: f32, remainder: f32) -> (i32, f32) {
+    let remainder = if remainder * delta as f32 > 0.0 { remainder } else { 0.0 };
+    let total = remainder + delta as f32 * sensitivity;
+    let lines = (total + total.signum() * SCROLL_EPSILON).trunc();
+    let remainder = if lines == 0.0 { total } else { total - lines };
+    let lines = (lines as i32).clamp(-MAX_SCROLL_LINES, MAX_SCROLL_LINES);
+    (lines, remainder)
+}
+
 fn reduce_adjustment(
     state: &mut DaemonState,
+    config: &RunbookConfig,
     kind: AdjustmentKind,
     delta: i32,
 ) -> Vec<SideEffect> {
     match kind {
         AdjustmentKind::Dial => {
-            // Scroll terminal output.
+            // Scroll terminal output, once the turns add up to a whole line.
+            let (lines, remainder) =
+                scale_scroll(delta, config.dial.sensitivity, state.scroll_remainder);
+            state.scroll_remainder = remainder;
+            if lines == 0 {
+                return vec![];
+            }
             let cmd = VscodeCommand::scroll_terminal(
-                TerminalTarget::ActiveClaude,
-                delta,
+                state.resolve_target(&TerminalTarget::ActiveClaude).into(),
+                lines,
                 TerminalScrollUnit::Lines,
             );
             vec![SideEffect::SendVscodeCommand(cmd)]
@@ -273,26 +607,175 @@ fn reduce_adjustment(
     }
 }
 
+/// Move to `page` (not the one shown): announce the move, then [`leave_page`].
+fn change_page(
+    state: &mut DaemonState,
+    config: &RunbookConfig,
+    page: usize,
+    direction: Option<PageDirection>,
+) -> Vec<SideEffect> {
+    let from = (state.page, state.current_page_name(config).to_string());
+    state.page = page;
+    vec![page_changed(state, config, from, direction), leave_page(state, config)]
+}
+
+/// A prompt armed from outside the keypad (VS Code, a CLI) that has no key
+/// on the current page: move to the first page that has one, so the armed
+/// key is on screen and page navigation continues from there.
+fn show_armed_page(state: &mut DaemonState, config: &RunbookConfig) -> Option<SideEffect> {
+    if state.armed_slot_index(config).is_some() {
+        return None;
+    }
+    let page = state.page_for_prompt(state.armed.as_deref()?, config)?;
+    let from = (state.page, state.current_page_name(config).to_string());
+    state.page = page;
+    Some(page_changed(state, config, from, None))
+}
+
+/// `page_changed` for a move from `from` (index and name) to the current page.
+fn page_changed(
+    state: &DaemonState,
+    config: &RunbookConfig,
+    (from_page, from_name): (usize, String),
+    direction: Option<PageDirection>,
+) -> SideEffect {
+    SideEffect::BroadcastPageChanged(PageChangedEvent {
+        from_page,
+        to_page: state.page,
+        from_name,
+        to_name: state.current_page_name(config).to_string(),
+        direction,
+    })
+}
+
+/// Finish a page change. Clears the armed prompt (its prompt_id may not exist
+/// on the new page); if nothing was armed, the keys are all that changed.
+fn leave_page(state: &mut DaemonState, config: &RunbookConfig) -> SideEffect {
+    debug!(page = state.page, name = state.current_page_name(config), "page changed");
+    if state.armed.take().is_some() {
+        SideEffect::BroadcastRender
+    } else {
+        SideEffect::BroadcastRenderPage
+    }
+}
+
+/// A hook arrived for a session we never saw start. A few of these are normal
+/// (daemon started mid-session); repeats, or one after a `SessionStart` proved
+/// those are delivered, mean hooks are being dropped.
+fn note_unknown_session(state: &mut DaemonState) {
+    state.unknown_session_hooks += 1;
+    if matches!(state.hooks_mode, HooksMode::Degraded { .. }) {
+        return;
+    }
+    let reason = if state.seen_session_start && !state.sessions.is_empty() {
+        "session appeared without SessionStart".to_string()
+    } else if state.unknown_session_hooks >= UNKNOWN_SESSION_LIMIT {
+        format!(
+            "hooks for {} unknown sessions",
+            state.unknown_session_hooks
+        )
+    } else {
+        return;
+    };
+    warn!(%reason, "hooks degraded");
+    state.hooks_mode = HooksMode::Degraded { reason };
+}
+
+/// Check a hook event against `hook_policy` before it is reduced. The error
+/// names the rule that rejected it.
+pub fn check_hook_policy(
+    state: &DaemonState,
+    config: &RunbookConfig,
+    ev: &HookEvent,
+) -> anyhow::Result<()> {
+    let policy = &config.hook_policy;
+    if policy.require_session_tag && ev.session_tag.as_deref().unwrap_or("").is_empty() {
+        anyhow::bail!("hook_policy.require_session_tag: {} event has no session_tag", ev.hook);
+    }
+
+    let sid = ev.session_id.as_deref().unwrap_or("_default");
+    if ev.hook == "SessionEnd" || state.sessions.contains_key(sid) {
+        return Ok(());
+    }
+    let limit = policy.max_concurrent_sessions;
+    if limit > 0 && state.sessions.len() >= limit {
+        anyhow::bail!(
+            "hook_policy.max_concurrent_sessions: already tracking {} sessions; rejected session '{sid}'",
+            state.sessions.len()
+        );
+    }
+    if policy.block_on_active_session {
+        let running = state
+            .sessions
+            .iter()
+            .find(|(_, s)| s.agent_state == AgentState::Running);
+        if let Some((other, _)) = running {
+            anyhow::bail!(
+                "hook_policy.block_on_active_session: session '{other}' is running; rejected session '{sid}'"
+            );
+        }
+    }
+    Ok(())
+}
+
 fn reduce_hook(
     state: &mut DaemonState,
+    config: &RunbookConfig,
     hook: String,
     matcher: Option<String>,
     session_id: Option<String>,
     session_tag: Option<String>,
+    payload: &serde_json::Value,
 ) -> Vec<SideEffect> {
-    // Transition hooks_mode: Absent → Active on first event.
-    state.hooks_mode = HooksMode::Active;
-    state.last_hook_ts = Some(std::time::Instant::now());
+    let _span = debug_span!("reduce_hook", session_id = session_id.as_deref()).entered();
+    debug!(%hook, ?matcher, ?session_tag, "hook event");
+
+    // Transition hooks_mode: Absent/Stale → Active on any event.
+    let now = std::time::Instant::now();
+    state.last_hook_ts = Some(now);
+    state.update_hooks_mode(now, HOOKS_STALE_AFTER);
 
     // Determine the session to update.
     let sid = session_id.unwrap_or_else(|| "_default".to_string());
 
+    // Claude Code sometimes delivers the same hook event twice.
+    let fingerprint = hook_fingerprint(&hook, matcher.as_deref(), &sid, payload);
+    if !state.remember_hook(fingerprint) {
+        debug!(%hook, "duplicate hook event ignored");
+        return vec![SideEffect::BroadcastRender];
+    }
+    // Past a turn boundary, earlier events can come round again for real
+    // (the same prompt, an identical Stop).
+    if matches!(hook.as_str(), "UserPromptSubmit" | "Stop" | "SessionEnd") {
+        state.recent_hook_hashes.clear();
+        state.remember_hook(fingerprint);
+    }
+
+    // An expired session that speaks again was only idle, not unknown.
+    let resumed = state.expired_session_ids.remove(&sid);
+    if hook == "SessionStart" {
+        state.seen_session_start = true;
+    } else if !resumed && !state.sessions.contains_key(&sid) {
+        note_unknown_session(state);
+    }
+
     // Learn session_tag → session_id mapping if both are present.
     if let Some(ref tag) = session_tag {
         state.learn_session_tag(tag, &sid);
     }
 
+    if let Some(branch) = payload.get("git_branch").and_then(|b| b.as_str()) {
+        state.current_branch = Some(branch.to_string());
+    }
+
+    // A new session under this ID starts from a clean slate.
+    if hook == "SessionStart" {
+        state.reset_for_new_session(&sid);
+    }
+
     let session = state.ensure_session(&sid);
+    session.last_hook_at = std::time::Instant::now();
+    let previous = session.agent_state;
 
     match hook.as_str() {
         "SessionStart" => {
@@ -300,18 +783,37 @@ fn reduce_hook(
         }
         "Notification" => match matcher.as_deref() {
             Some("idle_prompt") => session.agent_state = AgentState::Idle,
-            Some("permission_prompt") => session.agent_state = AgentState::WaitingPermission,
-            Some("elicitation_dialog") => session.agent_state = AgentState::WaitingInput,
+            Some("permission_prompt") => {
+                session.agent_state = AgentState::WaitingPermission;
+                session.pending_permission = Some(PermissionRequest::from_payload(payload));
+            }
+            Some("elicitation_dialog") => {
+                session.agent_state = AgentState::WaitingInput;
+                session.elicitation_prompt = payload
+                    .get("prompt")
+                    .or_else(|| payload.get("question"))
+                    .and_then(|v| v.as_str())
+                    .map(|s| s.to_string());
+            }
             _ => {}
         },
         "UserPromptSubmit" => {
             session.agent_state = AgentState::Running;
+            if let Some(prompt) = payload.get("prompt").and_then(|v| v.as_str()) {
+                session.last_prompt_text =
+                    Some(prompt.chars().take(MAX_PROMPT_TEXT_CHARS).collect());
+            }
         }
         "PreToolUse" => {
             session.agent_state = AgentState::Running;
+            session.tool_call_count += 1;
+            if let Some(name) = payload.get("tool_name").and_then(|v| v.as_str()) {
+                session.last_tool = Some(name.to_string());
+            }
         }
         "PermissionRequest" => {
             session.agent_state = AgentState::WaitingPermission;
+            session.pending_permission = Some(PermissionRequest::from_payload(payload));
         }
         "PostToolUse" | "PostToolUseFailure" => {
             session.agent_state = AgentState::Running;
@@ -325,16 +827,161 @@ fn reduce_hook(
         "SessionEnd" => {
             state.remove_session(&sid);
         }
-        "RunbookPolicy" => match matcher.as_deref() {
-            Some("blocked") => {
-                session.agent_state = AgentState::Blocked;
+        "RunbookPolicy" if matcher.as_deref() == Some("blocked") => {
+            session.agent_state = AgentState::Blocked;
+            session.blocked_count += 1;
+        }
+        // The tool call went ahead, so the session is working again.
+        "RunbookPolicy" if matches!(matcher.as_deref(), Some("allowed" | "overridden")) => {
+            session.agent_state = AgentState::Running;
+        }
+        _ => {}
+    }
+
+    // The dialog is resolved once the session moves to any other state.
+    if let Some(session) = state.sessions.get_mut(&sid) {
+        if session.agent_state != AgentState::WaitingPermission {
+            session.pending_permission = None;
+        }
+        if session.agent_state != AgentState::WaitingInput {
+            session.elicitation_prompt = None;
+        }
+    }
+
+    let mut effects = vec![SideEffect::BroadcastRender, SideEffect::BroadcastSessionList];
+    // An ended session has no state left to announce.
+    if let Some(session) = state.sessions.get_mut(&sid) {
+        if session.agent_state != previous {
+            session.previous_agent_state = previous;
+            effects.push(SideEffect::BroadcastStateChange(AgentStateChange {
+                session_id: Some(sid.clone()),
+                previous,
+                current: session.agent_state,
+                hook_trigger: hook.clone(),
+            }));
+        }
+    }
+    if hook == "RunbookPolicy" {
+        let command = payload
+            .pointer("/runbook_policy/command")
+            .and_then(|v| v.as_str())
+            .map(|s| s.to_string());
+        match matcher.as_deref() {
+            Some("blocked") => effects.push(SideEffect::BroadcastPolicyBlock(PolicyBlock {
+                session_id: Some(sid.clone()),
+                command,
+            })),
+            // Let through via `allow_override_env`: never quietly.
+            Some("overridden") => {
+                let command = command.unwrap_or_default();
+                warn!(session_id = %sid, %command, "runbook policy overridden");
+                let message = format!("Policy overridden: {command}");
+                effects.push(toast(state, message, NoticeSeverity::Warning));
             }
             _ => {}
-        },
-        _ => {}
+        }
+    }
+    if let Some(session) = state.sessions.get(&sid) {
+        if session.agent_state == AgentState::Running {
+            effects.push(SideEffect::BroadcastProgress(ProgressUpdate {
+                session_id: sid.clone(),
+                tool_call_count: session.tool_call_count,
+                last_tool_name: session.last_tool.clone(),
+            }));
+        }
+    }
+    if hook == "TaskCompleted" {
+        // A running macro or chain already decides what comes next.
+        match dispatch_next_step(state, config) {
+            Some(step) => effects.push(step),
+            None => effects.extend(suggest_next(state, config, &sid)),
+        }
+        // Only the session on screen moves the keypad (and drops what's armed).
+        // The render queued above shows the new page.
+        let advance = state
+            .current_page(config)
+            .and_then(|p| p.on_complete_advance_to.as_deref())
+            .filter(|_| state.current_session_id().as_deref() == Some(sid.as_str()));
+        if let Some(name) = advance {
+            let from = (state.page, state.current_page_name(config).to_string());
+            if state.select_page_by_name(name, config) && state.page != from.0 {
+                // Ahead of the render, like every other page change.
+                effects.insert(0, page_changed(state, config, from, None));
+            }
+        }
+    }
+    effects
+}
+
+/// Identity of a hook delivery, for spotting duplicates.
+fn hook_fingerprint(
+    hook: &str,
+    matcher: Option<&str>,
+    session_id: &str,
+    payload: &serde_json::Value,
+) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (hook, matcher, session_id, payload.to_string()).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// The last dispatched prompt's `suggests_next`, if it has one and
+/// `session_id` is the first completion from the session it went to.
+fn suggest_next(
+    state: &mut DaemonState,
+    config: &RunbookConfig,
+    session_id: &str,
+) -> Option<SideEffect> {
+    if state.last_dispatched_session_id.as_deref() != Some(session_id) {
+        return None;
+    }
+    if !std::mem::take(&mut state.suggestion_pending) {
+        return None;
     }
+    let last = state.last_dispatched.as_deref()?;
+    let next = config.prompts.get(last)?.suggests_next.clone()?;
+    Some(SideEffect::BroadcastSuggestion(PromptSuggestion {
+        prompt_id: next,
+        reason: format!("usually follows {last}"),
+        confidence: 1.0,
+    }))
+}
+
+/// Dispatch the next pending macro or chain step, if any, as if it had been
+/// armed and confirmed. At most one of the two is running.
+fn dispatch_next_step(state: &mut DaemonState, config: &RunbookConfig) -> Option<SideEffect> {
+    let (prompt_id, kind) = match state.next_macro_step() {
+        Some(step) => (step.prompt_id, "macro"),
+        None => (state.next_chain_step()?, "chain"),
+    };
+    let Some(cmd_text) = config
+        .prompts
+        .get(&prompt_id)
+        .and_then(|p| p.effective_command(config.is_claude_primary()))
+    else {
+        warn!(%prompt_id, "{kind} step has no command; skipped");
+        let message = format!("{kind} step '{prompt_id}' has no command; skipped");
+        return Some(toast(state, message, NoticeSeverity::Warning));
+    };
+    record_dispatch(state, &prompt_id);
+    let cmd = VscodeCommand::send_text(
+        state.resolve_target(&TerminalTarget::ActiveClaude).into(),
+        cmd_text,
+        true,
+    );
+    Some(SideEffect::SendVscodeCommand(cmd))
+}
 
-    vec![SideEffect::BroadcastRender]
+/// Bookkeeping shared by every prompt dispatch.
+fn record_dispatch(state: &mut DaemonState, prompt_id: &str) {
+    state.last_dispatched = Some(prompt_id.to_string());
+    state.last_dispatched_session_id = state.current_session_id();
+    state.suggestion_pending = true;
+    *state.dispatch_counts.entry(prompt_id.to_string()).or_insert(0) += 1;
+    state
+        .dispatch_timestamps
+        .insert(prompt_id.to_string(), std::time::SystemTime::now());
+    state.associate_prompt(prompt_id);
 }
 
 // ---------------------------------------------------------------------------
@@ -343,7 +990,10 @@ fn reduce_hook(
 
 #[cfg(test)]
 mod tests {
+    use std::time::Instant;
+
     use super::*;
+    use runbook_protocol::TerminalInfo;
     use crate::config::RunbookConfig;
     use crate::state::DaemonState;
 
@@ -376,6 +1026,59 @@ prompts:
         serde_yaml::from_str(yaml).unwrap()
     }
 
+    #[test]
+    fn arming_a_prompt_off_page_moves_to_its_page() {
+        let mut config = sample_config();
+        config.prompts.insert("review_pr".to_string(), config.prompts["prep_pr"].clone());
+        for name in ["review", "ship"] {
+            let mut page = config.keypad.pages[0].clone();
+            page.name = name.to_string();
+            page.slots[0].prompt_id = None;
+            config.keypad.pages.push(page);
+        }
+        config.keypad.pages[1].slots[3].prompt_id = Some("review_pr".to_string());
+        let mut state = DaemonState::new(2);
+
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::KeypadPress {
+                prompt_id: "review_pr".to_string(),
+            },
+        );
+        assert_eq!(state.page, 1);
+        assert_eq!(state.armed.as_deref(), Some("review_pr"));
+        assert!(matches!(
+            &effects[0],
+            SideEffect::BroadcastPageChanged(ev) if ev.from_page == 2 && ev.to_page == 1
+        ));
+        let model = crate::render::build_render_model(&state, &config);
+        assert_eq!(model.page_index, 1);
+        assert!(model.keypad.slots[3].armed);
+
+        // Paging carries on from the page on screen.
+        reduce(
+            &mut state,
+            &config,
+            Event::PageNav {
+                direction: PageDirection::Next,
+            },
+        );
+        assert_eq!(state.page, 2);
+
+        // Armed from the current page: nothing moves.
+        state.page = 0;
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::KeypadPress {
+                prompt_id: "prep_pr".to_string(),
+            },
+        );
+        assert_eq!(state.page, 0);
+        assert!(matches!(effects.as_slice(), [SideEffect::BroadcastRender]));
+    }
+
     #[test]
     fn arm_and_dispatch() {
         let config = sample_config();
@@ -406,52 +1109,118 @@ prompts:
     }
 
     #[test]
-    fn cancel_arm() {
+    fn dispatch_records_the_receiving_session() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
+        reduce(&mut state, &config, hook("SessionStart", "s1"));
+        press(&mut state, &config, "prep_pr");
+        let enter = || Event::DialpadButton {
+            button: DialpadButton::Enter,
+        };
+        reduce(&mut state, &config, enter());
+        assert_eq!(state.last_dispatched.as_deref(), Some("prep_pr"));
+        assert_eq!(state.last_dispatched_session_id.as_deref(), Some("s1"));
 
-        // Arm then cancel.
+        // Another session restarting or ending leaves the dispatch alone.
+        reduce(&mut state, &config, hook("SessionStart", "s2"));
+        assert_eq!(state.last_dispatched.as_deref(), Some("prep_pr"));
+        reduce(&mut state, &config, hook("SessionEnd", "s2"));
+        assert_eq!(state.last_dispatched.as_deref(), Some("prep_pr"));
+        assert_eq!(state.last_dispatched_session_id.as_deref(), Some("s1"));
+
+        reduce(&mut state, &config, hook("SessionStart", "s1"));
+        assert_eq!(state.last_dispatched, None);
+        assert_eq!(state.last_dispatched_session_id, None);
+
+        // Ending the session it went to clears it too.
+        press(&mut state, &config, "prep_pr");
+        reduce(&mut state, &config, enter());
+        assert_eq!(state.last_dispatched_session_id.as_deref(), Some("s1"));
+        reduce(&mut state, &config, hook("SessionEnd", "s1"));
+        assert_eq!(state.last_dispatched, None);
+        assert_eq!(state.last_dispatched_session_id, None);
+    }
+
+    fn press(state: &mut DaemonState, config: &RunbookConfig, prompt_id: &str) {
         reduce(
-            &mut state,
-            &config,
+            state,
+            config,
             Event::KeypadPress {
-                prompt_id: "prep_pr".to_string(),
+                prompt_id: prompt_id.to_string(),
             },
         );
-        assert!(state.armed.is_some());
+    }
 
-        let effects = reduce(
-            &mut state,
-            &config,
+    fn enter(state: &mut DaemonState, config: &RunbookConfig) {
+        reduce(
+            state,
+            config,
             Event::DialpadButton {
-                button: DialpadButton::Esc,
+                button: DialpadButton::Enter,
             },
         );
-        assert!(state.armed.is_none());
-        // Should broadcast render, should NOT send Esc to terminal.
-        assert!(effects.iter().all(|e| matches!(e, SideEffect::BroadcastRender)));
     }
 
     #[test]
-    fn esc_when_not_armed_sends_to_terminal() {
+    fn arm_without_dispatch_has_zero_rate() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        press(&mut state, &config, "prep_pr");
+
+        let stats = state.prompt_stats("prep_pr");
+        assert_eq!((stats.arm_count, stats.dispatch_count), (1, 0));
+        assert_eq!(stats.arm_rate, 0.0);
+        assert_eq!(state.prompt_stats("break_task").arm_rate, 0.0);
+    }
+
+    #[test]
+    fn arm_then_dispatch_has_full_rate() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        press(&mut state, &config, "prep_pr");
+        enter(&mut state, &config);
+
+        assert_eq!(state.prompt_stats("prep_pr").arm_rate, 1.0);
+    }
+
+    #[test]
+    fn arm_twice_dispatch_once_has_half_rate() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        press(&mut state, &config, "prep_pr");
+        press(&mut state, &config, "prep_pr");
+        enter(&mut state, &config);
+
+        let stats = state.prompt_stats("prep_pr");
+        assert_eq!((stats.arm_count, stats.dispatch_count), (2, 1));
+        assert_eq!(stats.arm_rate, 0.5);
+    }
+
+    #[test]
+    fn dispatch_highlights_the_prompts_slot() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
 
+        reduce(
+            &mut state,
+            &config,
+            Event::KeypadPress {
+                prompt_id: "break_task".to_string(),
+            },
+        );
         let effects = reduce(
             &mut state,
             &config,
             Event::DialpadButton {
-                button: DialpadButton::Esc,
+                button: DialpadButton::Enter,
             },
         );
-        assert!(effects.iter().any(|e| matches!(e, SideEffect::SendVscodeCommand(_))));
-    }
-
-    #[test]
-    fn enter_when_not_armed_sends_enter() {
-        let config = sample_config();
-        let mut state = DaemonState::new(0);
+        assert!(matches!(
+            effects.last(),
+            Some(SideEffect::BroadcastSlotHighlight(1, [0, 255, 0], 500))
+        ));
 
+        // A bare Enter dispatches nothing, so nothing flashes.
         let effects = reduce(
             &mut state,
             &config,
@@ -459,41 +1228,1195 @@ prompts:
                 button: DialpadButton::Enter,
             },
         );
-        assert!(effects.iter().any(|e| matches!(e, SideEffect::SendVscodeCommand(_))));
+        assert!(!effects
+            .iter()
+            .any(|e| matches!(e, SideEffect::BroadcastSlotHighlight(..))));
     }
 
     #[test]
-    fn page_nav_wraps() {
+    fn cancel_arm() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
 
-        // Only 1 page; prev wraps to 0.
+        // Arm then cancel.
         reduce(
             &mut state,
             &config,
-            Event::PageNav {
+            Event::KeypadPress {
+                prompt_id: "prep_pr".to_string(),
+            },
+        );
+        assert!(state.armed.is_some());
+
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::DialpadButton {
+                button: DialpadButton::Esc,
+            },
+        );
+        assert!(state.armed.is_none());
+        // Should broadcast render, should NOT send Esc to terminal.
+        assert!(effects.iter().all(|e| matches!(e, SideEffect::BroadcastRender)));
+    }
+
+    #[test]
+    fn esc_when_not_armed_sends_to_terminal() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::DialpadButton {
+                button: DialpadButton::Esc,
+            },
+        );
+        assert!(effects.iter().any(|e| matches!(e, SideEffect::SendVscodeCommand(_))));
+    }
+
+    #[test]
+    fn enter_when_not_armed_sends_enter() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::DialpadButton {
+                button: DialpadButton::Enter,
+            },
+        );
+        assert!(effects.iter().any(|e| matches!(e, SideEffect::SendVscodeCommand(_))));
+    }
+
+    #[test]
+    fn page_nav_wraps() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        // Only 1 page; prev wraps to 0.
+        reduce(
+            &mut state,
+            &config,
+            Event::PageNav {
+                direction: PageDirection::Prev,
+            },
+        );
+        assert_eq!(state.page, 0);
+
+        // Next wraps to 0.
+        reduce(
+            &mut state,
+            &config,
+            Event::PageNav {
+                direction: PageDirection::Next,
+            },
+        );
+        assert_eq!(state.page, 0);
+    }
+
+    #[test]
+    fn page_nav_jump_to_clamps_to_last_page() {
+        let mut config = sample_config();
+        for name in ["review", "ship"] {
+            let mut page = config.keypad.pages[0].clone();
+            page.name = name.to_string();
+            config.keypad.pages.push(page);
+        }
+        let mut state = DaemonState::new(0);
+        let jump = |index| Event::PageNav {
+            direction: PageDirection::JumpTo { index },
+        };
+
+        reduce(&mut state, &config, jump(1));
+        assert_eq!(state.page, 1);
+        reduce(&mut state, &config, jump(9));
+        assert_eq!(state.page, 2);
+    }
+
+    #[test]
+    fn page_nav_sends_page_render_when_only_page_changes() {
+        let mut config = sample_config();
+        let mut second = config.keypad.pages[0].clone();
+        second.name = "review".to_string();
+        config.keypad.pages.push(second);
+        let mut state = DaemonState::new(0);
+        let next = || Event::PageNav {
+            direction: PageDirection::Next,
+        };
+
+        let effects = reduce(&mut state, &config, next());
+        assert_eq!(state.page, 1);
+        assert!(matches!(
+            effects.as_slice(),
+            [SideEffect::BroadcastPageChanged(_), SideEffect::BroadcastRenderPage]
+        ));
+
+        // Dropping an armed prompt changes more than the keys.
+        state.armed = Some("prep_pr".to_string());
+        let effects = reduce(&mut state, &config, next());
+        assert_eq!(state.page, 0);
+        assert!(state.armed.is_none());
+        assert!(matches!(
+            effects.as_slice(),
+            [SideEffect::BroadcastPageChanged(_), SideEffect::BroadcastRender]
+        ));
+    }
+
+    #[test]
+    fn page_nav_announces_page_change() {
+        let mut config = sample_config();
+        for name in ["review", "ship"] {
+            let mut page = config.keypad.pages[0].clone();
+            page.name = name.to_string();
+            config.keypad.pages.push(page);
+        }
+        let mut state = DaemonState::new(0);
+        let changed = |effects: &[SideEffect]| {
+            effects.iter().find_map(|e| match e {
+                SideEffect::BroadcastPageChanged(c) => Some(c.clone()),
+                _ => None,
+            })
+        };
+
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::PageNav {
                 direction: PageDirection::Prev,
             },
         );
-        assert_eq!(state.page, 0);
+        let ev = changed(&effects).expect("prev changes page");
+        assert_eq!((ev.from_page, ev.to_page), (0, 2));
+        assert_eq!((ev.from_name.as_str(), ev.to_name.as_str()), ("core", "ship"));
+        assert_eq!(ev.direction, Some(PageDirection::Prev));
+
+        let jump = |index| Event::PageNav {
+            direction: PageDirection::JumpTo { index },
+        };
+        let ev = changed(&reduce(&mut state, &config, jump(1))).expect("jump changes page");
+        assert_eq!((ev.from_page, ev.to_page), (2, 1));
+        assert_eq!(ev.direction, None, "jumps have no direction");
+
+        // Already there: nothing to animate.
+        assert_eq!(changed(&reduce(&mut state, &config, jump(1))), None);
+    }
+
+    #[test]
+    fn page_jump_selects_page_by_hotkey() {
+        let mut config = sample_config();
+        let mut second = config.keypad.pages[0].clone();
+        second.name = "review".to_string();
+        second.hotkey = Some("p2".to_string());
+        config.keypad.pages.push(second);
+
+        let mut state = DaemonState::new(0);
+        state.armed = Some("prep_pr".to_string());
+
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::PageJump {
+                hotkey: "p2".to_string(),
+            },
+        );
+        assert_eq!(state.page, 1);
+        assert!(state.armed.is_none());
+        match effects.as_slice() {
+            [SideEffect::BroadcastPageChanged(ev), SideEffect::BroadcastRender] => {
+                assert_eq!((ev.from_page, ev.to_page, ev.direction), (0, 1, None));
+            }
+            other => panic!("expected page_changed then render, got {other:?}"),
+        }
+
+        // Unknown hotkey: nothing changes.
+        let now = Instant::now();
+        let before = state.snapshot_at(now);
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::PageJump {
+                hotkey: "F9".to_string(),
+            },
+        );
+        assert_eq!(state.snapshot_at(now), before);
+        assert!(effects.is_empty());
+    }
+
+    #[test]
+    fn task_completed_advances_to_named_page() {
+        let mut config = sample_config();
+        let mut second = config.keypad.pages[0].clone();
+        second.name = "review".to_string();
+        config.keypad.pages.push(second);
+        config.keypad.pages[0].on_complete_advance_to = Some("review".to_string());
+        let mut state = DaemonState::new(0);
+        state.armed = Some("prep_pr".to_string());
+
+        let effects = reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        assert_eq!(state.page, 1);
+        assert!(state.armed.is_none());
+        match effects.as_slice() {
+            [SideEffect::BroadcastPageChanged(ev), SideEffect::BroadcastRender, ..] => {
+                assert_eq!((ev.from_name.as_str(), ev.to_name.as_str()), ("core", "review"));
+                assert_eq!(ev.direction, None);
+            }
+            other => panic!("expected page_changed ahead of the render, got {other:?}"),
+        }
+
+        // The review page doesn't advance anywhere.
+        let effects = reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        assert_eq!(state.page, 1);
+        assert!(!effects
+            .iter()
+            .any(|e| matches!(e, SideEffect::BroadcastPageChanged(_))));
+    }
+
+    #[test]
+    fn task_completed_in_another_session_does_not_advance() {
+        let mut config = sample_config();
+        let mut second = config.keypad.pages[0].clone();
+        second.name = "review".to_string();
+        config.keypad.pages.push(second);
+        config.keypad.pages[0].on_complete_advance_to = Some("review".to_string());
+        let mut state = DaemonState::new(0);
+        reduce(&mut state, &config, hook("SessionStart", "s1"));
+        reduce(&mut state, &config, hook("SessionStart", "s2"));
+        state.active_session = Some("s1".to_string());
+        state.armed = Some("prep_pr".to_string());
+
+        reduce(&mut state, &config, hook("TaskCompleted", "s2"));
+        assert_eq!(state.page, 0);
+        assert_eq!(state.armed.as_deref(), Some("prep_pr"));
+
+        reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        assert_eq!(state.page, 1);
+        assert!(state.armed.is_none());
+    }
+
+    #[test]
+    fn first_client_connection_is_remembered() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let connect = |kind| Event::ClientConnected { kind };
+
+        reduce(&mut state, &config, connect(ClientKindTag::Vscode));
+        let first = state.first_client_at.expect("first client recorded");
+        assert!(state.vscode_connected);
+
+        reduce(
+            &mut state,
+            &config,
+            Event::ClientDisconnected {
+                kind: ClientKindTag::Vscode,
+            },
+        );
+        reduce(&mut state, &config, connect(ClientKindTag::Logi));
+        assert_eq!(state.first_client_at, Some(first));
+    }
+
+    fn dial(config: &RunbookConfig, delta: i32) -> i64 {
+        let mut state = DaemonState::new(0);
+        let effects = reduce(
+            &mut state,
+            config,
+            Event::Adjustment {
+                kind: AdjustmentKind::Dial,
+                delta,
+            },
+        );
+        match effects.as_slice() {
+            [SideEffect::SendVscodeCommand(cmd)] => cmd.payload["delta"].as_i64().unwrap(),
+            other => panic!("expected one scroll command, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn dial_sensitivity_scales_scroll_delta() {
+        let mut config = sample_config();
+        assert_eq!(dial(&config, 3), 3);
+
+        config.dial.sensitivity = 0.5;
+        assert_eq!(dial(&config, 4), 2);
+        assert_eq!(dial(&config, -4), -2);
+
+        config.dial.sensitivity = 2.0;
+        assert_eq!(dial(&config, 3), 6);
+        assert_eq!(dial(&config, -3), -6);
+    }
+
+    #[test]
+    fn dial_scroll_is_clamped() {
+        let mut config = sample_config();
+        config.dial.sensitivity = 5.0;
+        assert_eq!(dial(&config, 10), 20);
+        assert_eq!(dial(&config, -10), -20);
+
+        config.dial.sensitivity = 0.1;
+        assert_eq!(dial(&config, 30), 3);
+    }
+
+    #[test]
+    fn fractional_sensitivity_adds_up_across_detents() {
+        let mut config = sample_config();
+        config.dial.sensitivity = 0.5;
+        let mut state = DaemonState::new(0);
+        let mut turn = |delta| {
+            let effects = reduce(
+                &mut state,
+                &config,
+                Event::Adjustment {
+                    kind: AdjustmentKind::Dial,
+                    delta,
+                },
+            );
+            sent_scroll(&effects)
+        };
+
+        assert_eq!(turn(1), None, "half a line");
+        assert_eq!(turn(1), Some(1));
+        assert_eq!(turn(1), None);
+        // Turning back starts over rather than cancelling out.
+        assert_eq!(turn(-1), None);
+        assert_eq!(turn(-1), Some(-1));
+
+        config.dial.sensitivity = 0.1;
+        let mut state = DaemonState::new(0);
+        let scrolled: Vec<_> = (0..10)
+            .filter_map(|_| {
+                let ev = Event::Adjustment {
+                    kind: AdjustmentKind::Dial,
+                    delta: 1,
+                };
+                sent_scroll(&reduce(&mut state, &config, ev))
+            })
+            .collect();
+        assert_eq!(scrolled, [1]);
+    }
+
+    fn sent_scroll(effects: &[SideEffect]) -> Option<i64> {
+        match effects {
+            [] => None,
+            [SideEffect::SendVscodeCommand(cmd)] => cmd.payload["delta"].as_i64(),
+            other => panic!("expected at most one scroll command, got {other:?}"),
+        }
+    }
+
+    fn reconfigure(kind: ClientKind, dial_mode: Option<DialMode>) -> Event {
+        Event::Reconfigure {
+            kind,
+            request: ReconfigureRequest {
+                dial_mode,
+                label_max_chars: None,
+            },
+        }
+    }
+
+    #[test]
+    fn logi_dial_mode_override_wins_over_config() {
+        let mut config = sample_config();
+        config.dial.mode = DialMode::VscodeTerminalScroll;
+        let mut state = DaemonState::new(0);
+        let dial = || Event::Adjustment {
+            kind: AdjustmentKind::Dial,
+            delta: 1,
+        };
+        assert_eq!(reduce(&mut state, &config, dial()).len(), 1);
+
+        // Only the Logi override counts; it owns the dial.
+        reduce(&mut state, &config, reconfigure(ClientKind::Vscode, Some(DialMode::OsScroll)));
+        assert_eq!(reduce(&mut state, &config, dial()).len(), 1);
+
+        reduce(&mut state, &config, reconfigure(ClientKind::Logi, Some(DialMode::OsScroll)));
+        assert!(reduce(&mut state, &config, dial()).is_empty());
+        // Swipes still scroll; they aren't the dial.
+        assert_eq!(reduce(&mut state, &config, gesture(GestureDirection::SwipeDown, 2)).len(), 1);
+
+        // An omitted field keeps the override.
+        reduce(&mut state, &config, reconfigure(ClientKind::Logi, None));
+        assert!(reduce(&mut state, &config, dial()).is_empty());
+
+        reduce(
+            &mut state,
+            &config,
+            reconfigure(ClientKind::Logi, Some(DialMode::VscodeTerminalScroll)),
+        );
+        assert_eq!(reduce(&mut state, &config, dial()).len(), 1);
+    }
+
+    #[test]
+    fn logi_label_max_chars_override_rerenders() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let set = |max| Event::Reconfigure {
+            kind: ClientKind::Logi,
+            request: ReconfigureRequest {
+                dial_mode: None,
+                label_max_chars: Some(max),
+            },
+        };
+        assert_eq!(state.label_max_chars(&config), config.keypad.label_max_chars);
+
+        let effects = reduce(&mut state, &config, set(4));
+        assert!(matches!(effects.as_slice(), [SideEffect::BroadcastRender]));
+        assert_eq!(state.label_max_chars(&config), 4);
+        // Unchanged: nothing to redraw.
+        assert!(reduce(&mut state, &config, set(4)).is_empty());
+    }
+
+    fn gesture(direction: GestureDirection, distance: u32) -> Event {
+        Event::Gesture {
+            direction,
+            distance,
+        }
+    }
+
+    #[test]
+    fn swipes_navigate_pages_and_scroll() {
+        let mut config = sample_config();
+        let second = config.keypad.pages[0].clone();
+        config.keypad.pages.push(second);
+        let mut state = DaemonState::new(0);
+
+        reduce(&mut state, &config, gesture(GestureDirection::SwipeRight, 0));
+        assert_eq!(state.page, 1);
+        reduce(&mut state, &config, gesture(GestureDirection::SwipeLeft, 0));
+        assert_eq!(state.page, 0);
+
+        let scroll_delta = |effects: Vec<SideEffect>| match effects.as_slice() {
+            [SideEffect::SendVscodeCommand(cmd)] => {
+                assert_eq!(cmd.kind, runbook_protocol::VscodeCommandKind::ScrollTerminal);
+                cmd.payload["delta"].as_i64().unwrap()
+            }
+            other => panic!("expected one scroll command, got {other:?}"),
+        };
+        let up = reduce(&mut state, &config, gesture(GestureDirection::SwipeUp, 3));
+        assert_eq!(scroll_delta(up), -3);
+        // A zero-distance swipe still scrolls one line.
+        let down = reduce(&mut state, &config, gesture(GestureDirection::SwipeDown, 0));
+        assert_eq!(scroll_delta(down), 1);
+    }
+
+    #[test]
+    fn tap_center_confirms_only_when_armed() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        let now = Instant::now();
+        let before = state.snapshot_at(now);
+        let effects = reduce(&mut state, &config, gesture(GestureDirection::TapCenter, 0));
+        assert!(effects.is_empty());
+        assert_eq!(state.snapshot_at(now), before);
+
+        state.armed = Some("prep_pr".to_string());
+        let effects = reduce(&mut state, &config, gesture(GestureDirection::TapCenter, 0));
+        assert!(state.armed.is_none());
+        assert_eq!(state.last_dispatched.as_deref(), Some("prep_pr"));
+        assert!(effects.iter().any(|e| matches!(e, SideEffect::SendVscodeCommand(_))));
+    }
+
+    fn hook(name: &str, session_id: &str) -> Event {
+        Event::HookEvent {
+            hook: name.to_string(),
+            matcher: None,
+            session_id: Some(session_id.to_string()),
+            session_tag: None,
+            payload: serde_json::Value::Null,
+        }
+    }
+
+    /// `TaskCompleted` for its own task, so back-to-back ones aren't repeats.
+    fn task_completed(session_id: &str, task_id: &str) -> Event {
+        Event::HookEvent {
+            hook: "TaskCompleted".to_string(),
+            matcher: None,
+            session_id: Some(session_id.to_string()),
+            session_tag: None,
+            payload: serde_json::json!({"task_id": task_id}),
+        }
+    }
+
+    fn hook_ev(name: &str, session_id: &str, session_tag: Option<&str>) -> HookEvent {
+        HookEvent {
+            hook: name.to_string(),
+            matcher: None,
+            session_id: Some(session_id.to_string()),
+            session_tag: session_tag.map(str::to_string),
+            payload: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn hook_policy_allows_everything_by_default() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.ensure_session("a").agent_state = AgentState::Running;
+        assert!(check_hook_policy(&state, &config, &hook_ev("SessionStart", "b", None)).is_ok());
+    }
+
+    #[test]
+    fn hook_policy_requires_session_tag() {
+        let mut config = sample_config();
+        config.hook_policy.require_session_tag = true;
+        let state = DaemonState::new(0);
+
+        let err = check_hook_policy(&state, &config, &hook_ev("Stop", "a", None)).unwrap_err();
+        assert!(err.to_string().starts_with("hook_policy.require_session_tag"), "{err}");
+        assert!(check_hook_policy(&state, &config, &hook_ev("Stop", "a", Some(""))).is_err());
+        assert!(check_hook_policy(&state, &config, &hook_ev("Stop", "a", Some("t"))).is_ok());
+    }
+
+    #[test]
+    fn hook_policy_caps_concurrent_sessions() {
+        let mut config = sample_config();
+        config.hook_policy.max_concurrent_sessions = 2;
+        let mut state = DaemonState::new(0);
+        state.ensure_session("a");
+        state.ensure_session("b");
+
+        let err =
+            check_hook_policy(&state, &config, &hook_ev("SessionStart", "c", None)).unwrap_err();
+        assert!(err.to_string().starts_with("hook_policy.max_concurrent_sessions"), "{err}");
+        // Known sessions keep reporting, and ending one is always allowed.
+        assert!(check_hook_policy(&state, &config, &hook_ev("Stop", "a", None)).is_ok());
+        assert!(check_hook_policy(&state, &config, &hook_ev("SessionEnd", "c", None)).is_ok());
+    }
+
+    #[test]
+    fn hook_policy_blocks_new_sessions_while_one_runs() {
+        let mut config = sample_config();
+        config.hook_policy.block_on_active_session = true;
+        let mut state = DaemonState::new(0);
+        state.ensure_session("a").agent_state = AgentState::Running;
+
+        let err =
+            check_hook_policy(&state, &config, &hook_ev("SessionStart", "b", None)).unwrap_err();
+        assert!(err.to_string().contains("session 'a' is running"), "{err}");
+        assert!(check_hook_policy(&state, &config, &hook_ev("PreToolUse", "a", None)).is_ok());
+
+        state.ensure_session("a").agent_state = AgentState::Idle;
+        assert!(check_hook_policy(&state, &config, &hook_ev("SessionStart", "b", None)).is_ok());
+    }
+
+    fn sent_text(effects: &[SideEffect]) -> Vec<String> {
+        effects
+            .iter()
+            .filter_map(|e| match e {
+                SideEffect::SendVscodeCommand(cmd) => {
+                    cmd.payload["text"].as_str().map(str::to_string)
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn task_completed_suggests_next_prompt() {
+        let mut config = sample_config();
+        config.prompts.get_mut("prep_pr").unwrap().suggests_next = Some("break_task".to_string());
+        let mut state = DaemonState::new(0);
+        let suggestion = |effects: &[SideEffect]| {
+            effects.iter().find_map(|e| match e {
+                SideEffect::BroadcastSuggestion(s) => Some(s.clone()),
+                _ => None,
+            })
+        };
+        let dispatch = |state: &mut DaemonState| {
+            press(state, &config, "prep_pr");
+            let enter = Event::DialpadButton {
+                button: DialpadButton::Enter,
+            };
+            reduce(state, &config, enter);
+        };
+        reduce(&mut state, &config, hook("SessionStart", "s1"));
+
+        // Nothing dispatched yet.
+        let effects = reduce(&mut state, &config, task_completed("s1", "t1"));
+        assert_eq!(suggestion(&effects), None);
+
+        // Only the session the prompt went to triggers the suggestion.
+        dispatch(&mut state);
+        let effects = reduce(&mut state, &config, task_completed("s2", "t2"));
+        assert_eq!(suggestion(&effects), None);
+        let effects = reduce(&mut state, &config, task_completed("s1", "t3"));
+        let s = suggestion(&effects).expect("prep_pr suggests break_task");
+        assert_eq!(s.prompt_id, "break_task");
+        assert_eq!(s.reason, "usually follows prep_pr");
+        assert_eq!(s.confidence, 1.0);
+
+        // And only once per dispatch.
+        let effects = reduce(&mut state, &config, task_completed("s1", "t4"));
+        assert_eq!(suggestion(&effects), None);
+
+        // A macro step takes precedence over the suggestion.
+        dispatch(&mut state);
+        let steps = vec![MacroStep {
+            prompt_id: "prep_pr".to_string(),
+        }];
+        reduce(&mut state, &config, Event::StartMacro { steps });
+        let effects = reduce(&mut state, &config, task_completed("s1", "t5"));
+        assert_eq!(sent_text(&effects), ["/runbook:prep-pr"]);
+        assert_eq!(suggestion(&effects), None);
+    }
+
+    #[test]
+    fn skipped_macro_step_raises_a_toast() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let steps = vec![MacroStep {
+            prompt_id: "nope".to_string(),
+        }];
+        reduce(&mut state, &config, Event::StartMacro { steps });
+
+        let effects = reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        let toast = effects.iter().find_map(|e| match e {
+            SideEffect::BroadcastToast(message, _, severity) => Some((message, *severity)),
+            _ => None,
+        });
+        let (message, severity) = toast.expect("skipped step should toast");
+        assert_eq!(message, "macro step 'nope' has no command; skipped");
+        assert_eq!(severity, NoticeSeverity::Warning);
+        // No Logi plugin yet, so it waits for one.
+        assert_eq!(state.pending_toasts.len(), 1);
+    }
+
+    #[test]
+    fn task_completed_dispatches_next_macro_step() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let steps = ["break_task", "prep_pr"]
+            .iter()
+            .map(|id| MacroStep {
+                prompt_id: id.to_string(),
+            })
+            .collect();
+        let effects = reduce(&mut state, &config, Event::StartMacro { steps });
+        assert!(effects.is_empty());
+
+        let effects = reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        assert_eq!(sent_text(&effects), ["/runbook:break-task"]);
+        assert_eq!(state.last_dispatched.as_deref(), Some("break_task"));
+        assert_eq!(state.dispatch_counts.get("break_task"), Some(&1));
+
+        // Other hooks leave the queue alone.
+        let effects = reduce(&mut state, &config, hook("Stop", "s1"));
+        assert!(sent_text(&effects).is_empty());
+
+        let effects = reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        assert_eq!(sent_text(&effects), ["/runbook:prep-pr"]);
+        assert!(state.pending_macro.is_none());
+
+        let effects = reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        assert!(sent_text(&effects).is_empty());
+    }
+
+    #[test]
+    fn prefill_prompt_falls_back_without_hooks() {
+        let yaml = r#"
+keypad:
+  pages:
+    - name: core
+      slots: [{prompt_id: note}, {prompt_id: draft}, {}, {}, {}, {}, {}, {}, {}]
+prompts:
+  note: {label: NOTE, arm_style: prefill, claude_command: /runbook:note}
+  draft:
+    label: DRAFT
+    arm_style: chain
+    fallback_arm_style: prefill
+    claude_command: /runbook:draft
+"#;
+        let config: RunbookConfig = serde_yaml::from_str(yaml).unwrap();
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.hooks_mode, HooksMode::Absent);
+        let press = |prompt_id: &str| Event::KeypadPress {
+            prompt_id: prompt_id.to_string(),
+        };
+        let enter = || Event::DialpadButton {
+            button: DialpadButton::Enter,
+        };
+
+        // No hooks, no fallback: queued, so Enter sends the command.
+        let effects = reduce(&mut state, &config, press("note"));
+        assert!(sent_text(&effects).is_empty());
+        let effects = reduce(&mut state, &config, enter());
+        assert_eq!(sent_text(&effects), ["/runbook:note"]);
+
+        // The fallback applies to both the press and Enter.
+        let effects = reduce(&mut state, &config, press("draft"));
+        assert_eq!(sent_text(&effects), ["/runbook:draft"]);
+        let effects = reduce(&mut state, &config, enter());
+        assert_eq!(sent_text(&effects), [""]);
+        assert!(state.pending_macro.is_none());
+    }
+
+    #[test]
+    fn chain_prompt_dispatches_its_chain_on_task_completed() {
+        let yaml = r#"
+keypad:
+  pages:
+    - name: core
+      slots: [{prompt_id: ship}, {}, {}, {}, {}, {}, {}, {}, {}]
+prompts:
+  ship:
+    label: SHIP
+    arm_style: chain
+    claude_command: /runbook:ship
+    chain: [break_task, run_gates, prep_pr]
+  break_task: {label: BREAK, claude_command: /runbook:break-task}
+  run_gates: {label: GATES, claude_command: /runbook:run-gates}
+  prep_pr: {label: PREP, claude_command: /runbook:prep-pr}
+"#;
+        let config: RunbookConfig = serde_yaml::from_str(yaml).unwrap();
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = HooksMode::Active;
+
+        // Pressing a chain prompt only arms it, like queue.
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::KeypadPress {
+                prompt_id: "ship".to_string(),
+            },
+        );
+        assert!(sent_text(&effects).is_empty());
+        let enter = Event::DialpadButton {
+            button: DialpadButton::Enter,
+        };
+        let effects = reduce(&mut state, &config, enter);
+        assert_eq!(sent_text(&effects), ["/runbook:ship"]);
+
+        assert_eq!(state.chain_index, Some(0));
+        assert!(state.pending_macro.is_none(), "chains don't use the macro queue");
+
+        for (task, expected) in ["/runbook:break-task", "/runbook:run-gates", "/runbook:prep-pr"]
+            .iter()
+            .enumerate()
+        {
+            let effects = reduce(&mut state, &config, task_completed("s1", &task.to_string()));
+            assert_eq!(sent_text(&effects), [*expected], "one command per TaskCompleted");
+            let next = (task < 2).then_some(task + 1);
+            assert_eq!(state.chain_index, next);
+        }
+        assert!(state.chain_prompt_ids.is_empty());
+        let effects = reduce(&mut state, &config, task_completed("s1", "last"));
+        assert!(!effects
+            .iter()
+            .any(|e| matches!(e, SideEffect::SendVscodeCommand(_))));
+    }
+
+    #[test]
+    fn macro_step_for_unknown_prompt_is_skipped() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.start_macro(vec![MacroStep {
+            prompt_id: "missing".to_string(),
+        }]);
+
+        let effects = reduce(&mut state, &config, hook("TaskCompleted", "s1"));
+        assert!(sent_text(&effects).is_empty());
+        assert!(state.pending_macro.is_none());
+        assert!(state.last_dispatched.is_none());
+    }
+
+    #[test]
+    fn repeated_unknown_sessions_degrade_hooks() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        // Attaching to sessions already running is normal, up to a point.
+        reduce(&mut state, &config, hook("UserPromptSubmit", "a"));
+        reduce(&mut state, &config, hook("UserPromptSubmit", "b"));
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+        reduce(&mut state, &config, hook("PreToolUse", "a"));
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+
+        reduce(&mut state, &config, hook("UserPromptSubmit", "c"));
+        assert_eq!(
+            state.hooks_mode,
+            HooksMode::Degraded {
+                reason: "hooks for 3 unknown sessions".to_string()
+            }
+        );
+
+        // Degraded is sticky.
+        reduce(&mut state, &config, hook("SessionStart", "d"));
+        assert!(matches!(state.hooks_mode, HooksMode::Degraded { .. }));
+    }
+
+    #[test]
+    fn select_session_overrides_until_terminal_focus_changes() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        reduce(&mut state, &config, hook("UserPromptSubmit", "b"));
+        reduce(&mut state, &config, hook("Stop", "a"));
+        // Uncorrelated: the most active session.
+        assert_eq!(state.current_agent_state(), AgentState::Settled);
+
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::SelectSession {
+                session_id: "b".to_string(),
+            },
+        );
+        assert_eq!(effects.len(), 1);
+        assert_eq!(state.current_agent_state(), AgentState::Running);
+
+        // Unknown sessions are ignored.
+        let now = Instant::now();
+        let before = state.snapshot_at(now);
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::SelectSession {
+                session_id: "zzz".to_string(),
+            },
+        );
+        assert!(effects.is_empty());
+        assert_eq!(state.active_session.as_deref(), Some("b"));
+        assert_eq!(state.snapshot_at(now), before);
+
+        reduce(
+            &mut state,
+            &config,
+            Event::TerminalsSnapshot(TerminalsSnapshot {
+                terminals: vec![],
+                active_index: 2,
+                timestamp: None,
+            }),
+        );
+        assert!(state.active_session.is_none());
+        assert_eq!(state.current_agent_state(), AgentState::Settled);
+    }
+
+    #[test]
+    fn terminals_snapshot_marks_claude_sessions() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        // Whatever the client sent, the flag follows `session_tag`.
+        let terminal = |index, tag: Option<&str>, is_claude_session| TerminalInfo {
+            index,
+            name: format!("t{index}"),
+            session_tag: tag.map(str::to_string),
+            is_claude_session,
+        };
+        reduce(
+            &mut state,
+            &config,
+            Event::TerminalsSnapshot(TerminalsSnapshot {
+                terminals: vec![terminal(0, Some("tag-a"), false), terminal(1, None, true)],
+                active_index: 0,
+                timestamp: None,
+            }),
+        );
+
+        let flags: Vec<bool> = state.terminals.iter().map(|t| t.is_claude_session).collect();
+        assert_eq!(flags, [true, false]);
+    }
+
+    #[test]
+    fn export_request_types_export_into_the_sessions_terminal() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let terminal = |index, tag: &str| TerminalInfo {
+            index,
+            name: format!("t{index}"),
+            session_tag: Some(tag.to_string()),
+            is_claude_session: true,
+        };
+        state.terminals = vec![terminal(0, "tag-a"), terminal(2, "tag-b")];
+        state.learn_session_tag("tag-b", "s2");
+        let export = |session_id: Option<&str>, format| Event::Export {
+            session_id: session_id.map(str::to_string),
+            format,
+        };
+        let command = |effects: Vec<SideEffect>| match effects.as_slice() {
+            [SideEffect::SendVscodeCommand(cmd)] => cmd.clone(),
+            other => panic!("expected one command, got {other:?}"),
+        };
+
+        let cmd = command(reduce(&mut state, &config, export(Some("s2"), ExportFormat::Markdown)));
+        assert_eq!(cmd.target, TerminalTarget::ByIndex(2));
+        assert_eq!(cmd.payload["text"], "/export markdown");
+        assert_eq!(cmd.payload["add_newline"], false, "Claude's prompt confirms");
+
+        // No session: the active Claude terminal.
+        let active = command(reduce(&mut state, &config, export(None, ExportFormat::Default)));
+        assert_eq!(active.payload["text"], "/export");
+
+        // A session without a known terminal: nothing, rather than another session's.
+        assert!(reduce(&mut state, &config, export(Some("s9"), ExportFormat::Json)).is_empty());
+        state.learn_session_tag("tag-gone", "s3");
+        assert!(reduce(&mut state, &config, export(Some("s3"), ExportFormat::Json)).is_empty());
+    }
+
+    #[test]
+    fn older_terminals_snapshot_is_ignored() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let snapshot = |active_index, ts: Option<&str>| {
+            Event::TerminalsSnapshot(TerminalsSnapshot {
+                terminals: vec![],
+                active_index,
+                timestamp: ts.map(str::to_string),
+            })
+        };
+
+        reduce(&mut state, &config, snapshot(2, Some("2025-10-16T13:20:01.000Z")));
+        let effects = reduce(&mut state, &config, snapshot(1, Some("2025-10-16T13:20:00.500Z")));
+        assert!(effects.is_empty());
+        assert_eq!(state.selected_terminal_index, 2);
+        assert_eq!(
+            state.last_terminal_snapshot_ts.as_deref(),
+            Some("2025-10-16T13:20:01.000Z")
+        );
+
+        // Snapshots without a timestamp are always applied.
+        reduce(&mut state, &config, snapshot(3, None));
+        assert_eq!(state.selected_terminal_index, 3);
+    }
+
+    #[test]
+    fn session_without_session_start_degrades_once_starts_are_seen() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        reduce(&mut state, &config, hook("SessionStart", "a"));
+        reduce(&mut state, &config, hook("SessionStart", "b"));
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+
+        reduce(&mut state, &config, hook("UserPromptSubmit", "c"));
+        assert_eq!(
+            state.hooks_mode,
+            HooksMode::Degraded {
+                reason: "session appeared without SessionStart".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn expired_session_resuming_is_not_unknown() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        reduce(&mut state, &config, hook("SessionStart", "a"));
+        reduce(&mut state, &config, hook("SessionStart", "b"));
+        state.expire_session("a");
+
+        reduce(&mut state, &config, hook("UserPromptSubmit", "a"));
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+        assert_eq!(state.unknown_session_hooks, 0);
+        assert_eq!(state.sessions["a"].agent_state, AgentState::Running);
+        assert!(state.expired_session_ids.is_empty());
+    }
+
+    #[test]
+    fn hook_event_sets_session_state() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.current_agent_state(), AgentState::Unknown);
+
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "Notification".to_string(),
+                matcher: Some("idle_prompt".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
+
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "UserPromptSubmit".to_string(),
+                matcher: None,
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+        assert_eq!(state.current_agent_state(), AgentState::Running);
+    }
+
+    #[test]
+    fn no_hooks_means_unknown() {
+        let state = DaemonState::new(0);
+        assert_eq!(state.hooks_mode, HooksMode::Absent);
+        assert_eq!(state.current_agent_state(), AgentState::Unknown);
+    }
+
+    #[test]
+    fn session_end_removes_session_and_latches_ended() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        // Start a session.
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "Notification".to_string(),
+                matcher: Some("idle_prompt".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+        state.armed = Some("prep_pr".to_string());
+        state.last_dispatched = Some("prep_pr".to_string());
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
+
+        // End the session.
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "SessionEnd".to_string(),
+                matcher: None,
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+
+        // Session should be removed.
+        assert!(state.sessions.is_empty());
+        // Armed and last_dispatched should be cleared.
+        assert!(state.armed.is_none());
+        assert!(state.last_dispatched.is_none());
+        // Should briefly latch the ended state.
+        assert_eq!(state.last_ended_state, Some(AgentState::Idle));
+    }
+
+    #[test]
+    fn multi_session_shows_most_active_session() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        // Session 1: Idle.
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "Notification".to_string(),
+                matcher: Some("idle_prompt".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
+
+        // Session 2: Running. Now we have >1 sessions.
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "UserPromptSubmit".to_string(),
+                matcher: None,
+                session_id: Some("sess2".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+
+        // Multi-session without terminal correlation: the session that
+        // sent the latest hook.
+        assert_eq!(state.sessions.len(), 2);
+        assert_eq!(state.current_agent_state(), AgentState::Running);
+
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "Notification".to_string(),
+                matcher: Some("idle_prompt".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
+
+        // End one session; back to single = show that session's state.
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "SessionEnd".to_string(),
+                matcher: None,
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
+        assert_eq!(state.sessions.len(), 1);
+        assert_eq!(state.current_agent_state(), AgentState::Running);
+    }
+
+    #[test]
+    fn runbook_policy_blocked() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        // Start a session first.
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "Notification".to_string(),
+                matcher: Some("idle_prompt".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
+            },
+        );
 
-        // Next wraps to 0.
+        // Policy blocks a tool call.
         reduce(
             &mut state,
             &config,
-            Event::PageNav {
-                direction: PageDirection::Next,
+            Event::HookEvent {
+                hook: "RunbookPolicy".to_string(),
+                matcher: Some("blocked".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::Value::Null,
             },
         );
-        assert_eq!(state.page, 0);
+        assert_eq!(state.current_agent_state(), AgentState::Blocked);
+
+        // Allowed (or overridden) afterwards: running again.
+        for matcher in ["allowed", "overridden"] {
+            state.sessions.get_mut("sess1").unwrap().agent_state = AgentState::Blocked;
+            reduce(
+                &mut state,
+                &config,
+                Event::HookEvent {
+                    hook: "RunbookPolicy".to_string(),
+                    matcher: Some(matcher.to_string()),
+                    session_id: Some("sess1".to_string()),
+                    session_tag: None,
+                    payload: serde_json::Value::Null,
+                },
+            );
+            assert_eq!(state.current_agent_state(), AgentState::Running, "{matcher}");
+        }
+        assert_eq!(state.sessions["sess1"].blocked_count, 1);
     }
 
     #[test]
-    fn hook_event_sets_session_state() {
+    fn session_tag_learns_correlation() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
-        assert_eq!(state.current_agent_state(), AgentState::Unknown);
 
+        // Hook arrives with both session_id and session_tag.
         reduce(
             &mut state,
             &config,
@@ -501,176 +2424,523 @@ prompts:
                 hook: "Notification".to_string(),
                 matcher: Some("idle_prompt".to_string()),
                 session_id: Some("sess1".to_string()),
-                session_tag: None,
+                session_tag: Some("tag-abc".to_string()),
+                payload: serde_json::Value::Null,
             },
         );
-        assert_eq!(state.hooks_mode, HooksMode::Active);
-        assert_eq!(state.current_agent_state(), AgentState::Idle);
+
+        // The daemon should have learned the mapping.
+        assert_eq!(
+            state.session_tag_map.get("tag-abc"),
+            Some(&"sess1".to_string())
+        );
+    }
+
+    #[test]
+    fn pre_tool_use_records_last_tool() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
 
         reduce(
             &mut state,
             &config,
             Event::HookEvent {
-                hook: "UserPromptSubmit".to_string(),
-                matcher: None,
+                hook: "PreToolUse".to_string(),
+                matcher: Some("Bash".to_string()),
                 session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::json!({"tool_name": "Bash"}),
             },
         );
-        assert_eq!(state.current_agent_state(), AgentState::Running);
-    }
 
-    #[test]
-    fn no_hooks_means_unknown() {
-        let config = sample_config();
-        let state = DaemonState::new(0);
-        assert_eq!(state.hooks_mode, HooksMode::Absent);
-        assert_eq!(state.current_agent_state(), AgentState::Unknown);
+        assert_eq!(state.sessions["sess1"].last_tool.as_deref(), Some("Bash"));
+        assert_eq!(state.current_agent_state(), AgentState::Running);
     }
 
     #[test]
-    fn session_end_removes_session_and_latches_ended() {
+    fn session_start_resets_restarted_session() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
 
-        // Start a session.
         reduce(
             &mut state,
             &config,
             Event::HookEvent {
-                hook: "Notification".to_string(),
-                matcher: Some("idle_prompt".to_string()),
+                hook: "UserPromptSubmit".to_string(),
+                matcher: None,
                 session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::Value::Null,
             },
         );
-        state.armed = Some("prep_pr".to_string());
-        state.last_dispatched = Some("prep_pr".to_string());
-        assert_eq!(state.current_agent_state(), AgentState::Idle);
+        reduce(
+            &mut state,
+            &config,
+            Event::KeypadPress {
+                prompt_id: "prep_pr".to_string(),
+            },
+        );
+        reduce(
+            &mut state,
+            &config,
+            Event::DialpadButton {
+                button: DialpadButton::Enter,
+            },
+        );
+        assert_eq!(state.dispatch_counts.get("prep_pr"), Some(&1));
 
-        // End the session.
         reduce(
             &mut state,
             &config,
             Event::HookEvent {
-                hook: "SessionEnd".to_string(),
+                hook: "SessionStart".to_string(),
                 matcher: None,
                 session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::Value::Null,
             },
         );
 
-        // Session should be removed.
-        assert!(state.sessions.is_empty());
-        // Armed and last_dispatched should be cleared.
-        assert!(state.armed.is_none());
         assert!(state.last_dispatched.is_none());
-        // Should briefly latch the ended state.
-        assert_eq!(state.last_ended_state, Some(AgentState::Idle));
+        assert!(state.dispatch_counts.is_empty());
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
     }
 
     #[test]
-    fn multi_session_degrades_to_unknown() {
+    fn duplicate_hook_delivery_is_applied_once() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let permission = || Event::HookEvent {
+            hook: "Notification".to_string(),
+            matcher: Some("permission_prompt".to_string()),
+            session_id: Some("s1".to_string()),
+            session_tag: None,
+            payload: serde_json::json!({"tool_name": "Bash", "message": "Run cargo publish?"}),
+        };
+
+        reduce(&mut state, &config, permission());
+        state.sessions.get_mut("s1").unwrap().agent_state = AgentState::Running;
+        let effects = reduce(&mut state, &config, permission());
+        assert!(matches!(effects.as_slice(), [SideEffect::BroadcastRender]));
+        assert_eq!(state.sessions["s1"].agent_state, AgentState::Running, "not reapplied");
+
+        // Still a repeat with another delivery in between.
+        reduce(&mut state, &config, hook("PostToolUse", "s1"));
+        reduce(&mut state, &config, permission());
+        assert_eq!(state.sessions["s1"].agent_state, AgentState::Running);
+
+        // A session ending forgets what came before.
+        reduce(&mut state, &config, hook("SessionEnd", "s1"));
+        reduce(&mut state, &config, permission());
+        assert_eq!(state.sessions["s1"].agent_state, AgentState::WaitingPermission);
+    }
+
+    #[test]
+    fn identical_stops_in_separate_turns_both_apply() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let stop = || Event::HookEvent {
+            hook: "Stop".to_string(),
+            matcher: None,
+            session_id: Some("s1".to_string()),
+            session_tag: None,
+            payload: serde_json::json!({
+                "session_id": "s1",
+                "transcript_path": "/tmp/s1.jsonl",
+                "cwd": "/work",
+                "stop_hook_active": false,
+            }),
+        };
+
+        reduce(&mut state, &config, hook("SessionStart", "s1"));
+        reduce(&mut state, &config, hook("UserPromptSubmit", "s1"));
+        reduce(&mut state, &config, stop());
+        assert_eq!(state.sessions["s1"].agent_state, AgentState::Settled);
+        reduce(&mut state, &config, hook("UserPromptSubmit", "s1"));
+        assert_eq!(state.sessions["s1"].agent_state, AgentState::Running);
+        reduce(&mut state, &config, stop());
+        assert_eq!(state.sessions["s1"].agent_state, AgentState::Settled);
+    }
+
+    #[test]
+    fn hook_payload_git_branch_updates_current_branch() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        reduce(&mut state, &config, hook("Stop", "s1"));
+        assert_eq!(state.current_branch, None);
+
+        let ev = Event::HookEvent {
+            hook: "UserPromptSubmit".to_string(),
+            matcher: None,
+            session_id: Some("s1".to_string()),
+            session_tag: None,
+            payload: serde_json::json!({"prompt": "ship it", "git_branch": "feat/gates"}),
+        };
+        reduce(&mut state, &config, ev);
+        assert_eq!(state.current_branch.as_deref(), Some("feat/gates"));
+    }
+
+    #[test]
+    fn pre_tool_use_increments_tool_call_count() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        let mut effects = Vec::new();
+        for tool in ["Read", "Bash", "Edit"] {
+            effects = reduce(
+                &mut state,
+                &config,
+                Event::HookEvent {
+                    hook: "PreToolUse".to_string(),
+                    matcher: None,
+                    session_id: Some("sess1".to_string()),
+                    session_tag: None,
+                    payload: serde_json::json!({"tool_name": tool}),
+                },
+            );
+        }
+
+        assert_eq!(state.sessions["sess1"].tool_call_count, 3);
+        let progress = effects.iter().find_map(|e| match e {
+            SideEffect::BroadcastProgress(p) => Some(p),
+            _ => None,
+        });
+        let progress = progress.expect("running session should broadcast progress");
+        assert_eq!(progress.tool_call_count, 3);
+        assert_eq!(progress.last_tool_name.as_deref(), Some("Edit"));
+    }
+
+    #[test]
+    fn first_session_start_makes_agent_state_known() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.current_agent_state(), AgentState::Unknown);
+
+        reduce(&mut state, &config, hook("SessionStart", "sess1"));
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
+    }
+
+    #[test]
+    fn state_transition_broadcasts_state_change_with_render() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        reduce(&mut state, &config, hook("SessionStart", "sess1"));
+
+        let effects = reduce(&mut state, &config, hook("UserPromptSubmit", "sess1"));
+        assert!(effects.iter().any(|e| matches!(e, SideEffect::BroadcastRender)));
+        let change = effects.iter().find_map(|e| match e {
+            SideEffect::BroadcastStateChange(c) => Some(c),
+            _ => None,
+        });
+        let change = change.expect("Idle -> Running should broadcast a state change");
+        assert_eq!(change.session_id.as_deref(), Some("sess1"));
+        assert_eq!(change.previous, AgentState::Idle);
+        assert_eq!(change.current, AgentState::Running);
+        assert_eq!(change.hook_trigger, "UserPromptSubmit");
+        assert_eq!(state.sessions["sess1"].previous_agent_state, AgentState::Idle);
+
+        // Still running: render only.
+        let effects = reduce(&mut state, &config, hook("PostToolUse", "sess1"));
+        assert!(effects.iter().any(|e| matches!(e, SideEffect::BroadcastRender)));
+        assert!(!effects
+            .iter()
+            .any(|e| matches!(e, SideEffect::BroadcastStateChange(_))));
+    }
+
+    #[test]
+    fn targeted_effects_reach_their_client_kind() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
 
-        // Session 1: Idle.
         reduce(
+            &mut state,
+            &config,
+            Event::KeypadPress {
+                prompt_id: "prep_pr".to_string(),
+            },
+        );
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::DialpadButton {
+                button: DialpadButton::Enter,
+            },
+        );
+
+        let targeted: Vec<SideEffect> = effects
+            .into_iter()
+            .map(|e| e.into_targeted(&state, &config))
+            .collect();
+        assert!(targeted.iter().any(|e| matches!(
+            e,
+            SideEffect::BroadcastToClientKind(ClientKind::Vscode, msg)
+                if matches!(**msg, DaemonToClient::VscodeCommand(_))
+        )));
+        assert!(targeted.iter().any(|e| matches!(
+            e,
+            SideEffect::BroadcastToClientKind(ClientKind::Logi, msg)
+                if matches!(**msg, DaemonToClient::Render(_))
+        )));
+        assert!(!targeted
+            .iter()
+            .any(|e| matches!(e, SideEffect::BroadcastRender)));
+        // Everything else still goes to all clients.
+        assert!(targeted
+            .iter()
+            .any(|e| matches!(e, SideEffect::BroadcastSlotHighlight(..))));
+    }
+
+    #[test]
+    fn runbook_policy_blocked_emits_policy_block() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        let effects = reduce(
             &mut state,
             &config,
             Event::HookEvent {
-                hook: "Notification".to_string(),
-                matcher: Some("idle_prompt".to_string()),
+                hook: "RunbookPolicy".to_string(),
+                matcher: Some("blocked".to_string()),
                 session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::json!({
+                    "runbook_policy": {"name": "deny_destructive_bash", "command": "rm -rf /"}
+                }),
             },
         );
-        assert_eq!(state.current_agent_state(), AgentState::Idle);
 
-        // Session 2: Running. Now we have >1 sessions.
+        let block = effects.iter().find_map(|e| match e {
+            SideEffect::BroadcastPolicyBlock(b) => Some(b),
+            _ => None,
+        });
+        let block = block.expect("blocked hook should emit a policy block");
+        assert_eq!(block.session_id.as_deref(), Some("sess1"));
+        assert_eq!(block.command.as_deref(), Some("rm -rf /"));
+        assert!(effects
+            .iter()
+            .any(|e| matches!(e, SideEffect::BroadcastSessionList)));
+    }
+
+    #[test]
+    fn policy_override_raises_a_warning_toast() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "RunbookPolicy".to_string(),
+                matcher: Some("overridden".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::json!({
+                    "runbook_policy": {
+                        "name": "deny_destructive_bash",
+                        "command": "rm -rf /",
+                        "override": true,
+                    }
+                }),
+            },
+        );
+
+        let toast = effects.iter().find_map(|e| match e {
+            SideEffect::BroadcastToast(message, _, severity) => Some((message, *severity)),
+            _ => None,
+        });
+        let (message, severity) = toast.expect("override should be announced");
+        assert_eq!(message, "Policy overridden: rm -rf /");
+        assert_eq!(severity, NoticeSeverity::Warning);
+        assert!(!effects
+            .iter()
+            .any(|e| matches!(e, SideEffect::BroadcastPolicyBlock(_))));
+        assert_eq!(state.sessions["sess1"].blocked_count, 0, "it wasn't blocked");
+    }
+
+    #[test]
+    fn user_prompt_submit_records_prompt_text() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let submit = |prompt: &str| Event::HookEvent {
+            hook: "UserPromptSubmit".to_string(),
+            matcher: None,
+            session_id: Some("sess1".to_string()),
+            session_tag: None,
+            payload: serde_json::json!({ "prompt": prompt }),
+        };
+
+        reduce(&mut state, &config, submit("prep a PR"));
+        assert_eq!(
+            state.sessions["sess1"].last_prompt_text.as_deref(),
+            Some("prep a PR")
+        );
+
+        reduce(&mut state, &config, submit(&"x".repeat(500)));
+        let stored = state.sessions["sess1"].last_prompt_text.as_ref().unwrap();
+        assert_eq!(stored.chars().count(), MAX_PROMPT_TEXT_CHARS);
+    }
+
+    #[test]
+    fn policy_blocks_are_counted_per_session() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let hook = |name: &str, matcher: Option<&str>| Event::HookEvent {
+            hook: name.to_string(),
+            matcher: matcher.map(str::to_string),
+            session_id: Some("sess1".to_string()),
+            session_tag: None,
+            payload: serde_json::Value::Null,
+        };
+
+        for command in ["rm -rf /", "git push --force", "cargo publish"] {
+            let blocked = Event::HookEvent {
+                hook: "RunbookPolicy".to_string(),
+                matcher: Some("blocked".to_string()),
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::json!({"runbook_policy": {"command": command}}),
+            };
+            reduce(&mut state, &config, blocked);
+        }
+        assert_eq!(state.sessions["sess1"].blocked_count, 3);
+        assert_eq!(crate::render::build_session_list(&state).sessions[0].blocked_count, 3);
+
+        // A new session under the same id starts from zero.
+        reduce(&mut state, &config, hook("SessionStart", None));
+        assert_eq!(state.sessions["sess1"].blocked_count, 0);
+    }
+
+    #[test]
+    fn permission_request_tracked_until_resolved() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
         reduce(
             &mut state,
             &config,
             Event::HookEvent {
-                hook: "UserPromptSubmit".to_string(),
+                hook: "PermissionRequest".to_string(),
                 matcher: None,
-                session_id: Some("sess2".to_string()),
+                session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::json!({
+                    "tool_name": "Bash",
+                    "tool_input": {"command": "cargo publish"}
+                }),
             },
         );
+        let pending = state.sessions["sess1"].pending_permission.as_ref().unwrap();
+        assert_eq!(pending.tool_name, "Bash");
+        assert_eq!(pending.description, "cargo publish");
 
-        // Multi-session = degrade to Unknown (can't map terminals yet).
-        assert_eq!(state.sessions.len(), 2);
-        assert_eq!(state.current_agent_state(), AgentState::Unknown);
-
-        // End one session; back to single = show that session's state.
         reduce(
             &mut state,
             &config,
             Event::HookEvent {
-                hook: "SessionEnd".to_string(),
+                hook: "PostToolUse".to_string(),
                 matcher: None,
                 session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::Value::Null,
             },
         );
-        assert_eq!(state.sessions.len(), 1);
-        assert_eq!(state.current_agent_state(), AgentState::Running);
+        assert!(state.sessions["sess1"].pending_permission.is_none());
     }
 
     #[test]
-    fn runbook_policy_blocked() {
+    fn elicitation_prompt_tracked_while_waiting_input() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
 
-        // Start a session first.
         reduce(
             &mut state,
             &config,
             Event::HookEvent {
                 hook: "Notification".to_string(),
-                matcher: Some("idle_prompt".to_string()),
+                matcher: Some("elicitation_dialog".to_string()),
                 session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::json!({"prompt": "Which branch?"}),
             },
         );
+        assert_eq!(state.sessions["sess1"].agent_state, AgentState::WaitingInput);
+        assert_eq!(
+            state.sessions["sess1"].elicitation_prompt.as_deref(),
+            Some("Which branch?")
+        );
 
-        // Policy blocks a tool call.
         reduce(
             &mut state,
             &config,
             Event::HookEvent {
-                hook: "RunbookPolicy".to_string(),
-                matcher: Some("blocked".to_string()),
+                hook: "Notification".to_string(),
+                matcher: Some("elicitation_dialog".to_string()),
                 session_id: Some("sess1".to_string()),
                 session_tag: None,
+                payload: serde_json::json!({"question": "Squash commits?"}),
             },
         );
-        assert_eq!(state.current_agent_state(), AgentState::Blocked);
+        assert_eq!(
+            state.sessions["sess1"].elicitation_prompt.as_deref(),
+            Some("Squash commits?")
+        );
+
+        reduce(
+            &mut state,
+            &config,
+            Event::HookEvent {
+                hook: "UserPromptSubmit".to_string(),
+                matcher: None,
+                session_id: Some("sess1".to_string()),
+                session_tag: None,
+                payload: serde_json::json!({"prompt": "main"}),
+            },
+        );
+        assert!(state.sessions["sess1"].elicitation_prompt.is_none());
     }
 
     #[test]
-    fn session_tag_learns_correlation() {
+    fn config_reload_clamps_page_and_drops_stale_arm() {
         let config = sample_config();
         let mut state = DaemonState::new(0);
+        state.page = 3;
+        state.armed = Some("removed_prompt".to_string());
 
-        // Hook arrives with both session_id and session_tag.
-        reduce(
+        let effects = reduce(&mut state, &config, Event::ConfigReloaded);
+        assert_eq!(state.page, 0);
+        assert!(state.armed.is_none());
+        assert!(matches!(effects[..], [SideEffect::BroadcastRender]));
+
+        // A prompt that survived the reload stays armed.
+        state.armed = Some("prep_pr".to_string());
+        reduce(&mut state, &config, Event::ConfigReloaded);
+        assert_eq!(state.armed.as_deref(), Some("prep_pr"));
+    }
+
+    #[test]
+    fn slot_progress_is_clamped_and_stored() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+
+        let effects = reduce(
             &mut state,
             &config,
-            Event::HookEvent {
-                hook: "Notification".to_string(),
-                matcher: Some("idle_prompt".to_string()),
-                session_id: Some("sess1".to_string()),
-                session_tag: Some("tag-abc".to_string()),
+            Event::SlotProgress {
+                prompt_id: "prep_pr".to_string(),
+                progress: 1.7,
             },
         );
+        assert_eq!(state.slot_progress["prep_pr"], 1.0);
+        assert!(matches!(effects[..], [SideEffect::BroadcastRender]));
 
-        // The daemon should have learned the mapping.
-        assert_eq!(
-            state.session_tag_map.get("tag-abc"),
-            Some(&"sess1".to_string())
+        let effects = reduce(
+            &mut state,
+            &config,
+            Event::SlotProgress {
+                prompt_id: "prep_pr".to_string(),
+                progress: f32::NAN,
+            },
         );
+        assert!(effects.is_empty());
+        assert_eq!(state.slot_progress["prep_pr"], 1.0);
     }
 }
-
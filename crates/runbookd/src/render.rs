@@ -1,18 +1,260 @@
 //! Build the render model from daemon state + config.
 
-use runbook_protocol::{ArmedPrompt, KeypadRender, KeypadSlotRender, RenderModel};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-use crate::config::RunbookConfig;
+use runbook_protocol::{
+    AgentState, ArmedPrompt, BannerMessage, DebugDump, HooksMode, KeypadRender, KeypadSlotRender, MetricsSnapshot, PageRender,
+    PendingPermission, RenderModel, SessionList, SessionSummary,
+};
+
+use crate::config::{KeypadPageConfig, RunbookConfig};
 use crate::state::DaemonState;
 
+/// How much of the running prompt or pending question fits in `status_line`.
+const STATUS_PROMPT_MAX_CHARS: usize = 20;
+
 /// Build a `RenderModel` snapshot from the current state and config.
 pub fn build_render_model(state: &DaemonState, config: &RunbookConfig) -> RenderModel {
+    build_render_model_for_session(state, config, None)
+}
+
+/// Like [`build_render_model`], but the agent state, status line and
+/// permission come from `session_id` when it names a live session (a client's
+/// focused session). Keys, armed prompt and page are shared by every client.
+pub fn build_render_model_for_session(
+    state: &DaemonState,
+    config: &RunbookConfig,
+    session_id: Option<&str>,
+) -> RenderModel {
+    build_render_model_at(state, config, session_id, Instant::now())
+}
+
+/// Like [`build_render_model_for_session`], with an explicit current time.
+pub fn build_render_model_at(
+    state: &DaemonState,
+    config: &RunbookConfig,
+    session_id: Option<&str>,
+    now: Instant,
+) -> RenderModel {
     let page_count = config.keypad.pages.len();
-    let page_index = state.page.min(page_count.saturating_sub(1));
-    let page_cfg = &config.keypad.pages[page_index];
+    let (page_index, slots) = match shown_page(state, config) {
+        Some((index, page)) => (index, build_slots(state, config, page)),
+        None => (0, Vec::new()),
+    };
+
+    let armed = state.armed.as_ref().and_then(|pid| {
+        config.prompts.get(pid).map(|p| {
+            let is_claude = config.is_claude_primary();
+            ArmedPrompt {
+                prompt_id: pid.clone(),
+                label: p.label.clone(),
+                style: config.arm_style_for_mode(pid, &state.hooks_mode),
+                command: p
+                    .effective_command(is_claude)
+                    .unwrap_or("")
+                    .to_string(),
+            }
+        })
+    });
+
+    let focused = session_id.and_then(|sid| state.sessions.get(sid));
+    let session = focused.or_else(|| state.current_session());
+    let agent_state = focused.map_or_else(|| state.current_agent_state(), |s| s.agent_state);
+    let prompt = session
+        .and_then(|s| s.last_prompt_text.as_deref())
+        .filter(|_| agent_state == AgentState::Running);
+    let question = session
+        .and_then(|s| s.elicitation_prompt.as_deref())
+        .filter(|_| agent_state == AgentState::WaitingInput);
+    let status_line = match (&state.hooks_mode, prompt, question) {
+        (HooksMode::Degraded { reason }, _, _) => reason.clone(),
+        (_, Some(p), _) => format!("running: '{}'", truncate_label(p, STATUS_PROMPT_MAX_CHARS)),
+        (_, None, Some(q)) => format!("waiting: '{}'", truncate_label(q, STATUS_PROMPT_MAX_CHARS)),
+        (_, None, None) => agent_state.as_str().to_string(),
+    };
+    let status_line = match session.and_then(|s| s.last_tool.as_deref()) {
+        Some(tool) if !matches!(state.hooks_mode, HooksMode::Degraded { .. }) => {
+            format!("{status_line} | tool: {tool}")
+        }
+        _ => status_line,
+    };
+
+    RenderModel {
+        agent_state,
+        armed,
+        keypad: KeypadRender { slots },
+        page_index,
+        page_count,
+        hooks_mode: state.hooks_mode.clone(),
+        hooks_stale_secs: state.hooks_quiet_for(now).map(|d| d.as_secs()),
+        status_line: Some(status_line),
+        permission_description: session
+            .and_then(|s| s.pending_permission.as_ref())
+            .map(|p| p.description.clone()),
+    }
+}
 
-    let slots: Vec<KeypadSlotRender> = page_cfg
-        .slots
+/// Build the live session list, sorted by session_id.
+pub fn build_session_list(state: &DaemonState) -> SessionList {
+    let mut sessions: Vec<SessionSummary> = state
+        .sessions
+        .iter()
+        .map(|(sid, s)| SessionSummary {
+            session_id: sid.clone(),
+            session_tag: state
+                .session_tag_map
+                .iter()
+                .find(|(_, mapped)| *mapped == sid)
+                .map(|(tag, _)| tag.clone()),
+            agent_state: s.agent_state,
+            last_tool: s.last_tool.clone(),
+            tool_call_count: s.tool_call_count,
+            blocked_count: s.blocked_count,
+            last_prompt: s.last_prompt_text.clone(),
+            pending_permission: s.pending_permission.as_ref().map(|p| PendingPermission {
+                tool_name: p.tool_name.clone(),
+                description: p.description.clone(),
+                waiting_secs: p.requested_at.elapsed().as_secs(),
+            }),
+        })
+        .collect();
+    sessions.sort_by(|a, b| a.session_id.cmp(&b.session_id));
+    SessionList { sessions }
+}
+
+/// Welcome banner sent to Logi devices after `hello`.
+pub fn build_banner(state: &DaemonState, config: &RunbookConfig) -> BannerMessage {
+    BannerMessage {
+        line1: format!("runbookd {}", env!("CARGO_PKG_VERSION")),
+        line2: Some(format!("hooks: {}", state.hooks_mode.as_str())),
+        duration_ms: config.daemon.banner_duration_ms,
+    }
+}
+
+/// Shortest expanded value redacted where it appears inside a longer string;
+/// shorter ones (`1`, `on`, a port) only redact values they match exactly.
+const MIN_EMBEDDED_REDACT_LEN: usize = 4;
+
+/// Replace config values that came from (or contain) a `${VAR}` expansion:
+/// environment variables often carry secrets.
+fn redact_env_values(value: &mut serde_json::Value, env_values: &[String]) {
+    use serde_json::Value;
+
+    let expanded_into = |s: &str| {
+        env_values
+            .iter()
+            .any(|v| s == v || (v.len() >= MIN_EMBEDDED_REDACT_LEN && s.contains(v.as_str())))
+    };
+
+    match value {
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|v| redact_env_values(v, env_values)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|v| redact_env_values(v, env_values)),
+        Value::String(s) if expanded_into(s) => {
+            *value = Value::from("<redacted>");
+        }
+        Value::Number(_) | Value::Bool(_) if env_values.contains(&value.to_string()) => {
+            *value = Value::from("<redacted>");
+        }
+        _ => {}
+    }
+}
+
+/// Build a full state + config dump for `POST /debug/dump`.
+pub fn build_debug_dump(
+    state: &DaemonState,
+    config: &RunbookConfig,
+    client_count: usize,
+    uptime: Duration,
+) -> DebugDump {
+    let state_json = serde_json::json!({
+        "armed": state.armed,
+        "last_dispatched": state.last_dispatched,
+        "dispatch_counts": state.dispatch_counts,
+        "prompt_session_map": state.prompt_session_map,
+        "slot_progress": state.slot_progress,
+        "page": state.page,
+        "page_name": state.current_page_name(config),
+        "sessions": build_session_list(state).sessions,
+        "session_tag_map": state.session_tag_map,
+        "terminals": state.terminals,
+        "claude_terminal_count": state.claude_terminal_count(),
+        "selected_terminal_index": state.selected_terminal_index,
+        "hooks_mode": state.hooks_mode,
+        "last_hook_secs_ago": state.last_hook_ts.map(|t| t.elapsed().as_secs()),
+        "vscode_connected": state.vscode_connected,
+        "logi_connected": state.logi_connected,
+        "first_client_secs_ago": state.first_client_at.map(|t| t.elapsed().as_secs()),
+        "last_ended_state": state.last_ended_state,
+    });
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    let mut config_json = serde_json::to_value(config).unwrap_or_default();
+    redact_env_values(&mut config_json, &config.env_values);
+
+    DebugDump {
+        state_json: serde_json::to_string_pretty(&state_json).unwrap_or_default(),
+        config_json: serde_json::to_string_pretty(&config_json).unwrap_or_default(),
+        metrics: MetricsSnapshot {
+            session_count: state.sessions.len() as u32,
+            client_count: client_count as u32,
+            dispatch_count: state.dispatch_counts.values().sum(),
+            uptime_secs: uptime.as_secs(),
+        },
+        timestamp: timestamp.to_string(),
+    }
+}
+
+/// Build the keys of the current page, for clients that only need the page to change.
+pub fn build_page_render(state: &DaemonState, config: &RunbookConfig) -> PageRender {
+    let page_count = config.keypad.pages.len();
+    let Some((page_index, page)) = shown_page(state, config) else {
+        return PageRender {
+            page_index: 0,
+            page_count,
+            page_name: String::new(),
+            page_description: None,
+            slots: Vec::new(),
+        };
+    };
+    PageRender {
+        page_index,
+        page_count,
+        page_name: page.name.clone(),
+        page_description: page.description.clone(),
+        slots: build_slots(state, config, page),
+    }
+}
+
+/// The current page and its index; past the end (until the reducer clamps
+/// after a reload) this falls back to the last page. `None` if there are no
+/// pages at all, which only a config built in code can have.
+fn shown_page<'a>(
+    state: &DaemonState,
+    config: &'a RunbookConfig,
+) -> Option<(usize, &'a KeypadPageConfig)> {
+    match state.current_page(config) {
+        Some(page) => Some((state.page, page)),
+        None => {
+            let last = config.keypad.pages.len().checked_sub(1)?;
+            Some((last, &config.keypad.pages[last]))
+        }
+    }
+}
+
+fn build_slots(
+    state: &DaemonState,
+    config: &RunbookConfig,
+    page: &KeypadPageConfig,
+) -> Vec<KeypadSlotRender> {
+    let armed_slot = state.armed_slot_on(page);
+    page.slots
         .iter()
         .enumerate()
         .map(|(i, slot)| {
@@ -23,7 +265,9 @@ pub fn build_render_model(state: &DaemonState, config: &RunbookConfig) -> Render
                     (pid.clone(), "???".to_string(), None)
                 }
             } else if let Some(ref gid) = slot.gate {
-                if let Some(g) = config.gates.get(gid) {
+                if config.gates.contains_key(gid) && !config.gate_is_available(gid) {
+                    ("_empty".to_string(), "—".to_string(), None)
+                } else if let Some(g) = config.gates.get(gid) {
                     (gid.clone(), g.label.clone(), g.sublabel.clone())
                 } else {
                     (gid.clone(), "???".to_string(), None)
@@ -32,39 +276,38 @@ pub fn build_render_model(state: &DaemonState, config: &RunbookConfig) -> Render
                 ("_empty".to_string(), "—".to_string(), None)
             };
 
+            let max = state.label_max_chars(config);
             KeypadSlotRender {
                 slot: i as u8,
                 prompt_id,
-                label,
-                sublabel,
-                armed: state.armed.as_deref() == slot.prompt_id.as_deref(),
-            }
-        })
-        .collect();
-
-    let armed = state.armed.as_ref().and_then(|pid| {
-        config.prompts.get(pid).map(|p| {
-            let is_claude = config.is_claude_primary();
-            ArmedPrompt {
-                prompt_id: pid.clone(),
-                label: p.label.clone(),
-                style: config.arm_style_for(pid),
-                command: p
-                    .effective_command(is_claude)
-                    .unwrap_or("")
-                    .to_string(),
+                label: truncate_label(&label, max),
+                sublabel: sublabel.map(|s| truncate_label(&s, max)),
+                armed: armed_slot == Some(i as u8),
+                progress: slot
+                    .prompt_id
+                    .as_ref()
+                    .and_then(|pid| state.slot_progress.get(pid).copied()),
+                last_dispatched_at: slot
+                    .prompt_id
+                    .as_ref()
+                    .and_then(|pid| state.dispatch_timestamps.get(pid))
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs()),
             }
         })
-    });
+        .collect()
+}
 
-    RenderModel {
-        agent_state: state.current_agent_state(),
-        armed,
-        keypad: KeypadRender { slots },
-        page_index,
-        page_count,
-        hooks_mode: state.hooks_mode,
+/// Truncate a label to at most `max_chars` characters, ending with "…" when cut.
+///
+/// Logi LCD keys only fit ~8 characters; longer labels would be clipped by the device.
+pub fn truncate_label(label: &str, max_chars: usize) -> String {
+    if label.chars().count() <= max_chars {
+        return label.to_string();
     }
+    let mut out: String = label.chars().take(max_chars.saturating_sub(1)).collect();
+    out.push('…');
+    out
 }
 
 #[cfg(test)]
@@ -111,6 +354,8 @@ gates:
         assert_eq!(model.keypad.slots[0].label, "PREP PR");
         assert_eq!(model.keypad.slots[0].sublabel.as_deref(), Some("receipts"));
         assert_eq!(model.keypad.slots[8].label, "PR");
+        // Nothing armed: empty and gate keys aren't either.
+        assert!(model.keypad.slots.iter().all(|s| !s.armed));
     }
 
     #[test]
@@ -121,10 +366,72 @@ gates:
 
         let model = build_render_model(&state, &config);
         assert!(model.keypad.slots[0].armed);
+        assert!(model.keypad.slots[1..].iter().all(|s| !s.armed));
         assert!(model.armed.is_some());
         assert_eq!(model.armed.as_ref().unwrap().prompt_id, "prep_pr");
     }
 
+    #[test]
+    fn render_model_includes_slot_progress() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.slot_progress.insert("prep_pr".to_string(), 0.4);
+
+        let model = build_render_model(&state, &config);
+        assert_eq!(model.keypad.slots[0].progress, Some(0.4));
+        assert_eq!(model.keypad.slots[8].progress, None);
+    }
+
+    #[test]
+    fn render_model_includes_last_dispatched_at() {
+        use crate::reducer::{reduce, Event};
+        use runbook_protocol::DialpadButton;
+
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let model = build_render_model(&state, &config);
+        assert_eq!(model.keypad.slots[0].last_dispatched_at, None);
+
+        let before = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        reduce(
+            &mut state,
+            &config,
+            Event::KeypadPress {
+                prompt_id: "prep_pr".to_string(),
+            },
+        );
+        reduce(
+            &mut state,
+            &config,
+            Event::DialpadButton {
+                button: DialpadButton::Enter,
+            },
+        );
+
+        let model = build_render_model(&state, &config);
+        let at = model.keypad.slots[0].last_dispatched_at.unwrap();
+        assert!(at >= before);
+        // Gates and empty slots never carry a dispatch time.
+        assert_eq!(model.keypad.slots[1].last_dispatched_at, None);
+        assert_eq!(model.keypad.slots[8].last_dispatched_at, None);
+    }
+
+    #[test]
+    fn unavailable_gate_renders_as_empty_slot() {
+        let mut config = sample_config();
+        config.gates.get_mut("pr").unwrap().requires_env.insert(
+            "RUNBOOK_TEST_UNSET_GATE_VAR".to_string(),
+            "1".to_string(),
+        );
+        let model = build_render_model(&DaemonState::new(0), &config);
+        assert_eq!(model.keypad.slots[8].prompt_id, "_empty");
+        assert_eq!(model.keypad.slots[8].label, "—");
+        assert_eq!(model.keypad.slots[8].sublabel, None);
+    }
+
     #[test]
     fn render_model_page_metadata() {
         let config = sample_config();
@@ -134,5 +441,261 @@ gates:
         assert_eq!(model.page_index, 0);
         assert_eq!(model.page_count, 1);
         assert_eq!(model.hooks_mode, runbook_protocol::HooksMode::Absent);
+        assert_eq!(state.current_page_name(&config), config.keypad.pages[0].name);
+    }
+
+    #[test]
+    fn render_model_reports_how_long_hooks_are_stale() {
+        let config = sample_config();
+        let t0 = Instant::now();
+        let mut state = DaemonState::new_at(0, t0);
+        state.hooks_mode = HooksMode::Stale;
+        state.last_hook_ts = Some(t0 + Duration::from_secs(30));
+        let now = t0 + Duration::from_secs(330);
+        let stale_secs = |state: &DaemonState| {
+            build_render_model_at(state, &config, None, now).hooks_stale_secs
+        };
+        assert_eq!(stale_secs(&state), Some(300));
+
+        state.hooks_mode = HooksMode::Active;
+        assert_eq!(stale_secs(&state), None);
+    }
+
+    #[test]
+    fn render_model_falls_back_to_last_page_when_out_of_range() {
+        let config = sample_config();
+        let state = DaemonState::new(5);
+        assert!(state.current_page(&config).is_none());
+
+        let model = build_render_model(&state, &config);
+        assert_eq!(model.page_index, 0);
+        assert_eq!(model.keypad.slots.len(), 9);
+    }
+
+    #[test]
+    fn degraded_hooks_show_reason_in_status_line() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = HooksMode::Degraded {
+            reason: "hooks for 3 unknown sessions".to_string(),
+        };
+        let session = state.ensure_session("s1");
+        session.agent_state = AgentState::Running;
+        session.last_tool = Some("Bash".to_string());
+
+        let model = build_render_model(&state, &config);
+        assert_eq!(
+            model.status_line.as_deref(),
+            Some("hooks for 3 unknown sessions")
+        );
+        assert_eq!(model.agent_state, AgentState::Running);
+    }
+
+    #[test]
+    fn banner_shows_version_and_hooks_mode() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = runbook_protocol::HooksMode::Active;
+
+        let banner = build_banner(&state, &config);
+        assert_eq!(banner.line1, format!("runbookd {}", env!("CARGO_PKG_VERSION")));
+        assert_eq!(banner.line2.as_deref(), Some("hooks: active"));
+        assert_eq!(banner.duration_ms, 2000);
+    }
+
+    #[test]
+    fn debug_dump_is_valid_json_with_session_count() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.ensure_session("sess1");
+        state.dispatch_counts.insert("prep_pr".to_string(), 2);
+
+        let dump = build_debug_dump(&state, &config, 3, Duration::from_secs(90));
+        let json: serde_json::Value =
+            serde_json::from_str(&serde_json::to_string(&dump).unwrap()).unwrap();
+        assert_eq!(json["metrics"]["session_count"], 1);
+        assert_eq!(json["metrics"]["client_count"], 3);
+        assert_eq!(json["metrics"]["dispatch_count"], 2);
+        assert_eq!(json["metrics"]["uptime_secs"], 90);
+
+        let state_json: serde_json::Value = serde_json::from_str(&dump.state_json).unwrap();
+        assert_eq!(state_json["sessions"][0]["session_id"], "sess1");
+        let config_json: serde_json::Value = serde_json::from_str(&dump.config_json).unwrap();
+        assert!(config_json["prompts"].get("prep_pr").is_some());
+        assert_eq!(config_json["daemon"]["debug_enabled"], false);
+    }
+
+    #[test]
+    fn debug_dump_redacts_env_values() {
+        let mut config = sample_config();
+        config.daemon.fifo = Some("/run/s3cr3t/fifo".to_string());
+        config.daemon.shutdown_timeout_secs = 42;
+        config.prompts.get_mut("prep_pr").unwrap().label = "PR 42".to_string();
+        config.env_values = vec!["s3cr3t".to_string(), "42".to_string()];
+
+        let dump = build_debug_dump(&DaemonState::new(0), &config, 0, Duration::ZERO);
+        assert!(!dump.config_json.contains("s3cr3t"));
+        let config_json: serde_json::Value = serde_json::from_str(&dump.config_json).unwrap();
+        assert_eq!(config_json["daemon"]["fifo"], "<redacted>");
+        assert_eq!(config_json["daemon"]["shutdown_timeout_secs"], "<redacted>");
+        assert_eq!(config_json["daemon"]["debug_enabled"], false);
+        // Short values aren't hunted for inside other strings.
+        assert_eq!(config_json["prompts"]["prep_pr"]["label"], "PR 42");
+    }
+
+    #[test]
+    fn truncate_label_short_unchanged() {
+        assert_eq!(truncate_label("PREP PR", 8), "PREP PR");
+    }
+
+    #[test]
+    fn truncate_label_exact_unchanged() {
+        assert_eq!(truncate_label("RECEIPTS", 8), "RECEIPTS");
+    }
+
+    #[test]
+    fn truncate_label_long_gets_ellipsis() {
+        let out = truncate_label("RUN GATES", 8);
+        assert_eq!(out, "RUN GAT…");
+        assert_eq!(out.chars().count(), 8);
+    }
+
+    #[test]
+    fn render_model_truncates_long_labels() {
+        let mut config = sample_config();
+        config.prompts.get_mut("prep_pr").unwrap().label = "PREPARE PULL REQUEST".to_string();
+        let state = DaemonState::new(0);
+        let model = build_render_model(&state, &config);
+
+        assert_eq!(model.keypad.slots[0].label, "PREPARE…");
+        assert_eq!(model.keypad.slots[0].sublabel.as_deref(), Some("receipts"));
+    }
+
+    #[test]
+    fn render_model_status_line_includes_last_tool() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        let model = build_render_model(&state, &config);
+        assert_eq!(model.status_line.as_deref(), Some("unknown"));
+
+        state.hooks_mode = runbook_protocol::HooksMode::Active;
+        let session = state.ensure_session("sess1");
+        session.agent_state = runbook_protocol::AgentState::Running;
+        session.last_tool = Some("Bash".to_string());
+
+        let model = build_render_model(&state, &config);
+        assert_eq!(model.status_line.as_deref(), Some("running | tool: Bash"));
+    }
+
+    #[test]
+    fn render_model_status_line_shows_running_prompt() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = runbook_protocol::HooksMode::Active;
+        let session = state.ensure_session("sess1");
+        session.agent_state = AgentState::Running;
+        session.last_prompt_text = Some("prep a PR with receipts and a test plan".to_string());
+
+        let model = build_render_model(&state, &config);
+        assert_eq!(
+            model.status_line.as_deref(),
+            Some("running: 'prep a PR with rece…'")
+        );
+
+        // Only while running.
+        state.ensure_session("sess1").agent_state = AgentState::Settled;
+        let model = build_render_model(&state, &config);
+        assert_eq!(model.status_line.as_deref(), Some("settled"));
+    }
+
+    #[test]
+    fn render_model_status_line_shows_elicitation_question() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = runbook_protocol::HooksMode::Active;
+        let session = state.ensure_session("sess1");
+        session.agent_state = AgentState::WaitingInput;
+        session.elicitation_prompt = Some("Which branch should I target?".to_string());
+
+        let model = build_render_model(&state, &config);
+        assert_eq!(
+            model.status_line.as_deref(),
+            Some("waiting: 'Which branch should…'")
+        );
+    }
+
+    #[test]
+    fn render_model_for_focused_session() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.ensure_session("s1").agent_state = AgentState::Running;
+        let s2 = state.ensure_session("s2");
+        s2.agent_state = AgentState::Settled;
+        s2.last_tool = Some("Edit".to_string());
+        state.active_session = Some("s1".to_string());
+        state.armed = Some("prep_pr".to_string());
+
+        let shared = build_render_model(&state, &config);
+        let focused = build_render_model_for_session(&state, &config, Some("s2"));
+        assert_eq!(shared.agent_state, AgentState::Running);
+        assert_eq!(focused.agent_state, AgentState::Settled);
+        assert_eq!(focused.status_line.as_deref(), Some("settled | tool: Edit"));
+        // Keys and the armed prompt are shared.
+        assert_eq!(focused.armed.unwrap().prompt_id, "prep_pr");
+        assert!(focused.keypad.slots[0].armed);
+
+        // A session that isn't live falls back to the shared one.
+        let gone = build_render_model_for_session(&state, &config, Some("s3"));
+        assert_eq!(gone.agent_state, AgentState::Running);
+    }
+
+    #[test]
+    fn page_render_matches_full_render_keys() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.armed = Some("prep_pr".to_string());
+
+        let page = build_page_render(&state, &config);
+        let model = build_render_model(&state, &config);
+        assert_eq!(page.page_index, model.page_index);
+        assert_eq!(page.page_count, model.page_count);
+        assert_eq!(page.page_name, state.current_page_name(&config));
+        assert_eq!(
+            serde_json::to_value(&page.slots).unwrap(),
+            serde_json::to_value(&model.keypad.slots).unwrap()
+        );
+    }
+
+    #[test]
+    fn session_list_includes_tags_sorted() {
+        let mut state = DaemonState::new(0);
+        state.ensure_session("sess-b");
+        state.ensure_session("sess-a").tool_call_count = 2;
+        state.learn_session_tag("tag-a", "sess-a");
+
+        let list = build_session_list(&state);
+        assert_eq!(list.sessions.len(), 2);
+        assert_eq!(list.sessions[0].session_id, "sess-a");
+        assert_eq!(list.sessions[0].session_tag.as_deref(), Some("tag-a"));
+        assert_eq!(list.sessions[0].tool_call_count, 2);
+        assert!(list.sessions[1].session_tag.is_none());
+    }
+
+    #[test]
+    fn render_model_shows_permission_description() {
+        let config = sample_config();
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = runbook_protocol::HooksMode::Active;
+        let session = state.ensure_session("sess1");
+        session.agent_state = runbook_protocol::AgentState::WaitingPermission;
+        session.pending_permission = Some(crate::state::PermissionRequest::from_payload(
+            &serde_json::json!({"tool_name": "Write", "message": "Write src/main.rs?"}),
+        ));
+
+        let model = build_render_model(&state, &config);
+        assert_eq!(
+            model.permission_description.as_deref(),
+            Some("Write src/main.rs?")
+        );
     }
 }
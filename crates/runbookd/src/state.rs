@@ -1,7 +1,43 @@
-use std::collections::HashMap;
-use std::time::Instant;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::num::NonZeroUsize;
+use std::time::{Duration, Instant, SystemTime};
 
-use runbook_protocol::{AgentState, HooksMode, TerminalInfo};
+use lru::LruCache;
+use serde::{Deserialize, Serialize};
+
+use runbook_protocol::{
+    AgentState, ClientKind, DialMode, HooksMode, MacroStep, TerminalInfo, TerminalTarget,
+    ToastMessage, VscodeCommand,
+};
+
+use crate::config::{KeypadPageConfig, RunbookConfig};
+
+/// VS Code `hello` capability: the extension answers `vscode_command`s with
+/// `command_ack`.
+pub const VSCODE_COMMAND_ACK: &str = "command_ack";
+
+/// VS Code `hello` capability: the extension takes `by_index` terminal
+/// targets, so commands can be pinned to a terminal.
+pub const VSCODE_TERMINAL_INDEX: &str = "terminal_index";
+
+/// Longest prompt text kept per session.
+pub const MAX_PROMPT_TEXT_CHARS: usize = 200;
+
+/// Sessions that may appear without a `SessionStart` before hooks count as degraded.
+pub const UNKNOWN_SESSION_LIMIT: u32 = 3;
+
+/// Quiet period (with no live sessions) after which hooks count as stale.
+pub const HOOKS_STALE_AFTER: Duration = Duration::from_secs(10 * 60);
+
+/// Toasts held for a Logi plugin that isn't connected; older ones are dropped.
+pub const PENDING_TOAST_LIMIT: usize = 5;
+
+/// How many recent hook events a repeat is checked against.
+pub const RECENT_HOOK_LIMIT: usize = 50;
+
+fn recent_hook_hashes() -> LruCache<u64, ()> {
+    LruCache::new(NonZeroUsize::new(RECENT_HOOK_LIMIT).expect("limit is non-zero"))
+}
 
 /// Central daemon state. Owned by the daemon task behind a Mutex.
 #[derive(Debug)]
@@ -12,9 +48,41 @@ pub struct DaemonState {
     /// Last dispatched prompt_id (for display / debug).
     pub last_dispatched: Option<String>,
 
+    /// Session that was current when `last_dispatched` went out, if any.
+    pub last_dispatched_session_id: Option<String>,
+
+    /// Whether `last_dispatched` may still raise its `suggests_next`; cleared
+    /// once its session completes a task.
+    pub suggestion_pending: bool,
+
+    /// Number of times each prompt_id has been dispatched.
+    pub dispatch_counts: HashMap<String, u32>,
+
+    /// Number of times each prompt_id has been armed from the keypad.
+    pub arm_counts: HashMap<String, u32>,
+
+    /// When each prompt_id was last dispatched (wall clock, for display).
+    pub dispatch_timestamps: HashMap<String, SystemTime>,
+
+    /// Which session a prompt_id was last armed/dispatched against.
+    pub prompt_session_map: HashMap<String, String>,
+
+    /// Subtask progress (0.0–1.0) per prompt_id, as reported by VS Code.
+    pub slot_progress: HashMap<String, f32>,
+
     /// Active page index.
     pub page: usize,
 
+    /// Remaining macro steps; the next one runs on `TaskCompleted`.
+    pub pending_macro: Option<VecDeque<MacroStep>>,
+
+    /// `chain` of the last `arm_style: chain` prompt dispatched.
+    pub chain_prompt_ids: Vec<String>,
+
+    /// Index into `chain_prompt_ids` of the step the next `TaskCompleted`
+    /// dispatches; `None` when no chain is running.
+    pub chain_index: Option<usize>,
+
     // ----- Per-session hook state -----
     /// Session states keyed by `session_id` (from Claude Code hooks).
     pub sessions: HashMap<String, SessionState>,
@@ -22,6 +90,10 @@ pub struct DaemonState {
     /// Learned mapping: session_tag → session_id (populated from hook events).
     pub session_tag_map: HashMap<String, String>,
 
+    /// Session explicitly focused via `POST /sessions/{id}/select`. Wins over
+    /// terminal correlation until the selected terminal changes.
+    pub active_session: Option<String>,
+
     // ----- Terminal tracking (from VS Code extension) -----
     /// Terminal list as last reported by VS Code.
     pub terminals: Vec<TerminalInfo>,
@@ -29,9 +101,16 @@ pub struct DaemonState {
     /// Which terminal the roller has selected.
     pub selected_terminal_index: usize,
 
+    /// Fraction of a line the dial has turned but not yet scrolled, when
+    /// `dial.sensitivity` is not a whole number.
+    pub scroll_remainder: f32,
+
     /// Mapping: terminal_index → session_tag (from VS Code terminal env).
     pub terminal_tag_map: HashMap<usize, String>,
 
+    /// `timestamp` of the last terminals snapshot applied, to drop stale ones.
+    pub last_terminal_snapshot_ts: Option<String>,
+
     // ----- Capability tracking -----
     /// Hook integration mode.
     pub hooks_mode: HooksMode,
@@ -39,33 +118,251 @@ pub struct DaemonState {
     /// When the last hook event was received.
     pub last_hook_ts: Option<Instant>,
 
+    /// Hashes of the last [`RECENT_HOOK_LIMIT`] hook events applied, to drop
+    /// a repeated delivery. Cleared at turn boundaries (`UserPromptSubmit`,
+    /// `Stop`, `SessionEnd`), so identical events in separate turns both apply.
+    pub recent_hook_hashes: LruCache<u64, ()>,
+
+    /// Sessions first seen through a hook other than `SessionStart`.
+    pub unknown_session_hooks: u32,
+
+    /// Sessions dropped for going quiet past `daemon.session_ttl_secs`. They
+    /// may only be waiting on the user, so their next hook isn't unknown.
+    pub expired_session_ids: HashSet<String>,
+
+    /// Whether any `SessionStart` has arrived (so missing ones are suspicious).
+    pub seen_session_start: bool,
+
+    /// Git branch of the workspace: read at startup, updated by the
+    /// `git_branch` runbook-hooks adds to `SessionStart` and
+    /// `UserPromptSubmit` payloads.
+    pub current_branch: Option<String>,
+
+    /// This machine's name for `{hostname}` in gate URLs; looked up once at
+    /// startup.
+    pub hostname: Option<String>,
+
     /// True when VS Code extension is connected.
     pub vscode_connected: bool,
 
+    /// `capabilities` from the VS Code extension's `hello` (e.g.
+    /// `"terminal_index"`); empty until it connects.
+    pub vscode_capabilities: HashSet<String>,
+
+    /// Sent `vscode_command`s awaiting a `command_ack`, by `command_id`,
+    /// with when they were sent.
+    pub pending_commands: HashMap<u64, (Instant, VscodeCommand)>,
+
+    /// Last `command_id` handed out.
+    pub last_command_id: u64,
+
     /// True when Logi plugin is connected.
     pub logi_connected: bool,
 
+    /// `dial.mode` overrides sent in `reconfigure`, by client kind.
+    pub per_client_dial_mode: HashMap<ClientKind, DialMode>,
+
+    /// `keypad.label_max_chars` overrides sent in `reconfigure`, by client kind.
+    pub per_client_label_max_chars: HashMap<ClientKind, usize>,
+
+    /// Toasts raised while no Logi plugin was connected, oldest first; sent
+    /// to the next one that says `hello`.
+    pub pending_toasts: VecDeque<ToastMessage>,
+
     /// Latched: the most recent state of the last session to end.
     pub last_ended_state: Option<AgentState>,
+
+    // ----- Lifetime -----
+    /// When the daemon state was created (process start, or restore).
+    pub started_at: Instant,
+
+    /// When a Logi or VS Code client first connected.
+    pub first_client_at: Option<Instant>,
 }
 
 impl DaemonState {
     pub fn new(initial_page: usize) -> Self {
+        Self::new_at(initial_page, Instant::now())
+    }
+
+    /// Like [`new`](Self::new), with an explicit start time.
+    pub fn new_at(initial_page: usize, started_at: Instant) -> Self {
         Self {
             armed: None,
             last_dispatched: None,
+            last_dispatched_session_id: None,
+            suggestion_pending: false,
+            dispatch_counts: HashMap::new(),
+            arm_counts: HashMap::new(),
+            dispatch_timestamps: HashMap::new(),
+            prompt_session_map: HashMap::new(),
+            slot_progress: HashMap::new(),
             page: initial_page,
+            pending_macro: None,
+            chain_prompt_ids: Vec::new(),
+            chain_index: None,
             sessions: HashMap::new(),
             session_tag_map: HashMap::new(),
+            active_session: None,
             terminals: Vec::new(),
             selected_terminal_index: 0,
+            scroll_remainder: 0.0,
             terminal_tag_map: HashMap::new(),
+            last_terminal_snapshot_ts: None,
             hooks_mode: HooksMode::Absent,
             last_hook_ts: None,
+            recent_hook_hashes: recent_hook_hashes(),
+            unknown_session_hooks: 0,
+            expired_session_ids: HashSet::new(),
+            seen_session_start: false,
+            current_branch: None,
+            hostname: None,
             vscode_connected: false,
+            vscode_capabilities: HashSet::new(),
+            pending_commands: HashMap::new(),
+            last_command_id: 0,
             logi_connected: false,
+            per_client_dial_mode: HashMap::new(),
+            per_client_label_max_chars: HashMap::new(),
+            pending_toasts: VecDeque::new(),
             last_ended_state: None,
+            started_at,
+            first_client_at: None,
+        }
+    }
+
+    /// Time since the daemon started.
+    pub fn uptime(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// How long hooks have been quiet, while `hooks_mode` is `Absent` or
+    /// `Stale`: since the last hook, or since startup if none ever arrived.
+    pub fn hooks_quiet_for(&self, now: Instant) -> Option<Duration> {
+        match self.hooks_mode {
+            HooksMode::Absent | HooksMode::Stale => {
+                let since = self.last_hook_ts.unwrap_or(self.started_at);
+                Some(now.saturating_duration_since(since))
+            }
+            HooksMode::Active | HooksMode::Degraded { .. } => None,
+        }
+    }
+
+    /// The page being shown, or `None` if `page` is past the configured pages
+    /// (e.g. a reload removed it and the reducer hasn't clamped yet).
+    pub fn current_page<'a>(&self, config: &'a RunbookConfig) -> Option<&'a KeypadPageConfig> {
+        config.keypad.pages.get(self.page)
+    }
+
+    /// Show the first page named `name`, disarming whatever was armed (its
+    /// prompt may not be on that page). `false`, with nothing changed, if no
+    /// page has that name.
+    pub fn select_page_by_name(&mut self, name: &str, config: &RunbookConfig) -> bool {
+        let Some(index) = config.keypad.pages.iter().position(|p| p.name == name) else {
+            return false;
+        };
+        self.page = index;
+        self.armed = None;
+        true
+    }
+
+    /// Name of the page being shown, or `"<unknown>"` when out of range.
+    pub fn current_page_name<'a>(&self, config: &'a RunbookConfig) -> &'a str {
+        self.current_page(config)
+            .map(|p| p.name.as_str())
+            .unwrap_or("<unknown>")
+    }
+
+    /// Index of the first page with a key that arms `prompt_id`.
+    pub fn page_for_prompt(&self, prompt_id: &str, config: &RunbookConfig) -> Option<usize> {
+        config.keypad.pages.iter().position(|page| {
+            page.slots
+                .iter()
+                .any(|slot| slot.prompt_id.as_deref() == Some(prompt_id))
+        })
+    }
+
+    /// Slot (0-8) of the armed prompt on the current page, if it is there.
+    pub fn armed_slot_index(&self, config: &RunbookConfig) -> Option<u8> {
+        self.armed_slot_on(self.current_page(config)?)
+    }
+
+    /// Slot of `page` that arms the armed prompt.
+    pub fn armed_slot_on(&self, page: &KeypadPageConfig) -> Option<u8> {
+        let armed = self.armed.as_deref()?;
+        let idx = page
+            .slots
+            .iter()
+            .position(|slot| slot.prompt_id.as_deref() == Some(armed))?;
+        u8::try_from(idx).ok()
+    }
+
+    /// Whether the connected VS Code extension listed `cap` in its `hello`.
+    pub fn vscode_supports(&self, cap: &str) -> bool {
+        self.vscode_capabilities.contains(cap)
+    }
+
+    /// Give `cmd` the next `command_id` and, if the connected VS Code
+    /// extension declared [`VSCODE_COMMAND_ACK`], hold it until acked.
+    /// Whether it is held.
+    pub fn track_command(&mut self, cmd: &mut VscodeCommand, now: Instant) -> bool {
+        self.last_command_id += 1;
+        cmd.command_id = self.last_command_id;
+        let held = self.vscode_connected && self.vscode_supports(VSCODE_COMMAND_ACK);
+        if held {
+            self.pending_commands.insert(cmd.command_id, (now, cmd.clone()));
+        }
+        held
+    }
+
+    /// Stop waiting for `command_id`; the command, if it was pending.
+    pub fn ack_command(&mut self, command_id: u64) -> Option<VscodeCommand> {
+        self.pending_commands.remove(&command_id).map(|(_, cmd)| cmd)
+    }
+
+    /// Drop commands unacked for longer than `timeout` as of `now`, by id.
+    pub fn expire_commands(&mut self, timeout: Duration, now: Instant) -> Vec<VscodeCommand> {
+        let mut expired: Vec<u64> = self
+            .pending_commands
+            .iter()
+            .filter(|(_, (sent, _))| now.saturating_duration_since(*sent) > timeout)
+            .map(|(id, _)| *id)
+            .collect();
+        expired.sort_unstable();
+        expired
+            .into_iter()
+            .filter_map(|id| self.ack_command(id))
+            .collect()
+    }
+
+    /// Key label width: the Logi plugin's override, else the config.
+    pub fn label_max_chars(&self, config: &RunbookConfig) -> usize {
+        self.per_client_label_max_chars
+            .get(&ClientKind::Logi)
+            .copied()
+            .unwrap_or(config.keypad.label_max_chars)
+    }
+
+    /// Hold `toast` for the Logi plugin if it isn't connected to see it now.
+    pub fn hold_toast(&mut self, toast: ToastMessage) {
+        if self.logi_connected {
+            return;
+        }
+        if self.pending_toasts.len() == PENDING_TOAST_LIMIT {
+            self.pending_toasts.pop_front();
         }
+        self.pending_toasts.push_back(toast);
+    }
+
+    /// Remember a hook event. `false` if it repeats one of the last
+    /// [`RECENT_HOOK_LIMIT`], i.e. a second delivery.
+    pub fn remember_hook(&mut self, fingerprint: u64) -> bool {
+        self.recent_hook_hashes.put(fingerprint, ()).is_none()
+    }
+
+    /// Record a Logi or VS Code client connecting; only the first one sticks.
+    pub fn note_client_connected(&mut self, now: Instant) {
+        self.first_client_at.get_or_insert(now);
     }
 
     /// Returns the agent state to render.
@@ -74,35 +371,198 @@ impl DaemonState {
     /// - **Hooks absent** → `Unknown`
     /// - **0 live sessions** → `last_ended_state`, then `Unknown`
     /// - **1 session** → that session's state
-    /// - **>1 sessions** → try to resolve via terminal↔session correlation, else `Unknown`
+    /// - **>1 sessions** → try to resolve via terminal↔session correlation,
+    ///   else the [most active](Self::most_active_session_id) session
     pub fn current_agent_state(&self) -> AgentState {
         if self.hooks_mode == HooksMode::Absent {
             return AgentState::Unknown;
         }
 
+        if self.sessions.is_empty() {
+            return self.last_ended_state.unwrap_or(AgentState::Unknown);
+        }
+
+        self.current_session()
+            .map(|s| s.agent_state)
+            .unwrap_or(AgentState::Unknown)
+    }
+
+    /// The session that sent the latest hook event (ties go to the greatest
+    /// id, so the pick is stable).
+    pub fn most_active_session_id(&self) -> Option<String> {
+        self.sessions
+            .iter()
+            .max_by_key(|(id, s)| (s.last_hook_at, *id))
+            .map(|(id, _)| id.clone())
+    }
+
+    /// Returns the session whose state is rendered, using the same rules as
+    /// [`current_agent_state`](Self::current_agent_state): the correlated
+    /// session, else the most active one.
+    ///
+    /// `None` when hooks are absent or no sessions are live.
+    pub fn current_session(&self) -> Option<&SessionState> {
+        if self.hooks_mode == HooksMode::Absent {
+            return None;
+        }
+        let session_id = self
+            .current_session_id()
+            .or_else(|| self.most_active_session_id())?;
+        self.sessions.get(&session_id)
+    }
+
+    /// The session commands are attributed to: the only live session, the
+    /// active one, or the one on the selected terminal. Unlike
+    /// [`current_session`](Self::current_session), never guesses by activity.
+    pub fn current_session_id(&self) -> Option<String> {
+        if self.hooks_mode == HooksMode::Absent {
+            return None;
+        }
+
         match self.sessions.len() {
-            0 => self.last_ended_state.unwrap_or(AgentState::Unknown),
-            1 => self
-                .sessions
-                .values()
-                .next()
-                .map(|s| s.agent_state)
-                .unwrap_or(AgentState::Unknown),
+            0 => None,
+            1 => self.sessions.keys().next().cloned(),
             _ => {
-                // Multi-session: try to resolve via terminal selection.
-                if let Some(session_id) = self.selected_session_id() {
-                    self.sessions
-                        .get(&session_id)
-                        .map(|s| s.agent_state)
-                        .unwrap_or(AgentState::Unknown)
-                } else {
-                    // Can't correlate terminal → session. Degrade.
-                    AgentState::Unknown
+                if let Some(ref sid) = self.active_session {
+                    if self.sessions.contains_key(sid) {
+                        return Some(sid.clone());
+                    }
                 }
+                // Multi-session: try to resolve via terminal selection.
+                // Can't correlate terminal → session? Degrade.
+                let session_id = self.selected_session_id()?;
+                self.sessions.contains_key(&session_id).then_some(session_id)
             }
         }
     }
 
+    /// The terminal VS Code reported at `idx` (its terminal index, not the
+    /// position in `terminals`).
+    pub fn terminal_by_index(&self, idx: usize) -> Option<&TerminalInfo> {
+        self.terminals.iter().find(|t| t.index == idx)
+    }
+
+    /// The first terminal launched with `tag` via "Start Claude Session".
+    pub fn terminal_by_tag(&self, tag: &str) -> Option<&TerminalInfo> {
+        self.terminals
+            .iter()
+            .find(|t| t.session_tag.as_deref() == Some(tag))
+    }
+
+    pub fn terminal_count(&self) -> usize {
+        self.terminals.len()
+    }
+
+    /// Terminals with a `session_tag`.
+    pub fn claude_terminal_count(&self) -> usize {
+        self.terminals
+            .iter()
+            .filter(|t| t.session_tag.is_some())
+            .count()
+    }
+
+    /// Name of the terminal the roller has selected, if it is still in the list.
+    pub fn active_terminal_name(&self) -> Option<&str> {
+        self.terminal_by_index(self.selected_terminal_index)
+            .map(|t| t.name.as_str())
+    }
+
+    /// Session tag of the selected terminal, if it was launched via "Start Claude Session".
+    pub fn active_terminal_session_tag(&self) -> Option<&str> {
+        self.terminal_by_index(self.selected_terminal_index)
+            .and_then(|t| t.session_tag.as_deref())
+    }
+
+    /// Session tag of the session the device shows: the selected terminal's,
+    /// else the one learned for the current session.
+    pub fn current_session_tag(&self) -> Option<&str> {
+        self.active_terminal_session_tag()
+            .or_else(|| self.session_tag_for(&self.current_session_id()?))
+    }
+
+    /// The tag learned for `session_id` from its hook events.
+    pub fn session_tag_for(&self, session_id: &str) -> Option<&str> {
+        self.session_tag_map
+            .iter()
+            .find(|(_, sid)| *sid == session_id)
+            .map(|(tag, _)| tag.as_str())
+    }
+
+    /// The terminal `session_id` runs in, found through its learned tag.
+    pub fn session_terminal(&self, session_id: &str) -> Option<&TerminalInfo> {
+        self.terminal_by_tag(self.session_tag_for(session_id)?)
+    }
+
+    /// Fill `{session_tag}`, `{git_branch}` and `{hostname}` in a gate's
+    /// `url_template`. Unknown values become empty strings.
+    pub fn resolve_url_template(&self, template: &str) -> String {
+        template
+            .replace("{session_tag}", self.current_session_tag().unwrap_or_default())
+            .replace("{git_branch}", self.current_branch.as_deref().unwrap_or_default())
+            .replace("{hostname}", self.hostname.as_deref().unwrap_or_default())
+    }
+
+    /// Resolve a target to something VS Code can act on.
+    ///
+    /// `ByTag` picks the first terminal with that tag; an unknown tag stays
+    /// unresolved. `ByName` scans the terminal list and stays a name if
+    /// no terminal matches.
+    pub fn resolve_target(&self, target: &TerminalTarget) -> ResolvedTarget {
+        match target {
+            TerminalTarget::ActiveClaude => ResolvedTarget::ActiveClaude,
+            TerminalTarget::Active => ResolvedTarget::Active,
+            TerminalTarget::ByIndex(i) => ResolvedTarget::Index(*i),
+            TerminalTarget::ByTag(tag) => self
+                .terminal_by_tag(tag)
+                .map(|t| ResolvedTarget::Index(t.index))
+                .unwrap_or_else(|| ResolvedTarget::UnknownTag(tag.clone())),
+            TerminalTarget::ByName(name) => self
+                .terminals
+                .iter()
+                .find(|t| t.name == *name)
+                .map(|t| ResolvedTarget::Index(t.index))
+                .unwrap_or_else(|| ResolvedTarget::Name(name.clone())),
+        }
+    }
+
+    /// Pick the terminal an `ActiveClaude` command should land in.
+    ///
+    /// Resolution order:
+    /// 1. the terminal tagged with the current session's tag (the session the
+    ///    device shows, i.e. `active_session` when one is selected)
+    /// 2. any Claude terminal (one with a `session_tag`), preferring the
+    ///    selected one
+    /// 3. the selected terminal
+    ///
+    /// `None` only when VS Code hasn't reported any terminals.
+    pub fn resolve_active_claude_index(&self) -> Option<usize> {
+        let current = self.current_session_id().and_then(|sid| {
+            let tag = self
+                .session_tag_map
+                .iter()
+                .find(|(_, s)| **s == sid)
+                .map(|(tag, _)| tag)?;
+            self.terminal_tag_map
+                .iter()
+                .find(|(_, t)| *t == tag)
+                .map(|(i, _)| *i)
+        });
+        if current.is_some() {
+            return current;
+        }
+
+        let any_claude = self
+            .terminal_by_index(self.selected_terminal_index)
+            .filter(|t| t.session_tag.is_some())
+            .or_else(|| self.terminals.iter().find(|t| t.session_tag.is_some()))
+            .map(|t| t.index);
+        if any_claude.is_some() {
+            return any_claude;
+        }
+
+        (self.terminal_count() > 0).then_some(self.selected_terminal_index)
+    }
+
     /// Attempt to resolve the currently selected terminal to a session_id.
     ///
     /// Path: selected_terminal_index → terminal_tag_map → session_tag → session_tag_map → session_id
@@ -112,11 +572,124 @@ impl DaemonState {
         Some(session_id.clone())
     }
 
+    /// Re-derive `hooks_mode` from `last_hook_ts` and the live sessions.
+    ///
+    /// - `Absent`/`Stale` → `Active` once a hook arrived within `stale_after`
+    /// - `Active`/`Degraded` → `Stale` when no session is live and the last
+    ///   hook is older than `stale_after` (this also clears degraded tracking)
+    pub fn update_hooks_mode(&mut self, now: Instant, stale_after: Duration) {
+        let fresh = self
+            .last_hook_ts
+            .is_some_and(|t| now.saturating_duration_since(t) < stale_after);
+        match self.hooks_mode {
+            HooksMode::Absent | HooksMode::Stale if fresh => {
+                self.hooks_mode = HooksMode::Active;
+            }
+            HooksMode::Active | HooksMode::Degraded { .. }
+                if !fresh && self.sessions.is_empty() =>
+            {
+                self.hooks_mode = HooksMode::Stale;
+                self.unknown_session_hooks = 0;
+                self.seen_session_start = false;
+            }
+            _ => {}
+        }
+    }
+
+    /// Queue a macro, replacing any steps still pending. An empty macro
+    /// cancels the pending one. Only one sequence runs at a time, so this
+    /// also cancels a running chain.
+    pub fn start_macro(&mut self, steps: Vec<MacroStep>) {
+        self.cancel_chain();
+        self.pending_macro = (!steps.is_empty()).then(|| steps.into());
+    }
+
+    /// Start a chain, replacing any chain or macro still running. An empty
+    /// chain just cancels the running one.
+    pub fn start_chain(&mut self, prompt_ids: Vec<String>) {
+        self.pending_macro = None;
+        self.chain_index = (!prompt_ids.is_empty()).then_some(0);
+        self.chain_prompt_ids = prompt_ids;
+    }
+
+    /// Take the next chain step and advance `chain_index`; the chain is
+    /// dropped once its last step is taken.
+    pub fn next_chain_step(&mut self) -> Option<String> {
+        let index = self.chain_index?;
+        let step = self.chain_prompt_ids.get(index).cloned();
+        if index + 1 < self.chain_prompt_ids.len() {
+            self.chain_index = Some(index + 1);
+        } else {
+            self.cancel_chain();
+        }
+        step
+    }
+
+    /// Stop the running chain, if any.
+    pub fn cancel_chain(&mut self) {
+        self.chain_prompt_ids.clear();
+        self.chain_index = None;
+    }
+
+    /// Take the next macro step; the queue is dropped once it runs dry.
+    pub fn next_macro_step(&mut self) -> Option<MacroStep> {
+        let queue = self.pending_macro.as_mut()?;
+        let step = queue.pop_front();
+        if queue.is_empty() {
+            self.pending_macro = None;
+        }
+        step
+    }
+
     /// Ensure a session entry exists and return a mutable reference.
+    ///
+    /// A session is proof that hooks are installed, so the first one moves
+    /// `hooks_mode` off `Absent`; otherwise `current_agent_state` would keep
+    /// answering `Unknown` for it.
     pub fn ensure_session(&mut self, session_id: &str) -> &mut SessionState {
+        if self.hooks_mode == HooksMode::Absent && !self.sessions.contains_key(session_id) {
+            self.hooks_mode = HooksMode::Active;
+        }
         self.sessions
             .entry(session_id.to_string())
-            .or_insert_with(SessionState::new)
+            .or_default()
+    }
+
+    /// Sessions with no hook event for longer than `ttl` as of `now`, sorted.
+    pub fn expired_sessions(&self, ttl: Duration, now: Instant) -> Vec<String> {
+        let mut expired: Vec<String> = self
+            .sessions
+            .iter()
+            .filter(|(_, s)| now.saturating_duration_since(s.last_hook_at) > ttl)
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired.sort();
+        expired
+    }
+
+    /// Drop a session that went quiet past the TTL. It may still be alive, so
+    /// it is remembered in `expired_session_ids` rather than latched as ended,
+    /// and `armed` / `last_dispatched` only go if they were its own.
+    pub fn expire_session(&mut self, session_id: &str) {
+        self.sessions.remove(session_id);
+        self.expired_session_ids.insert(session_id.to_string());
+        self.session_tag_map.retain(|_tag, sid| sid != session_id);
+        if self.active_session.as_deref() == Some(session_id) {
+            self.active_session = None;
+        }
+
+        let armed_here = self
+            .armed
+            .as_ref()
+            .and_then(|pid| self.prompt_session_map.get(pid))
+            .is_some_and(|sid| sid == session_id);
+        if armed_here {
+            self.armed = None;
+        }
+        if self.last_dispatched_session_id.as_deref() == Some(session_id) {
+            self.last_dispatched = None;
+            self.last_dispatched_session_id = None;
+        }
     }
 
     /// Remove a session (on SessionEnd) and clean up related state.
@@ -127,10 +700,84 @@ impl DaemonState {
 
         // Clean up session_tag_map entries pointing to this session.
         self.session_tag_map.retain(|_tag, sid| sid != session_id);
+        if self.active_session.as_deref() == Some(session_id) {
+            self.active_session = None;
+        }
 
-        // Clear armed + last_dispatched — no valid target anymore.
+        // Nothing armed has a valid target anymore; a dispatch to another
+        // session still stands.
         self.armed = None;
-        self.last_dispatched = None;
+        self.forget_dispatch_to(session_id);
+    }
+
+    /// Clear `last_dispatched` if it went to `session_id` (or to no known
+    /// session).
+    fn forget_dispatch_to(&mut self, session_id: &str) {
+        let dispatched_here = self
+            .last_dispatched_session_id
+            .as_deref()
+            .is_none_or(|sid| sid == session_id);
+        if dispatched_here {
+            self.last_dispatched = None;
+            self.last_dispatched_session_id = None;
+        }
+    }
+
+    /// Clear stale state when a new Claude session starts under `session_id`.
+    ///
+    /// Drops the old session entry and arm/dispatch counts for prompts tied to
+    /// that session. `armed` is only cleared when the armed prompt belongs to
+    /// the restarting session, and `last_dispatched` when it went to that
+    /// session (or to no known session).
+    pub fn reset_for_new_session(&mut self, session_id: &str) {
+        self.sessions.remove(session_id);
+        self.forget_dispatch_to(session_id);
+
+        let armed_here = self
+            .armed
+            .as_ref()
+            .and_then(|pid| self.prompt_session_map.get(pid))
+            .is_some_and(|sid| sid == session_id);
+        if armed_here {
+            self.armed = None;
+        }
+
+        let counts = &mut self.dispatch_counts;
+        let arms = &mut self.arm_counts;
+        let timestamps = &mut self.dispatch_timestamps;
+        self.prompt_session_map.retain(|pid, sid| {
+            if sid == session_id {
+                counts.remove(pid);
+                arms.remove(pid);
+                timestamps.remove(pid);
+                false
+            } else {
+                true
+            }
+        });
+    }
+
+    /// How often `prompt_id` was armed versus dispatched.
+    pub fn prompt_stats(&self, prompt_id: &str) -> PromptStats {
+        let arm_count = self.arm_counts.get(prompt_id).copied().unwrap_or(0);
+        let dispatch_count = self.dispatch_counts.get(prompt_id).copied().unwrap_or(0);
+        PromptStats {
+            prompt_id: prompt_id.to_string(),
+            arm_count,
+            dispatch_count,
+            arm_rate: if arm_count == 0 {
+                0.0
+            } else {
+                dispatch_count as f32 / arm_count as f32
+            },
+        }
+    }
+
+    /// Remember which session (if resolvable) a prompt was armed/dispatched against.
+    pub fn associate_prompt(&mut self, prompt_id: &str) {
+        if let Some(sid) = self.current_session_id() {
+            self.prompt_session_map.insert(prompt_id.to_string(), sid);
+        }
     }
 
     /// Learn the session_tag → session_id mapping from a hook event.
@@ -138,22 +785,1316 @@ impl DaemonState {
         self.session_tag_map
             .insert(session_tag.to_string(), session_id.to_string());
     }
+
+    /// Show the session that reported `tag`. Returns false (and changes
+    /// nothing) if no live session has that tag.
+    pub fn set_active_session_by_tag(&mut self, tag: &str) -> bool {
+        match self.session_tag_map.get(tag) {
+            Some(sid) => {
+                self.active_session = Some(sid.clone());
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A [`TerminalTarget`] after [`DaemonState::resolve_target`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolvedTarget {
+    Index(usize),
+    Name(String),
+    Active,
+    ActiveClaude,
+    /// No terminal carries this tag; VS Code can't act on it.
+    UnknownTag(String),
+}
+
+impl From<ResolvedTarget> for TerminalTarget {
+    fn from(target: ResolvedTarget) -> Self {
+        match target {
+            ResolvedTarget::Index(i) => TerminalTarget::ByIndex(i),
+            ResolvedTarget::Name(name) => TerminalTarget::ByName(name),
+            ResolvedTarget::Active => TerminalTarget::Active,
+            ResolvedTarget::ActiveClaude => TerminalTarget::ActiveClaude,
+            ResolvedTarget::UnknownTag(tag) => TerminalTarget::ByTag(tag),
+        }
+    }
 }
 
 /// Per-session state derived from hook events.
 #[derive(Debug, Clone)]
 pub struct SessionState {
     pub agent_state: AgentState,
+    /// `agent_state` before its most recent change.
+    pub previous_agent_state: AgentState,
     pub last_tool: Option<String>,
+    /// Number of `PreToolUse` events seen in this session.
+    pub tool_call_count: u32,
+    /// The permission dialog Claude is currently blocked on, if any.
+    pub pending_permission: Option<PermissionRequest>,
+    /// Number of tool calls Runbook policy blocked in this session.
+    pub blocked_count: u32,
+    /// Most recent `UserPromptSubmit` prompt, capped at [`MAX_PROMPT_TEXT_CHARS`].
+    pub last_prompt_text: Option<String>,
+    /// The question Claude asked in an `elicitation_dialog`, while `WaitingInput`.
+    pub elicitation_prompt: Option<String>,
     pub started_at: Instant,
+    /// When this session's most recent hook event arrived.
+    pub last_hook_at: Instant,
+}
+
+/// An outstanding permission dialog, parsed from the hook payload.
+#[derive(Debug, Clone)]
+pub struct PermissionRequest {
+    pub tool_name: String,
+    pub description: String,
+    pub requested_at: Instant,
+}
+
+impl PermissionRequest {
+    /// Extract what is being requested from a `PermissionRequest` or
+    /// `Notification/permission_prompt` payload.
+    pub fn from_payload(payload: &serde_json::Value) -> Self {
+        let str_at = |ptr: &str| payload.pointer(ptr).and_then(|v| v.as_str());
+        let tool_name = str_at("/tool_name").unwrap_or_default().to_string();
+        let description = str_at("/message")
+            .or_else(|| str_at("/tool_input/description"))
+            .or_else(|| str_at("/tool_input/command"))
+            .unwrap_or(&tool_name)
+            .to_string();
+        Self {
+            tool_name,
+            description,
+            requested_at: Instant::now(),
+        }
+    }
+}
+
+impl Default for SessionState {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SessionState {
     pub fn new() -> Self {
         Self {
             agent_state: AgentState::Unknown,
+            previous_agent_state: AgentState::Unknown,
             last_tool: None,
+            tool_call_count: 0,
+            pending_permission: None,
+            blocked_count: 0,
+            last_prompt_text: None,
+            elicitation_prompt: None,
             started_at: Instant::now(),
+            last_hook_at: Instant::now(),
         }
     }
 }
+
+/// Per-prompt usage, served by `GET /prompt-stats`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PromptStats {
+    pub prompt_id: String,
+    pub arm_count: u32,
+    pub dispatch_count: u32,
+    /// Dispatches per arm; 0.0 if never armed. A low rate means the prompt is
+    /// often armed and then cancelled.
+    pub arm_rate: f32,
+}
+
+// ---------------------------------------------------------------------------
+// Snapshots (crash-dump / restore)
+// ---------------------------------------------------------------------------
+
+/// Serializable mirror of [`DaemonState`].
+///
+/// `Instant`s are stored as milliseconds before a reference instant (see
+/// [`DaemonState::snapshot_at`]). Client connection flags, lifetime
+/// timestamps and `recent_hook_hashes` are not kept, and
+/// `vscode_capabilities` is not restored: clients reconnect and say `hello`
+/// again after a restart.
+///
+/// Also the way to compare states in tests (`DaemonState` itself can't be
+/// `PartialEq`): snapshot both at one `now`, or each at its own reference.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DaemonStateSnapshot {
+    pub armed: Option<String>,
+    pub last_dispatched: Option<String>,
+    #[serde(default)]
+    pub last_dispatched_session_id: Option<String>,
+    #[serde(default)]
+    pub suggestion_pending: bool,
+    pub dispatch_counts: HashMap<String, u32>,
+    #[serde(default)]
+    pub arm_counts: HashMap<String, u32>,
+    #[serde(default)]
+    pub dispatch_timestamps: HashMap<String, SystemTime>,
+    pub prompt_session_map: HashMap<String, String>,
+    pub slot_progress: HashMap<String, f32>,
+    pub page: usize,
+    #[serde(default)]
+    pub pending_macro: Option<VecDeque<MacroStep>>,
+    #[serde(default)]
+    pub chain_prompt_ids: Vec<String>,
+    #[serde(default)]
+    pub chain_index: Option<usize>,
+    pub sessions: HashMap<String, SessionSnapshot>,
+    pub session_tag_map: HashMap<String, String>,
+    #[serde(default)]
+    pub active_session: Option<String>,
+    pub terminals: Vec<TerminalInfo>,
+    pub selected_terminal_index: usize,
+    #[serde(default)]
+    pub scroll_remainder: f32,
+    pub terminal_tag_map: HashMap<usize, String>,
+    #[serde(default)]
+    pub last_terminal_snapshot_ts: Option<String>,
+    pub hooks_mode: HooksMode,
+    pub last_hook_ms_ago: Option<u64>,
+    #[serde(default)]
+    pub unknown_session_hooks: u32,
+    #[serde(default)]
+    pub expired_session_ids: HashSet<String>,
+    #[serde(default)]
+    pub seen_session_start: bool,
+    #[serde(default)]
+    pub current_branch: Option<String>,
+    #[serde(default)]
+    pub hostname: Option<String>,
+    #[serde(default)]
+    pub vscode_capabilities: HashSet<String>,
+    #[serde(default)]
+    pub pending_commands: HashMap<u64, PendingCommandSnapshot>,
+    #[serde(default)]
+    pub last_command_id: u64,
+    #[serde(default)]
+    pub per_client_dial_mode: HashMap<ClientKind, DialMode>,
+    #[serde(default)]
+    pub per_client_label_max_chars: HashMap<ClientKind, usize>,
+    #[serde(default)]
+    pub pending_toasts: VecDeque<ToastMessage>,
+    pub last_ended_state: Option<AgentState>,
+    /// Wall-clock time the snapshot was written to the state file; the time
+    /// since is added to every age on restore.
+    #[serde(default)]
+    pub saved_at: Option<SystemTime>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SessionSnapshot {
+    pub agent_state: AgentState,
+    #[serde(default = "unknown_agent_state")]
+    pub previous_agent_state: AgentState,
+    pub last_tool: Option<String>,
+    pub tool_call_count: u32,
+    pub pending_permission: Option<PermissionSnapshot>,
+    pub blocked_count: u32,
+    pub last_prompt_text: Option<String>,
+    #[serde(default)]
+    pub elicitation_prompt: Option<String>,
+    pub started_ms_ago: u64,
+    #[serde(default)]
+    pub last_hook_ms_ago: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionSnapshot {
+    pub tool_name: String,
+    pub description: String,
+    pub requested_ms_ago: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PendingCommandSnapshot {
+    pub sent_ms_ago: u64,
+    pub command: VscodeCommand,
+}
+
+fn unknown_agent_state() -> AgentState {
+    AgentState::Unknown
+}
+
+/// Milliseconds from `then` to `now`; 0 if `then` is later.
+fn ms_before(now: Instant, then: Instant) -> u64 {
+    now.saturating_duration_since(then).as_millis() as u64
+}
+
+/// `now - ms`, saturating at `now` if the clock can't go back that far.
+fn instant_before(now: Instant, ms: u64) -> Instant {
+    now.checked_sub(Duration::from_millis(ms)).unwrap_or(now)
+}
+
+impl From<&DaemonState> for DaemonStateSnapshot {
+    fn from(state: &DaemonState) -> Self {
+        state.snapshot()
+    }
+}
+
+impl DaemonState {
+    /// Snapshot with ages measured back from now.
+    pub fn snapshot(&self) -> DaemonStateSnapshot {
+        self.snapshot_at(Instant::now())
+    }
+
+    /// Snapshot with every age measured back from the one instant `now`.
+    pub fn snapshot_at(&self, now: Instant) -> DaemonStateSnapshot {
+        DaemonStateSnapshot {
+            armed: self.armed.clone(),
+            last_dispatched: self.last_dispatched.clone(),
+            last_dispatched_session_id: self.last_dispatched_session_id.clone(),
+            suggestion_pending: self.suggestion_pending,
+            dispatch_counts: self.dispatch_counts.clone(),
+            arm_counts: self.arm_counts.clone(),
+            dispatch_timestamps: self.dispatch_timestamps.clone(),
+            prompt_session_map: self.prompt_session_map.clone(),
+            slot_progress: self.slot_progress.clone(),
+            page: self.page,
+            pending_macro: self.pending_macro.clone(),
+            chain_prompt_ids: self.chain_prompt_ids.clone(),
+            chain_index: self.chain_index,
+            sessions: self
+                .sessions
+                .iter()
+                .map(|(id, s)| (id.clone(), s.to_snapshot(now)))
+                .collect(),
+            session_tag_map: self.session_tag_map.clone(),
+            active_session: self.active_session.clone(),
+            terminals: self.terminals.clone(),
+            selected_terminal_index: self.selected_terminal_index,
+            scroll_remainder: self.scroll_remainder,
+            terminal_tag_map: self.terminal_tag_map.clone(),
+            last_terminal_snapshot_ts: self.last_terminal_snapshot_ts.clone(),
+            hooks_mode: self.hooks_mode.clone(),
+            last_hook_ms_ago: self.last_hook_ts.map(|t| ms_before(now, t)),
+            unknown_session_hooks: self.unknown_session_hooks,
+            expired_session_ids: self.expired_session_ids.clone(),
+            seen_session_start: self.seen_session_start,
+            current_branch: self.current_branch.clone(),
+            hostname: self.hostname.clone(),
+            vscode_capabilities: self.vscode_capabilities.clone(),
+            pending_commands: self
+                .pending_commands
+                .iter()
+                .map(|(&id, (sent_at, command))| {
+                    let sent_ms_ago = ms_before(now, *sent_at);
+                    let command = command.clone();
+                    (id, PendingCommandSnapshot { sent_ms_ago, command })
+                })
+                .collect(),
+            last_command_id: self.last_command_id,
+            per_client_dial_mode: self.per_client_dial_mode.clone(),
+            per_client_label_max_chars: self.per_client_label_max_chars.clone(),
+            pending_toasts: self.pending_toasts.clone(),
+            last_ended_state: self.last_ended_state,
+            saved_at: None,
+        }
+    }
+
+    /// Rebuild state from a snapshot; stored ages are measured back from `now`,
+    /// plus however long ago `saved_at` was.
+    pub fn from_snapshot(snap: DaemonStateSnapshot, now: Instant) -> Self {
+        let downtime = snap
+            .saved_at
+            .and_then(|t| SystemTime::now().duration_since(t).ok())
+            .unwrap_or_default();
+        let then = instant_before(now, downtime.as_millis() as u64);
+        Self {
+            armed: snap.armed,
+            last_dispatched: snap.last_dispatched,
+            last_dispatched_session_id: snap.last_dispatched_session_id,
+            suggestion_pending: snap.suggestion_pending,
+            dispatch_counts: snap.dispatch_counts,
+            arm_counts: snap.arm_counts,
+            dispatch_timestamps: snap.dispatch_timestamps,
+            prompt_session_map: snap.prompt_session_map,
+            slot_progress: snap.slot_progress,
+            page: snap.page,
+            pending_macro: snap.pending_macro,
+            chain_prompt_ids: snap.chain_prompt_ids,
+            chain_index: snap.chain_index,
+            sessions: snap
+                .sessions
+                .into_iter()
+                .map(|(id, s)| (id, SessionState::from_snapshot(s, then)))
+                .collect(),
+            session_tag_map: snap.session_tag_map,
+            active_session: snap.active_session,
+            terminals: snap.terminals,
+            selected_terminal_index: snap.selected_terminal_index,
+            scroll_remainder: snap.scroll_remainder,
+            terminal_tag_map: snap.terminal_tag_map,
+            last_terminal_snapshot_ts: snap.last_terminal_snapshot_ts,
+            hooks_mode: snap.hooks_mode,
+            last_hook_ts: snap.last_hook_ms_ago.map(|ms| instant_before(then, ms)),
+            recent_hook_hashes: recent_hook_hashes(),
+            unknown_session_hooks: snap.unknown_session_hooks,
+            expired_session_ids: snap.expired_session_ids,
+            seen_session_start: snap.seen_session_start,
+            current_branch: snap.current_branch,
+            hostname: snap.hostname,
+            vscode_connected: false,
+            vscode_capabilities: HashSet::new(),
+            pending_commands: snap
+                .pending_commands
+                .into_iter()
+                .map(|(id, p)| (id, (instant_before(then, p.sent_ms_ago), p.command)))
+                .collect(),
+            last_command_id: snap.last_command_id,
+            logi_connected: false,
+            per_client_dial_mode: snap.per_client_dial_mode,
+            per_client_label_max_chars: snap.per_client_label_max_chars,
+            pending_toasts: snap.pending_toasts,
+            last_ended_state: snap.last_ended_state,
+            started_at: now,
+            first_client_at: None,
+        }
+    }
+}
+
+impl Serialize for DaemonState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.snapshot().serialize(serializer)
+    }
+}
+
+impl SessionState {
+    fn to_snapshot(&self, now: Instant) -> SessionSnapshot {
+        SessionSnapshot {
+            agent_state: self.agent_state,
+            previous_agent_state: self.previous_agent_state,
+            last_tool: self.last_tool.clone(),
+            tool_call_count: self.tool_call_count,
+            pending_permission: self
+                .pending_permission
+                .as_ref()
+                .map(|p| PermissionSnapshot {
+                    tool_name: p.tool_name.clone(),
+                    description: p.description.clone(),
+                    requested_ms_ago: ms_before(now, p.requested_at),
+                }),
+            blocked_count: self.blocked_count,
+            last_prompt_text: self.last_prompt_text.clone(),
+            elicitation_prompt: self.elicitation_prompt.clone(),
+            started_ms_ago: ms_before(now, self.started_at),
+            last_hook_ms_ago: ms_before(now, self.last_hook_at),
+        }
+    }
+
+    fn from_snapshot(snap: SessionSnapshot, now: Instant) -> Self {
+        Self {
+            agent_state: snap.agent_state,
+            previous_agent_state: snap.previous_agent_state,
+            last_tool: snap.last_tool,
+            tool_call_count: snap.tool_call_count,
+            pending_permission: snap.pending_permission.map(|p| PermissionRequest {
+                tool_name: p.tool_name,
+                description: p.description,
+                requested_at: instant_before(now, p.requested_ms_ago),
+            }),
+            blocked_count: snap.blocked_count,
+            last_prompt_text: snap.last_prompt_text,
+            elicitation_prompt: snap.elicitation_prompt,
+            started_at: instant_before(now, snap.started_ms_ago),
+            last_hook_at: instant_before(now, snap.last_hook_ms_ago),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::KeypadSlotConfig;
+
+    fn state_with_session(session_id: &str) -> DaemonState {
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = HooksMode::Active;
+        state.ensure_session(session_id).agent_state = AgentState::Idle;
+        state
+    }
+
+    fn config_with_pages(names: &[&str]) -> RunbookConfig {
+        let mut config: RunbookConfig = serde_yaml::from_str("{}").unwrap();
+        config.keypad.pages = names
+            .iter()
+            .map(|name| KeypadPageConfig {
+                name: name.to_string(),
+                hotkey: None,
+                description: None,
+                on_complete_advance_to: None,
+                slots: vec![],
+            })
+            .collect();
+        config
+    }
+
+    #[test]
+    fn current_page_in_bounds() {
+        let config = config_with_pages(&["core", "review"]);
+        let state = DaemonState::new(1);
+        assert_eq!(state.current_page(&config).unwrap().name, "review");
+        assert_eq!(state.current_page_name(&config), "review");
+    }
+
+    #[test]
+    fn current_page_out_of_bounds_after_shrink() {
+        let state = DaemonState::new(1);
+        let config = config_with_pages(&["core"]);
+        assert!(state.current_page(&config).is_none());
+        assert_eq!(state.current_page_name(&config), "<unknown>");
+    }
+
+    #[test]
+    fn select_page_by_name_disarms() {
+        let config = config_with_pages(&["core", "review", "review"]);
+        let mut state = DaemonState::new(0);
+        state.armed = Some("prep_pr".to_string());
+
+        assert!(state.select_page_by_name("review", &config));
+        assert_eq!(state.page, 1, "first page with the name");
+        assert_eq!(state.armed, None);
+    }
+
+    #[test]
+    fn select_page_by_name_unknown_changes_nothing() {
+        let config = config_with_pages(&["core", "review"]);
+        let mut state = DaemonState::new(1);
+        state.armed = Some("prep_pr".to_string());
+
+        assert!(!state.select_page_by_name("ship", &config));
+        assert_eq!(state.page, 1);
+        assert_eq!(state.armed.as_deref(), Some("prep_pr"));
+    }
+
+    #[test]
+    fn page_for_prompt_searches_every_page() {
+        let mut config = config_with_pages(&["core", "review", "ship"]);
+        let slot = |id: &str| KeypadSlotConfig {
+            prompt_id: Some(id.to_string()),
+            gate: None,
+        };
+        config.keypad.pages[0].slots = vec![slot("prep_pr")];
+        config.keypad.pages[1].slots = vec![slot("break_task"), slot("review_pr")];
+        config.keypad.pages[2].slots = vec![slot("review_pr")];
+
+        let state = DaemonState::new(0);
+        assert_eq!(state.page_for_prompt("review_pr", &config), Some(1));
+        assert_eq!(state.page_for_prompt("prep_pr", &config), Some(0));
+        assert_eq!(state.page_for_prompt("missing", &config), None);
+    }
+
+    #[test]
+    fn armed_slot_index_on_current_page() {
+        let mut config = config_with_pages(&["core", "review"]);
+        let slot = |id: &str| KeypadSlotConfig {
+            prompt_id: Some(id.to_string()),
+            gate: None,
+        };
+        config.keypad.pages[0].slots = (0..9).map(|i| slot(&format!("p{i}"))).collect();
+        config.keypad.pages[1].slots = vec![slot("review_pr")];
+
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.armed_slot_index(&config), None);
+
+        state.armed = Some("p0".to_string());
+        assert_eq!(state.armed_slot_index(&config), Some(0));
+        state.armed = Some("p8".to_string());
+        assert_eq!(state.armed_slot_index(&config), Some(8));
+
+        // Armed, but on another page.
+        state.armed = Some("review_pr".to_string());
+        assert_eq!(state.armed_slot_index(&config), None);
+        state.page = 1;
+        assert_eq!(state.armed_slot_index(&config), Some(0));
+    }
+
+    #[test]
+    fn first_session_activates_hooks_mode() {
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.current_agent_state(), AgentState::Unknown);
+
+        state.ensure_session("sess1").agent_state = AgentState::Idle;
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
+    }
+
+    #[test]
+    fn vscode_supports_only_declared_capabilities() {
+        let mut state = DaemonState::new(0);
+        assert!(!state.vscode_supports(VSCODE_TERMINAL_INDEX));
+
+        state.vscode_capabilities = [VSCODE_TERMINAL_INDEX.to_string()].into_iter().collect();
+        assert!(state.vscode_supports(VSCODE_TERMINAL_INDEX));
+        assert!(!state.vscode_supports("snippet_insert"));
+    }
+
+    #[test]
+    fn current_page_with_no_pages() {
+        let state = DaemonState::new(0);
+        let config = config_with_pages(&[]);
+        assert!(state.current_page(&config).is_none());
+        assert_eq!(state.current_page_name(&config), "<unknown>");
+    }
+
+    #[test]
+    fn render_with_no_pages_is_an_empty_page() {
+        let state = DaemonState::new(0);
+        let config = config_with_pages(&[]);
+        let model = crate::render::build_render_model(&state, &config);
+        assert_eq!((model.page_index, model.page_count), (0, 0));
+        assert!(model.keypad.slots.is_empty());
+        let page = crate::render::build_page_render(&state, &config);
+        assert!(page.slots.is_empty());
+        assert_eq!(page.page_name, "");
+    }
+
+    #[test]
+    fn set_active_session_by_tag_uses_learned_tags() {
+        let mut state = state_with_session("sess1");
+        state.learn_session_tag("tag-a", "sess1");
+
+        assert!(!state.set_active_session_by_tag("tag-b"));
+        assert!(state.active_session.is_none());
+
+        assert!(state.set_active_session_by_tag("tag-a"));
+        assert_eq!(state.active_session.as_deref(), Some("sess1"));
+
+        // Ending the session forgets its tag.
+        state.remove_session("sess1");
+        assert!(!state.set_active_session_by_tag("tag-a"));
+    }
+
+    #[test]
+    fn ack_clears_pending_command() {
+        let now = Instant::now();
+        let mut state = DaemonState::new(0);
+        state.vscode_connected = true;
+        state.vscode_capabilities.insert(VSCODE_COMMAND_ACK.to_string());
+        let mut first = VscodeCommand::open_uri("a");
+        let mut second = VscodeCommand::open_uri("b");
+        state.track_command(&mut first, now);
+        state.track_command(&mut second, now);
+        assert_eq!((first.command_id, second.command_id), (1, 2));
+        assert_eq!(state.pending_commands.len(), 2);
+
+        assert!(state.ack_command(1).is_some());
+        assert!(state.ack_command(1).is_none());
+        assert_eq!(state.pending_commands.keys().collect::<Vec<_>>(), [&2]);
+    }
+
+    #[test]
+    fn commands_are_not_held_without_vscode() {
+        let mut state = DaemonState::new(0);
+        let mut cmd = VscodeCommand::open_uri("a");
+        assert!(!state.track_command(&mut cmd, Instant::now()));
+        assert_eq!(cmd.command_id, 1);
+        assert!(state.pending_commands.is_empty());
+    }
+
+    #[test]
+    fn commands_are_not_held_unless_vscode_acks() {
+        let mut state = DaemonState::new(0);
+        state.vscode_connected = true;
+        let mut cmd = VscodeCommand::open_uri("a");
+        assert!(!state.track_command(&mut cmd, Instant::now()));
+        assert!(state.pending_commands.is_empty());
+
+        state.vscode_capabilities.insert(VSCODE_COMMAND_ACK.to_string());
+        assert!(state.track_command(&mut cmd, Instant::now()));
+        assert_eq!(state.pending_commands.len(), 1);
+    }
+
+    #[test]
+    fn unacked_commands_expire_after_timeout() {
+        let now = Instant::now();
+        let mut state = DaemonState::new(0);
+        state.vscode_connected = true;
+        state.vscode_capabilities.insert(VSCODE_COMMAND_ACK.to_string());
+        let mut old = VscodeCommand::open_uri("old");
+        let mut fresh = VscodeCommand::open_uri("fresh");
+        state.track_command(&mut old, now);
+        state.track_command(&mut fresh, now + Duration::from_secs(4));
+
+        let expired = state.expire_commands(Duration::from_secs(5), now + Duration::from_secs(6));
+        assert_eq!(expired.iter().map(|c| c.command_id).collect::<Vec<_>>(), [1]);
+        assert_eq!(state.pending_commands.keys().collect::<Vec<_>>(), [&2]);
+    }
+
+    #[test]
+    fn pending_toasts_keep_the_newest() {
+        let toast = |i: usize| ToastMessage {
+            message: format!("toast {i}"),
+            duration_ms: 3000,
+            severity: Default::default(),
+        };
+        let mut state = DaemonState::new(0);
+        for i in 0..7 {
+            state.hold_toast(toast(i));
+        }
+        let held: Vec<&str> = state.pending_toasts.iter().map(|t| t.message.as_str()).collect();
+        assert_eq!(held, ["toast 2", "toast 3", "toast 4", "toast 5", "toast 6"]);
+
+        // The plugin sees it live; nothing to hold.
+        state.logi_connected = true;
+        state.hold_toast(toast(7));
+        assert_eq!(state.pending_toasts.len(), PENDING_TOAST_LIMIT);
+    }
+
+    #[test]
+    fn remember_hook_drops_repeats_of_recent_events() {
+        let mut state = DaemonState::new(0);
+        assert!(state.remember_hook(1));
+        assert!(state.remember_hook(2));
+        assert!(!state.remember_hook(1), "repeat with another event in between");
+
+        // 1 was just seen again, so 2 is the oldest and is pushed out first.
+        for fingerprint in 100..100 + RECENT_HOOK_LIMIT as u64 - 1 {
+            assert!(state.remember_hook(fingerprint));
+        }
+        assert!(!state.remember_hook(1));
+        assert!(state.remember_hook(2), "pushed out, so new again");
+    }
+
+    #[test]
+    fn most_active_session_is_the_latest_hook() {
+        let now = Instant::now() + Duration::from_secs(100);
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.most_active_session_id(), None);
+
+        let s1 = state.ensure_session("s1");
+        s1.agent_state = AgentState::Idle;
+        s1.last_hook_at = now;
+        let s2 = state.ensure_session("s2");
+        s2.agent_state = AgentState::Running;
+        s2.last_hook_at = now - Duration::from_secs(5);
+        assert_eq!(state.most_active_session_id().as_deref(), Some("s1"));
+        // No terminal correlation, so the most active session is shown.
+        assert_eq!(state.current_agent_state(), AgentState::Idle);
+
+        state.sessions.get_mut("s2").unwrap().last_hook_at = now + Duration::from_secs(1);
+        assert_eq!(state.most_active_session_id().as_deref(), Some("s2"));
+        assert_eq!(state.current_agent_state(), AgentState::Running);
+        // The rendered session follows the same fallback; attribution doesn't guess.
+        assert_eq!(state.current_session().map(|s| s.agent_state), Some(AgentState::Running));
+        assert_eq!(state.current_session_id(), None);
+    }
+
+    #[test]
+    fn expired_sessions_are_those_quiet_past_the_ttl() {
+        // Ahead of the sessions' start, so the offsets below can't underflow.
+        let now = Instant::now() + Duration::from_secs(1000);
+        let mut state = DaemonState::new(0);
+        state.ensure_session("fresh").last_hook_at = now - Duration::from_secs(30);
+        state.ensure_session("stale_b").last_hook_at = now - Duration::from_secs(700);
+        state.ensure_session("stale_a").last_hook_at = now - Duration::from_secs(601);
+
+        let ttl = Duration::from_secs(600);
+        assert_eq!(state.expired_sessions(ttl, now), ["stale_a", "stale_b"]);
+        assert!(state.expired_sessions(Duration::from_secs(700), now).is_empty());
+    }
+
+    #[test]
+    fn expiring_a_session_keeps_other_sessions_prompts() {
+        let mut state = state_with_session("quiet");
+        state.associate_prompt("prep_pr");
+        state.ensure_session("busy");
+        state.active_session = Some("busy".to_string());
+        state.associate_prompt("break_task");
+        state.armed = Some("break_task".to_string());
+        state.last_dispatched = Some("break_task".to_string());
+        state.last_dispatched_session_id = Some("busy".to_string());
+
+        state.expire_session("quiet");
+        assert!(!state.sessions.contains_key("quiet"));
+        assert!(state.expired_session_ids.contains("quiet"));
+        assert_eq!(state.last_ended_state, None, "not ended, just quiet");
+        assert_eq!(state.armed.as_deref(), Some("break_task"));
+        assert_eq!(state.last_dispatched.as_deref(), Some("break_task"));
+
+        state.expire_session("busy");
+        assert_eq!(state.armed, None);
+        assert_eq!(state.last_dispatched, None);
+        assert_eq!(state.last_dispatched_session_id, None);
+    }
+
+    #[test]
+    fn reset_for_new_session_clears_session_state() {
+        let mut state = state_with_session("sess1");
+        state.associate_prompt("prep_pr");
+        state.dispatch_counts.insert("prep_pr".to_string(), 3);
+        state.last_dispatched = Some("prep_pr".to_string());
+        state.armed = Some("prep_pr".to_string());
+
+        state.reset_for_new_session("sess1");
+
+        assert!(state.sessions.is_empty());
+        assert!(state.last_dispatched.is_none());
+        assert!(state.armed.is_none());
+        assert!(state.dispatch_counts.is_empty());
+        assert!(state.prompt_session_map.is_empty());
+    }
+
+    #[test]
+    fn hooks_quiet_for_counts_from_last_hook_or_startup() {
+        let t0 = Instant::now();
+        let mut state = DaemonState::new_at(0, t0);
+        let later = t0 + Duration::from_secs(90);
+        assert_eq!(state.hooks_quiet_for(later), Some(Duration::from_secs(90)));
+
+        state.last_hook_ts = Some(t0 + Duration::from_secs(30));
+        state.hooks_mode = HooksMode::Active;
+        assert_eq!(state.hooks_quiet_for(later), None);
+
+        state.hooks_mode = HooksMode::Stale;
+        assert_eq!(state.hooks_quiet_for(later), Some(Duration::from_secs(60)));
+
+        state.hooks_mode = HooksMode::Degraded {
+            reason: "gaps".to_string(),
+        };
+        assert_eq!(state.hooks_quiet_for(later), None);
+    }
+
+    #[test]
+    fn reset_for_new_session_keeps_dispatch_to_another_session() {
+        let mut state = state_with_session("sess1");
+        state.last_dispatched = Some("prep_pr".to_string());
+        state.last_dispatched_session_id = Some("sess1".to_string());
+
+        state.reset_for_new_session("sess2");
+        assert_eq!(state.last_dispatched.as_deref(), Some("prep_pr"));
+        assert_eq!(state.last_dispatched_session_id.as_deref(), Some("sess1"));
+
+        state.reset_for_new_session("sess1");
+        assert!(state.last_dispatched.is_none());
+        assert!(state.last_dispatched_session_id.is_none());
+    }
+
+    #[test]
+    fn reset_for_new_session_keeps_other_sessions_armed() {
+        let mut state = state_with_session("sess1");
+        state.associate_prompt("prep_pr");
+        state.dispatch_counts.insert("prep_pr".to_string(), 2);
+        state.armed = Some("prep_pr".to_string());
+
+        state.reset_for_new_session("sess2");
+
+        assert_eq!(state.armed.as_deref(), Some("prep_pr"));
+        assert_eq!(state.dispatch_counts.get("prep_pr"), Some(&2));
+        assert!(state.sessions.contains_key("sess1"));
+    }
+
+    #[test]
+    fn permission_request_prefers_message_then_tool_input() {
+        let req = PermissionRequest::from_payload(&serde_json::json!({
+            "tool_name": "Bash",
+            "tool_input": {"command": "cargo publish"}
+        }));
+        assert_eq!(req.tool_name, "Bash");
+        assert_eq!(req.description, "cargo publish");
+
+        let req = PermissionRequest::from_payload(&serde_json::json!({
+            "message": "Claude needs your permission to use Write"
+        }));
+        assert_eq!(req.tool_name, "");
+        assert_eq!(req.description, "Claude needs your permission to use Write");
+    }
+
+    fn terminal(index: usize, name: &str, tag: Option<&str>) -> TerminalInfo {
+        TerminalInfo {
+            index,
+            name: name.to_string(),
+            session_tag: tag.map(str::to_string),
+            is_claude_session: tag.is_some(),
+        }
+    }
+
+    #[test]
+    fn active_terminal_helpers_with_no_terminals() {
+        let state = DaemonState::new(0);
+        assert_eq!(state.active_terminal_name(), None);
+        assert_eq!(state.active_terminal_session_tag(), None);
+    }
+
+    #[test]
+    fn active_terminal_helpers_in_bounds() {
+        let mut state = DaemonState::new(0);
+        state.terminals = vec![
+            terminal(0, "bash", None),
+            terminal(1, "Claude", Some("tag-001")),
+        ];
+        state.selected_terminal_index = 1;
+        assert_eq!(state.active_terminal_name(), Some("Claude"));
+        assert_eq!(state.active_terminal_session_tag(), Some("tag-001"));
+
+        state.selected_terminal_index = 0;
+        assert_eq!(state.active_terminal_name(), Some("bash"));
+        assert_eq!(state.active_terminal_session_tag(), None);
+    }
+
+    #[test]
+    fn active_terminal_helpers_after_list_shrinks() {
+        let mut state = DaemonState::new(0);
+        state.terminals = vec![terminal(0, "bash", None), terminal(1, "Claude", Some("t"))];
+        state.selected_terminal_index = 1;
+        state.terminals.truncate(1);
+        assert_eq!(state.active_terminal_name(), None);
+        assert_eq!(state.active_terminal_session_tag(), None);
+    }
+
+    #[test]
+    fn resolve_url_template_fills_session_tag_and_branch() {
+        let mut state = state_with_session("s1");
+        let template = "https://ci.example/{git_branch}/{session_tag}";
+        assert_eq!(state.resolve_url_template(template), "https://ci.example//");
+
+        state.current_branch = Some("feat/dial".to_string());
+        state.learn_session_tag("tag-001", "s1");
+        assert_eq!(
+            state.resolve_url_template(template),
+            "https://ci.example/feat/dial/tag-001"
+        );
+
+        // The selected terminal's tag wins over the learned one.
+        state.terminals = vec![terminal(0, "Claude", Some("tag-002"))];
+        assert_eq!(
+            state.resolve_url_template("{session_tag}?pr=1"),
+            "tag-002?pr=1"
+        );
+    }
+
+    #[test]
+    fn resolve_url_template_fills_hostname() {
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.resolve_url_template("http://{hostname}:3000"), "http://:3000");
+
+        state.hostname = Some("devbox".to_string());
+        assert_eq!(
+            state.resolve_url_template("http://{hostname}:3000"),
+            "http://devbox:3000"
+        );
+        assert_eq!(state.resolve_url_template("no placeholders"), "no placeholders");
+    }
+
+    #[test]
+    fn terminal_lookups_with_no_terminals() {
+        let state = DaemonState::new(0);
+        assert!(state.terminal_by_index(0).is_none());
+        assert!(state.terminal_by_tag("tag-001").is_none());
+        assert_eq!(state.terminal_count(), 0);
+        assert_eq!(state.claude_terminal_count(), 0);
+    }
+
+    #[test]
+    fn terminal_lookups_with_one_terminal() {
+        let mut state = DaemonState::new(0);
+        state.terminals = vec![terminal(2, "Claude", Some("tag-001"))];
+        assert_eq!(state.terminal_by_index(2).unwrap().name, "Claude");
+        assert!(state.terminal_by_index(0).is_none(), "by terminal index, not position");
+        assert_eq!(state.terminal_by_tag("tag-001").unwrap().index, 2);
+        assert!(state.terminal_by_tag("tag-002").is_none());
+        assert_eq!(state.terminal_count(), 1);
+        assert_eq!(state.claude_terminal_count(), 1);
+    }
+
+    #[test]
+    fn terminal_lookups_with_several_terminals() {
+        let mut state = DaemonState::new(0);
+        state.terminals = vec![
+            terminal(0, "bash", None),
+            terminal(1, "Claude", Some("tag-001")),
+            terminal(4, "Claude 2", Some("tag-002")),
+            terminal(5, "Claude 3", Some("tag-002")),
+        ];
+        assert_eq!(state.terminal_by_index(4).unwrap().name, "Claude 2");
+        assert!(state.terminal_by_index(3).is_none());
+        assert_eq!(state.terminal_by_tag("tag-002").unwrap().index, 4, "first match");
+        assert_eq!(state.terminal_count(), 4);
+        assert_eq!(state.claude_terminal_count(), 3);
+    }
+
+    #[test]
+    fn resolve_target_passes_through_index_and_active() {
+        let state = DaemonState::new(0);
+        assert_eq!(
+            state.resolve_target(&TerminalTarget::ByIndex(2)),
+            ResolvedTarget::Index(2)
+        );
+        assert_eq!(
+            state.resolve_target(&TerminalTarget::ActiveClaude),
+            ResolvedTarget::ActiveClaude
+        );
+        assert_eq!(
+            state.resolve_target(&TerminalTarget::Active),
+            ResolvedTarget::Active
+        );
+    }
+
+    #[test]
+    fn resolve_target_by_tag_finds_tagged_terminal() {
+        let mut state = DaemonState::new(0);
+        state.terminals = vec![terminal(0, "bash", None), terminal(1, "Claude", Some("tag-001"))];
+
+        let resolved = state.resolve_target(&TerminalTarget::ByTag("tag-001".to_string()));
+        assert_eq!(resolved, ResolvedTarget::Index(1));
+        assert_eq!(TerminalTarget::from(resolved), TerminalTarget::ByIndex(1));
+
+        assert_eq!(
+            state.resolve_target(&TerminalTarget::ByTag("missing".to_string())),
+            ResolvedTarget::UnknownTag("missing".to_string())
+        );
+    }
+
+    #[test]
+    fn resolve_target_by_name_scans_terminals() {
+        let mut state = DaemonState::new(0);
+        state.terminals = vec![terminal(0, "bash", None), terminal(3, "Claude", None)];
+
+        assert_eq!(
+            state.resolve_target(&TerminalTarget::ByName("Claude".to_string())),
+            ResolvedTarget::Index(3)
+        );
+        assert_eq!(
+            state.resolve_target(&TerminalTarget::ByName("zsh".to_string())),
+            ResolvedTarget::Name("zsh".to_string())
+        );
+    }
+
+    #[test]
+    fn uptime_counts_from_started_at() {
+        let started = Instant::now() - Duration::from_secs(90);
+        let state = DaemonState::new_at(0, started);
+        let uptime = state.uptime();
+        assert!(uptime >= Duration::from_secs(90), "{uptime:?}");
+        assert!(uptime < Duration::from_secs(100), "{uptime:?}");
+
+        assert!(DaemonState::new(0).uptime() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn first_client_time_is_kept() {
+        let mut state = DaemonState::new(0);
+        assert!(state.first_client_at.is_none());
+
+        let first = Instant::now();
+        state.note_client_connected(first);
+        state.note_client_connected(first + Duration::from_secs(5));
+        assert_eq!(state.first_client_at, Some(first));
+    }
+
+    fn steps(ids: &[&str]) -> Vec<MacroStep> {
+        ids.iter()
+            .map(|id| MacroStep {
+                prompt_id: id.to_string(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn macro_steps_come_out_in_order() {
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.next_macro_step(), None);
+
+        state.start_macro(steps(&["a", "b"]));
+        assert_eq!(state.next_macro_step().unwrap().prompt_id, "a");
+        assert_eq!(state.pending_macro.as_ref().map(|q| q.len()), Some(1));
+        assert_eq!(state.next_macro_step().unwrap().prompt_id, "b");
+        assert!(state.pending_macro.is_none());
+        assert_eq!(state.next_macro_step(), None);
+    }
+
+    #[test]
+    fn start_macro_replaces_pending_steps() {
+        let mut state = DaemonState::new(0);
+        state.start_macro(steps(&["a", "b"]));
+        state.start_macro(steps(&["c"]));
+        assert_eq!(state.next_macro_step().unwrap().prompt_id, "c");
+
+        state.start_macro(steps(&["d"]));
+        state.start_macro(Vec::new());
+        assert!(state.pending_macro.is_none());
+    }
+
+    #[test]
+    fn chain_steps_advance_the_index() {
+        let mut state = DaemonState::new(0);
+        assert_eq!(state.next_chain_step(), None);
+
+        state.start_chain(vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(state.chain_index, Some(0));
+        assert_eq!(state.next_chain_step().as_deref(), Some("a"));
+        assert_eq!(state.chain_index, Some(1));
+        assert_eq!(state.next_chain_step().as_deref(), Some("b"));
+        assert_eq!(state.chain_index, None);
+        assert!(state.chain_prompt_ids.is_empty());
+        assert_eq!(state.next_chain_step(), None);
+    }
+
+    #[test]
+    fn chains_and_macros_replace_each_other() {
+        let mut state = DaemonState::new(0);
+        state.start_macro(steps(&["a"]));
+        state.start_chain(vec!["b".to_string()]);
+        assert!(state.pending_macro.is_none());
+
+        state.start_macro(steps(&["c"]));
+        assert_eq!(state.chain_index, None);
+        assert!(state.chain_prompt_ids.is_empty());
+    }
+
+    /// Two sessions; `s2` is tagged `tag-002` and shown on the device.
+    fn claude_terminal_state() -> DaemonState {
+        let mut state = state_with_session("s1");
+        state.ensure_session("s2");
+        state.learn_session_tag("tag-002", "s2");
+        state.active_session = Some("s2".to_string());
+        state
+    }
+
+    fn set_terminals(state: &mut DaemonState, terminals: Vec<TerminalInfo>) {
+        state.terminal_tag_map = terminals
+            .iter()
+            .filter_map(|t| Some((t.index, t.session_tag.clone()?)))
+            .collect();
+        state.terminals = terminals;
+    }
+
+    #[test]
+    fn active_claude_index_prefers_current_session_terminal() {
+        let mut state = claude_terminal_state();
+        set_terminals(
+            &mut state,
+            vec![
+                terminal(0, "bash", None),
+                terminal(1, "Claude", Some("tag-001")),
+                terminal(2, "Claude", Some("tag-002")),
+            ],
+        );
+        assert_eq!(state.resolve_active_claude_index(), Some(2));
+    }
+
+    #[test]
+    fn active_claude_index_falls_back_to_any_tagged_terminal() {
+        let mut state = claude_terminal_state();
+        set_terminals(
+            &mut state,
+            vec![
+                terminal(0, "bash", None),
+                terminal(1, "Claude", Some("tag-001")),
+                terminal(2, "Claude", Some("tag-003")),
+            ],
+        );
+        assert_eq!(state.resolve_active_claude_index(), Some(1));
+
+        // A tagged selected terminal wins over the first tagged one.
+        state.selected_terminal_index = 2;
+        assert_eq!(state.resolve_active_claude_index(), Some(2));
+    }
+
+    #[test]
+    fn active_claude_index_falls_back_to_selected_terminal() {
+        let mut state = claude_terminal_state();
+        assert_eq!(state.resolve_active_claude_index(), None);
+
+        set_terminals(
+            &mut state,
+            vec![terminal(0, "bash", None), terminal(1, "zsh", None)],
+        );
+        state.selected_terminal_index = 1;
+        assert_eq!(state.resolve_active_claude_index(), Some(1));
+    }
+
+    #[test]
+    fn snapshot_round_trips_through_json() {
+        let mut state = state_with_session("sess1");
+        state.armed = Some("prep_pr".to_string());
+        state.associate_prompt("prep_pr");
+        state.dispatch_counts.insert("prep_pr".to_string(), 2);
+        state.start_macro(steps(&["break_task"]));
+        state.learn_session_tag("tag-001", "sess1");
+        state.terminals = vec![terminal(0, "Claude", Some("tag-001"))];
+        state.terminal_tag_map.insert(0, "tag-001".to_string());
+        state.last_hook_ts = Some(Instant::now());
+        state.vscode_connected = true;
+        state.suggestion_pending = true;
+        state.scroll_remainder = 0.5;
+        state.current_branch = Some("main".to_string());
+        state.per_client_dial_mode.insert(ClientKind::Logi, DialMode::VscodeTerminalScroll);
+        state.per_client_label_max_chars.insert(ClientKind::Logi, 8);
+        state.hold_toast(ToastMessage {
+            message: "held".to_string(),
+            duration_ms: 3000,
+            severity: Default::default(),
+        });
+        {
+            let session = state.ensure_session("sess1");
+            session.tool_call_count = 4;
+            session.last_prompt_text = Some("prep a PR".to_string());
+            session.pending_permission = Some(PermissionRequest::from_payload(
+                &serde_json::json!({"tool_name": "Bash", "message": "rm"}),
+            ));
+        }
+
+        let now = Instant::now();
+        let json = serde_json::to_string(&state.snapshot_at(now)).unwrap();
+        let snap: DaemonStateSnapshot = serde_json::from_str(&json).unwrap();
+        let restored = DaemonState::from_snapshot(snap, now);
+
+        assert_eq!(restored.armed.as_deref(), Some("prep_pr"));
+        assert_eq!(restored.dispatch_counts.get("prep_pr"), Some(&2));
+        assert_eq!(restored.pending_macro, state.pending_macro);
+        assert_eq!(restored.prompt_session_map, state.prompt_session_map);
+        assert_eq!(restored.session_tag_map, state.session_tag_map);
+        assert_eq!(restored.terminal_tag_map, state.terminal_tag_map);
+        assert_eq!(restored.hooks_mode, HooksMode::Active);
+        assert!(restored.last_hook_ts.is_some());
+        assert!(!restored.vscode_connected);
+        assert!(restored.suggestion_pending);
+        assert_eq!(restored.scroll_remainder, 0.5);
+        assert_eq!(restored.current_branch.as_deref(), Some("main"));
+        assert_eq!(
+            restored.per_client_dial_mode[&ClientKind::Logi],
+            DialMode::VscodeTerminalScroll
+        );
+        assert_eq!(restored.pending_toasts.len(), 1);
+
+        let session = &restored.sessions["sess1"];
+        assert_eq!(session.agent_state, AgentState::Idle);
+        assert_eq!(session.tool_call_count, 4);
+        assert_eq!(session.last_prompt_text.as_deref(), Some("prep a PR"));
+        assert_eq!(session.pending_permission.as_ref().unwrap().description, "rm");
+        assert_eq!(restored.current_agent_state(), AgentState::Idle);
+        assert_eq!(restored.snapshot_at(now), state.snapshot_at(now));
+        // `DaemonState` serializes as its snapshot.
+        assert_eq!(serde_json::to_value(&state).unwrap()["armed"], "prep_pr");
+    }
+
+    #[test]
+    fn snapshots_measure_ages_from_one_reference() {
+        let now = Instant::now();
+        let build = |at: Instant| {
+            let mut state = DaemonState::new_at(0, at);
+            state.last_hook_ts = Some(at - Duration::from_millis(300));
+            state.ensure_session("sess1").started_at = at - Duration::from_millis(900);
+            state.armed = Some("prep_pr".to_string());
+            state
+        };
+        // Built 1.5s apart, across a second boundary: same ages from each
+        // state's own reference.
+        let earlier = now - Duration::from_millis(1500);
+        let (a, b) = (build(now), build(earlier));
+        assert_ne!(a.started_at, b.started_at);
+        assert_eq!(a.snapshot_at(now), b.snapshot_at(earlier));
+        assert_eq!(a.snapshot_at(now).last_hook_ms_ago, Some(300));
+        assert_eq!(a.snapshot_at(now).sessions["sess1"].started_ms_ago, 900);
+
+        // From one reference, the 1.5s shows up.
+        assert_ne!(a.snapshot_at(now), b.snapshot_at(now));
+
+        let mut c = build(now);
+        c.armed = None;
+        assert_ne!(a.snapshot_at(now), c.snapshot_at(now));
+        let mut d = build(now);
+        d.vscode_capabilities.insert(VSCODE_TERMINAL_INDEX.to_string());
+        assert_ne!(a.snapshot_at(now), d.snapshot_at(now));
+        assert_eq!(DaemonStateSnapshot::from(&a).armed, a.armed);
+    }
+
+    #[test]
+    fn from_snapshot_measures_ages_back_from_now() {
+        let mut snap = DaemonState::new(0).snapshot();
+        snap.last_hook_ms_ago = Some(30_000);
+        let now = Instant::now();
+        let restored = DaemonState::from_snapshot(snap, now);
+        let ts = restored.last_hook_ts.unwrap();
+        assert!(ts <= now);
+        assert!(now.duration_since(ts) <= Duration::from_secs(30));
+    }
+
+    #[test]
+    fn from_snapshot_adds_time_since_saved() {
+        let mut snap = DaemonState::new(0).snapshot();
+        snap.last_hook_ms_ago = Some(30_000);
+        snap.saved_at = Some(SystemTime::now() - Duration::from_secs(60));
+        let now = Instant::now();
+        let restored = DaemonState::from_snapshot(snap, now);
+        let age = now.duration_since(restored.last_hook_ts.unwrap());
+        assert!(age >= Duration::from_secs(90), "{age:?}");
+        assert!(age < Duration::from_secs(91), "{age:?}");
+    }
+
+    #[test]
+    fn restore_keeps_expired_sessions_and_pending_commands() {
+        let now = Instant::now();
+        let mut state = state_with_session("sess1");
+        state.vscode_connected = true;
+        state.vscode_capabilities.insert(VSCODE_COMMAND_ACK.to_string());
+        let mut cmd = VscodeCommand::send_text(TerminalTarget::ActiveClaude, "hi", true);
+        assert!(state.track_command(&mut cmd, now - Duration::from_millis(400)));
+        state.expire_session("sess1");
+
+        let json = serde_json::to_string(&state.snapshot_at(now)).unwrap();
+        let snap: DaemonStateSnapshot = serde_json::from_str(&json).unwrap();
+        assert_eq!(snap.pending_commands[&1].sent_ms_ago, 400);
+        let mut restored = DaemonState::from_snapshot(snap, now);
+
+        assert!(restored.expired_session_ids.contains("sess1"));
+        assert_eq!(restored.last_command_id, 1);
+        let (sent_at, pending) = &restored.pending_commands[&1];
+        assert_eq!(now.duration_since(*sent_at), Duration::from_millis(400));
+        assert_eq!(pending, &cmd);
+
+        // Ids carry on from the restored counter.
+        let mut next = VscodeCommand::send_text(TerminalTarget::ActiveClaude, "again", true);
+        restored.track_command(&mut next, now);
+        assert_eq!(next.command_id, 2);
+    }
+
+    const STALE: Duration = Duration::from_secs(60);
+
+    #[test]
+    fn hooks_mode_absent_to_active() {
+        let mut state = DaemonState::new(0);
+        let now = Instant::now();
+        state.update_hooks_mode(now, STALE);
+        assert_eq!(state.hooks_mode, HooksMode::Absent);
+
+        state.last_hook_ts = Some(now);
+        state.update_hooks_mode(now, STALE);
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+    }
+
+    #[test]
+    fn hooks_mode_active_to_stale_only_without_sessions() {
+        let mut state = state_with_session("sess1");
+        let now = Instant::now();
+        state.last_hook_ts = Some(now);
+        let later = now + STALE * 2;
+
+        state.update_hooks_mode(later, STALE);
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+
+        state.remove_session("sess1");
+        state.update_hooks_mode(now + STALE / 2, STALE);
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+
+        state.unknown_session_hooks = 2;
+        state.update_hooks_mode(later, STALE);
+        assert_eq!(state.hooks_mode, HooksMode::Stale);
+        assert_eq!(state.unknown_session_hooks, 0);
+    }
+
+    #[test]
+    fn hooks_mode_stale_to_active() {
+        let mut state = DaemonState::new(0);
+        state.hooks_mode = HooksMode::Stale;
+        let now = Instant::now() + STALE * 3;
+        state.update_hooks_mode(now, STALE);
+        assert_eq!(state.hooks_mode, HooksMode::Stale);
+
+        state.last_hook_ts = Some(now);
+        state.update_hooks_mode(now, STALE);
+        assert_eq!(state.hooks_mode, HooksMode::Active);
+    }
+}
@@ -4,11 +4,13 @@
 //! covering the four acceptance criteria: safety, truthfulness,
 //! multi-session honesty, and gating.
 
+mod common;
+
 use cucumber::{given, then, when, World as _};
 use runbookd::config::RunbookConfig;
 use runbookd::reducer::{self, Event, SideEffect};
 use runbookd::state::DaemonState;
-use runbook_protocol::{AgentState, DialpadButton, HooksMode, TerminalInfo, TerminalsSnapshot};
+use runbook_protocol::{DialpadButton, HooksMode, MacroStep, PageDirection, TerminalInfo};
 
 // ---------------------------------------------------------------------------
 // World — the BDD test state container
@@ -68,7 +70,8 @@ impl DaemonWorld {
 }
 
 fn sample_config() -> RunbookConfig {
-    let yaml = r#"
+    let yaml = format!(
+        r#"
 keypad:
   pages:
     - name: core
@@ -76,12 +79,17 @@ keypad:
         - prompt_id: prep_pr
         - prompt_id: break_task
         - prompt_id: scratch_note
-        - {}
-        - {}
-        - {}
-        - {}
-        - {}
+        - {{}}
+        - {{}}
+        - {{}}
+        - {{}}
+        - {{}}
         - gate: pr
+    - name: review
+      hotkey: p2
+      slots: {empty}
+    - name: ship
+      slots: {empty}
 prompts:
   prep_pr:
     label: "PREP PR"
@@ -100,8 +108,17 @@ gates:
     label: "PR"
     sublabel: "jump"
     action: open_pr
-"#;
-    serde_yaml::from_str(yaml).unwrap()
+"#,
+        empty = common::empty_slots(9)
+    );
+    serde_yaml::from_str(&yaml).unwrap()
+}
+
+/// Prompts used for macro step N (1-based), cycling.
+const MACRO_PROMPTS: &[&str] = &["break_task", "prep_pr", "scratch_note"];
+
+fn macro_prompt(step: usize) -> &'static str {
+    MACRO_PROMPTS[(step - 1) % MACRO_PROMPTS.len()]
 }
 
 // ===========================================================================
@@ -113,6 +130,11 @@ async fn fresh_daemon(w: &mut DaemonWorld) {
     *w = DaemonWorld::default();
 }
 
+#[given("Claude Code hooks are active")]
+async fn hooks_active(w: &mut DaemonWorld) {
+    w.state.hooks_mode = HooksMode::Active;
+}
+
 #[given(expr = "the operator has armed {string}")]
 async fn operator_armed(w: &mut DaemonWorld, prompt_id: String) {
     w.effects.clear();
@@ -120,6 +142,17 @@ async fn operator_armed(w: &mut DaemonWorld, prompt_id: String) {
     w.effects.clear(); // Clear arming effects; we only want to observe the next action.
 }
 
+#[given(expr = "a macro with {int} step(s)")]
+async fn macro_with_steps(w: &mut DaemonWorld, n: usize) {
+    let steps = (1..=n)
+        .map(|i| MacroStep {
+            prompt_id: macro_prompt(i).to_string(),
+        })
+        .collect();
+    w.apply(Event::StartMacro { steps });
+    w.effects.clear();
+}
+
 // ===========================================================================
 // When steps
 // ===========================================================================
@@ -170,6 +203,7 @@ async fn hook_with_matcher(w: &mut DaemonWorld, hook: String, matcher: String, s
         matcher: Some(matcher),
         session_id: Some(session),
         session_tag: None,
+        payload: serde_json::Value::Null,
     });
 }
 
@@ -181,6 +215,7 @@ async fn hook_no_matcher(w: &mut DaemonWorld, hook: String, session: String) {
         matcher: None,
         session_id: Some(session),
         session_tag: None,
+        payload: serde_json::Value::Null,
     });
 }
 
@@ -198,6 +233,7 @@ async fn hook_with_tag(
         matcher: Some(matcher),
         session_id: Some(session),
         session_tag: Some(tag),
+        payload: serde_json::Value::Null,
     });
 }
 
@@ -214,9 +250,62 @@ async fn hook_no_matcher_with_tag(
         matcher: None,
         session_id: Some(session),
         session_tag: Some(tag),
+        payload: serde_json::Value::Null,
+    });
+}
+
+#[when(expr = "a hook arrives for session {string} with session_tag {string}")]
+async fn hook_with_session_tag(w: &mut DaemonWorld, session: String, tag: String) {
+    w.effects.clear();
+    w.apply(Event::HookEvent {
+        hook: "PreToolUse".to_string(),
+        matcher: Some("Bash".to_string()),
+        session_id: Some(session),
+        session_tag: Some(tag),
+        payload: serde_json::json!({"tool_name": "Bash"}),
+    });
+}
+
+#[when(expr = "the task completes {int} time(s)")]
+async fn task_completes(w: &mut DaemonWorld, n: usize) {
+    w.effects.clear();
+    // Each is its own task, so none is taken for a repeated delivery.
+    for task_id in 0..n {
+        w.apply(Event::HookEvent {
+            hook: "TaskCompleted".to_string(),
+            matcher: None,
+            session_id: Some("sess1".to_string()),
+            session_tag: None,
+            payload: serde_json::json!({"task_id": task_id.to_string()}),
+        });
+    }
+}
+
+#[when(expr = "the operator jumps to page with hotkey {string}")]
+async fn jump_to_page(w: &mut DaemonWorld, hotkey: String) {
+    w.effects.clear();
+    w.apply(Event::PageJump { hotkey });
+}
+
+#[when(expr = "page jump to index {int} arrives")]
+async fn page_jump_to_index(w: &mut DaemonWorld, index: usize) {
+    w.effects.clear();
+    w.apply(Event::PageNav {
+        direction: PageDirection::JumpTo { index },
     });
 }
 
+#[when(expr = "the operator pages {word}")]
+async fn page_nav(w: &mut DaemonWorld, direction: String) {
+    w.effects.clear();
+    let direction = match direction.as_str() {
+        "next" => PageDirection::Next,
+        "prev" => PageDirection::Prev,
+        other => panic!("unknown page direction '{other}'"),
+    };
+    w.apply(Event::PageNav { direction });
+}
+
 #[when(expr = "terminal {int} has tag {string}")]
 async fn terminal_has_tag(w: &mut DaemonWorld, index: usize, tag: String) {
     // Inject terminal info into daemon state.
@@ -230,9 +319,17 @@ async fn terminal_has_tag(w: &mut DaemonWorld, index: usize, tag: String) {
             index: w.state.terminals.len(),
             name: format!("terminal-{}", w.state.terminals.len()),
             session_tag: None,
+            is_claude_session: false,
         });
     }
     w.state.terminals[index].session_tag = Some(tag);
+    w.state.terminals[index].is_claude_session = true;
+}
+
+#[when(expr = "tag {string} is selected")]
+async fn tag_selected(w: &mut DaemonWorld, tag: String) {
+    w.effects.clear();
+    w.apply(Event::TagSelect { session_tag: tag });
 }
 
 #[when(expr = "terminal {int} is selected")]
@@ -344,10 +441,49 @@ async fn literal_ctrl_c_sent(w: &mut DaemonWorld) {
     );
 }
 
+#[then(expr = "step {int} remains queued")]
+async fn macro_step_queued(w: &mut DaemonWorld, n: usize) {
+    let next = w
+        .state
+        .pending_macro
+        .as_ref()
+        .and_then(|q| q.front())
+        .map(|s| s.prompt_id.as_str());
+    assert_eq!(
+        next,
+        Some(macro_prompt(n)),
+        "expected macro step {n} next, queue is {:?}",
+        w.state.pending_macro
+    );
+}
+
+#[then("no macro steps remain")]
+async fn no_macro_steps(w: &mut DaemonWorld) {
+    assert!(
+        w.state.pending_macro.is_none(),
+        "expected empty macro queue, got {:?}",
+        w.state.pending_macro
+    );
+}
+
+#[then(expr = "the active page is {int}")]
+async fn active_page_is(w: &mut DaemonWorld, page: usize) {
+    assert_eq!(w.state.page, page, "expected page {page}, got {}", w.state.page);
+}
+
+#[then(expr = "a page changed event from page {int} to page {int} is broadcast")]
+async fn page_changed_broadcast(w: &mut DaemonWorld, from: usize, to: usize) {
+    let found = w.effects.iter().any(|e| match e {
+        SideEffect::BroadcastPageChanged(ev) => ev.from_page == from && ev.to_page == to,
+        _ => false,
+    });
+    assert!(found, "expected page_changed {from} → {to}, got {:?}", w.effects);
+}
+
 #[then(expr = "the agent state is {string}")]
 async fn agent_state_is(w: &mut DaemonWorld, expected: String) {
     let actual = w.state.current_agent_state();
-    let actual_str = serde_json::to_value(&actual)
+    let actual_str = serde_json::to_value(actual)
         .unwrap()
         .as_str()
         .unwrap()
@@ -360,12 +496,7 @@ async fn agent_state_is(w: &mut DaemonWorld, expected: String) {
 
 #[then(expr = "hooks mode is {string}")]
 async fn hooks_mode_is(w: &mut DaemonWorld, expected: String) {
-    let actual = &w.state.hooks_mode;
-    let actual_str = serde_json::to_value(actual)
-        .unwrap()
-        .as_str()
-        .unwrap()
-        .to_string();
+    let actual_str = w.state.hooks_mode.as_str();
     assert_eq!(
         actual_str, expected,
         "expected hooks mode '{expected}', got '{actual_str}'"
@@ -384,7 +515,7 @@ async fn no_sessions(w: &mut DaemonWorld) {
 #[then(expr = "the last ended state is {string}")]
 async fn last_ended_state(w: &mut DaemonWorld, expected: String) {
     let actual = w.state.last_ended_state.expect("no last_ended_state");
-    let actual_str = serde_json::to_value(&actual)
+    let actual_str = serde_json::to_value(actual)
         .unwrap()
         .as_str()
         .unwrap()
@@ -0,0 +1,6 @@
+//! Fixture helpers shared by the integration tests.
+
+/// YAML flow list of `n` empty slots, e.g. `[{}, {}]`.
+pub fn empty_slots(n: usize) -> String {
+    format!("[{}]", vec!["{}"; n].join(", "))
+}
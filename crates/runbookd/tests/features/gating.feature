@@ -14,3 +14,17 @@ Feature: Gating is real
     Then the agent state is "blocked"
     When hook "UserPromptSubmit" arrives for session "s1"
     Then the agent state is "running"
+
+  Scenario: RunbookPolicy allowed sets agent state back to running
+    Given a fresh daemon with prompts
+    When hook "Notification" arrives with matcher "idle_prompt" for session "s1"
+    And hook "RunbookPolicy" arrives with matcher "blocked" for session "s1"
+    Then the agent state is "blocked"
+    When hook "RunbookPolicy" arrives with matcher "allowed" for session "s1"
+    Then the agent state is "running"
+
+  Scenario: RunbookPolicy overridden sets agent state to running
+    Given a fresh daemon with prompts
+    When hook "Notification" arrives with matcher "idle_prompt" for session "s1"
+    And hook "RunbookPolicy" arrives with matcher "overridden" for session "s1"
+    Then the agent state is "running"
@@ -0,0 +1,18 @@
+Feature: Macros
+  A macro queues prompts that run one per completed task, without the
+  operator arming and confirming each one.
+
+  Scenario: Each completed task dispatches the next step
+    Given a fresh daemon with prompts
+    And a macro with 3 steps
+    When the task completes 2 times
+    Then "/runbook:break-task" is sent to the terminal with newline
+    And "/runbook:prep-pr" is sent to the terminal with newline
+    And step 3 remains queued
+
+  Scenario: The queue empties after the last step
+    Given a fresh daemon with prompts
+    And a macro with 1 step
+    When the task completes 1 time
+    Then "/runbook:break-task" is sent to the terminal with newline
+    And no macro steps remain
@@ -1,24 +1,27 @@
 Feature: Multi-session honesty
   With tagged sessions: roller selection shows correct session state.
-  Without tags: UI explicitly shows unknown mapping.
+  Without tags: UI shows the session that sent the latest hook.
 
   Scenario: Single session shows its state directly
     Given a fresh daemon with prompts
     When hook "Notification" arrives with matcher "idle_prompt" for session "s1"
     Then the agent state is "idle"
 
-  Scenario: Multiple sessions without tags degrade to unknown
+  Scenario: Multiple sessions without tags show the most active one
     Given a fresh daemon with prompts
     When hook "Notification" arrives with matcher "idle_prompt" for session "s1"
     And hook "UserPromptSubmit" arrives for session "s2"
     Then there are 2 sessions
-    And the agent state is "unknown"
+    And the agent state is "running"
+    When hook "Notification" arrives with matcher "idle_prompt" for session "s1"
+    Then the agent state is "idle"
 
   Scenario: Ending one session recovers single-session truth
     Given a fresh daemon with prompts
     When hook "Notification" arrives with matcher "idle_prompt" for session "s1"
     And hook "UserPromptSubmit" arrives for session "s2"
-    Then the agent state is "unknown"
+    And hook "Notification" arrives with matcher "idle_prompt" for session "s1"
+    Then the agent state is "idle"
     When hook "SessionEnd" arrives for session "s1"
     Then there is 1 session
     And the agent state is "running"
@@ -28,6 +31,13 @@ Feature: Multi-session honesty
     When hook "Notification" arrives with matcher "idle_prompt" for session "s1" with tag "tag-abc"
     Then session tag "tag-abc" maps to session "s1"
 
+  Scenario: A restarted session takes over its terminal's tag
+    Given a fresh daemon with prompts
+    When a hook arrives for session "s1" with session_tag "tag-abc"
+    Then session tag "tag-abc" maps to session "s1"
+    When a hook arrives for session "s2" with session_tag "tag-abc"
+    Then session tag "tag-abc" maps to session "s2"
+
   Scenario: Tagged multi-session resolves via terminal correlation
     Given a fresh daemon with prompts
     When hook "Notification" arrives with matcher "idle_prompt" for session "s1" with tag "tag-a"
@@ -36,3 +46,13 @@ Feature: Multi-session honesty
     And terminal 1 has tag "tag-b"
     And terminal 0 is selected
     Then the agent state is "idle"
+
+  Scenario: Selecting a tag shows that session
+    Given a fresh daemon with prompts
+    When hook "Notification" arrives with matcher "idle_prompt" for session "s1" with tag "tag-a"
+    And hook "UserPromptSubmit" arrives for session "s2" with tag "tag-b"
+    Then the agent state is "running"
+    When tag "tag-a" is selected
+    Then the agent state is "idle"
+    When tag "tag-zzz" is selected
+    Then the agent state is "idle"
@@ -0,0 +1,40 @@
+Feature: Page navigation
+  Clients can jump straight to a page by its configured hotkey or its index.
+
+  Scenario: Hotkey jump selects the page and clears arming
+    Given a fresh daemon with prompts
+    And the operator has armed "prep_pr"
+    When the operator jumps to page with hotkey "p2"
+    Then the active page is 1
+    And the daemon is no longer armed
+
+  Scenario: Unknown hotkey leaves the page alone
+    Given a fresh daemon with prompts
+    When the operator jumps to page with hotkey "F9"
+    Then the active page is 0
+
+  Scenario: Jump to a page by index
+    Given a fresh daemon with prompts
+    When page jump to index 2 arrives
+    Then the active page is 2
+
+  Scenario: Jump past the last page shows the last page
+    Given a fresh daemon with prompts
+    When page jump to index 9 arrives
+    Then the active page is 2
+
+  Scenario: Paging announces the move for slide animations
+    Given a fresh daemon with prompts
+    When the operator pages next
+    Then the active page is 1
+    And a page changed event from page 0 to page 1 is broadcast
+
+  Scenario: Paging back from the first page wraps to the last
+    Given a fresh daemon with prompts
+    When the operator pages prev
+    Then a page changed event from page 0 to page 2 is broadcast
+
+  Scenario: Index jumps are announced too
+    Given a fresh daemon with prompts
+    When page jump to index 2 arrives
+    Then a page changed event from page 0 to page 2 is broadcast
@@ -0,0 +1,38 @@
+//! Reducer operations run inside `reduce` spans tagged with the session id.
+
+mod common;
+
+use runbookd::config::RunbookConfig;
+use runbookd::reducer::{self, Event};
+use runbookd::state::DaemonState;
+use tracing_test::traced_test;
+
+fn yaml() -> String {
+    format!(
+        "keypad:\n  pages:\n    - name: core\n      slots: {}\n",
+        common::empty_slots(9)
+    )
+}
+
+#[traced_test]
+#[test]
+fn hook_events_are_logged_in_a_reduce_span_with_session_id() {
+    let config: RunbookConfig = serde_yaml::from_str(&yaml()).unwrap();
+    let mut state = DaemonState::new(0);
+
+    reducer::reduce(
+        &mut state,
+        &config,
+        Event::HookEvent {
+            hook: "UserPromptSubmit".to_string(),
+            matcher: None,
+            session_id: Some("sess-trace".to_string()),
+            session_tag: None,
+            payload: serde_json::json!({"prompt": "hi"}),
+        },
+    );
+
+    assert!(logs_contain("reduce{session_id=\"sess-trace\"}"));
+    assert!(logs_contain("reduce_hook"));
+    assert!(logs_contain("hook event"));
+}
@@ -0,0 +1,306 @@
+//! Runs the built `runbookd` binary and talks to it over a real WebSocket.
+
+use std::net::{SocketAddr, TcpListener};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+use futures::{SinkExt, StreamExt};
+use runbook_protocol::{ClientToDaemon, DaemonToClient, Hello, KeypadPress, PROTOCOL_VERSION};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+type Ws = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Every step (connect, each expected message) must finish within this.
+const STEP: Duration = Duration::from_secs(2);
+
+/// The daemon process; killed when the test ends, pass or fail.
+struct Daemon {
+    child: Child,
+    addr: SocketAddr,
+    config: std::path::PathBuf,
+}
+
+impl Drop for Daemon {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_file(&self.config);
+    }
+}
+
+fn spawn_daemon() -> Daemon {
+    spawn_daemon_with_args(&[])
+}
+
+fn spawn_daemon_with_args(args: &[&str]) -> Daemon {
+    // Bind then drop to find a free port for the daemon.
+    let addr = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+    let yaml = format!(
+        r#"
+daemon:
+  listen: "{addr}"
+keypad:
+  pages:
+    - name: core
+      slots: [{{prompt_id: prep_pr}}, {{}}, {{}}, {{}}, {{}}, {{}}, {{}}, {{}}, {{}}]
+prompts:
+  prep_pr:
+    label: "PREP PR"
+    claude_command: "/runbook:prep-pr"
+"#
+    );
+    let config = std::env::temp_dir().join(format!("runbookd-ws-{}.yaml", addr.port()));
+    std::fs::write(&config, yaml).unwrap();
+    let child = Command::new(env!("CARGO_BIN_EXE_runbookd"))
+        .arg("--config")
+        .arg(&config)
+        .args(args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .expect("spawn runbookd");
+    Daemon {
+        child,
+        addr,
+        config,
+    }
+}
+
+/// Retry until the daemon is listening.
+async fn connect(addr: SocketAddr) -> Ws {
+    timeout(STEP, async {
+        loop {
+            if let Ok((ws, _)) = tokio_tungstenite::connect_async(format!("ws://{addr}/ws")).await {
+                return ws;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("runbookd accepts WebSocket connections")
+}
+
+async fn send(ws: &mut Ws, msg: ClientToDaemon) {
+    let text = serde_json::to_string(&msg).unwrap();
+    ws.send(Message::text(text)).await.unwrap();
+}
+
+/// Skip messages until `pick` accepts one.
+async fn next_matching<T>(ws: &mut Ws, pick: impl Fn(DaemonToClient) -> Option<T>) -> T {
+    timeout(STEP, async {
+        loop {
+            let msg = ws.next().await.expect("socket open").unwrap();
+            let Ok(text) = msg.to_text() else { continue };
+            if let Some(found) = serde_json::from_str(text).ok().and_then(&pick) {
+                return found;
+            }
+        }
+    })
+    .await
+    .expect("expected message within the step timeout")
+}
+
+/// `POST` `body` to the daemon's HTTP API; returns the status code.
+async fn http_post(addr: SocketAddr, path: &str, body: &str) -> u16 {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "POST {path} HTTP/1.1\r\nHost: {addr}\r\nContent-Type: application/json\r\n\
+         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+    response[9..12].parse().unwrap()
+}
+
+/// Connect and say `hello` as a Logi client with `capabilities`.
+async fn logi_client(addr: SocketAddr, capabilities: &[&str]) -> Ws {
+    let mut ws = connect(addr).await;
+    let hello = Hello {
+        capabilities: capabilities.iter().map(|c| c.to_string()).collect(),
+        ..Hello::default()
+    };
+    send(&mut ws, ClientToDaemon::Hello(hello)).await;
+    // The render answers `hello`: from here on the capabilities apply.
+    next_matching(&mut ws, |m| {
+        matches!(m, DaemonToClient::Render(_)).then_some(())
+    })
+    .await;
+    ws
+}
+
+/// `type` of every message received until the socket goes quiet.
+async fn received_types(ws: &mut Ws) -> Vec<String> {
+    let mut types = Vec::new();
+    while let Ok(Some(Ok(msg))) = timeout(Duration::from_millis(300), ws.next()).await {
+        let Ok(text) = msg.to_text() else { continue };
+        if let Ok(value) = serde_json::from_str::<serde_json::Value>(text) {
+            types.push(value["type"].as_str().unwrap_or_default().to_string());
+        }
+    }
+    types
+}
+
+#[tokio::test]
+async fn hello_handshake_then_keypad_press_renders() {
+    let daemon = spawn_daemon();
+    let mut ws = connect(daemon.addr).await;
+
+    send(&mut ws, ClientToDaemon::Hello(Hello::default())).await;
+    let ack = next_matching(&mut ws, |m| match m {
+        DaemonToClient::Hello(ack) => Some(ack),
+        _ => None,
+    })
+    .await;
+    assert_eq!(ack.protocol, PROTOCOL_VERSION);
+
+    send(
+        &mut ws,
+        ClientToDaemon::KeypadPress(KeypadPress {
+            prompt_id: "prep_pr".to_string(),
+        }),
+    )
+    .await;
+    // `hello` is answered with renders too; wait for the one showing the press.
+    let render = next_matching(&mut ws, |m| match m {
+        DaemonToClient::Render(render) if render.armed.is_some() => Some(render),
+        _ => None,
+    })
+    .await;
+    assert_eq!(render.armed.unwrap().prompt_id, "prep_pr");
+    assert!(render.keypad.slots[0].armed);
+}
+
+#[tokio::test]
+async fn reload_renders_before_config_reloaded() {
+    let daemon = spawn_daemon();
+    let mut ws = connect(daemon.addr).await;
+    send(&mut ws, ClientToDaemon::Hello(Hello::default())).await;
+    next_matching(&mut ws, |m| {
+        matches!(m, DaemonToClient::Render(_)).then_some(())
+    })
+    .await;
+
+    let yaml = std::fs::read_to_string(&daemon.config).unwrap();
+    std::fs::write(&daemon.config, yaml.replace("PREP PR", "SHIP PR")).unwrap();
+    assert_eq!(http_post(daemon.addr, "/reload", "").await, 200);
+
+    // The render of the new config comes first, then `config_reloaded`.
+    let first = next_matching(&mut ws, |m| match m {
+        DaemonToClient::Render(r) if r.keypad.slots[0].label == "SHIP PR" => Some("render"),
+        DaemonToClient::ConfigReloaded(_) => Some("config_reloaded"),
+        _ => None,
+    })
+    .await;
+    assert_eq!(first, "render");
+    next_matching(&mut ws, |m| {
+        matches!(m, DaemonToClient::ConfigReloaded(_)).then_some(())
+    })
+    .await;
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn sigterm_notifies_clients_closes_and_saves_state() {
+    let state = std::env::temp_dir().join(format!("runbookd-ws-{}.state.json", std::process::id()));
+    let _ = std::fs::remove_file(&state);
+    let mut daemon = spawn_daemon_with_args(&["--state-file", state.to_str().unwrap()]);
+    let mut ws = connect(daemon.addr).await;
+    send(&mut ws, ClientToDaemon::Hello(Hello::default())).await;
+    send(
+        &mut ws,
+        ClientToDaemon::KeypadPress(KeypadPress {
+            prompt_id: "prep_pr".to_string(),
+        }),
+    )
+    .await;
+    next_matching(&mut ws, |m| match m {
+        DaemonToClient::Render(render) if render.armed.is_some() => Some(()),
+        _ => None,
+    })
+    .await;
+
+    let kill = Command::new("kill")
+        .arg("-TERM")
+        .arg(daemon.child.id().to_string())
+        .status()
+        .unwrap();
+    assert!(kill.success());
+
+    let notice = next_matching(&mut ws, |m| match m {
+        DaemonToClient::Notice(notice) => Some(notice.message),
+        _ => None,
+    })
+    .await;
+    assert_eq!(notice, "daemon shutting down");
+    // Then the daemon closes the socket without waiting for us.
+    timeout(STEP, async {
+        while let Some(Ok(msg)) = ws.next().await {
+            if msg.is_close() {
+                break;
+            }
+        }
+    })
+    .await
+    .expect("daemon closes the WebSocket");
+
+    let exited = timeout(STEP, async {
+        loop {
+            if let Some(status) = daemon.child.try_wait().unwrap() {
+                return status;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .expect("runbookd exits after draining");
+    assert!(exited.success(), "{exited}");
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&std::fs::read_to_string(&state).unwrap()).unwrap();
+    std::fs::remove_file(&state).unwrap();
+    assert_eq!(saved["armed"], "prep_pr");
+    assert!(!saved["saved_at"].is_null());
+}
+
+#[tokio::test]
+async fn policy_block_reaches_only_hooks_clients() {
+    let daemon = spawn_daemon();
+    let mut plain = logi_client(daemon.addr, &["keypad"]).await;
+    let mut hooked = logi_client(daemon.addr, &["keypad", "hooks"]).await;
+
+    let hook = r#"{"hook":"RunbookPolicy","matcher":"blocked","session_id":"s1",
+                   "payload":{"runbook_policy":{"command":"rm -rf /"}}}"#;
+    assert_eq!(http_post(daemon.addr, "/hook", hook).await, 200);
+
+    let plain = received_types(&mut plain).await;
+    let hooked = received_types(&mut hooked).await;
+    assert!(!plain.contains(&"policy_block".to_string()), "{plain:?}");
+    assert!(hooked.contains(&"policy_block".to_string()), "{hooked:?}");
+    // Ungated messages still reach both.
+    assert!(plain.contains(&"render".to_string()), "{plain:?}");
+}
+
+#[tokio::test]
+async fn session_list_reaches_only_sessions_clients() {
+    let daemon = spawn_daemon();
+    let mut plain = logi_client(daemon.addr, &["hooks"]).await;
+    let mut sessions = logi_client(daemon.addr, &["sessions"]).await;
+
+    let hook = r#"{"hook":"SessionStart","session_id":"s1","payload":null}"#;
+    assert_eq!(http_post(daemon.addr, "/hook", hook).await, 200);
+
+    let plain = received_types(&mut plain).await;
+    let sessions = received_types(&mut sessions).await;
+    assert!(!plain.contains(&"session_list".to_string()), "{plain:?}");
+    assert!(
+        sessions.contains(&"session_list".to_string()),
+        "{sessions:?}"
+    );
+}
@@ -0,0 +1,384 @@
+//! Sample protocol messages behind `crates/runbook-protocol/fixtures/*.json`.
+//!
+//! The fixtures are generated from this table; edit a sample here and run
+//! `cargo xtask gen-fixtures` rather than hand-editing the JSON.
+
+use runbook_protocol::*;
+
+/// A fixture's message, in whichever direction it travels.
+pub enum Fixture {
+    Client(ClientToDaemon),
+    Daemon(DaemonToClient),
+}
+
+impl Fixture {
+    /// Pretty JSON, as committed (trailing newline included).
+    pub fn render(&self) -> anyhow::Result<String> {
+        let json = match self {
+            Fixture::Client(msg) => serde_json::to_string_pretty(msg)?,
+            Fixture::Daemon(msg) => serde_json::to_string_pretty(msg)?,
+        };
+        Ok(json + "\n")
+    }
+
+    /// Parse `raw` as a message in the same direction as this fixture.
+    pub fn reparse(&self, raw: &str) -> anyhow::Result<Fixture> {
+        Ok(match self {
+            Fixture::Client(_) => Fixture::Client(serde_json::from_str(raw)?),
+            Fixture::Daemon(_) => Fixture::Daemon(serde_json::from_str(raw)?),
+        })
+    }
+}
+
+/// Every fixture file name with its sample message.
+pub fn all() -> Vec<(&'static str, Fixture)> {
+    use Fixture::{Client, Daemon};
+
+    vec![
+        (
+            "client_hello.json",
+            Client(ClientToDaemon::Hello(Hello {
+                client: ClientKind::Logi,
+                protocol: PROTOCOL_VERSION,
+                version: "0.1.0".to_string(),
+                capabilities: vec!["keypad".to_string(), "dialpad".to_string()],
+            })),
+        ),
+        (
+            "keypad_press.json",
+            Client(ClientToDaemon::KeypadPress(KeypadPress {
+                prompt_id: "prep_pr".to_string(),
+            })),
+        ),
+        (
+            "page_nav.json",
+            Client(ClientToDaemon::PageNav(PageNav {
+                direction: PageDirection::Next,
+            })),
+        ),
+        (
+            "page_nav_jump_to.json",
+            Client(ClientToDaemon::PageNav(PageNav {
+                direction: PageDirection::JumpTo { index: 4 },
+            })),
+        ),
+        (
+            "gesture_input.json",
+            Client(ClientToDaemon::GestureInput(GestureInput {
+                direction: GestureDirection::SwipeUp,
+                distance: 3,
+            })),
+        ),
+        (
+            "page_jump.json",
+            Client(ClientToDaemon::PageJump(PageJump {
+                hotkey: "p2".to_string(),
+            })),
+        ),
+        (
+            "macro.json",
+            Client(ClientToDaemon::Macro(MacroRequest {
+                steps: ["break_task", "run_gates", "prep_pr"]
+                    .into_iter()
+                    .map(|id| MacroStep {
+                        prompt_id: id.to_string(),
+                    })
+                    .collect(),
+            })),
+        ),
+        (
+            "export_request.json",
+            Client(ClientToDaemon::ExportRequest(ExportRequest {
+                session_id: Some("sess-abc123".to_string()),
+                format: ExportFormat::Json,
+            })),
+        ),
+        (
+            "hook_event.json",
+            Client(ClientToDaemon::HookEvent(HookEvent {
+                hook: "Notification".to_string(),
+                matcher: Some("idle_prompt".to_string()),
+                session_id: Some("sess-abc123".to_string()),
+                session_tag: Some("tag-001".to_string()),
+                payload: serde_json::json!({ "notification_type": "idle_prompt" }),
+            })),
+        ),
+        (
+            "terminals_snapshot.json",
+            Client(ClientToDaemon::TerminalsSnapshot(TerminalsSnapshot {
+                terminals: vec![
+                    TerminalInfo {
+                        index: 0,
+                        name: "Claude (prep-pr)".to_string(),
+                        session_tag: Some("tag-001".to_string()),
+                        is_claude_session: true,
+                    },
+                    TerminalInfo {
+                        index: 1,
+                        name: "zsh".to_string(),
+                        session_tag: None,
+                        is_claude_session: false,
+                    },
+                ],
+                active_index: 0,
+                timestamp: Some("2025-10-16T13:20:00.000Z".to_string()),
+            })),
+        ),
+        (
+            "slot_progress.json",
+            Client(ClientToDaemon::SlotProgress(SlotProgress {
+                prompt_id: "prep_pr".to_string(),
+                progress: 0.5,
+            })),
+        ),
+        (
+            "tag_select.json",
+            Client(ClientToDaemon::TagSelect(TagSelect {
+                session_tag: "tag-001".to_string(),
+            })),
+        ),
+        (
+            "command_ack.json",
+            Client(ClientToDaemon::CommandAck(CommandAck {
+                command_id: 1,
+                success: false,
+                error: Some("no terminal at index 3".to_string()),
+            })),
+        ),
+        (
+            "reconfigure.json",
+            Client(ClientToDaemon::Reconfigure(ReconfigureRequest {
+                dial_mode: Some(DialMode::VscodeTerminalScroll),
+                label_max_chars: Some(8),
+            })),
+        ),
+        (
+            "set_focused_session.json",
+            Client(ClientToDaemon::SetFocusedSession(SetFocusedSession {
+                session_id: "sess-abc123".to_string(),
+            })),
+        ),
+        (
+            "health_check.json",
+            Client(ClientToDaemon::HealthCheck(HealthCheck { request_id: 7 })),
+        ),
+        (
+            "daemon_hello_ack.json",
+            Daemon(DaemonToClient::Hello(HelloAck {
+                protocol: PROTOCOL_VERSION,
+                daemon_version: "0.1.0".to_string(),
+                capabilities: vec!["hooks".to_string(), "sessions".to_string()],
+            })),
+        ),
+        (
+            "render.json",
+            Daemon(DaemonToClient::Render(RenderModel {
+                agent_state: AgentState::Idle,
+                armed: Some(ArmedPrompt {
+                    prompt_id: "prep_pr".to_string(),
+                    label: "PREP PR".to_string(),
+                    style: ArmStyle::Queue,
+                    command: "/runbook:prep-pr".to_string(),
+                }),
+                keypad: KeypadRender {
+                    slots: vec![KeypadSlotRender {
+                        slot: 0,
+                        prompt_id: "prep_pr".to_string(),
+                        label: "PREP PR".to_string(),
+                        sublabel: Some("receipts".to_string()),
+                        armed: true,
+                        progress: Some(0.5),
+                        last_dispatched_at: Some(1_760_620_800),
+                    }],
+                },
+                page_index: 0,
+                page_count: 2,
+                hooks_mode: HooksMode::Active,
+                hooks_stale_secs: None,
+                status_line: Some("idle | tool: Bash".to_string()),
+                permission_description: None,
+            })),
+        ),
+        (
+            "render_page.json",
+            Daemon(DaemonToClient::RenderPage(PageRender {
+                page_index: 1,
+                page_count: 2,
+                page_name: "review".to_string(),
+                page_description: Some("Review and merge".to_string()),
+                slots: vec![KeypadSlotRender {
+                    slot: 0,
+                    prompt_id: "review_pr".to_string(),
+                    label: "REVIEW".to_string(),
+                    sublabel: None,
+                    armed: false,
+                    progress: None,
+                    last_dispatched_at: None,
+                }],
+            })),
+        ),
+        (
+            "vscode_command.json",
+            Daemon(DaemonToClient::VscodeCommand(VscodeCommand {
+                command_id: 1,
+                ..VscodeCommand::send_text(TerminalTarget::ActiveClaude, "/runbook:prep-pr", true)
+            })),
+        ),
+        (
+            "vscode_command_by_tag.json",
+            Daemon(DaemonToClient::VscodeCommand(VscodeCommand {
+                command_id: 2,
+                ..VscodeCommand::send_text(
+                    TerminalTarget::ByTag("tag-001".to_string()),
+                    "/runbook:prep-pr",
+                    true,
+                )
+            })),
+        ),
+        (
+            "progress.json",
+            Daemon(DaemonToClient::Progress(ProgressUpdate {
+                session_id: "sess-abc123".to_string(),
+                tool_call_count: 4,
+                last_tool_name: Some("Bash".to_string()),
+            })),
+        ),
+        (
+            "policy_block.json",
+            Daemon(DaemonToClient::PolicyBlock(PolicyBlock {
+                session_id: Some("sess-abc123".to_string()),
+                command: Some("rm -rf /".to_string()),
+            })),
+        ),
+        (
+            "session_list.json",
+            Daemon(DaemonToClient::SessionList(SessionList {
+                sessions: vec![SessionSummary {
+                    session_id: "sess-abc123".to_string(),
+                    session_tag: Some("tag-001".to_string()),
+                    agent_state: AgentState::Running,
+                    last_tool: Some("Bash".to_string()),
+                    tool_call_count: 4,
+                    blocked_count: 1,
+                    last_prompt: Some("prep a PR".to_string()),
+                    pending_permission: Some(PendingPermission {
+                        tool_name: "Bash".to_string(),
+                        description: "cargo publish".to_string(),
+                        waiting_secs: 12,
+                    }),
+                }],
+            })),
+        ),
+        (
+            "config_reloaded.json",
+            Daemon(DaemonToClient::ConfigReloaded(ConfigSummary {
+                prompt_count: 12,
+                gate_count: 2,
+                pages: vec!["Core".to_string(), "Review".to_string()],
+                initial_page: 0,
+                policy_enabled: true,
+                page_summaries: vec![
+                    PageSummary {
+                        name: "Core".to_string(),
+                        description: Some("Core workflow prompts".to_string()),
+                    },
+                    PageSummary {
+                        name: "Review".to_string(),
+                        description: None,
+                    },
+                ],
+            })),
+        ),
+        (
+            "debug.json",
+            Daemon(DaemonToClient::Debug(DebugDump {
+                state_json: "{\n  \"page\": 0\n}".to_string(),
+                config_json: "{\n  \"version\": 1\n}".to_string(),
+                metrics: MetricsSnapshot {
+                    session_count: 1,
+                    client_count: 2,
+                    dispatch_count: 3,
+                    uptime_secs: 60,
+                },
+                timestamp: "1760620800".to_string(),
+            })),
+        ),
+        (
+            "error.json",
+            Daemon(DaemonToClient::Error(ErrorMessage {
+                code: ErrorCode::PayloadTooLarge,
+                message: "message of 2097152 bytes exceeds limit of 1048576".to_string(),
+            })),
+        ),
+        (
+            "banner.json",
+            Daemon(DaemonToClient::Banner(BannerMessage {
+                line1: "runbookd 0.1.0".to_string(),
+                line2: Some("hooks: active".to_string()),
+                duration_ms: 2000,
+            })),
+        ),
+        (
+            "health_ack.json",
+            Daemon(DaemonToClient::HealthAck(HealthAck {
+                request_id: 7,
+                uptime_secs: 3600,
+                session_count: 2,
+            })),
+        ),
+        (
+            "heartbeat.json",
+            Daemon(DaemonToClient::Heartbeat(Heartbeat { ts: 1_760_000_000 })),
+        ),
+        (
+            "slot_highlight.json",
+            Daemon(DaemonToClient::SlotHighlight(SlotHighlight {
+                slot: 0,
+                color_rgb: [0, 255, 0],
+                duration_ms: 500,
+            })),
+        ),
+        (
+            "agent_state_change.json",
+            Daemon(DaemonToClient::AgentStateChange(AgentStateChange {
+                session_id: Some("sess-abc123".to_string()),
+                previous: AgentState::Idle,
+                current: AgentState::Running,
+                hook_trigger: "UserPromptSubmit".to_string(),
+            })),
+        ),
+        (
+            "toast.json",
+            Daemon(DaemonToClient::Toast(ToastMessage {
+                message: "macro step 'nope' has no command; skipped".to_string(),
+                duration_ms: 3000,
+                severity: NoticeSeverity::Warning,
+            })),
+        ),
+        (
+            "prompt_suggestion.json",
+            Daemon(DaemonToClient::PromptSuggestion(PromptSuggestion {
+                prompt_id: "run_gates".to_string(),
+                reason: "usually follows prep_pr".to_string(),
+                confidence: 1.0,
+            })),
+        ),
+        (
+            "config_error.json",
+            Daemon(DaemonToClient::ConfigError(ConfigError {
+                field_path: "keypad.grid_size".to_string(),
+                message: "keypad.grid_size must be at least 1x1. Got 0x3.".to_string(),
+            })),
+        ),
+        (
+            "page_changed.json",
+            Daemon(DaemonToClient::PageChanged(PageChangedEvent {
+                from_page: 0,
+                to_page: 1,
+                from_name: "core".to_string(),
+                to_name: "review".to_string(),
+                direction: Some(PageDirection::Next),
+            })),
+        ),
+    ]
+}
@@ -0,0 +1,180 @@
+//! Repo maintenance tasks: `cargo xtask <task>` (alias in `.cargo/config.toml`).
+
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use clap::{Parser, Subcommand};
+
+mod fixtures;
+
+#[derive(Debug, Parser)]
+#[command(name = "xtask", about = "Runbook repo maintenance tasks")]
+struct Args {
+    #[command(subcommand)]
+    task: Task,
+}
+
+#[derive(Debug, Subcommand)]
+enum Task {
+    /// Write every protocol fixture from the samples in `xtask/src/fixtures.rs`.
+    GenFixtures,
+    /// Fail if a committed fixture differs from what `gen-fixtures` would write (for CI).
+    CheckFixtures,
+    /// Re-serialize every fixture file in the canonical pretty format.
+    FmtFixtures,
+    /// Regenerate the protocol JSON schemas and runbookd's OpenAPI spec.
+    GenSchema,
+    /// Run every `runbook-protocol` example, failing if one errors (for CI).
+    RunExamples,
+}
+
+fn main() -> anyhow::Result<()> {
+    let dir = fixtures_dir();
+    match Args::parse().task {
+        Task::GenFixtures => gen_fixtures(&dir),
+        Task::CheckFixtures => check_fixtures(&dir),
+        Task::FmtFixtures => fmt_fixtures(&dir),
+        Task::GenSchema => gen_schema(),
+        Task::RunExamples => run_examples(&dir),
+    }
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("xtask lives in the workspace root")
+        .to_path_buf()
+}
+
+fn fixtures_dir() -> PathBuf {
+    workspace_root().join("crates/runbook-protocol/fixtures")
+}
+
+fn gen_fixtures(dir: &Path) -> anyhow::Result<()> {
+    for (name, fixture) in fixtures::all() {
+        let path = dir.join(name);
+        std::fs::write(&path, fixture.render()?)
+            .map_err(|e| anyhow::anyhow!("failed to write '{}': {e}", path.display()))?;
+        println!("Generated {}", path.display());
+    }
+    Ok(())
+}
+
+/// Fixture files that are missing, differ from their sample, or have no sample.
+fn stale_fixtures(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let samples = fixtures::all();
+    let mut stale = Vec::new();
+    for (name, fixture) in &samples {
+        let committed = std::fs::read_to_string(dir.join(name)).unwrap_or_default();
+        if committed != fixture.render()? {
+            stale.push(name.to_string());
+        }
+    }
+    for name in json_files(dir)? {
+        if !samples.iter().any(|(n, _)| *n == name) {
+            stale.push(format!("{name} (no sample in xtask/src/fixtures.rs)"));
+        }
+    }
+    Ok(stale)
+}
+
+fn check_fixtures(dir: &Path) -> anyhow::Result<()> {
+    let stale = stale_fixtures(dir)?;
+    if !stale.is_empty() {
+        anyhow::bail!(
+            "fixtures out of date; run `cargo xtask gen-fixtures`:\n  {}",
+            stale.join("\n  ")
+        );
+    }
+    println!("All fixtures up to date");
+    Ok(())
+}
+
+/// Parses each file as the message type its sample uses, so unknown fields
+/// are dropped and defaults filled in.
+fn fmt_fixtures(dir: &Path) -> anyhow::Result<()> {
+    let samples = fixtures::all();
+    for name in json_files(dir)? {
+        let Some((_, sample)) = samples.iter().find(|(n, _)| *n == name) else {
+            anyhow::bail!("{name} has no sample in xtask/src/fixtures.rs");
+        };
+        let path = dir.join(&name);
+        let raw = std::fs::read_to_string(&path)?;
+        let parsed = sample
+            .reparse(&raw)
+            .map_err(|e| anyhow::anyhow!("failed to parse '{}': {e}", path.display()))?;
+        std::fs::write(&path, parsed.render()?)?;
+        println!("Formatted {}", path.display());
+    }
+    Ok(())
+}
+
+fn json_files(dir: &Path) -> anyhow::Result<Vec<String>> {
+    let mut names: Vec<String> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter(|name| name.ends_with(".json"))
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+fn gen_schema() -> anyhow::Result<()> {
+    cargo(&["run", "-p", "runbook-protocol", "--features", "schemars", "--bin", "gen_schema"])?;
+    cargo(&["run", "-p", "runbookd", "--bin", "gen_openapi"])
+}
+
+/// Examples that read a message from stdin are fed the matching fixture.
+fn run_examples(dir: &Path) -> anyhow::Result<()> {
+    let examples_dir = workspace_root().join("crates/runbook-protocol/examples");
+    let mut names: Vec<String> = std::fs::read_dir(&examples_dir)?
+        .filter_map(|entry| entry.ok()?.file_name().into_string().ok())
+        .filter_map(|name| name.strip_suffix(".rs").map(str::to_string))
+        .collect();
+    names.sort();
+    for name in names {
+        let stdin = match name.as_str() {
+            "parse_render" => Stdio::from(std::fs::File::open(dir.join("render.json"))?),
+            _ => Stdio::null(),
+        };
+        println!("Running example {name}");
+        cargo_with_stdin(&["run", "-q", "-p", "runbook-protocol", "--example", &name], stdin)?;
+    }
+    Ok(())
+}
+
+fn cargo(args: &[&str]) -> anyhow::Result<()> {
+    cargo_with_stdin(args, Stdio::inherit())
+}
+
+fn cargo_with_stdin(args: &[&str], stdin: Stdio) -> anyhow::Result<()> {
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+    let status = Command::new(cargo)
+        .args(args)
+        .current_dir(workspace_root())
+        .stdin(stdin)
+        .status()?;
+    if !status.success() {
+        anyhow::bail!("cargo {} failed: {status}", args.join(" "));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn committed_fixtures_match_samples() {
+        let stale = stale_fixtures(&fixtures_dir()).unwrap();
+        assert!(stale.is_empty(), "run `cargo xtask gen-fixtures`: {stale:?}");
+    }
+
+    #[test]
+    fn fixture_names_match_message_types() {
+        for (name, fixture) in fixtures::all() {
+            let json: serde_json::Value = serde_json::from_str(&fixture.render().unwrap()).unwrap();
+            let tag = json["type"].as_str().unwrap();
+            assert!(name.contains(tag), "{name} holds a `{tag}` message");
+        }
+    }
+}